@@ -32,6 +32,12 @@ impl From<EventSdk> for Event {
     }
 }
 
+impl From<Event> for EventSdk {
+    fn from(event: Event) -> Self {
+        event.inner
+    }
+}
+
 impl Deref for Event {
     type Target = EventSdk;
     fn deref(&self) -> &Self::Target {
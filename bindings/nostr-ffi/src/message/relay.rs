@@ -32,6 +32,7 @@ pub enum RelayMessage {
     Count {
         subscription_id: String,
         count: u64,
+        approximate: bool,
     },
     NegMsg {
         subscription_id: String,
@@ -77,9 +78,11 @@ impl From<NRelayMessage> for RelayMessage {
             NRelayMessage::Count {
                 subscription_id,
                 count,
+                approximate,
             } => Self::Count {
                 subscription_id: subscription_id.to_string(),
                 count: count as u64,
+                approximate,
             },
             NRelayMessage::NegMsg {
                 subscription_id,
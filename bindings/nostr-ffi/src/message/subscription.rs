@@ -94,6 +94,12 @@ impl From<nostr::Filter> for Filter {
     }
 }
 
+impl From<Filter> for nostr::Filter {
+    fn from(f: Filter) -> Self {
+        f.inner
+    }
+}
+
 #[uniffi::export]
 impl Filter {
     #[uniffi::constructor]
@@ -44,6 +44,12 @@ pub struct JsFilter {
     inner: Filter,
 }
 
+impl From<Filter> for JsFilter {
+    fn from(inner: Filter) -> Self {
+        Self { inner }
+    }
+}
+
 impl From<&JsFilter> for Filter {
     fn from(filter: &JsFilter) -> Self {
         filter.inner.clone()
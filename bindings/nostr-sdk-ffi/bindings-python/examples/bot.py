@@ -1,5 +1,5 @@
+import asyncio
 from nostr_sdk import Keys, Client, Event, EventBuilder, Filter, HandleNotification, Timestamp, nip04_decrypt, SecretKey, init_logger, LogLevel
-import time
 
 init_logger(LogLevel.DEBUG)
 
@@ -12,17 +12,12 @@ sk = keys.secret_key()
 pk = keys.public_key()
 print(f"Bot public key: {pk.to_bech32()}")
 
-client = Client(keys)
-
-client.add_relay("wss://relay.damus.io", None)
-client.add_relay("wss://nostr.mom", None)
-client.add_relay("wss://nostr.oxtr.dev", None)
-client.connect()
-
-filter = Filter().pubkey(pk).kind(4).since(Timestamp.now())
-client.subscribe([filter])
 
 class NotificationHandler(HandleNotification):
+    def __init__(self, loop, client):
+        self.loop = loop
+        self.client = client
+
     def handle(self, relay_url, event):
         print(f"Received new event from {relay_url}: {event.as_json()}")
         if event.kind() == 4:
@@ -31,14 +26,26 @@ class NotificationHandler(HandleNotification):
                 msg = nip04_decrypt(sk, event.pubkey(), event.content())
                 print(f"Received new msg: {msg}")
                 event = EventBuilder.new_encrypted_direct_msg(keys, event.pubkey(), f"Echo: {msg}", event.id()).to_event(keys)
-                client.send_event(event)
+                asyncio.run_coroutine_threadsafe(self.client.send_event(event), self.loop)
             except Exception as e:
                 print(f"Error during content decryption: {e}")
 
     def handle_msg(self, relay_url, msg):
         None
-    
-client.handle_notifications(NotificationHandler())
 
-while True:
-    time.sleep(5.0)
\ No newline at end of file
+
+async def main():
+    client = Client(keys)
+
+    await client.add_relay("wss://relay.damus.io", None)
+    await client.add_relay("wss://nostr.mom", None)
+    await client.add_relay("wss://nostr.oxtr.dev", None)
+    await client.connect()
+
+    filter = Filter().pubkey(pk).kind(4).since(Timestamp.now())
+    await client.subscribe([filter])
+
+    # Return when the client is stopped or shutdown
+    await client.handle_notifications(NotificationHandler(asyncio.get_running_loop(), client))
+
+asyncio.run(main())
@@ -12,7 +12,7 @@ use std::time::Duration;
 use nostr_ffi::{
     ClientMessage, Event, EventId, FileMetadata, Filter, Keys, Metadata, PublicKey, RelayMessage,
 };
-use nostr_sdk::client::blocking::Client as ClientSdk;
+use nostr_sdk::client::Client as ClientSdk;
 use nostr_sdk::relay::RelayPoolNotification as RelayPoolNotificationSdk;
 use nostr_sdk::{NegentropyOptions, Options as OptionsSdk};
 use uniffi::Object;
@@ -23,7 +23,7 @@ mod options;
 pub use self::builder::ClientBuilder;
 pub use self::options::Options;
 use crate::error::Result;
-use crate::{NostrDatabase, Relay};
+use crate::{NostrDatabase, Reconciliation, Relay};
 
 #[derive(Object)]
 pub struct Client {
@@ -36,7 +36,7 @@ impl From<ClientSdk> for Client {
     }
 }
 
-#[uniffi::export]
+#[uniffi::export(async_runtime = "tokio")]
 impl Client {
     #[uniffi::constructor]
     pub fn new(keys: Arc<Keys>) -> Arc<Self> {
@@ -62,8 +62,8 @@ impl Client {
         self.inner.update_difficulty(difficulty);
     }
 
-    pub fn keys(&self) -> Arc<Keys> {
-        Arc::new(self.inner.keys().into())
+    pub async fn keys(&self) -> Arc<Keys> {
+        Arc::new(self.inner.keys().await.into())
     }
 
     pub fn database(&self) -> Arc<NostrDatabase> {
@@ -74,84 +74,97 @@ impl Client {
 
     // TODO: add remote_signer
 
-    pub fn start(&self) {
-        self.inner.start();
+    pub async fn start(&self) {
+        self.inner.start().await;
     }
 
-    pub fn stop(&self) -> Result<()> {
-        Ok(self.inner.stop()?)
+    pub async fn stop(&self) -> Result<()> {
+        Ok(self.inner.stop().await?)
     }
 
     pub fn is_running(&self) -> bool {
         self.inner.is_running()
     }
 
-    pub fn shutdown(&self) -> Result<()> {
-        Ok(self.inner.clone().shutdown()?)
+    pub async fn shutdown(&self) -> Result<()> {
+        Ok(self.inner.clone().shutdown().await?)
     }
 
-    pub fn relays(&self) -> HashMap<String, Arc<Relay>> {
+    pub async fn relays(&self) -> HashMap<String, Arc<Relay>> {
         self.inner
             .relays()
+            .await
             .into_iter()
             .map(|(u, r)| (u.to_string(), Arc::new(r.into())))
             .collect()
     }
 
-    pub fn relay(&self, url: String) -> Result<Arc<Relay>> {
-        Ok(Arc::new(self.inner.relay(url)?.into()))
+    pub async fn relay(&self, url: String) -> Result<Arc<Relay>> {
+        Ok(Arc::new(self.inner.relay(url).await?.into()))
     }
 
-    pub fn add_relay(&self, url: String, proxy: Option<String>) -> Result<bool> {
+    pub async fn add_relay(&self, url: String, proxy: Option<String>) -> Result<bool> {
         let proxy: Option<SocketAddr> = match proxy {
             Some(proxy) => Some(proxy.parse()?),
             None => None,
         };
 
-        Ok(self.inner.add_relay(url, proxy)?)
+        Ok(self.inner.add_relay(url, proxy).await?)
     }
 
     // TODO: add add_relay_with_opts
 
-    pub fn remove_relay(&self, url: String) -> Result<()> {
-        Ok(self.inner.remove_relay(url)?)
+    pub async fn remove_relay(&self, url: String) -> Result<()> {
+        Ok(self.inner.remove_relay(url).await?)
     }
 
     // TODO: add add_relays
 
-    pub fn connect_relay(&self, url: String) -> Result<()> {
-        Ok(self.inner.connect_relay(url)?)
+    pub async fn connect_relay(&self, url: String) -> Result<()> {
+        Ok(self.inner.connect_relay(url).await?)
     }
 
-    pub fn disconnect_relay(&self, url: String) -> Result<()> {
-        Ok(self.inner.disconnect_relay(url)?)
+    pub async fn disconnect_relay(&self, url: String) -> Result<()> {
+        Ok(self.inner.disconnect_relay(url).await?)
     }
 
-    pub fn connect(&self) {
-        self.inner.connect()
+    pub async fn connect(&self) {
+        self.inner.connect().await
     }
 
-    pub fn disconnect(&self) -> Result<()> {
-        Ok(self.inner.disconnect()?)
+    pub async fn disconnect(&self) -> Result<()> {
+        Ok(self.inner.disconnect().await?)
     }
 
-    pub fn subscribe(&self, filters: Vec<Arc<Filter>>) {
+    pub async fn subscribe(&self, filters: Vec<Arc<Filter>>) {
         let filters = filters
             .into_iter()
             .map(|f| f.as_ref().deref().clone())
             .collect();
-        self.inner.subscribe(filters);
+        self.inner.subscribe(filters).await;
     }
 
-    // TODO: add subscribe_with_custom_wait
+    pub async fn subscribe_with_custom_wait(
+        &self,
+        filters: Vec<Arc<Filter>>,
+        wait: Option<Duration>,
+    ) {
+        let filters = filters
+            .into_iter()
+            .map(|f| f.as_ref().deref().clone())
+            .collect();
+        self.inner.subscribe_with_custom_wait(filters, wait).await;
+    }
 
-    pub fn unsubscribe(&self) {
-        self.inner.unsubscribe();
+    pub async fn unsubscribe(&self) {
+        self.inner.unsubscribe().await;
     }
 
-    // TODO: add unsubscribe_with_custom_wait
+    pub async fn unsubscribe_with_custom_wait(&self, wait: Option<Duration>) {
+        self.inner.unsubscribe_with_custom_wait(wait).await;
+    }
 
-    pub fn get_events_of(
+    pub async fn get_events_of(
         &self,
         filters: Vec<Arc<Filter>>,
         timeout: Option<Duration>,
@@ -162,7 +175,8 @@ impl Client {
             .collect();
         Ok(self
             .inner
-            .get_events_of(filters, timeout)?
+            .get_events_of(filters, timeout)
+            .await?
             .into_iter()
             .map(|e| Arc::new(e.into()))
             .collect())
@@ -170,55 +184,60 @@ impl Client {
 
     // TODO: add get_events_of_with_opts
 
-    pub fn req_events_of(&self, filters: Vec<Arc<Filter>>, timeout: Option<Duration>) {
+    pub async fn req_events_of(&self, filters: Vec<Arc<Filter>>, timeout: Option<Duration>) {
         let filters = filters
             .into_iter()
             .map(|f| f.as_ref().deref().clone())
             .collect();
-        self.inner.req_events_of(filters, timeout);
+        self.inner.req_events_of(filters, timeout).await;
     }
 
     // TODO: add req_events_of_with_opts
 
-    pub fn send_msg(&self, msg: ClientMessage) -> Result<()> {
-        Ok(self.inner.send_msg(msg.into())?)
+    pub async fn send_msg(&self, msg: ClientMessage) -> Result<()> {
+        Ok(self.inner.send_msg(msg.into()).await?)
     }
 
     // TODO: add send_msg_with_custom_wait
 
-    pub fn send_msg_to(&self, url: String, msg: ClientMessage) -> Result<()> {
-        Ok(self.inner.send_msg_to(url, msg.into())?)
+    pub async fn send_msg_to(&self, url: String, msg: ClientMessage) -> Result<()> {
+        Ok(self.inner.send_msg_to(url, msg.into()).await?)
     }
 
     // TODO: add send_msg_to_with_custom_wait
 
-    pub fn send_event(&self, event: Arc<Event>) -> Result<Arc<EventId>> {
+    pub async fn send_event(&self, event: Arc<Event>) -> Result<Arc<EventId>> {
         Ok(Arc::new(
             self.inner
-                .send_event(event.as_ref().deref().clone())?
+                .send_event(event.as_ref().deref().clone())
+                .await?
                 .into(),
         ))
     }
 
     // TODO: add send_event_with_custom_wait
 
-    pub fn send_event_to(&self, url: String, event: Arc<Event>) -> Result<Arc<EventId>> {
+    pub async fn send_event_to(&self, url: String, event: Arc<Event>) -> Result<Arc<EventId>> {
         Ok(Arc::new(
             self.inner
-                .send_event_to(url, event.as_ref().deref().clone())?
+                .send_event_to(url, event.as_ref().deref().clone())
+                .await?
                 .into(),
         ))
     }
 
     // TODO: add send_event_to_with_custom_wait
 
-    pub fn set_metadata(&self, metadata: Arc<Metadata>) -> Result<Arc<EventId>> {
+    pub async fn set_metadata(&self, metadata: Arc<Metadata>) -> Result<Arc<EventId>> {
         Ok(Arc::new(
-            self.inner.set_metadata(metadata.as_ref().deref())?.into(),
+            self.inner
+                .set_metadata(metadata.as_ref().deref())
+                .await?
+                .into(),
         ))
     }
 
-    pub fn send_direct_msg(
+    pub async fn send_direct_msg(
         &self,
         receiver: Arc<PublicKey>,
         msg: String,
@@ -226,47 +245,85 @@ impl Client {
     ) -> Result<Arc<EventId>> {
         Ok(Arc::new(
             self.inner
-                .send_direct_msg(**receiver, msg, reply.map(|r| **r))?
+                .send_direct_msg(**receiver, msg, reply.map(|r| **r))
+                .await?
                 .into(),
         ))
     }
 
-    pub fn file_metadata(
+    pub async fn file_metadata(
         &self,
         description: String,
         metadata: Arc<FileMetadata>,
     ) -> Result<Arc<EventId>> {
         Ok(Arc::new(
             self.inner
-                .file_metadata(description, metadata.as_ref().deref().clone())?
+                .file_metadata(description, metadata.as_ref().deref().clone())
+                .await?
                 .into(),
         ))
     }
 
-    pub fn reconcile(&self, filter: Arc<Filter>) -> Result<()> {
-        Ok(self.inner.reconcile(
-            filter.as_ref().deref().clone(),
-            NegentropyOptions::default(),
-        )?)
+    /// Negentropy reconciliation
+    pub async fn reconcile(&self, filter: Arc<Filter>) -> Result<Reconciliation> {
+        Ok(self
+            .inner
+            .reconcile(
+                filter.as_ref().deref().clone(),
+                NegentropyOptions::default(),
+            )
+            .await?
+            .into())
     }
 
-    pub fn handle_notifications(self: Arc<Self>, handler: Box<dyn HandleNotification>) {
-        crate::thread::spawn("client", move || {
-            tracing::debug!("Client Thread Started");
-            Ok(self.inner.handle_notifications(|notification| {
-                match notification {
-                    RelayPoolNotificationSdk::Message(url, msg) => {
-                        handler.handle_msg(url.to_string(), msg.into())
-                    }
-                    RelayPoolNotificationSdk::Event(url, event) => {
-                        handler.handle(url.to_string(), Arc::new(event.into()))
+    /// Negentropy reconciliation of multiple filters
+    pub async fn reconcile_multi(&self, filters: Vec<Arc<Filter>>) -> Result<Reconciliation> {
+        let filters = filters
+            .into_iter()
+            .map(|f| f.as_ref().deref().clone())
+            .collect();
+        Ok(self
+            .inner
+            .reconcile_multi(filters, NegentropyOptions::default())
+            .await?
+            .into())
+    }
+
+    /// Sync filter across all relays
+    pub async fn sync(&self, filter: Arc<Filter>) -> Result<Reconciliation> {
+        Ok(self
+            .inner
+            .sync(
+                filter.as_ref().deref().clone(),
+                NegentropyOptions::default(),
+            )
+            .await?
+            .into())
+    }
+
+    /// Handle notifications
+    ///
+    /// Return when the client is stopped or shutdown.
+    pub async fn handle_notifications(&self, handler: Box<dyn HandleNotification>) -> Result<()> {
+        Ok(self
+            .inner
+            .handle_notifications(|notification| {
+                let handler = &handler;
+                async move {
+                    match notification {
+                        RelayPoolNotificationSdk::Message(url, msg) => {
+                            handler.handle_msg(url.to_string(), msg.into())
+                        }
+                        RelayPoolNotificationSdk::Event(url, event) => {
+                            handler.handle(url.to_string(), Arc::new(event.into()))
+                        }
+                        _ => (),
                     }
-                    _ => (),
-                }
 
-                Ok(false)
-            })?)
-        });
+                    Ok(false)
+                }
+            })
+            .await?)
     }
 }
 
@@ -60,6 +60,7 @@ impl Options {
         Arc::new(builder)
     }
 
+    #[allow(deprecated)]
     pub fn req_filters_chunk_size(self: Arc<Self>, req_filters_chunk_size: u8) -> Arc<Self> {
         let mut builder = unwrap_or_clone_arc(self);
         builder.inner = builder.inner.req_filters_chunk_size(req_filters_chunk_size);
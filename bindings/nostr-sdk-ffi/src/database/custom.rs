@@ -0,0 +1,216 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+use std::collections::HashSet;
+use std::fmt::Debug;
+use std::ops::Deref;
+use std::sync::Arc;
+
+use nostr_ffi::{Event, EventId, Filter, Timestamp};
+use nostr_sdk::database::{
+    async_trait, Backend, DatabaseError, DatabaseOptions, NostrDatabase as NostrDatabaseSdk,
+};
+use nostr_sdk::{nostr, Url};
+use tokio::task;
+use uniffi::Record;
+
+use crate::error::Result;
+
+/// Negentropy item
+#[derive(Record)]
+pub struct NegentropyItem {
+    pub id: Arc<EventId>,
+    pub timestamp: Arc<Timestamp>,
+}
+
+/// Custom database, implemented in the host language
+///
+/// Methods are called from a blocking thread, so they can perform blocking I/O.
+/// Filters are moved into the new objects, without copies. The event of `save_event` is cloned,
+/// since the SDK only lends it. The returned events are cloned only if still referenced by the host language.
+#[uniffi::export(callback_interface)]
+pub trait CustomNostrDatabase: Send + Sync + Debug {
+    /// Name of the backend
+    fn backend(&self) -> String;
+
+    /// Save [`Event`] into store
+    ///
+    /// Return `true` if event was successfully saved into database.
+    fn save_event(&self, event: Arc<Event>) -> Result<bool>;
+
+    /// Check if [`Event`] has already been saved
+    fn has_event_already_been_saved(&self, event_id: Arc<EventId>) -> Result<bool>;
+
+    /// Check if [`EventId`] has already been seen
+    fn has_event_already_been_seen(&self, event_id: Arc<EventId>) -> Result<bool>;
+
+    /// Set [`EventId`] as seen by relay
+    fn event_id_seen(&self, event_id: Arc<EventId>, relay_url: String) -> Result<()>;
+
+    /// Get list of relays that have seen the [`EventId`]
+    fn event_seen_on_relays(&self, event_id: Arc<EventId>) -> Result<Option<Vec<String>>>;
+
+    /// Get [`Event`] by [`EventId`]
+    fn event_by_id(&self, event_id: Arc<EventId>) -> Result<Arc<Event>>;
+
+    /// Count number of [`Event`] found by filters
+    fn count(&self, filters: Vec<Arc<Filter>>) -> Result<u64>;
+
+    /// Query store with filters
+    fn query(&self, filters: Vec<Arc<Filter>>) -> Result<Vec<Arc<Event>>>;
+
+    /// Get event IDs by filters
+    fn event_ids_by_filters(&self, filters: Vec<Arc<Filter>>) -> Result<Vec<Arc<EventId>>>;
+
+    /// Get `negentropy` items
+    fn negentropy_items(&self, filter: Arc<Filter>) -> Result<Vec<NegentropyItem>>;
+
+    /// Wipe all data
+    fn wipe(&self) -> Result<()>;
+}
+
+#[derive(Debug)]
+pub(super) struct IntermediateCustomNostrDatabase {
+    inner: Arc<dyn CustomNostrDatabase>,
+}
+
+impl IntermediateCustomNostrDatabase {
+    pub fn new(inner: Box<dyn CustomNostrDatabase>) -> Self {
+        Self {
+            inner: Arc::from(inner),
+        }
+    }
+
+    /// Call the foreign database without blocking the async runtime
+    async fn call<T, F>(&self, f: F) -> Result<T, DatabaseError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&dyn CustomNostrDatabase) -> Result<T> + Send + 'static,
+    {
+        let inner = self.inner.clone();
+        task::spawn_blocking(move || f(inner.as_ref()))
+            .await
+            .map_err(DatabaseError::backend)?
+            .map_err(DatabaseError::backend)
+    }
+}
+
+fn into_filters(filters: Vec<nostr::Filter>) -> Vec<Arc<Filter>> {
+    filters.into_iter().map(|f| Arc::new(f.into())).collect()
+}
+
+/// Cloned only if still referenced by the host language
+fn into_event(event: Arc<Event>) -> nostr::Event {
+    Arc::try_unwrap(event).map_or_else(|e| e.as_ref().deref().clone(), nostr::Event::from)
+}
+
+#[async_trait]
+impl NostrDatabaseSdk for IntermediateCustomNostrDatabase {
+    type Err = DatabaseError;
+
+    fn backend(&self) -> Backend {
+        Backend::Custom(self.inner.backend())
+    }
+
+    fn opts(&self) -> DatabaseOptions {
+        DatabaseOptions::default()
+    }
+
+    async fn save_event(&self, event: &nostr::Event) -> Result<bool, Self::Err> {
+        let event: Arc<Event> = Arc::new(event.clone().into());
+        self.call(move |db| db.save_event(event)).await
+    }
+
+    async fn has_event_already_been_saved(
+        &self,
+        event_id: nostr::EventId,
+    ) -> Result<bool, Self::Err> {
+        self.call(move |db| db.has_event_already_been_saved(Arc::new(event_id.into())))
+            .await
+    }
+
+    async fn has_event_already_been_seen(
+        &self,
+        event_id: nostr::EventId,
+    ) -> Result<bool, Self::Err> {
+        self.call(move |db| db.has_event_already_been_seen(Arc::new(event_id.into())))
+            .await
+    }
+
+    async fn event_id_seen(
+        &self,
+        event_id: nostr::EventId,
+        relay_url: Url,
+    ) -> Result<(), Self::Err> {
+        self.call(move |db| db.event_id_seen(Arc::new(event_id.into()), relay_url.to_string()))
+            .await
+    }
+
+    async fn event_seen_on_relays(
+        &self,
+        event_id: nostr::EventId,
+    ) -> Result<Option<HashSet<Url>>, Self::Err> {
+        let res = self
+            .call(move |db| db.event_seen_on_relays(Arc::new(event_id.into())))
+            .await?;
+        Ok(res.map(|list| {
+            list.into_iter()
+                .filter_map(|u| Url::parse(&u).ok())
+                .collect()
+        }))
+    }
+
+    async fn event_by_id(&self, event_id: nostr::EventId) -> Result<nostr::Event, Self::Err> {
+        let event = self
+            .call(move |db| db.event_by_id(Arc::new(event_id.into())))
+            .await?;
+        Ok(into_event(event))
+    }
+
+    async fn count(&self, filters: Vec<nostr::Filter>) -> Result<usize, Self::Err> {
+        let filters = into_filters(filters);
+        let count = self.call(move |db| db.count(filters)).await?;
+        Ok(count as usize)
+    }
+
+    async fn query(&self, filters: Vec<nostr::Filter>) -> Result<Vec<nostr::Event>, Self::Err> {
+        let filters = into_filters(filters);
+        Ok(self
+            .call(move |db| db.query(filters))
+            .await?
+            .into_iter()
+            .map(into_event)
+            .collect())
+    }
+
+    async fn event_ids_by_filters(
+        &self,
+        filters: Vec<nostr::Filter>,
+    ) -> Result<Vec<nostr::EventId>, Self::Err> {
+        let filters = into_filters(filters);
+        Ok(self
+            .call(move |db| db.event_ids_by_filters(filters))
+            .await?
+            .into_iter()
+            .map(|id| **id)
+            .collect())
+    }
+
+    async fn negentropy_items(
+        &self,
+        filter: nostr::Filter,
+    ) -> Result<Vec<(nostr::EventId, nostr::Timestamp)>, Self::Err> {
+        let filter: Arc<Filter> = Arc::new(filter.into());
+        Ok(self
+            .call(move |db| db.negentropy_items(filter))
+            .await?
+            .into_iter()
+            .map(|item| (**item.id, **item.timestamp))
+            .collect())
+    }
+
+    async fn wipe(&self) -> Result<(), Self::Err> {
+        self.call(|db| db.wipe()).await
+    }
+}
@@ -0,0 +1,193 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::sync::Arc;
+
+use nostr_ffi::helper::unwrap_or_clone_arc;
+use nostr_ffi::{Event, EventId, Filter, Metadata, PublicKey};
+use nostr_sdk::database::{
+    DatabaseOptions, DynNostrDatabase, IntoNostrDatabase, MemoryDatabase, NostrDatabaseExt,
+};
+use nostr_sdk::SQLiteDatabase;
+use uniffi::Object;
+
+mod custom;
+
+use self::custom::IntermediateCustomNostrDatabase;
+pub use self::custom::{CustomNostrDatabase, NegentropyItem};
+use crate::error::Result;
+
+#[derive(Object)]
+pub struct NostrDatabase {
+    inner: Arc<DynNostrDatabase>,
+}
+
+impl From<Arc<DynNostrDatabase>> for NostrDatabase {
+    fn from(inner: Arc<DynNostrDatabase>) -> Self {
+        Self { inner }
+    }
+}
+
+impl From<&NostrDatabase> for Arc<DynNostrDatabase> {
+    fn from(db: &NostrDatabase) -> Self {
+        db.inner.clone()
+    }
+}
+
+/// Filters are cloned only if still referenced by the host language
+fn into_filters(filters: Vec<Arc<Filter>>) -> Vec<nostr_sdk::Filter> {
+    filters
+        .into_iter()
+        .map(|f| unwrap_or_clone_arc(f).into())
+        .collect()
+}
+
+#[uniffi::export(async_runtime = "tokio")]
+impl NostrDatabase {
+    #[uniffi::constructor]
+    pub async fn sqlite(path: String) -> Result<Arc<Self>> {
+        let db = Arc::new(SQLiteDatabase::open(path).await?);
+        Ok(Arc::new(Self {
+            inner: db.into_nostr_database(),
+        }))
+    }
+
+    /// In-memory database
+    #[uniffi::constructor]
+    pub fn memory() -> Arc<Self> {
+        let db = MemoryDatabase::new(DatabaseOptions::default());
+        Arc::new(Self {
+            inner: db.into_nostr_database(),
+        })
+    }
+
+    /// Database implemented in the host language
+    #[uniffi::constructor]
+    pub fn custom(database: Box<dyn CustomNostrDatabase>) -> Arc<Self> {
+        let db = IntermediateCustomNostrDatabase::new(database);
+        Arc::new(Self {
+            inner: db.into_nostr_database(),
+        })
+    }
+
+    /// Save [`Event`] into store
+    ///
+    /// Return `true` if event was successfully saved into database.
+    pub async fn save_event(&self, event: Arc<Event>) -> Result<bool> {
+        Ok(self.inner.save_event(event.as_ref().deref()).await?)
+    }
+
+    /// Check if [`Event`] has already been saved
+    pub async fn has_event_already_been_saved(&self, event_id: Arc<EventId>) -> Result<bool> {
+        Ok(self.inner.has_event_already_been_saved(**event_id).await?)
+    }
+
+    /// Check if [`EventId`] has already been seen
+    pub async fn has_event_already_been_seen(&self, event_id: Arc<EventId>) -> Result<bool> {
+        Ok(self.inner.has_event_already_been_seen(**event_id).await?)
+    }
+
+    /// Set [`EventId`] as seen by relay
+    pub async fn event_id_seen(&self, event_id: Arc<EventId>, relay_url: String) -> Result<()> {
+        let relay_url = nostr_sdk::Url::parse(&relay_url)?;
+        Ok(self.inner.event_id_seen(**event_id, relay_url).await?)
+    }
+
+    /// Get list of relays that have seen the [`EventId`]
+    pub async fn event_seen_on_relays(
+        &self,
+        event_id: Arc<EventId>,
+    ) -> Result<Option<Vec<String>>> {
+        let res = self.inner.event_seen_on_relays(**event_id).await?;
+        Ok(res.map(|set| set.into_iter().map(|u| u.to_string()).collect()))
+    }
+
+    /// Get [`Event`] by [`EventId`]
+    pub async fn event_by_id(&self, event_id: Arc<EventId>) -> Result<Arc<Event>> {
+        Ok(Arc::new(self.inner.event_by_id(**event_id).await?.into()))
+    }
+
+    /// Count number of [`Event`] found by filters
+    pub async fn count(&self, filters: Vec<Arc<Filter>>) -> Result<u64> {
+        Ok(self.inner.count(into_filters(filters)).await? as u64)
+    }
+
+    /// Query store with filters
+    pub async fn query(&self, filters: Vec<Arc<Filter>>) -> Result<Vec<Arc<Event>>> {
+        Ok(self
+            .inner
+            .query(into_filters(filters))
+            .await?
+            .into_iter()
+            .map(|e| Arc::new(e.into()))
+            .collect())
+    }
+
+    /// Get event IDs by filters
+    pub async fn event_ids_by_filters(
+        &self,
+        filters: Vec<Arc<Filter>>,
+    ) -> Result<Vec<Arc<EventId>>> {
+        Ok(self
+            .inner
+            .event_ids_by_filters(into_filters(filters))
+            .await?
+            .into_iter()
+            .map(|id| Arc::new(id.into()))
+            .collect())
+    }
+
+    /// Get `negentropy` items
+    pub async fn negentropy_items(&self, filter: Arc<Filter>) -> Result<Vec<NegentropyItem>> {
+        Ok(self
+            .inner
+            .negentropy_items(unwrap_or_clone_arc(filter).into())
+            .await?
+            .into_iter()
+            .map(|(id, timestamp)| NegentropyItem {
+                id: Arc::new(id.into()),
+                timestamp: Arc::new(timestamp.into()),
+            })
+            .collect())
+    }
+
+    /// Wipe all data
+    pub async fn wipe(&self) -> Result<()> {
+        Ok(self.inner.wipe().await?)
+    }
+
+    pub async fn profile(&self, public_key: Arc<PublicKey>) -> Result<Arc<Metadata>> {
+        Ok(Arc::new(self.inner.profile(**public_key).await?.into()))
+    }
+
+    /// Get contact list public keys
+    pub async fn contacts_public_keys(
+        &self,
+        public_key: Arc<PublicKey>,
+    ) -> Result<Vec<Arc<PublicKey>>> {
+        Ok(self
+            .inner
+            .contacts_public_keys(**public_key)
+            .await?
+            .into_iter()
+            .map(|p| Arc::new(p.into()))
+            .collect())
+    }
+
+    /// Get contact list with metadata, by hex public key
+    pub async fn contacts(
+        &self,
+        public_key: Arc<PublicKey>,
+    ) -> Result<HashMap<String, Arc<Metadata>>> {
+        Ok(self
+            .inner
+            .contacts(**public_key)
+            .await?
+            .into_iter()
+            .map(|(p, m)| (p.to_string(), Arc::new(m.into())))
+            .collect())
+    }
+}
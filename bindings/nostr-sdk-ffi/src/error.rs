@@ -25,6 +25,12 @@ impl fmt::Display for NostrSdkError {
     }
 }
 
+impl From<uniffi::UnexpectedUniFFICallbackError> for NostrSdkError {
+    fn from(e: uniffi::UnexpectedUniFFICallbackError) -> NostrSdkError {
+        Self::Generic { err: e.reason }
+    }
+}
+
 impl From<nostr_ffi::NostrError> for NostrSdkError {
     fn from(e: nostr_ffi::NostrError) -> NostrSdkError {
         Self::Generic { err: e.to_string() }
@@ -11,16 +11,17 @@ mod database;
 mod error;
 mod logger;
 mod relay;
-mod thread;
 
 trait FromResult<T>: Sized {
     fn from_result(_: T) -> error::Result<Self>;
 }
 
 pub use crate::client::{Client, ClientBuilder, HandleNotification, Options};
-pub use crate::database::NostrDatabase;
+pub use crate::database::{CustomNostrDatabase, NegentropyItem, NostrDatabase};
 pub use crate::error::NostrSdkError;
 pub use crate::logger::{init_logger, LogLevel};
-pub use crate::relay::{ActiveSubscription, Relay, RelayConnectionStats, RelayStatus};
+pub use crate::relay::{
+    ActiveSubscription, Reconciliation, Relay, RelayConnectionStats, RelayStatus,
+};
 
 uniffi::setup_scaffolding!("nostr_sdk");
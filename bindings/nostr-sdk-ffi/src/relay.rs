@@ -6,9 +6,9 @@ use std::sync::Arc;
 use std::time::Duration;
 use std::{collections::HashMap, ops::Deref};
 
-use nostr_ffi::{ClientMessage, Event, Filter, RelayInformationDocument, Timestamp};
-use nostr_sdk::{block_on, relay, FilterOptions};
-use uniffi::{Enum, Object};
+use nostr_ffi::{ClientMessage, Event, EventId, Filter, RelayInformationDocument, Timestamp};
+use nostr_sdk::{relay, FilterOptions, NegentropyOptions};
+use uniffi::{Enum, Object, Record};
 
 use crate::error::Result;
 
@@ -23,7 +23,7 @@ impl From<relay::RelayConnectionStats> for RelayConnectionStats {
     }
 }
 
-#[uniffi::export]
+#[uniffi::export(async_runtime = "tokio")]
 impl RelayConnectionStats {
     pub fn attempts(&self) -> u64 {
         self.inner.attempts() as u64
@@ -50,8 +50,24 @@ impl RelayConnectionStats {
         self.inner.bytes_received() as u64
     }
 
-    pub fn latency(&self) -> Option<Duration> {
-        self.inner.latency_blocking()
+    pub fn wire_bytes_sent(&self) -> u64 {
+        self.inner.wire_bytes_sent() as u64
+    }
+
+    pub fn wire_bytes_received(&self) -> u64 {
+        self.inner.wire_bytes_received() as u64
+    }
+
+    pub fn compression(&self) -> bool {
+        self.inner.compression()
+    }
+
+    pub async fn latency(&self) -> Option<Duration> {
+        self.inner.latency().await
+    }
+
+    pub async fn latencies(&self) -> Vec<Duration> {
+        self.inner.latencies().await
     }
 }
 
@@ -124,7 +140,7 @@ impl From<relay::Relay> for Relay {
     }
 }
 
-#[uniffi::export]
+#[uniffi::export(async_runtime = "tokio")]
 impl Relay {
     pub fn url(&self) -> String {
         self.inner.url().to_string()
@@ -134,27 +150,25 @@ impl Relay {
         self.inner.proxy().map(|p| p.to_string())
     }
 
-    pub fn status(&self) -> RelayStatus {
-        self.inner.status_blocking().into()
+    pub async fn status(&self) -> RelayStatus {
+        self.inner.status().await.into()
     }
 
-    pub fn is_connected(&self) -> bool {
-        block_on(async move { self.inner.is_connected().await })
+    pub async fn is_connected(&self) -> bool {
+        self.inner.is_connected().await
     }
 
-    pub fn document(&self) -> Arc<RelayInformationDocument> {
-        Arc::new(self.inner.document_blocking().into())
+    pub async fn document(&self) -> Arc<RelayInformationDocument> {
+        Arc::new(self.inner.document().await.into())
     }
 
-    pub fn subscriptions(&self) -> HashMap<String, Arc<ActiveSubscription>> {
-        block_on(async move {
-            self.inner
-                .subscriptions()
-                .await
-                .into_iter()
-                .map(|(id, sub)| (id.to_string(), Arc::new(sub.into())))
-                .collect()
-        })
+    pub async fn subscriptions(&self) -> HashMap<String, Arc<ActiveSubscription>> {
+        self.inner
+            .subscriptions()
+            .await
+            .into_iter()
+            .map(|(id, sub)| (id.to_string(), Arc::new(sub.into())))
+            .collect()
     }
 
     // TODO: add opts
@@ -167,59 +181,50 @@ impl Relay {
         self.inner.queue() as u64
     }
 
-    pub fn connect(&self, wait_for_connection: bool) {
-        block_on(self.inner.connect(wait_for_connection))
+    pub async fn connect(&self, wait_for_connection: bool) {
+        self.inner.connect(wait_for_connection).await
     }
 
-    pub fn stop(&self) -> Result<()> {
-        block_on(async move { Ok(self.inner.stop().await?) })
+    pub async fn stop(&self) -> Result<()> {
+        Ok(self.inner.stop().await?)
     }
 
-    pub fn terminate(&self) -> Result<()> {
-        block_on(async move { Ok(self.inner.terminate().await?) })
+    pub async fn terminate(&self) -> Result<()> {
+        Ok(self.inner.terminate().await?)
     }
 
-    pub fn send_msg(&self, msg: ClientMessage, wait: Option<Duration>) -> Result<()> {
-        block_on(async move { Ok(self.inner.send_msg(msg.into(), wait).await?) })
+    pub async fn send_msg(&self, msg: ClientMessage, wait: Option<Duration>) -> Result<()> {
+        Ok(self.inner.send_msg(msg.into(), wait).await?)
     }
 
-    pub fn subscribe(&self, filters: Vec<Arc<Filter>>, wait: Option<Duration>) -> Result<()> {
-        block_on(async move {
-            Ok(self
-                .inner
-                .subscribe(
-                    filters
-                        .into_iter()
-                        .map(|f| f.as_ref().deref().clone())
-                        .collect(),
-                    wait,
-                )
-                .await?)
-        })
+    pub async fn subscribe(&self, filters: Vec<Arc<Filter>>, wait: Option<Duration>) -> Result<()> {
+        let filters = filters
+            .into_iter()
+            .map(|f| f.as_ref().deref().clone())
+            .collect();
+        Ok(self.inner.subscribe(filters, wait).await?)
     }
 
-    pub fn unsubscribe(&self, wait: Option<Duration>) -> Result<()> {
-        block_on(async move { Ok(self.inner.unsubscribe(wait).await?) })
+    pub async fn unsubscribe(&self, wait: Option<Duration>) -> Result<()> {
+        Ok(self.inner.unsubscribe(wait).await?)
     }
 
-    pub fn get_events_of(
+    pub async fn get_events_of(
         &self,
         filters: Vec<Arc<Filter>>,
         timeout: Duration,
     ) -> Result<Vec<Arc<Event>>> {
-        block_on(async move {
-            let filters = filters
-                .into_iter()
-                .map(|f| f.as_ref().deref().clone())
-                .collect();
-            Ok(self
-                .inner
-                .get_events_of(filters, timeout, FilterOptions::ExitOnEOSE)
-                .await?
-                .into_iter()
-                .map(|e| Arc::new(e.into()))
-                .collect())
-        })
+        let filters = filters
+            .into_iter()
+            .map(|f| f.as_ref().deref().clone())
+            .collect();
+        Ok(self
+            .inner
+            .get_events_of(filters, timeout, FilterOptions::ExitOnEOSE)
+            .await?
+            .into_iter()
+            .map(|e| Arc::new(e.into()))
+            .collect())
     }
 
     pub fn req_events_of(&self, filters: Vec<Arc<Filter>>, timeout: Duration) {
@@ -230,4 +235,73 @@ impl Relay {
         self.inner
             .req_events_of(filters, timeout, FilterOptions::ExitOnEOSE);
     }
+
+    /// Negentropy reconciliation
+    pub async fn reconcile(&self, filter: Arc<Filter>) -> Result<Reconciliation> {
+        Ok(self
+            .inner
+            .reconcile(
+                filter.as_ref().deref().clone(),
+                NegentropyOptions::default(),
+            )
+            .await?
+            .into())
+    }
+
+    pub async fn support_negentropy(&self) -> Result<bool> {
+        Ok(self.inner.support_negentropy().await?)
+    }
+}
+
+/// Negentropy reconciliation report
+#[derive(Record)]
+pub struct Reconciliation {
+    /// Events that the local database have and the relay not
+    pub local: Vec<Arc<EventId>>,
+    /// Events that the relay have and the local database not
+    pub remote: Vec<Arc<EventId>>,
+    /// Events sent to relay (only if bidirectional)
+    pub sent: Vec<Arc<EventId>>,
+    /// Events received from relay
+    pub received: Vec<Arc<EventId>>,
+    /// Errors, by relay
+    pub errors: HashMap<String, String>,
+    /// Number of negentropy rounds
+    pub rounds: u64,
+    /// Reconciliation duration
+    pub duration: Duration,
+}
+
+impl From<relay::Reconciliation> for Reconciliation {
+    fn from(value: relay::Reconciliation) -> Self {
+        Self {
+            local: value
+                .local
+                .into_iter()
+                .map(|id| Arc::new(id.into()))
+                .collect(),
+            remote: value
+                .remote
+                .into_iter()
+                .map(|id| Arc::new(id.into()))
+                .collect(),
+            sent: value
+                .sent
+                .into_iter()
+                .map(|id| Arc::new(id.into()))
+                .collect(),
+            received: value
+                .received
+                .into_iter()
+                .map(|id| Arc::new(id.into()))
+                .collect(),
+            errors: value
+                .errors
+                .into_iter()
+                .map(|(url, e)| (url.to_string(), e))
+                .collect(),
+            rounds: value.rounds as u64,
+            duration: value.duration,
+        }
+    }
 }
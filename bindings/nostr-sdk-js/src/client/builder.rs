@@ -0,0 +1,42 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+use std::ops::Deref;
+
+use nostr_js::JsKeys;
+use nostr_sdk::ClientBuilder;
+use wasm_bindgen::prelude::*;
+
+use super::JsClient;
+use crate::database::JsNostrDatabase;
+
+#[wasm_bindgen(js_name = ClientBuilder)]
+pub struct JsClientBuilder {
+    inner: ClientBuilder,
+}
+
+#[wasm_bindgen(js_class = ClientBuilder)]
+impl JsClientBuilder {
+    /// New client builder
+    #[wasm_bindgen(constructor)]
+    pub fn new(keys: &JsKeys) -> Self {
+        Self {
+            inner: ClientBuilder::new(keys.deref()),
+        }
+    }
+
+    /// Set database
+    #[wasm_bindgen]
+    pub fn database(self, database: &JsNostrDatabase) -> Self {
+        Self {
+            inner: self.inner.database(database.deref().clone()),
+        }
+    }
+
+    /// Build `Client`
+    #[wasm_bindgen]
+    pub fn build(self) -> JsClient {
+        self.inner.build().into()
+    }
+}
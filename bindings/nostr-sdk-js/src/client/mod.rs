@@ -15,6 +15,10 @@ use nostr_js::{
 use nostr_sdk::prelude::*;
 use wasm_bindgen::prelude::*;
 
+mod builder;
+
+pub use self::builder::JsClientBuilder;
+use crate::database::JsNostrDatabase;
 // use crate::relay::JsRelay;
 
 #[wasm_bindgen(js_name = Client)]
@@ -22,6 +26,12 @@ pub struct JsClient {
     inner: Client,
 }
 
+impl From<Client> for JsClient {
+    fn from(inner: Client) -> Self {
+        Self { inner }
+    }
+}
+
 #[wasm_bindgen(js_class = Client)]
 impl JsClient {
     #[wasm_bindgen(constructor)]
@@ -43,6 +53,12 @@ impl JsClient {
         self.inner.keys().await.into()
     }
 
+    /// Get database
+    #[wasm_bindgen(getter)]
+    pub fn database(&self) -> JsNostrDatabase {
+        self.inner.database().into()
+    }
+
     /// Completely shutdown `Client`
     #[wasm_bindgen]
     pub async fn shutdown(&self) -> Result<()> {
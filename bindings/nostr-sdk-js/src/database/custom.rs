@@ -0,0 +1,265 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+use std::collections::HashSet;
+use std::fmt;
+
+use js_sys::{Array, Promise};
+use nostr_js::util;
+use nostr_js::{JsEvent, JsEventId, JsFilter};
+use nostr_sdk::database::{async_trait, Backend, DatabaseError, DatabaseOptions, NostrDatabase};
+use nostr_sdk::{Event, EventId, Filter, Timestamp, Url};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+
+#[wasm_bindgen(typescript_custom_section)]
+const CUSTOM_NOSTR_DATABASE: &str = r#"
+interface CustomNostrDatabase {
+    backend(): string;
+    saveEvent(event: Event): Promise<boolean>;
+    hasEventAlreadyBeenSaved(eventId: EventId): Promise<boolean>;
+    hasEventAlreadyBeenSeen(eventId: EventId): Promise<boolean>;
+    eventIdSeen(eventId: EventId, relayUrl: string): Promise<void>;
+    eventSeenOnRelays(eventId: EventId): Promise<string[] | undefined>;
+    eventById(eventId: EventId): Promise<Event>;
+    count(filters: Filter[]): Promise<number>;
+    query(filters: Filter[]): Promise<Event[]>;
+    eventIdsByFilters(filters: Filter[]): Promise<EventId[]>;
+    negentropyItems(filter: Filter): Promise<[EventId, number][]>;
+    wipe(): Promise<void>;
+}
+"#;
+
+#[wasm_bindgen]
+extern "C" {
+    /// Database implemented in JavaScript
+    ///
+    /// Events, event IDs and filters are passed as objects, without any serialization.
+    #[wasm_bindgen(typescript_type = "CustomNostrDatabase")]
+    pub type JsCustomNostrDatabase;
+
+    #[wasm_bindgen(method)]
+    fn backend(this: &JsCustomNostrDatabase) -> String;
+
+    #[wasm_bindgen(method, js_name = saveEvent)]
+    fn save_event(this: &JsCustomNostrDatabase, event: JsEvent) -> Promise;
+
+    #[wasm_bindgen(method, js_name = hasEventAlreadyBeenSaved)]
+    fn has_event_already_been_saved(this: &JsCustomNostrDatabase, event_id: JsEventId) -> Promise;
+
+    #[wasm_bindgen(method, js_name = hasEventAlreadyBeenSeen)]
+    fn has_event_already_been_seen(this: &JsCustomNostrDatabase, event_id: JsEventId) -> Promise;
+
+    #[wasm_bindgen(method, js_name = eventIdSeen)]
+    fn event_id_seen(
+        this: &JsCustomNostrDatabase,
+        event_id: JsEventId,
+        relay_url: String,
+    ) -> Promise;
+
+    #[wasm_bindgen(method, js_name = eventSeenOnRelays)]
+    fn event_seen_on_relays(this: &JsCustomNostrDatabase, event_id: JsEventId) -> Promise;
+
+    #[wasm_bindgen(method, js_name = eventById)]
+    fn event_by_id(this: &JsCustomNostrDatabase, event_id: JsEventId) -> Promise;
+
+    #[wasm_bindgen(method)]
+    fn count(this: &JsCustomNostrDatabase, filters: Array) -> Promise;
+
+    #[wasm_bindgen(method)]
+    fn query(this: &JsCustomNostrDatabase, filters: Array) -> Promise;
+
+    #[wasm_bindgen(method, js_name = eventIdsByFilters)]
+    fn event_ids_by_filters(this: &JsCustomNostrDatabase, filters: Array) -> Promise;
+
+    #[wasm_bindgen(method, js_name = negentropyItems)]
+    fn negentropy_items(this: &JsCustomNostrDatabase, filter: JsFilter) -> Promise;
+
+    #[wasm_bindgen(method)]
+    fn wipe(this: &JsCustomNostrDatabase) -> Promise;
+}
+
+/// Error thrown by a [`JsCustomNostrDatabase`]
+#[derive(Debug)]
+struct JsDatabaseError(String);
+
+impl std::error::Error for JsDatabaseError {}
+
+impl fmt::Display for JsDatabaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<JsValue> for JsDatabaseError {
+    fn from(e: JsValue) -> Self {
+        Self(e.as_string().unwrap_or_else(|| format!("{e:?}")))
+    }
+}
+
+impl From<JsError> for JsDatabaseError {
+    fn from(e: JsError) -> Self {
+        JsValue::from(e).into()
+    }
+}
+
+async fn call(promise: Promise) -> Result<JsValue, DatabaseError> {
+    JsFuture::from(promise)
+        .await
+        .map_err(|e| DatabaseError::backend(JsDatabaseError::from(e)))
+}
+
+fn into_array<T, I>(iter: I) -> Array
+where
+    T: Into<JsValue>,
+    I: IntoIterator<Item = T>,
+{
+    iter.into_iter().map(|v| v.into()).collect()
+}
+
+fn filters_into_array(filters: Vec<Filter>) -> Array {
+    into_array(filters.into_iter().map(JsFilter::from))
+}
+
+fn as_bool(value: JsValue) -> Result<bool, DatabaseError> {
+    value
+        .as_bool()
+        .ok_or_else(|| DatabaseError::backend(JsDatabaseError(String::from("expected a boolean"))))
+}
+
+fn as_event(value: &JsValue) -> Result<Event, DatabaseError> {
+    let event = util::downcast::<JsEvent>(value, "Event")
+        .map_err(|e| DatabaseError::backend(JsDatabaseError::from(e)))?;
+    Ok((**event).clone())
+}
+
+fn as_event_id(value: &JsValue) -> Result<EventId, DatabaseError> {
+    let event_id = util::downcast::<JsEventId>(value, "EventId")
+        .map_err(|e| DatabaseError::backend(JsDatabaseError::from(e)))?;
+    Ok(EventId::from(&*event_id))
+}
+
+pub(super) struct IntermediateCustomNostrDatabase {
+    inner: JsCustomNostrDatabase,
+}
+
+impl fmt::Debug for IntermediateCustomNostrDatabase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IntermediateCustomNostrDatabase")
+            .field("backend", &self.inner.backend())
+            .finish()
+    }
+}
+
+impl IntermediateCustomNostrDatabase {
+    pub fn new(inner: JsCustomNostrDatabase) -> Self {
+        Self { inner }
+    }
+}
+
+#[async_trait(?Send)]
+impl NostrDatabase for IntermediateCustomNostrDatabase {
+    type Err = DatabaseError;
+
+    fn backend(&self) -> Backend {
+        Backend::Custom(self.inner.backend())
+    }
+
+    fn opts(&self) -> DatabaseOptions {
+        DatabaseOptions::default()
+    }
+
+    async fn save_event(&self, event: &Event) -> Result<bool, Self::Err> {
+        let value = call(self.inner.save_event(event.clone().into())).await?;
+        as_bool(value)
+    }
+
+    async fn has_event_already_been_saved(&self, event_id: EventId) -> Result<bool, Self::Err> {
+        let value = call(self.inner.has_event_already_been_saved(event_id.into())).await?;
+        as_bool(value)
+    }
+
+    async fn has_event_already_been_seen(&self, event_id: EventId) -> Result<bool, Self::Err> {
+        let value = call(self.inner.has_event_already_been_seen(event_id.into())).await?;
+        as_bool(value)
+    }
+
+    async fn event_id_seen(&self, event_id: EventId, relay_url: Url) -> Result<(), Self::Err> {
+        call(
+            self.inner
+                .event_id_seen(event_id.into(), relay_url.to_string()),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn event_seen_on_relays(
+        &self,
+        event_id: EventId,
+    ) -> Result<Option<HashSet<Url>>, Self::Err> {
+        let value = call(self.inner.event_seen_on_relays(event_id.into())).await?;
+        if value.is_undefined() || value.is_null() {
+            return Ok(None);
+        }
+        Ok(Some(
+            Array::from(&value)
+                .iter()
+                .filter_map(|v| v.as_string())
+                .filter_map(|u| Url::parse(&u).ok())
+                .collect(),
+        ))
+    }
+
+    async fn event_by_id(&self, event_id: EventId) -> Result<Event, Self::Err> {
+        let value = call(self.inner.event_by_id(event_id.into())).await?;
+        if value.is_undefined() || value.is_null() {
+            return Err(DatabaseError::NotFound);
+        }
+        as_event(&value)
+    }
+
+    async fn count(&self, filters: Vec<Filter>) -> Result<usize, Self::Err> {
+        let value = call(self.inner.count(filters_into_array(filters))).await?;
+        let count = value.as_f64().ok_or_else(|| {
+            DatabaseError::backend(JsDatabaseError(String::from("expected a number")))
+        })?;
+        Ok(count as usize)
+    }
+
+    async fn query(&self, filters: Vec<Filter>) -> Result<Vec<Event>, Self::Err> {
+        let value = call(self.inner.query(filters_into_array(filters))).await?;
+        Array::from(&value).iter().map(|v| as_event(&v)).collect()
+    }
+
+    async fn event_ids_by_filters(&self, filters: Vec<Filter>) -> Result<Vec<EventId>, Self::Err> {
+        let value = call(self.inner.event_ids_by_filters(filters_into_array(filters))).await?;
+        Array::from(&value)
+            .iter()
+            .map(|v| as_event_id(&v))
+            .collect()
+    }
+
+    async fn negentropy_items(
+        &self,
+        filter: Filter,
+    ) -> Result<Vec<(EventId, Timestamp)>, Self::Err> {
+        let value = call(self.inner.negentropy_items(filter.into())).await?;
+        Array::from(&value)
+            .iter()
+            .map(|item| {
+                let item = Array::from(&item);
+                let event_id = as_event_id(&item.get(0))?;
+                let timestamp = item.get(1).as_f64().ok_or_else(|| {
+                    DatabaseError::backend(JsDatabaseError(String::from("expected a timestamp")))
+                })?;
+                Ok((event_id, Timestamp::from(timestamp as u64)))
+            })
+            .collect()
+    }
+
+    async fn wipe(&self) -> Result<(), Self::Err> {
+        call(self.inner.wipe()).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,203 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+use std::ops::Deref;
+use std::sync::Arc;
+
+use js_sys::Array;
+use nostr_js::error::{into_err, Result};
+use nostr_js::util;
+use nostr_js::{JsEvent, JsEventId, JsFilter, JsMetadata, JsPublicKey};
+use nostr_sdk::database::{
+    DatabaseOptions, DynNostrDatabase, IntoNostrDatabase, MemoryDatabase, NostrDatabaseExt,
+};
+use nostr_sdk::{Filter, WebDatabase};
+use wasm_bindgen::prelude::*;
+
+mod custom;
+
+use self::custom::IntermediateCustomNostrDatabase;
+pub use self::custom::JsCustomNostrDatabase;
+
+fn into_filters(filters: Array) -> Result<Vec<Filter>> {
+    Ok(filters
+        .iter()
+        .map(|v| Ok(util::downcast::<JsFilter>(&v, "Filter")?.inner()))
+        .collect::<Result<Vec<Filter>, JsError>>()?)
+}
+
+fn into_array<T, I>(iter: I) -> Array
+where
+    T: Into<JsValue>,
+    I: IntoIterator<Item = T>,
+{
+    iter.into_iter().map(|v| v.into()).collect()
+}
+
+#[wasm_bindgen(js_name = NostrDatabase)]
+pub struct JsNostrDatabase {
+    inner: Arc<DynNostrDatabase>,
+}
+
+impl From<Arc<DynNostrDatabase>> for JsNostrDatabase {
+    fn from(inner: Arc<DynNostrDatabase>) -> Self {
+        Self { inner }
+    }
+}
+
+impl Deref for JsNostrDatabase {
+    type Target = Arc<DynNostrDatabase>;
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+#[wasm_bindgen(js_class = NostrDatabase)]
+impl JsNostrDatabase {
+    /// Open (or create) an IndexedDB database
+    #[wasm_bindgen]
+    pub async fn indexeddb(name: String) -> Result<JsNostrDatabase> {
+        let db = WebDatabase::open(name).await.map_err(into_err)?;
+        Ok(Self {
+            inner: db.into_nostr_database(),
+        })
+    }
+
+    /// In-memory database
+    #[wasm_bindgen]
+    pub fn memory() -> Self {
+        let db = MemoryDatabase::new(DatabaseOptions::default());
+        Self {
+            inner: db.into_nostr_database(),
+        }
+    }
+
+    /// Database implemented in JavaScript
+    #[wasm_bindgen]
+    pub fn custom(database: JsCustomNostrDatabase) -> Self {
+        let db = IntermediateCustomNostrDatabase::new(database);
+        Self {
+            inner: db.into_nostr_database(),
+        }
+    }
+
+    /// Save `Event` into store
+    ///
+    /// Return `true` if event was successfully saved into database.
+    #[wasm_bindgen(js_name = saveEvent)]
+    pub async fn save_event(&self, event: &JsEvent) -> Result<bool> {
+        self.inner.save_event(event.deref()).await.map_err(into_err)
+    }
+
+    /// Check if `Event` has already been saved
+    #[wasm_bindgen(js_name = hasEventAlreadyBeenSaved)]
+    pub async fn has_event_already_been_saved(&self, event_id: &JsEventId) -> Result<bool> {
+        self.inner
+            .has_event_already_been_saved(event_id.into())
+            .await
+            .map_err(into_err)
+    }
+
+    /// Check if `EventId` has already been seen
+    #[wasm_bindgen(js_name = hasEventAlreadyBeenSeen)]
+    pub async fn has_event_already_been_seen(&self, event_id: &JsEventId) -> Result<bool> {
+        self.inner
+            .has_event_already_been_seen(event_id.into())
+            .await
+            .map_err(into_err)
+    }
+
+    /// Get list of relays that have seen the `EventId`
+    #[wasm_bindgen(js_name = eventSeenOnRelays)]
+    pub async fn event_seen_on_relays(&self, event_id: &JsEventId) -> Result<Option<Array>> {
+        let res = self
+            .inner
+            .event_seen_on_relays(event_id.into())
+            .await
+            .map_err(into_err)?;
+        Ok(res.map(|set| into_array(set.into_iter().map(|u| u.to_string()))))
+    }
+
+    /// Get `Event` by `EventId`
+    #[wasm_bindgen(js_name = eventById)]
+    pub async fn event_by_id(&self, event_id: &JsEventId) -> Result<JsEvent> {
+        self.inner
+            .event_by_id(event_id.into())
+            .await
+            .map_err(into_err)
+            .map(|e| e.into())
+    }
+
+    /// Count number of `Event` found by filters
+    #[wasm_bindgen]
+    pub async fn count(&self, filters: Array) -> Result<u64> {
+        let filters = into_filters(filters)?;
+        let count = self.inner.count(filters).await.map_err(into_err)?;
+        Ok(count as u64)
+    }
+
+    /// Query store with filters
+    #[wasm_bindgen]
+    pub async fn query(&self, filters: Array) -> Result<Array> {
+        let filters = into_filters(filters)?;
+        let events = self.inner.query(filters).await.map_err(into_err)?;
+        Ok(into_array(events.into_iter().map(JsEvent::from)))
+    }
+
+    /// Get event IDs by filters
+    #[wasm_bindgen(js_name = eventIdsByFilters)]
+    pub async fn event_ids_by_filters(&self, filters: Array) -> Result<Array> {
+        let filters = into_filters(filters)?;
+        let ids = self
+            .inner
+            .event_ids_by_filters(filters)
+            .await
+            .map_err(into_err)?;
+        Ok(into_array(ids.into_iter().map(JsEventId::from)))
+    }
+
+    /// Get `negentropy` items
+    ///
+    /// Return an array of `[EventId, timestamp]` pairs.
+    #[wasm_bindgen(js_name = negentropyItems)]
+    pub async fn negentropy_items(&self, filter: &JsFilter) -> Result<Array> {
+        let items = self
+            .inner
+            .negentropy_items(filter.into())
+            .await
+            .map_err(into_err)?;
+        Ok(into_array(items.into_iter().map(|(id, timestamp)| {
+            let id: JsValue = JsEventId::from(id).into();
+            let timestamp = JsValue::from_f64(timestamp.as_u64() as f64);
+            Array::of2(&id, &timestamp)
+        })))
+    }
+
+    /// Wipe all data
+    #[wasm_bindgen]
+    pub async fn wipe(&self) -> Result<()> {
+        self.inner.wipe().await.map_err(into_err)
+    }
+
+    /// Get profile metadata
+    #[wasm_bindgen]
+    pub async fn profile(&self, public_key: &JsPublicKey) -> Result<JsMetadata> {
+        self.inner
+            .profile(public_key.into())
+            .await
+            .map_err(into_err)
+            .map(|m| m.into())
+    }
+
+    /// Get contact list public keys
+    #[wasm_bindgen(js_name = contactsPublicKeys)]
+    pub async fn contacts_public_keys(&self, public_key: &JsPublicKey) -> Result<Array> {
+        let public_keys = self
+            .inner
+            .contacts_public_keys(public_key.into())
+            .await
+            .map_err(into_err)?;
+        Ok(into_array(public_keys.into_iter().map(JsPublicKey::from)))
+    }
+}
@@ -11,6 +11,7 @@ use wasm_bindgen::prelude::*;
 pub use nostr_js;
 
 pub mod client;
+pub mod database;
 //pub mod relay;
 
 #[wasm_bindgen(js_name = initLogger)]
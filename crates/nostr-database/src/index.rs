@@ -38,6 +38,8 @@ struct EventIndex {
     event_id: EventId,
     /// Public key prefix
     pubkey: PublicKeyPrefix,
+    /// NIP26 delegator public key prefix
+    delegator: Option<PublicKeyPrefix>,
     /// Kind
     kind: Kind,
     /// Tag indexes
@@ -67,6 +69,7 @@ impl TryFrom<RawEvent> for EventIndex {
             created_at: raw.created_at,
             event_id: EventId::from_slice(&raw.id)?,
             pubkey: PublicKeyPrefix::from(raw.pubkey),
+            delegator: raw.delegator().map(PublicKeyPrefix::from),
             kind: raw.kind,
             tags: TagIndexes::from(raw.tags.into_iter()),
         })
@@ -79,6 +82,7 @@ impl From<&Event> for EventIndex {
             created_at: e.created_at,
             event_id: e.id,
             pubkey: PublicKeyPrefix::from(e.pubkey),
+            delegator: e.delegator().ok().flatten().map(PublicKeyPrefix::from),
             kind: e.kind,
             tags: e.build_tags_index(),
         }
@@ -86,6 +90,17 @@ impl From<&Event> for EventIndex {
 }
 
 impl EventIndex {
+    fn filter_authors_match(&self, filter: &FilterIndex) -> bool {
+        if filter.authors.is_empty() || filter.authors.contains(&self.pubkey) {
+            return true;
+        }
+
+        filter.delegated_authors
+            && self
+                .delegator
+                .map_or(false, |delegator| filter.authors.contains(&delegator))
+    }
+
     fn filter_tags_match(&self, filter: &FilterIndex) -> bool {
         if filter.generic_tags.is_empty() {
             return true;
@@ -139,6 +154,7 @@ struct FilterIndex {
     since: Option<Timestamp>,
     until: Option<Timestamp>,
     generic_tags: HashMap<Alphabet, HashSet<GenericTagValue>>,
+    delegated_authors: bool,
 }
 
 impl FilterIndex {
@@ -181,6 +197,7 @@ impl From<Filter> for FilterIndex {
             since: value.since,
             until: value.until,
             generic_tags: value.generic_tags,
+            delegated_authors: false,
         }
     }
 }
@@ -199,6 +216,7 @@ pub struct EventIndexResult {
 pub struct DatabaseIndexes {
     index: Arc<RwLock<BTreeSet<EventIndex>>>,
     deleted: Arc<RwLock<HashSet<EventId>>>,
+    delegated_authors: bool,
 }
 
 impl DatabaseIndexes {
@@ -207,6 +225,22 @@ impl DatabaseIndexes {
         Self::default()
     }
 
+    /// Include NIP26 delegated events in queries by author (default: false)
+    ///
+    /// Events are matched by delegator only if the delegation tag is valid.
+    pub fn delegated_authors(self, enable: bool) -> Self {
+        Self {
+            delegated_authors: enable,
+            ..self
+        }
+    }
+
+    fn filter_index(&self, filter: Filter) -> FilterIndex {
+        let mut filter: FilterIndex = FilterIndex::from(filter);
+        filter.delegated_authors = self.delegated_authors;
+        filter
+    }
+
     /// Bulk index
     #[tracing::instrument(skip_all)]
     pub async fn bulk_index(&self, events: BTreeSet<RawEvent>) -> HashSet<EventId> {
@@ -321,6 +355,7 @@ impl DatabaseIndexes {
                 created_at: raw.created_at,
                 event_id,
                 pubkey: pubkey_prefix,
+                delegator: raw.delegator().map(PublicKeyPrefix::from),
                 kind: raw.kind,
                 tags: TagIndexes::from(raw.tags.into_iter()),
             });
@@ -456,7 +491,7 @@ impl DatabaseIndexes {
                 && filter.until.map_or(true, |t| m.created_at <= t)
                 && filter.since.map_or(true, |t| m.created_at >= t)
                 && (filter.ids.is_empty() || filter.ids.contains(&m.event_id))
-                && (filter.authors.is_empty() || filter.authors.contains(&m.pubkey))
+                && m.filter_authors_match(&filter)
                 && (filter.kinds.is_empty() || filter.kinds.contains(&m.kind))
                 && m.filter_tags_match(&filter)
         })
@@ -528,7 +563,9 @@ impl DatabaseIndexes {
                 }
             }
 
-            if let Some(limit) = filter.limit {
+            let limit: Option<usize> = filter.limit;
+            let filter: FilterIndex = self.filter_index(filter);
+            if let Some(limit) = limit {
                 matching_ids.par_extend(
                     self.internal_query(&index, &deleted, filter)
                         .take(limit)
@@ -566,6 +603,7 @@ impl DatabaseIndexes {
             }
 
             let limit: Option<usize> = filter.limit;
+            let filter: FilterIndex = self.filter_index(filter);
             let count = self
                 .internal_parallel_query(&index, &deleted, filter)
                 .count();
@@ -706,4 +744,41 @@ mod tests {
             indexes.count([Filter::new()]).await
         );
     }
+
+    #[tokio::test]
+    async fn test_database_indexes_delegated_authors() {
+        use std::str::FromStr;
+
+        use nostr::nips::nip26::{Conditions, DelegationTag};
+
+        let delegator_keys = Keys::new(SecretKey::from_bech32(SECRET_KEY_A).unwrap());
+        let delegatee_keys = Keys::new(SecretKey::from_bech32(SECRET_KEY_B).unwrap());
+        let conditions = Conditions::from_str("kind=1").unwrap();
+        let tag =
+            DelegationTag::new(&delegator_keys, delegatee_keys.public_key(), conditions).unwrap();
+
+        let events = [
+            EventBuilder::new_text_note("Delegated", [Tag::from(tag.clone())])
+                .to_event(&delegatee_keys)
+                .unwrap(),
+            // Delegation conditions not satisfied
+            EventBuilder::new(Kind::Reaction, "+", [Tag::from(tag)])
+                .to_event(&delegatee_keys)
+                .unwrap(),
+        ];
+
+        let filter = Filter::new().author(delegator_keys.public_key());
+
+        let indexes = DatabaseIndexes::new();
+        for event in events.iter() {
+            indexes.index_event(event).await;
+        }
+        assert_eq!(indexes.count([filter.clone()]).await, 0);
+
+        let indexes = DatabaseIndexes::new().delegated_authors(true);
+        for event in events.iter() {
+            indexes.index_event(event).await;
+        }
+        assert_eq!(indexes.query([filter]).await, vec![events[0].id]);
+    }
 }
@@ -23,6 +23,7 @@ pub mod flatbuffers;
 pub mod index;
 pub mod memory;
 mod options;
+pub mod outbox;
 mod raw;
 
 pub use self::error::DatabaseError;
@@ -31,6 +32,7 @@ pub use self::flatbuffers::{FlatBufferBuilder, FlatBufferDecode, FlatBufferEncod
 pub use self::index::{DatabaseIndexes, EventIndexResult};
 pub use self::memory::MemoryDatabase;
 pub use self::options::DatabaseOptions;
+pub use self::outbox::OutboxEvent;
 pub use self::raw::RawEvent;
 
 /// Backend
@@ -143,6 +145,42 @@ pub trait NostrDatabase: AsyncTraitDeps {
         filter: Filter,
     ) -> Result<Vec<(EventId, Timestamp)>, Self::Err>;
 
+    /// Save relay score
+    ///
+    /// Used to keep relay scores between runs.
+    async fn save_relay_score(&self, relay_url: Url, score: f64) -> Result<(), Self::Err> {
+        let _ = (relay_url, score);
+        Err(DatabaseError::NotSupported.into())
+    }
+
+    /// Get relay scores
+    async fn relay_scores(&self) -> Result<HashMap<Url, f64>, Self::Err> {
+        Err(DatabaseError::NotSupported.into())
+    }
+
+    /// Save [`OutboxEvent`]
+    ///
+    /// If an item with the same event ID and relay URL already exists, it's replaced.
+    async fn save_outbox_event(&self, item: OutboxEvent) -> Result<(), Self::Err> {
+        let _ = item;
+        Err(DatabaseError::NotSupported.into())
+    }
+
+    /// Get all [`OutboxEvent`]
+    async fn outbox_events(&self) -> Result<Vec<OutboxEvent>, Self::Err> {
+        Err(DatabaseError::NotSupported.into())
+    }
+
+    /// Remove [`OutboxEvent`]
+    async fn remove_outbox_event(
+        &self,
+        event_id: EventId,
+        relay_url: Url,
+    ) -> Result<(), Self::Err> {
+        let _ = (event_id, relay_url);
+        Err(DatabaseError::NotSupported.into())
+    }
+
     /// Wipe all data
     async fn wipe(&self) -> Result<(), Self::Err>;
 }
@@ -310,6 +348,36 @@ impl<T: NostrDatabase> NostrDatabase for EraseNostrDatabaseError<T> {
         self.0.negentropy_items(filter).await.map_err(Into::into)
     }
 
+    async fn save_relay_score(&self, relay_url: Url, score: f64) -> Result<(), Self::Err> {
+        self.0
+            .save_relay_score(relay_url, score)
+            .await
+            .map_err(Into::into)
+    }
+
+    async fn relay_scores(&self) -> Result<HashMap<Url, f64>, Self::Err> {
+        self.0.relay_scores().await.map_err(Into::into)
+    }
+
+    async fn save_outbox_event(&self, item: OutboxEvent) -> Result<(), Self::Err> {
+        self.0.save_outbox_event(item).await.map_err(Into::into)
+    }
+
+    async fn outbox_events(&self) -> Result<Vec<OutboxEvent>, Self::Err> {
+        self.0.outbox_events().await.map_err(Into::into)
+    }
+
+    async fn remove_outbox_event(
+        &self,
+        event_id: EventId,
+        relay_url: Url,
+    ) -> Result<(), Self::Err> {
+        self.0
+            .remove_outbox_event(event_id, relay_url)
+            .await
+            .map_err(Into::into)
+    }
+
     async fn wipe(&self) -> Result<(), Self::Err> {
         self.0.wipe().await.map_err(Into::into)
     }
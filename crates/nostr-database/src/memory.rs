@@ -13,6 +13,7 @@ use tokio::sync::RwLock;
 
 use crate::{
     Backend, DatabaseError, DatabaseIndexes, DatabaseOptions, EventIndexResult, NostrDatabase,
+    OutboxEvent,
 };
 
 /// Memory Database (RAM)
@@ -21,6 +22,8 @@ pub struct MemoryDatabase {
     opts: DatabaseOptions,
     seen_event_ids: Arc<RwLock<HashMap<EventId, HashSet<Url>>>>,
     events: Arc<RwLock<HashMap<EventId, Event>>>,
+    relay_scores: Arc<RwLock<HashMap<Url, f64>>>,
+    outbox: Arc<RwLock<HashMap<(EventId, Url), OutboxEvent>>>,
     indexes: DatabaseIndexes,
 }
 
@@ -28,7 +31,10 @@ pub struct MemoryDatabase {
 
 impl Default for MemoryDatabase {
     fn default() -> Self {
-        Self::new(DatabaseOptions { events: false })
+        Self::new(DatabaseOptions {
+            events: false,
+            ..Default::default()
+        })
     }
 }
 
@@ -39,7 +45,9 @@ impl MemoryDatabase {
             opts,
             seen_event_ids: Arc::new(RwLock::new(HashMap::new())),
             events: Arc::new(RwLock::new(HashMap::new())),
-            indexes: DatabaseIndexes::new(),
+            relay_scores: Arc::new(RwLock::new(HashMap::new())),
+            outbox: Arc::new(RwLock::new(HashMap::new())),
+            indexes: DatabaseIndexes::new().delegated_authors(opts.delegated_authors),
         }
     }
 
@@ -178,9 +186,42 @@ impl NostrDatabase for MemoryDatabase {
 
     async fn negentropy_items(
         &self,
-        _filter: Filter,
+        filter: Filter,
     ) -> Result<Vec<(EventId, Timestamp)>, Self::Err> {
-        Err(DatabaseError::NotSupported)
+        let events: Vec<Event> = self.query(vec![filter]).await?;
+        Ok(events.into_iter().map(|e| (e.id, e.created_at)).collect())
+    }
+
+    async fn save_relay_score(&self, relay_url: Url, score: f64) -> Result<(), Self::Err> {
+        let mut relay_scores = self.relay_scores.write().await;
+        relay_scores.insert(relay_url, score);
+        Ok(())
+    }
+
+    async fn relay_scores(&self) -> Result<HashMap<Url, f64>, Self::Err> {
+        let relay_scores = self.relay_scores.read().await;
+        Ok(relay_scores.clone())
+    }
+
+    async fn save_outbox_event(&self, item: OutboxEvent) -> Result<(), Self::Err> {
+        let mut outbox = self.outbox.write().await;
+        outbox.insert((item.event.id, item.relay_url.clone()), item);
+        Ok(())
+    }
+
+    async fn outbox_events(&self) -> Result<Vec<OutboxEvent>, Self::Err> {
+        let outbox = self.outbox.read().await;
+        Ok(outbox.values().cloned().collect())
+    }
+
+    async fn remove_outbox_event(
+        &self,
+        event_id: EventId,
+        relay_url: Url,
+    ) -> Result<(), Self::Err> {
+        let mut outbox = self.outbox.write().await;
+        outbox.remove(&(event_id, relay_url));
+        Ok(())
     }
 
     async fn wipe(&self) -> Result<(), Self::Err> {
@@ -188,6 +229,10 @@ impl NostrDatabase for MemoryDatabase {
         seen_event_ids.clear();
         let mut events = self.events.write().await;
         events.clear();
+        let mut relay_scores = self.relay_scores.write().await;
+        relay_scores.clear();
+        let mut outbox = self.outbox.write().await;
+        outbox.clear();
         Ok(())
     }
 }
@@ -9,11 +9,16 @@
 pub struct DatabaseOptions {
     /// Store events (?)
     pub events: bool,
+    /// Include NIP26 delegated events in queries by author
+    pub delegated_authors: bool,
 }
 
 impl Default for DatabaseOptions {
     fn default() -> Self {
-        Self { events: true }
+        Self {
+            events: true,
+            delegated_authors: false,
+        }
     }
 }
 
@@ -0,0 +1,34 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Outbox
+
+use nostr::{Event, Timestamp, Url};
+
+/// Event waiting to be sent to a relay
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OutboxEvent {
+    /// Event
+    pub event: Event,
+    /// Target relay
+    pub relay_url: Url,
+    /// Stop to retry after this timestamp
+    pub expiration: Timestamp,
+}
+
+impl OutboxEvent {
+    /// New outbox event
+    pub fn new(event: Event, relay_url: Url, expiration: Timestamp) -> Self {
+        Self {
+            event,
+            relay_url,
+            expiration,
+        }
+    }
+
+    /// Check if expired
+    pub fn is_expired(&self) -> bool {
+        self.expiration < Timestamp::now()
+    }
+}
@@ -8,6 +8,7 @@ use core::cmp::Ordering;
 use core::str::FromStr;
 
 use nostr::nips::nip01::Coordinate;
+use nostr::nips::nip26::{DelegationTag, EventProperties};
 use nostr::secp256k1::XOnlyPublicKey;
 use nostr::{Event, EventId, Kind, Timestamp};
 
@@ -71,6 +72,21 @@ impl RawEvent {
         None
     }
 
+    /// Extract NIP26 delegator, if the delegation tag is valid
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/26.md>
+    pub fn delegator(&self) -> Option<XOnlyPublicKey> {
+        let tag = self
+            .tags
+            .iter()
+            .find(|tag| tag.first().map(|x| x.as_str()) == Some("delegation"))?;
+        let tag = DelegationTag::try_from(tag.clone()).ok()?;
+        let pubkey = XOnlyPublicKey::from_slice(&self.pubkey).ok()?;
+        let properties = EventProperties::new(self.kind.as_u64(), self.created_at.as_u64());
+        tag.validate(pubkey, &properties).ok()?;
+        Some(tag.delegator_pubkey())
+    }
+
     /// Extract public keys from tags (`p` tag)
     pub fn public_keys(&self) -> impl Iterator<Item = XOnlyPublicKey> + '_ {
         self.tags.iter().filter_map(|tag| {
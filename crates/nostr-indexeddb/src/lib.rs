@@ -58,6 +58,7 @@ pub struct OngoingMigration {
 #[derive(Clone)]
 pub struct WebDatabase {
     db: Arc<IdbDatabase>,
+    opts: DatabaseOptions,
     indexes: DatabaseIndexes,
     fbb: Arc<Mutex<FlatBufferBuilder<'static>>>,
 }
@@ -73,12 +74,21 @@ impl fmt::Debug for WebDatabase {
 impl WebDatabase {
     /// Open IndexedDB store
     pub async fn open<S>(name: S) -> Result<Self, IndexedDBError>
+    where
+        S: AsRef<str>,
+    {
+        Self::open_with_opts(name, DatabaseOptions::default()).await
+    }
+
+    /// Open IndexedDB store with [`DatabaseOptions`]
+    pub async fn open_with_opts<S>(name: S, opts: DatabaseOptions) -> Result<Self, IndexedDBError>
     where
         S: AsRef<str>,
     {
         let mut this = Self {
             db: Arc::new(IdbDatabase::open(name.as_ref())?.into_future().await?),
-            indexes: DatabaseIndexes::new(),
+            opts,
+            indexes: DatabaseIndexes::new().delegated_authors(opts.delegated_authors),
             fbb: Arc::new(Mutex::new(FlatBufferBuilder::with_capacity(70_000))),
         };
 
@@ -250,7 +260,7 @@ impl_nostr_database!({
     }
 
     fn opts(&self) -> DatabaseOptions {
-        DatabaseOptions::default()
+        self.opts
     }
 
     #[tracing::instrument(skip_all, level = "trace")]
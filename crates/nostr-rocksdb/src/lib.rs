@@ -8,7 +8,7 @@
 #![warn(missing_docs)]
 #![warn(rustdoc::bare_urls)]
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
 use std::sync::Arc;
 
@@ -19,7 +19,7 @@ use async_trait::async_trait;
 use nostr::{Event, EventId, Filter, Timestamp, Url};
 use nostr_database::{
     Backend, DatabaseError, DatabaseIndexes, DatabaseOptions, EventIndexResult, FlatBufferBuilder,
-    FlatBufferDecode, FlatBufferEncode, NostrDatabase, RawEvent,
+    FlatBufferDecode, FlatBufferEncode, NostrDatabase, OutboxEvent, RawEvent,
 };
 use rocksdb::{
     BoundColumnFamily, ColumnFamilyDescriptor, DBCompactionStyle, DBCompressionType, IteratorMode,
@@ -31,11 +31,14 @@ mod ops;
 
 const EVENTS_CF: &str = "events";
 const EVENTS_SEEN_BY_RELAYS_CF: &str = "event-seen-by-relays";
+const RELAY_SCORES_CF: &str = "relay-scores";
+const OUTBOX_CF: &str = "outbox";
 
 /// RocksDB Nostr Database
 #[derive(Debug, Clone)]
 pub struct RocksDatabase {
     db: Arc<OptimisticTransactionDB>,
+    opts: DatabaseOptions,
     indexes: DatabaseIndexes,
     fbb: Arc<RwLock<FlatBufferBuilder<'static>>>,
 }
@@ -64,12 +67,28 @@ fn column_families() -> Vec<ColumnFamilyDescriptor> {
     vec![
         ColumnFamilyDescriptor::new(EVENTS_CF, default_opts()),
         ColumnFamilyDescriptor::new(EVENTS_SEEN_BY_RELAYS_CF, relay_urls_opts),
+        ColumnFamilyDescriptor::new(RELAY_SCORES_CF, default_opts()),
+        ColumnFamilyDescriptor::new(OUTBOX_CF, default_opts()),
     ]
 }
 
+fn outbox_key(event_id: &EventId, relay_url: &Url) -> Vec<u8> {
+    let mut key: Vec<u8> = event_id.as_bytes().to_vec();
+    key.extend_from_slice(relay_url.as_str().as_bytes());
+    key
+}
+
 impl RocksDatabase {
     /// Open RocksDB store
     pub async fn open<P>(path: P) -> Result<Self, DatabaseError>
+    where
+        P: AsRef<Path>,
+    {
+        Self::open_with_opts(path, DatabaseOptions::default()).await
+    }
+
+    /// Open RocksDB store with [`DatabaseOptions`]
+    pub async fn open_with_opts<P>(path: P, opts: DatabaseOptions) -> Result<Self, DatabaseError>
     where
         P: AsRef<Path>,
     {
@@ -97,7 +116,8 @@ impl RocksDatabase {
 
         let this = Self {
             db: Arc::new(db),
-            indexes: DatabaseIndexes::new(),
+            opts,
+            indexes: DatabaseIndexes::new().delegated_authors(opts.delegated_authors),
             fbb: Arc::new(RwLock::new(FlatBufferBuilder::with_capacity(70_000))),
         };
 
@@ -150,7 +170,7 @@ impl NostrDatabase for RocksDatabase {
     }
 
     fn opts(&self) -> DatabaseOptions {
-        DatabaseOptions::default()
+        self.opts
     }
 
     #[tracing::instrument(skip_all, level = "trace")]
@@ -319,6 +339,73 @@ impl NostrDatabase for RocksDatabase {
         .map_err(DatabaseError::backend)?
     }
 
+    async fn save_relay_score(&self, relay_url: Url, score: f64) -> Result<(), Self::Err> {
+        let cf = self.cf_handle(RELAY_SCORES_CF)?;
+        self.db
+            .put_cf(&cf, relay_url.as_str(), score.to_be_bytes())
+            .map_err(DatabaseError::backend)
+    }
+
+    async fn relay_scores(&self) -> Result<HashMap<Url, f64>, Self::Err> {
+        let cf = self.cf_handle(RELAY_SCORES_CF)?;
+        Ok(self
+            .db
+            .full_iterator_cf(&cf, IteratorMode::Start)
+            .flatten()
+            .filter_map(|(key, value)| {
+                let url: Url = Url::parse(std::str::from_utf8(&key).ok()?).ok()?;
+                let score: [u8; 8] = value.as_ref().try_into().ok()?;
+                Some((url, f64::from_be_bytes(score)))
+            })
+            .collect())
+    }
+
+    async fn save_outbox_event(&self, item: OutboxEvent) -> Result<(), Self::Err> {
+        let mut fbb = self.fbb.write().await;
+        let cf = self.cf_handle(OUTBOX_CF)?;
+
+        // Key: event ID + relay URL
+        let key: Vec<u8> = outbox_key(&item.event.id, &item.relay_url);
+
+        // Value: expiration + event
+        let mut value: Vec<u8> = item.expiration.as_u64().to_be_bytes().to_vec();
+        value.extend_from_slice(item.event.encode(&mut fbb));
+
+        self.db
+            .put_cf(&cf, key, value)
+            .map_err(DatabaseError::backend)
+    }
+
+    async fn outbox_events(&self) -> Result<Vec<OutboxEvent>, Self::Err> {
+        let cf = self.cf_handle(OUTBOX_CF)?;
+        Ok(self
+            .db
+            .full_iterator_cf(&cf, IteratorMode::Start)
+            .flatten()
+            .filter_map(|(key, value)| {
+                let url: &str = std::str::from_utf8(key.get(32..)?).ok()?;
+                let expiration: [u8; 8] = value.get(..8)?.try_into().ok()?;
+                let event: Event = Event::decode(value.get(8..)?).ok()?;
+                Some(OutboxEvent::new(
+                    event,
+                    Url::parse(url).ok()?,
+                    Timestamp::from(u64::from_be_bytes(expiration)),
+                ))
+            })
+            .collect())
+    }
+
+    async fn remove_outbox_event(
+        &self,
+        event_id: EventId,
+        relay_url: Url,
+    ) -> Result<(), Self::Err> {
+        let cf = self.cf_handle(OUTBOX_CF)?;
+        self.db
+            .delete_cf(&cf, outbox_key(&event_id, &relay_url))
+            .map_err(DatabaseError::backend)
+    }
+
     async fn wipe(&self) -> Result<(), Self::Err> {
         Err(DatabaseError::NotSupported)
     }
@@ -6,14 +6,18 @@
 
 #![forbid(unsafe_code)]
 
+pub extern crate futures_channel;
 pub extern crate futures_util;
 
 #[cfg(not(target_arch = "wasm32"))]
 pub mod native;
+pub mod transport;
 #[cfg(target_arch = "wasm32")]
 pub mod wasm;
 
 #[cfg(not(target_arch = "wasm32"))]
-pub use self::native::Message as WsMessage;
-#[cfg(target_arch = "wasm32")]
-pub use ws_stream_wasm::WsMessage;
+pub use self::native::TlsConfig;
+pub use self::transport::{
+    CloseFrame, ConnectOptions, DefaultWebSocketTransport, DynWebSocketTransport, TransportError,
+    TransportStats, WebSocketTransport, WsMessage,
+};
@@ -0,0 +1,642 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! permessage-deflate (RFC 7692)
+//!
+//! `tungstenite` not support WebSocket extensions, so compressed frames are inflated below it:
+//! [`DeflateStream`] sits between the TCP/TLS stream and `tungstenite`, rewriting every compressed
+//! message received from the relay into a single uncompressed frame.
+//! Outgoing messages are never compressed (allowed by the RFC).
+
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use flate2::{Decompress, FlushDecompress, Status};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+/// `Sec-WebSocket-Extensions` offer
+pub(super) const PERMESSAGE_DEFLATE: &str = "permessage-deflate";
+
+/// Max size of an inflated message (same as `tungstenite` default `max_message_size`)
+const MAX_MESSAGE_SIZE: usize = 64 << 20;
+/// Trailer removed by the sender from every compressed message
+const DEFLATE_TRAILER: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+const HTTP_HEADER_END: &[u8; 4] = b"\r\n\r\n";
+
+const FIN: u8 = 0x80;
+const RSV1: u8 = 0x40;
+const MASK: u8 = 0x80;
+const OPCODE_CONTINUATION: u8 = 0x0;
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_BINARY: u8 = 0x2;
+
+enum State {
+    /// Reading HTTP response: number of bytes of [`HTTP_HEADER_END`] matched
+    Handshake(usize),
+    /// Reading WebSocket frames
+    Frames,
+}
+
+struct Frame {
+    fin: bool,
+    rsv1: bool,
+    opcode: u8,
+    /// Header length (including mask key)
+    header_len: usize,
+    payload_len: usize,
+    mask: Option<[u8; 4]>,
+}
+
+impl Frame {
+    fn parse(buf: &[u8]) -> io::Result<Option<Self>> {
+        if buf.len() < 2 {
+            return Ok(None);
+        }
+
+        let (b0, b1) = (buf[0], buf[1]);
+        let mut header_len: usize = 2;
+        let payload_len: u64 = match b1 & 0x7f {
+            126 => {
+                if buf.len() < 4 {
+                    return Ok(None);
+                }
+                header_len = 4;
+                u16::from_be_bytes([buf[2], buf[3]]) as u64
+            }
+            127 => {
+                if buf.len() < 10 {
+                    return Ok(None);
+                }
+                header_len = 10;
+                let mut len = [0u8; 8];
+                len.copy_from_slice(&buf[2..10]);
+                u64::from_be_bytes(len)
+            }
+            len => len as u64,
+        };
+
+        if payload_len > MAX_MESSAGE_SIZE as u64 {
+            return Err(too_large());
+        }
+
+        let mask: Option<[u8; 4]> = if b1 & MASK != 0 {
+            if buf.len() < header_len + 4 {
+                return Ok(None);
+            }
+            let mut key = [0u8; 4];
+            key.copy_from_slice(&buf[header_len..header_len + 4]);
+            header_len += 4;
+            Some(key)
+        } else {
+            None
+        };
+
+        Ok(Some(Self {
+            fin: b0 & FIN != 0,
+            rsv1: b0 & RSV1 != 0,
+            opcode: b0 & 0x0f,
+            header_len,
+            payload_len: payload_len as usize,
+            mask,
+        }))
+    }
+
+    fn len(&self) -> usize {
+        self.header_len + self.payload_len
+    }
+
+    fn is_control(&self) -> bool {
+        self.opcode & 0x08 != 0
+    }
+
+    /// Extract the unmasked payload from the complete frame in `buf`
+    fn payload(&self, buf: &[u8]) -> Vec<u8> {
+        let mut payload: Vec<u8> = buf[self.header_len..self.len()].to_vec();
+        if let Some(key) = self.mask {
+            for (i, byte) in payload.iter_mut().enumerate() {
+                *byte ^= key[i % 4];
+            }
+        }
+        payload
+    }
+}
+
+/// Inflate state
+struct Inflater {
+    decompress: Decompress,
+    /// Opcode and compressed payload of a fragmented message
+    pending: Option<(u8, Vec<u8>)>,
+}
+
+impl Inflater {
+    fn new() -> Self {
+        Self {
+            decompress: Decompress::new(false),
+            pending: None,
+        }
+    }
+
+    fn inflate(&mut self, mut payload: Vec<u8>) -> io::Result<Vec<u8>> {
+        payload.extend_from_slice(&DEFLATE_TRAILER);
+
+        let mut output: Vec<u8> = Vec::with_capacity(payload.len().saturating_mul(4));
+        let mut consumed: usize = 0;
+
+        loop {
+            if output.len() == output.capacity() {
+                if output.len() >= MAX_MESSAGE_SIZE {
+                    return Err(too_large());
+                }
+                output.reserve(output.capacity().max(1024));
+            }
+
+            let total_in: u64 = self.decompress.total_in();
+            let total_out: u64 = self.decompress.total_out();
+            let status: Status = self
+                .decompress
+                .decompress_vec(&payload[consumed..], &mut output, FlushDecompress::Sync)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            consumed += (self.decompress.total_in() - total_in) as usize;
+
+            match status {
+                Status::StreamEnd => {
+                    // The sender closed the deflate stream (`BFINAL`): next message start a new one
+                    self.decompress.reset(false);
+                    break;
+                }
+                Status::Ok | Status::BufError => {
+                    let no_progress: bool = self.decompress.total_in() == total_in
+                        && self.decompress.total_out() == total_out;
+                    if consumed >= payload.len() && output.len() < output.capacity() {
+                        break;
+                    }
+                    if no_progress && output.len() < output.capacity() {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            "truncated compressed message",
+                        ));
+                    }
+                }
+            }
+        }
+
+        if output.len() > MAX_MESSAGE_SIZE {
+            return Err(too_large());
+        }
+
+        Ok(output)
+    }
+}
+
+/// Stream that, if compression is enabled, inflate the messages received.
+pub struct DeflateStream<S> {
+    inner: S,
+    inflater: Option<Inflater>,
+    state: State,
+    /// Bytes received but not processed yet (incomplete frame)
+    input: Vec<u8>,
+    /// Bytes ready to be read by `tungstenite`
+    output: Vec<u8>,
+    output_pos: usize,
+    eof: bool,
+}
+
+impl<S> DeflateStream<S> {
+    pub fn new(inner: S, compression: bool) -> Self {
+        Self {
+            inner,
+            inflater: if compression {
+                Some(Inflater::new())
+            } else {
+                None
+            },
+            state: State::Handshake(0),
+            input: Vec::new(),
+            output: Vec::new(),
+            output_pos: 0,
+            eof: false,
+        }
+    }
+
+    /// Disable inflating (extension not accepted by the relay)
+    pub fn disable_compression(&mut self) {
+        self.inflater = None;
+    }
+
+    fn process(&mut self, data: &[u8]) -> io::Result<()> {
+        match self.state {
+            State::Handshake(mut matched) => {
+                for (i, byte) in data.iter().enumerate() {
+                    if *byte == HTTP_HEADER_END[matched] {
+                        matched += 1;
+                    } else if *byte == HTTP_HEADER_END[0] {
+                        matched = 1;
+                    } else {
+                        matched = 0;
+                    }
+
+                    if matched == HTTP_HEADER_END.len() {
+                        self.output.extend_from_slice(&data[..=i]);
+                        self.state = State::Frames;
+                        return self.process(&data[i + 1..]);
+                    }
+                }
+                self.output.extend_from_slice(data);
+                self.state = State::Handshake(matched);
+                Ok(())
+            }
+            State::Frames => {
+                if self.inflater.is_none() {
+                    // Nothing to rewrite
+                    self.output.append(&mut self.input);
+                    self.output.extend_from_slice(data);
+                    return Ok(());
+                }
+                self.input.extend_from_slice(data);
+                self.process_frames()
+            }
+        }
+    }
+
+    fn process_frames(&mut self) -> io::Result<()> {
+        let mut start: usize = 0;
+
+        while let Some(frame) = Frame::parse(&self.input[start..])? {
+            let end: usize = start + frame.len();
+            if self.input.len() < end {
+                break;
+            }
+
+            let raw: &[u8] = &self.input[start..end];
+            match self.inflater.as_mut() {
+                Some(inflater) if !frame.is_control() => match frame.opcode {
+                    OPCODE_TEXT | OPCODE_BINARY if frame.rsv1 => {
+                        let payload: Vec<u8> = frame.payload(raw);
+                        if frame.fin {
+                            let message: Vec<u8> = inflater.inflate(payload)?;
+                            write_frame(&mut self.output, frame.opcode, &message);
+                        } else {
+                            inflater.pending = Some((frame.opcode, payload));
+                        }
+                    }
+                    OPCODE_CONTINUATION if inflater.pending.is_some() => {
+                        let payload: Vec<u8> = frame.payload(raw);
+                        if let Some((_, buf)) = inflater.pending.as_mut() {
+                            if buf.len() + payload.len() > MAX_MESSAGE_SIZE {
+                                return Err(too_large());
+                            }
+                            buf.extend_from_slice(&payload);
+                        }
+                        if frame.fin {
+                            if let Some((opcode, buf)) = inflater.pending.take() {
+                                let message: Vec<u8> = inflater.inflate(buf)?;
+                                write_frame(&mut self.output, opcode, &message);
+                            }
+                        }
+                    }
+                    // Uncompressed message
+                    _ => self.output.extend_from_slice(raw),
+                },
+                // Control frames (never compressed)
+                _ => self.output.extend_from_slice(raw),
+            }
+
+            start = end;
+        }
+
+        self.input.drain(..start);
+        Ok(())
+    }
+
+    fn has_output(&self) -> bool {
+        self.output_pos < self.output.len()
+    }
+}
+
+impl<S> AsyncRead for DeflateStream<S>
+where
+    S: AsyncRead + Unpin,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        // Compression disabled and handshake completed: nothing to rewrite
+        if this.inflater.is_none()
+            && matches!(this.state, State::Frames)
+            && this.input.is_empty()
+            && !this.has_output()
+        {
+            return Pin::new(&mut this.inner).poll_read(cx, buf);
+        }
+
+        loop {
+            if this.has_output() {
+                let available: &[u8] = &this.output[this.output_pos..];
+                let len: usize = available.len().min(buf.remaining());
+                buf.put_slice(&available[..len]);
+                this.output_pos += len;
+                if !this.has_output() {
+                    this.output.clear();
+                    this.output_pos = 0;
+                }
+                return Poll::Ready(Ok(()));
+            }
+
+            if this.eof {
+                return Poll::Ready(Ok(()));
+            }
+
+            let mut data = [0u8; 8192];
+            let mut read_buf = ReadBuf::new(&mut data);
+            match Pin::new(&mut this.inner).poll_read(cx, &mut read_buf) {
+                Poll::Ready(Ok(())) => {
+                    let filled: &[u8] = read_buf.filled();
+                    if filled.is_empty() {
+                        this.eof = true;
+                    } else {
+                        this.process(filled)?;
+                    }
+                }
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl<S> AsyncWrite for DeflateStream<S>
+where
+    S: AsyncWrite + Unpin,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
+
+/// Write unmasked, unfragmented and uncompressed frame
+fn write_frame(output: &mut Vec<u8>, opcode: u8, payload: &[u8]) {
+    output.push(FIN | opcode);
+    let len: usize = payload.len();
+    if len < 126 {
+        output.push(len as u8);
+    } else if len <= u16::MAX as usize {
+        output.push(126);
+        output.extend_from_slice(&(len as u16).to_be_bytes());
+    } else {
+        output.push(127);
+        output.extend_from_slice(&(len as u64).to_be_bytes());
+    }
+    output.extend_from_slice(payload);
+}
+
+fn too_large() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "message too large")
+}
+
+#[cfg(test)]
+mod tests {
+    use flate2::{Compress, Compression, FlushCompress};
+    use tokio::io::AsyncReadExt;
+
+    use super::*;
+
+    const HANDSHAKE: &[u8] = b"HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nSec-WebSocket-Extensions: permessage-deflate\r\n\r\n";
+    const OPCODE_PING: u8 = 0x9;
+
+    /// Reader that return at most `chunk` bytes per read
+    struct ChunkedReader {
+        data: Vec<u8>,
+        pos: usize,
+        chunk: usize,
+    }
+
+    impl AsyncRead for ChunkedReader {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<io::Result<()>> {
+            let this = self.get_mut();
+            let end: usize = (this.pos + this.chunk.min(buf.remaining())).min(this.data.len());
+            buf.put_slice(&this.data[this.pos..end]);
+            this.pos = end;
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    /// Compress a message as a permessage-deflate sender (trailer removed)
+    fn compress(compress: &mut Compress, data: &[u8]) -> Vec<u8> {
+        let mut output: Vec<u8> = Vec::with_capacity(data.len() + 64);
+        compress
+            .compress_vec(data, &mut output, FlushCompress::Sync)
+            .unwrap();
+        assert!(output.ends_with(&DEFLATE_TRAILER));
+        output.truncate(output.len() - DEFLATE_TRAILER.len());
+        output
+    }
+
+    fn frame(b0: u8, payload: &[u8], mask: Option<[u8; 4]>) -> Vec<u8> {
+        let mut output: Vec<u8> = Vec::new();
+        write_frame(&mut output, 0, payload);
+        output[0] = b0;
+        if let Some(key) = mask {
+            let header_len: usize = output.len() - payload.len();
+            output[1] |= MASK;
+            let mut masked: Vec<u8> = output[..header_len].to_vec();
+            masked.extend_from_slice(&key);
+            masked.extend(payload.iter().enumerate().map(|(i, b)| b ^ key[i % 4]));
+            return masked;
+        }
+        output
+    }
+
+    async fn read_all(input: Vec<u8>, compression: bool) -> io::Result<Vec<u8>> {
+        let reader = ChunkedReader {
+            data: input,
+            pos: 0,
+            chunk: 1,
+        };
+        let mut stream = DeflateStream::new(reader, compression);
+        let mut output: Vec<u8> = Vec::new();
+        stream.read_to_end(&mut output).await?;
+        Ok(output)
+    }
+
+    #[test]
+    fn test_parse_frame_length() {
+        let f = Frame::parse(&[FIN | OPCODE_TEXT, 5]).unwrap().unwrap();
+        assert!(f.fin && !f.rsv1);
+        assert_eq!(f.opcode, OPCODE_TEXT);
+        assert_eq!(f.len(), 2 + 5);
+
+        let f = Frame::parse(&[OPCODE_BINARY | RSV1, 126, 0x01, 0x00])
+            .unwrap()
+            .unwrap();
+        assert!(!f.fin && f.rsv1);
+        assert_eq!(f.len(), 4 + 256);
+
+        let mut buf: Vec<u8> = vec![FIN | OPCODE_BINARY, 127];
+        buf.extend_from_slice(&65536u64.to_be_bytes());
+        let f = Frame::parse(&buf).unwrap().unwrap();
+        assert_eq!(f.len(), 10 + 65536);
+
+        let f = Frame::parse(&[FIN | OPCODE_PING, 0]).unwrap().unwrap();
+        assert!(f.is_control());
+    }
+
+    #[test]
+    fn test_parse_incomplete_frame() {
+        assert!(Frame::parse(&[]).unwrap().is_none());
+        assert!(Frame::parse(&[FIN | OPCODE_TEXT]).unwrap().is_none());
+        assert!(Frame::parse(&[FIN | OPCODE_TEXT, 126, 0x01])
+            .unwrap()
+            .is_none());
+        assert!(Frame::parse(&[FIN | OPCODE_TEXT, 127, 0, 0, 0])
+            .unwrap()
+            .is_none());
+        // Mask key missing
+        assert!(Frame::parse(&[FIN | OPCODE_TEXT, MASK | 5, 1, 2])
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_parse_too_large_frame() {
+        let mut buf: Vec<u8> = vec![FIN | OPCODE_BINARY, 127];
+        buf.extend_from_slice(&(MAX_MESSAGE_SIZE as u64 + 1).to_be_bytes());
+        assert!(Frame::parse(&buf).is_err());
+    }
+
+    #[test]
+    fn test_masked_payload() {
+        let raw: Vec<u8> = frame(FIN | OPCODE_TEXT, b"hello", Some([1, 2, 3, 4]));
+        let f = Frame::parse(&raw).unwrap().unwrap();
+        assert_eq!(f.len(), raw.len());
+        assert_eq!(f.payload(&raw), b"hello");
+    }
+
+    #[test]
+    fn test_inflate_context_takeover() {
+        let mut deflate = Compress::new(Compression::default(), false);
+        let mut inflater = Inflater::new();
+
+        let msg: &[u8] = br#"["EVENT","sub",{"content":"hello world"}]"#;
+        let first: Vec<u8> = compress(&mut deflate, msg);
+        let second: Vec<u8> = compress(&mut deflate, msg);
+        // Second message reference the first one
+        assert!(second.len() < first.len());
+
+        assert_eq!(inflater.inflate(first).unwrap(), msg);
+        assert_eq!(inflater.inflate(second).unwrap(), msg);
+    }
+
+    #[test]
+    fn test_inflate_invalid() {
+        let mut inflater = Inflater::new();
+        assert!(inflater.inflate(vec![0xff, 0xff, 0xff]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_stream_compressed_frame() {
+        let mut deflate = Compress::new(Compression::default(), false);
+        let msg: &[u8] = br#"["EOSE","sub"]"#;
+
+        let mut input: Vec<u8> = HANDSHAKE.to_vec();
+        input.extend(frame(
+            FIN | RSV1 | OPCODE_TEXT,
+            &compress(&mut deflate, msg),
+            None,
+        ));
+        input.extend(frame(
+            FIN | RSV1 | OPCODE_BINARY,
+            &compress(&mut deflate, msg),
+            None,
+        ));
+
+        let mut expected: Vec<u8> = HANDSHAKE.to_vec();
+        write_frame(&mut expected, OPCODE_TEXT, msg);
+        write_frame(&mut expected, OPCODE_BINARY, msg);
+
+        assert_eq!(read_all(input, true).await.unwrap(), expected);
+    }
+
+    #[tokio::test]
+    async fn test_stream_fragmented_message_with_control_frame() {
+        let mut deflate = Compress::new(Compression::default(), false);
+        let msg: Vec<u8> = br#"["NOTICE","fragmented"]"#.repeat(10);
+        let payload: Vec<u8> = compress(&mut deflate, &msg);
+        let (a, b) = payload.split_at(payload.len() / 2);
+
+        let mut input: Vec<u8> = HANDSHAKE.to_vec();
+        input.extend(frame(RSV1 | OPCODE_TEXT, a, None));
+        let ping: Vec<u8> = frame(FIN | OPCODE_PING, b"ping", None);
+        input.extend_from_slice(&ping);
+        input.extend(frame(FIN | OPCODE_CONTINUATION, b, None));
+
+        // Control frame forwarded as soon as received
+        let mut expected: Vec<u8> = HANDSHAKE.to_vec();
+        expected.extend_from_slice(&ping);
+        write_frame(&mut expected, OPCODE_TEXT, &msg);
+
+        assert_eq!(read_all(input, true).await.unwrap(), expected);
+    }
+
+    #[tokio::test]
+    async fn test_stream_uncompressed_frames() {
+        let mut input: Vec<u8> = HANDSHAKE.to_vec();
+        input.extend(frame(OPCODE_TEXT, b"[\"EOSE\",", None));
+        input.extend(frame(FIN | OPCODE_CONTINUATION, b"\"sub\"]", None));
+        input.extend(frame(FIN | OPCODE_PING, b"", None));
+
+        assert_eq!(read_all(input.clone(), true).await.unwrap(), input);
+        assert_eq!(read_all(input.clone(), false).await.unwrap(), input);
+    }
+
+    #[tokio::test]
+    async fn test_stream_compression_disabled() {
+        let mut deflate = Compress::new(Compression::default(), false);
+        let mut input: Vec<u8> = HANDSHAKE.to_vec();
+        input.extend(frame(
+            FIN | RSV1 | OPCODE_TEXT,
+            &compress(&mut deflate, b"[]"),
+            None,
+        ));
+
+        // Extension not accepted: forward frames unchanged
+        let reader = ChunkedReader {
+            data: input.clone(),
+            pos: 0,
+            chunk: 7,
+        };
+        let mut stream = DeflateStream::new(reader, true);
+        stream.disable_compression();
+        let mut output: Vec<u8> = Vec::new();
+        stream.read_to_end(&mut output).await.unwrap();
+        assert_eq!(output, input);
+    }
+
+    #[tokio::test]
+    async fn test_stream_invalid_compressed_frame() {
+        let mut input: Vec<u8> = HANDSHAKE.to_vec();
+        input.extend(frame(FIN | RSV1 | OPCODE_TEXT, &[0xff, 0xff], None));
+        assert!(read_all(input, true).await.is_err());
+    }
+}
@@ -5,7 +5,6 @@
 //! Native Network
 
 use std::net::SocketAddr;
-use std::sync::Arc;
 use std::time::Duration;
 
 use futures_util::stream::{SplitSink, SplitStream};
@@ -13,20 +12,31 @@ use futures_util::StreamExt;
 use thiserror::Error;
 use tokio::net::TcpStream;
 use tokio_rustls::client::TlsStream;
-use tokio_rustls::rustls::{ClientConfig, OwnedTrustAnchor, RootCertStore, ServerName};
+use tokio_rustls::rustls::{Error as TlsError, ServerName};
 use tokio_rustls::TlsConnector;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::header::SEC_WEBSOCKET_EXTENSIONS;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
 use tokio_tungstenite::tungstenite::Error as WsError;
 pub use tokio_tungstenite::tungstenite::Message;
 use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
 use url_fork::{ParseError, Url};
 
-type WebSocket = WebSocketStream<MaybeTlsStream<TcpStream>>;
-type Sink = SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>;
-type Stream = SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>;
+use crate::transport::TransportStats;
 
+type WebSocket = WebSocketStream<DeflateStream<MaybeTlsStream<WireStream<TcpStream>>>>;
+type Sink = SplitSink<WebSocket, Message>;
+type Stream = SplitStream<WebSocket>;
+
+mod deflate;
 mod socks;
+mod tls;
+mod wire;
 
+use self::deflate::{DeflateStream, PERMESSAGE_DEFLATE};
 use self::socks::TpcSocks5Stream;
+pub use self::tls::TlsConfig;
+use self::wire::WireStream;
 
 #[derive(Debug, Error)]
 pub enum Error {
@@ -38,6 +48,9 @@ pub enum Error {
     Ws(#[from] WsError),
     #[error("socks error: {0}")]
     Socks(#[from] tokio_socks::Error),
+    /// TLS error
+    #[error("tls error: {0}")]
+    Tls(#[from] TlsError),
     /// Timeout
     #[error("timeout")]
     Timeout,
@@ -52,71 +65,79 @@ pub enum Error {
 pub async fn connect(
     url: &Url,
     proxy: Option<SocketAddr>,
+    tls: &TlsConfig,
     timeout: Option<Duration>,
+    compression: bool,
+    stats: TransportStats,
 ) -> Result<(Sink, Stream), Error> {
-    let stream = match proxy {
-        Some(proxy) => connect_proxy(url, proxy, timeout).await?,
-        None => connect_direct(url, timeout).await?,
-    };
-    Ok(stream.split())
-}
-
-async fn connect_direct(url: &Url, timeout: Option<Duration>) -> Result<WebSocket, Error> {
     let timeout = timeout.unwrap_or(Duration::from_secs(60));
-    let (stream, _) =
-        tokio::time::timeout(timeout, tokio_tungstenite::connect_async(url.to_string()))
-            .await
-            .map_err(|_| Error::Timeout)??;
-    Ok(stream)
+    let stream = tokio::time::timeout(timeout, async {
+        let addr: String = match url.host_str() {
+            Some(host) => match url.port_or_known_default() {
+                Some(port) => format!("{host}:{port}"),
+                None => return Err(Error::Url(ParseError::InvalidPort)),
+            },
+            None => return Err(Error::Url(ParseError::EmptyHost)),
+        };
+        let conn: TcpStream = match proxy {
+            Some(proxy) => TpcSocks5Stream::connect(proxy, addr).await?,
+            None => TcpStream::connect(addr).await?,
+        };
+        handshake(url, conn, tls, compression, stats).await
+    })
+    .await
+    .map_err(|_| Error::Timeout)??;
+    Ok(stream.split())
 }
 
-async fn connect_proxy(
+async fn handshake(
     url: &Url,
-    proxy: SocketAddr,
-    timeout: Option<Duration>,
+    conn: TcpStream,
+    tls: &TlsConfig,
+    compression: bool,
+    stats: TransportStats,
 ) -> Result<WebSocket, Error> {
-    let timeout = timeout.unwrap_or(Duration::from_secs(60));
-    let addr: String = match url.host_str() {
-        Some(host) => match url.port_or_known_default() {
-            Some(port) => format!("{host}:{port}"),
-            None => return Err(Error::Url(ParseError::EmptyHost)),
-        },
-        None => return Err(Error::Url(ParseError::InvalidPort)),
+    // Count below TLS: the stats include TLS records, WebSocket handshake and framing
+    let conn = WireStream::new(conn, stats.clone());
+    let conn = match url.scheme() {
+        "wss" => MaybeTlsStream::Rustls(connect_with_tls(conn, url, tls).await?),
+        _ => MaybeTlsStream::Plain(conn),
     };
+    let conn = DeflateStream::new(conn, compression);
 
-    let conn = TpcSocks5Stream::connect(proxy, addr.clone()).await?;
-    let conn = match connect_with_tls(conn, url).await {
-        Ok(stream) => MaybeTlsStream::Rustls(stream),
-        Err(_) => {
-            let conn = TpcSocks5Stream::connect(proxy, addr).await?;
-            MaybeTlsStream::Plain(conn)
-        }
-    };
+    let mut request = url.as_str().into_client_request()?;
+    if compression {
+        request.headers_mut().insert(
+            SEC_WEBSOCKET_EXTENSIONS,
+            HeaderValue::from_static(PERMESSAGE_DEFLATE),
+        );
+    }
+
+    let (mut stream, response) = tokio_tungstenite::client_async(request, conn).await?;
+
+    // Check if the relay accepted the extension
+    let negotiated: bool = compression
+        && response
+            .headers()
+            .get_all(SEC_WEBSOCKET_EXTENSIONS)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .any(|value| value.contains(PERMESSAGE_DEFLATE));
+    if !negotiated {
+        stream.get_mut().disable_compression();
+    }
+    stats.set_compression(negotiated);
 
-    let (stream, _) = tokio::time::timeout(
-        timeout,
-        tokio_tungstenite::client_async(url.to_string(), conn),
-    )
-    .await
-    .map_err(|_| Error::Timeout)??;
     Ok(stream)
 }
 
-async fn connect_with_tls(stream: TcpStream, url: &Url) -> Result<TlsStream<TcpStream>, Error> {
-    let mut root_cert_store = RootCertStore::empty();
-    root_cert_store.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|ta| {
-        OwnedTrustAnchor::from_subject_spki_name_constraints(
-            ta.subject,
-            ta.spki,
-            ta.name_constraints,
-        )
-    }));
-    let config = ClientConfig::builder()
-        .with_safe_defaults()
-        .with_root_certificates(root_cert_store)
-        .with_no_client_auth();
-    let connector = TlsConnector::from(Arc::new(config));
-    let domain = url.domain().ok_or(Error::InvalidDNSName)?;
+async fn connect_with_tls(
+    stream: WireStream<TcpStream>,
+    url: &Url,
+    tls: &TlsConfig,
+) -> Result<TlsStream<WireStream<TcpStream>>, Error> {
+    let connector = TlsConnector::from(tls.client_config(url)?);
+    let domain = url.host_str().ok_or(Error::InvalidDNSName)?;
     let domain = ServerName::try_from(domain).map_err(|_| Error::InvalidDNSName)?;
     Ok(connector.connect(domain, stream).await?)
 }
@@ -0,0 +1,406 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! TLS
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::SystemTime;
+
+use ring::digest::{digest, SHA256};
+use tokio_rustls::rustls::client::{ServerCertVerified, ServerCertVerifier, WebPkiVerifier};
+use tokio_rustls::rustls::{
+    Certificate, CertificateError, ClientConfig, Error as RustlsError, OwnedTrustAnchor,
+    PrivateKey, RootCertStore, ServerName,
+};
+use url_fork::{Host, Url};
+
+/// TLS options of the relay connections
+///
+/// By default, the server certificate is verified against the `webpki` roots.
+///
+/// The rustls configs are built on first use and shared by all the clones.
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    webpki_roots: bool,
+    system_roots: bool,
+    root_certificates: Vec<Vec<u8>>,
+    pins: HashMap<String, HashSet<[u8; 32]>>,
+    client_auth: Option<(Vec<Vec<u8>>, Vec<u8>)>,
+    insecure: bool,
+    cache: Arc<Mutex<Cache>>,
+}
+
+/// Built configs (reset by every setter)
+#[derive(Default)]
+struct Cache {
+    webpki: Option<Arc<WebPkiVerifier>>,
+    /// Client configs by host
+    configs: HashMap<String, Arc<ClientConfig>>,
+}
+
+impl fmt::Debug for Cache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Cache")
+            .field("hosts", &self.configs.keys())
+            .finish()
+    }
+}
+
+impl Default for TlsConfig {
+    fn default() -> Self {
+        Self {
+            webpki_roots: true,
+            system_roots: false,
+            root_certificates: Vec::new(),
+            pins: HashMap::new(),
+            client_auth: None,
+            insecure: false,
+            cache: Arc::default(),
+        }
+    }
+}
+
+impl TlsConfig {
+    /// New default TLS config
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trust the Mozilla root certificates bundled with `webpki-roots` (default: true)
+    pub fn webpki_roots(self, enable: bool) -> Self {
+        Self {
+            webpki_roots: enable,
+            cache: Arc::default(),
+            ..self
+        }
+    }
+
+    /// Trust the root certificates of the operating system (default: false)
+    pub fn system_roots(self, enable: bool) -> Self {
+        Self {
+            system_roots: enable,
+            cache: Arc::default(),
+            ..self
+        }
+    }
+
+    /// Trust an additional root certificate (DER encoded)
+    ///
+    /// Useful for relays behind a private CA.
+    pub fn add_root_certificate(mut self, der: Vec<u8>) -> Self {
+        self.root_certificates.push(der);
+        self.cache = Arc::default();
+        self
+    }
+
+    /// Pin the public key of the relay certificate
+    ///
+    /// `spki_sha256` is the SHA-256 of the DER encoded `SubjectPublicKeyInfo` (the same used for HPKP).
+    /// Pins are matched by host: when at least one pin is set for the `url` host,
+    /// the connection fails if the relay certificate doesn't match any of them.
+    pub fn pin(mut self, url: &Url, spki_sha256: [u8; 32]) -> Self {
+        if let Some(host) = url.host_str() {
+            self.pins
+                .entry(host.to_string())
+                .or_default()
+                .insert(spki_sha256);
+        }
+        self.cache = Arc::default();
+        self
+    }
+
+    /// Authenticate with a client certificate (mTLS)
+    ///
+    /// `cert_chain` and `key` must be DER encoded (the key in PKCS#8, PKCS#1 or SEC1 format).
+    pub fn client_auth(self, cert_chain: Vec<Vec<u8>>, key: Vec<u8>) -> Self {
+        Self {
+            client_auth: Some((cert_chain, key)),
+            cache: Arc::default(),
+            ..self
+        }
+    }
+
+    /// Accept any server certificate of loopback relays (default: false)
+    ///
+    /// Only for local development with self-signed certificates: ignored for relays that aren't
+    /// on `localhost`, `127.0.0.0/8` or `::1` (use [`TlsConfig::add_root_certificate`] for them).
+    /// Pins are still checked.
+    pub fn insecure(self, insecure: bool) -> Self {
+        Self {
+            insecure,
+            cache: Arc::default(),
+            ..self
+        }
+    }
+
+    /// Get the rustls config for `url`
+    pub(super) fn client_config(&self, url: &Url) -> Result<Arc<ClientConfig>, RustlsError> {
+        let host: &str = url.host_str().unwrap_or_default();
+        let mut cache = self.cache.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if let Some(config) = cache.configs.get(host) {
+            return Ok(config.clone());
+        }
+
+        let webpki: Option<Arc<WebPkiVerifier>> = if self.insecure && is_loopback(url) {
+            None
+        } else {
+            match &cache.webpki {
+                Some(webpki) => Some(webpki.clone()),
+                None => {
+                    let webpki = Arc::new(WebPkiVerifier::new(self.root_store()?, None));
+                    cache.webpki = Some(webpki.clone());
+                    Some(webpki)
+                }
+            }
+        };
+
+        let verifier = Verifier {
+            webpki,
+            pins: self.pins.get(host).cloned().unwrap_or_default(),
+        };
+
+        let builder = ClientConfig::builder()
+            .with_safe_defaults()
+            .with_custom_certificate_verifier(Arc::new(verifier));
+
+        let config = match &self.client_auth {
+            Some((cert_chain, key)) => builder.with_client_auth_cert(
+                cert_chain.iter().cloned().map(Certificate).collect(),
+                PrivateKey(key.clone()),
+            )?,
+            None => builder.with_no_client_auth(),
+        };
+        let config = Arc::new(config);
+        cache.configs.insert(host.to_string(), config.clone());
+        Ok(config)
+    }
+
+    fn root_store(&self) -> Result<RootCertStore, RustlsError> {
+        let mut root_store = RootCertStore::empty();
+
+        if self.webpki_roots {
+            root_store.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|ta| {
+                OwnedTrustAnchor::from_subject_spki_name_constraints(
+                    ta.subject,
+                    ta.spki,
+                    ta.name_constraints,
+                )
+            }));
+        }
+
+        if self.system_roots {
+            let certs = rustls_native_certs::load_native_certs().map_err(|e| {
+                RustlsError::General(format!("impossible to load system root certificates: {e}"))
+            })?;
+            root_store
+                .add_parsable_certificates(&certs.into_iter().map(|c| c.0).collect::<Vec<_>>());
+        }
+
+        for der in self.root_certificates.iter() {
+            root_store.add(&Certificate(der.clone()))?;
+        }
+
+        Ok(root_store)
+    }
+}
+
+/// Check if the relay is on the local machine
+fn is_loopback(url: &Url) -> bool {
+    match url.host() {
+        Some(Host::Domain(domain)) => domain == "localhost" || domain.ends_with(".localhost"),
+        Some(Host::Ipv4(ip)) => ip.is_loopback(),
+        Some(Host::Ipv6(ip)) => ip.is_loopback(),
+        None => false,
+    }
+}
+
+/// Server certificate verifier: `webpki` (if not insecure) + SPKI pins
+struct Verifier {
+    webpki: Option<Arc<WebPkiVerifier>>,
+    pins: HashSet<[u8; 32]>,
+}
+
+impl ServerCertVerifier for Verifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        intermediates: &[Certificate],
+        server_name: &ServerName,
+        scts: &mut dyn Iterator<Item = &[u8]>,
+        ocsp_response: &[u8],
+        now: SystemTime,
+    ) -> Result<ServerCertVerified, RustlsError> {
+        if let Some(webpki) = &self.webpki {
+            webpki.verify_server_cert(
+                end_entity,
+                intermediates,
+                server_name,
+                scts,
+                ocsp_response,
+                now,
+            )?;
+        }
+
+        if !self.pins.is_empty() {
+            let spki: &[u8] = spki(&end_entity.0).ok_or(RustlsError::InvalidCertificate(
+                CertificateError::BadEncoding,
+            ))?;
+            let hash = digest(&SHA256, spki);
+            if !self.pins.iter().any(|pin| pin.as_slice() == hash.as_ref()) {
+                return Err(RustlsError::InvalidCertificate(
+                    CertificateError::ApplicationVerificationFailure,
+                ));
+            }
+        }
+
+        Ok(ServerCertVerified::assertion())
+    }
+}
+
+/// DER element
+struct Der<'a> {
+    tag: u8,
+    /// Whole element (header included)
+    element: &'a [u8],
+    content: &'a [u8],
+    /// Bytes after the element
+    rest: &'a [u8],
+}
+
+/// Parse the next DER element
+fn der_next(buf: &[u8]) -> Option<Der<'_>> {
+    let tag: u8 = *buf.first()?;
+    let first: u8 = *buf.get(1)?;
+    let (header_len, len): (usize, usize) = if first < 0x80 {
+        (2, first as usize)
+    } else {
+        let n: usize = (first & 0x7f) as usize;
+        if n == 0 || n > 4 {
+            return None;
+        }
+        let len = buf
+            .get(2..2 + n)?
+            .iter()
+            .fold(0usize, |acc, b| (acc << 8) | *b as usize);
+        (2 + n, len)
+    };
+    let end: usize = header_len.checked_add(len)?;
+    let element: &[u8] = buf.get(..end)?;
+    Some(Der {
+        tag,
+        element,
+        content: &element[header_len..],
+        rest: &buf[end..],
+    })
+}
+
+/// Get the DER encoded `SubjectPublicKeyInfo` of a X.509 certificate
+fn spki(cert: &[u8]) -> Option<&[u8]> {
+    // Certificate ::= SEQUENCE { tbsCertificate, signatureAlgorithm, signature }
+    let cert = der_next(cert)?;
+    let tbs = der_next(cert.content)?;
+
+    // TBSCertificate ::= SEQUENCE { [0] version OPTIONAL, serialNumber, signature, issuer, validity, subject, subjectPublicKeyInfo, ... }
+    let first = der_next(tbs.content)?;
+    let mut rest: &[u8] = if first.tag == 0xa0 {
+        first.rest
+    } else {
+        // No version: the first element is the serial number
+        tbs.content
+    };
+    for _ in 0..5 {
+        rest = der_next(rest)?.rest;
+    }
+    Some(der_next(rest)?.element)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ISRG_ROOT_X1: &[u8] = include_bytes!("../../testdata/isrg-root-x1.der");
+    const ISRG_ROOT_X2: &[u8] = include_bytes!("../../testdata/isrg-root-x2.der");
+    /// Self-signed P-256 certificate for `relay.local`
+    const RELAY_LOCAL: &[u8] = include_bytes!("../../testdata/relay-local.der");
+
+    fn spki_sha256(cert: &[u8]) -> String {
+        let spki: &[u8] = spki(cert).unwrap();
+        digest(&SHA256, spki)
+            .as_ref()
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect()
+    }
+
+    #[test]
+    fn test_spki_hash() {
+        // openssl x509 -pubkey -noout | openssl pkey -pubin -outform der | sha256sum
+        assert_eq!(
+            spki_sha256(ISRG_ROOT_X1),
+            "0b9fa5a59eed715c26c1020c711b4f6ec42d58b0015e14337a39dad301c5afc3"
+        );
+        assert_eq!(
+            spki_sha256(ISRG_ROOT_X2),
+            "762195c225586ee6c0237456e2107dc54f1efc21f61a792ebd515913cce68332"
+        );
+        assert_eq!(
+            spki_sha256(RELAY_LOCAL),
+            "7cdf65312035b3b08f9d854f0d95d7efdf7bd7baf813eab31e30a4eebf63a1dd"
+        );
+    }
+
+    #[test]
+    fn test_spki_match_webpki_roots() {
+        for cert in [ISRG_ROOT_X1, ISRG_ROOT_X2] {
+            // `webpki-roots` store the SPKI without the outer SEQUENCE header
+            let spki = der_next(spki(cert).unwrap()).unwrap();
+            assert_eq!(spki.tag, 0x30);
+            assert!(spki.rest.is_empty());
+            assert!(webpki_roots::TLS_SERVER_ROOTS
+                .iter()
+                .any(|ta| ta.spki == spki.content));
+        }
+    }
+
+    #[test]
+    fn test_spki_invalid_certificate() {
+        assert!(spki(&[]).is_none());
+        assert!(spki(&[0x30, 0x82]).is_none());
+        assert!(spki(&ISRG_ROOT_X1[..ISRG_ROOT_X1.len() / 2]).is_none());
+        assert!(spki(&RELAY_LOCAL[..64]).is_none());
+    }
+
+    #[test]
+    fn test_is_loopback() {
+        assert!(is_loopback(&Url::parse("wss://localhost:7777").unwrap()));
+        assert!(is_loopback(&Url::parse("wss://relay.localhost").unwrap()));
+        assert!(is_loopback(&Url::parse("wss://127.0.0.1").unwrap()));
+        assert!(is_loopback(&Url::parse("wss://127.1.2.3").unwrap()));
+        assert!(is_loopback(&Url::parse("wss://[::1]").unwrap()));
+        assert!(!is_loopback(&Url::parse("wss://relay.damus.io").unwrap()));
+        assert!(!is_loopback(
+            &Url::parse("wss://localhost.evil.com").unwrap()
+        ));
+        assert!(!is_loopback(&Url::parse("wss://192.168.1.1").unwrap()));
+    }
+
+    #[test]
+    fn test_client_config_cache() {
+        let url = Url::parse("wss://relay.damus.io").unwrap();
+        let tls = TlsConfig::new();
+        let config = tls.client_config(&url).unwrap();
+
+        // Shared by clones
+        let cloned = tls.clone();
+        assert!(Arc::ptr_eq(&config, &cloned.client_config(&url).unwrap()));
+
+        // Rebuilt after a change
+        let pinned = cloned.pin(&url, [0u8; 32]);
+        assert!(!Arc::ptr_eq(&config, &pinned.client_config(&url).unwrap()));
+        assert!(Arc::ptr_eq(&config, &tls.client_config(&url).unwrap()));
+    }
+}
@@ -0,0 +1,91 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Wire stats
+
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use crate::transport::TransportStats;
+
+/// Stream that count the bytes exchanged on the TCP connection (below TLS)
+pub struct WireStream<S> {
+    inner: S,
+    stats: TransportStats,
+}
+
+impl<S> WireStream<S> {
+    pub fn new(inner: S, stats: TransportStats) -> Self {
+        Self { inner, stats }
+    }
+}
+
+impl<S> AsyncRead for WireStream<S>
+where
+    S: AsyncRead + Unpin,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let before: usize = buf.filled().len();
+        let res = Pin::new(&mut this.inner).poll_read(cx, buf);
+        this.stats
+            .add_bytes_received(buf.filled().len().saturating_sub(before));
+        res
+    }
+}
+
+impl<S> AsyncWrite for WireStream<S>
+where
+    S: AsyncWrite + Unpin,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let res = Pin::new(&mut this.inner).poll_write(cx, buf);
+        if let Poll::Ready(Ok(size)) = res {
+            this.stats.add_bytes_sent(size);
+        }
+        res
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_wire_stats() {
+        let stats = TransportStats::new();
+
+        let mut stream = WireStream::new(&b"received"[..], stats.clone());
+        let mut buf: Vec<u8> = Vec::new();
+        stream.read_to_end(&mut buf).await.unwrap();
+
+        let mut stream = WireStream::new(Vec::new(), stats.clone());
+        stream.write_all(b"sent").await.unwrap();
+
+        assert_eq!(stats.bytes_received(), 8);
+        assert_eq!(stats.bytes_sent(), 4);
+    }
+}
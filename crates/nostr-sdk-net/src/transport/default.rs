@@ -0,0 +1,156 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Default transport
+//!
+//! `tokio-tungstenite` (with configurable TLS and optional SOCKS5 proxy) on native, `ws_stream_wasm` on WASM.
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::net::SocketAddr;
+
+#[cfg(target_arch = "wasm32")]
+use futures_util::StreamExt;
+#[cfg(not(target_arch = "wasm32"))]
+use futures_util::TryStreamExt;
+use futures_util::{future, SinkExt};
+#[cfg(not(target_arch = "wasm32"))]
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+#[cfg(not(target_arch = "wasm32"))]
+use tokio_tungstenite::tungstenite::protocol::CloseFrame as TungsteniteCloseFrame;
+use url_fork::Url;
+
+#[cfg(not(target_arch = "wasm32"))]
+use super::CloseFrame;
+use super::{
+    async_trait, BoxedSink, BoxedStream, ConnectOptions, TransportError, WebSocketTransport,
+    WsMessage,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::native::{self, Message, TlsConfig};
+#[cfg(target_arch = "wasm32")]
+use crate::wasm;
+
+/// Default WebSocket transport
+#[derive(Debug, Clone, Default)]
+pub struct DefaultWebSocketTransport {
+    #[cfg(not(target_arch = "wasm32"))]
+    proxy: Option<SocketAddr>,
+    #[cfg(not(target_arch = "wasm32"))]
+    tls: TlsConfig,
+}
+
+impl DefaultWebSocketTransport {
+    /// New default transport
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Connect through a SOCKS5 proxy
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn proxy(self, proxy: Option<SocketAddr>) -> Self {
+        Self { proxy, ..self }
+    }
+
+    /// Set [`TlsConfig`]
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn tls(self, tls: TlsConfig) -> Self {
+        Self { tls, ..self }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl From<WsMessage> for Message {
+    fn from(msg: WsMessage) -> Self {
+        match msg {
+            WsMessage::Text(text) => Self::Text(text),
+            WsMessage::Binary(data) => Self::Binary(data),
+            WsMessage::Ping(data) => Self::Ping(data),
+            WsMessage::Pong(data) => Self::Pong(data),
+            WsMessage::Close(frame) => Self::Close(frame.map(|frame| TungsteniteCloseFrame {
+                code: CloseCode::from(frame.code),
+                reason: frame.reason.into(),
+            })),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl From<Message> for WsMessage {
+    fn from(msg: Message) -> Self {
+        match msg {
+            Message::Text(text) => Self::Text(text),
+            Message::Binary(data) => Self::Binary(data),
+            Message::Ping(data) => Self::Ping(data),
+            Message::Pong(data) => Self::Pong(data),
+            Message::Close(frame) => Self::Close(frame.map(|frame| CloseFrame {
+                code: frame.code.into(),
+                reason: frame.reason.into_owned(),
+            })),
+            Message::Frame(frame) => Self::Binary(frame.into_data()),
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl From<ws_stream_wasm::WsMessage> for WsMessage {
+    fn from(msg: ws_stream_wasm::WsMessage) -> Self {
+        match msg {
+            ws_stream_wasm::WsMessage::Text(text) => Self::Text(text),
+            ws_stream_wasm::WsMessage::Binary(data) => Self::Binary(data),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[async_trait]
+impl WebSocketTransport for DefaultWebSocketTransport {
+    async fn connect(
+        &self,
+        url: &Url,
+        opts: &ConnectOptions,
+    ) -> Result<(BoxedSink, BoxedStream), TransportError> {
+        let (sink, stream) = native::connect(
+            url,
+            self.proxy,
+            &self.tls,
+            opts.timeout,
+            opts.compression,
+            opts.stats.clone(),
+        )
+        .await
+        .map_err(TransportError::backend)?;
+        let sink = sink
+            .sink_map_err(TransportError::backend)
+            .with(|msg: WsMessage| future::ok::<Message, TransportError>(Message::from(msg)));
+        let stream = stream
+            .map_ok(WsMessage::from)
+            .map_err(TransportError::backend);
+        Ok((Box::pin(sink), Box::pin(stream)))
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+#[async_trait(?Send)]
+impl WebSocketTransport for DefaultWebSocketTransport {
+    async fn connect(
+        &self,
+        url: &Url,
+        opts: &ConnectOptions,
+    ) -> Result<(BoxedSink, BoxedStream), TransportError> {
+        let (meta, sink, stream) = wasm::connect(url).await.map_err(TransportError::backend)?;
+
+        // Compression is handled by the browser, that doesn't expose the wire: stats not updated
+        opts.stats
+            .set_compression(meta.extensions().contains("permessage-deflate"));
+        let sink = sink
+            .sink_map_err(TransportError::backend)
+            .with(|msg: WsMessage| match msg {
+                WsMessage::Text(text) => future::ok(ws_stream_wasm::WsMessage::Text(text)),
+                WsMessage::Binary(data) => future::ok(ws_stream_wasm::WsMessage::Binary(data)),
+                _ => future::err(TransportError::msg("unsupported message")),
+            });
+        let stream = stream.map(|msg| Ok(WsMessage::from(msg)));
+        Ok((Box::pin(sink), Box::pin(stream)))
+    }
+}
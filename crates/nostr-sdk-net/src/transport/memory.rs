@@ -0,0 +1,118 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! In-process transport
+//!
+//! Connections never leave the process: every [`WebSocketTransport::connect`] call is delivered to
+//! the paired [`InMemoryListener`]. Useful for tests.
+
+use futures_channel::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use futures_util::{future, SinkExt, StreamExt};
+use url_fork::Url;
+
+use super::{
+    async_trait, BoxedSink, BoxedStream, ConnectOptions, TransportError, WebSocketTransport,
+    WsMessage,
+};
+
+/// In-process WebSocket transport
+#[derive(Debug, Clone)]
+pub struct InMemoryTransport {
+    listener: UnboundedSender<InMemoryConnection>,
+}
+
+impl InMemoryTransport {
+    /// New in-process transport and its listener
+    pub fn new() -> (Self, InMemoryListener) {
+        let (tx, rx) = mpsc::unbounded();
+        (Self { listener: tx }, InMemoryListener { incoming: rx })
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl WebSocketTransport for InMemoryTransport {
+    async fn connect(
+        &self,
+        url: &Url,
+        opts: &ConnectOptions,
+    ) -> Result<(BoxedSink, BoxedStream), TransportError> {
+        let (client_tx, server_rx) = mpsc::unbounded();
+        let (server_tx, client_rx) = mpsc::unbounded();
+
+        let conn = InMemoryConnection {
+            url: url.clone(),
+            tx: server_tx,
+            rx: server_rx,
+        };
+        self.listener
+            .unbounded_send(conn)
+            .map_err(|_| TransportError::msg("listener dropped"))?;
+
+        // No network and no compression: the wire size is the payload size
+        opts.stats.set_compression(false);
+        let stats = opts.stats.clone();
+        let sink = client_tx
+            .sink_map_err(TransportError::backend)
+            .with(move |msg: WsMessage| {
+                stats.add_bytes_sent(msg.len());
+                future::ok::<WsMessage, TransportError>(msg)
+            });
+        let stats = opts.stats.clone();
+        let stream = client_rx.map(move |msg| {
+            stats.add_bytes_received(msg.len());
+            Ok(msg)
+        });
+        Ok((Box::pin(sink), Box::pin(stream)))
+    }
+}
+
+/// Accept the connections opened through an [`InMemoryTransport`]
+#[derive(Debug)]
+pub struct InMemoryListener {
+    incoming: UnboundedReceiver<InMemoryConnection>,
+}
+
+impl InMemoryListener {
+    /// Wait for the next connection
+    ///
+    /// Return `None` when all the [`InMemoryTransport`] clones are dropped.
+    pub async fn accept(&mut self) -> Option<InMemoryConnection> {
+        self.incoming.next().await
+    }
+}
+
+/// Server side of an in-process connection
+#[derive(Debug)]
+pub struct InMemoryConnection {
+    url: Url,
+    tx: UnboundedSender<WsMessage>,
+    rx: UnboundedReceiver<WsMessage>,
+}
+
+impl InMemoryConnection {
+    /// Url requested by the client
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// Send message to the client
+    pub fn send(&self, msg: WsMessage) -> Result<(), TransportError> {
+        self.tx
+            .unbounded_send(msg)
+            .map_err(|_| TransportError::msg("connection closed"))
+    }
+
+    /// Receive next message from the client
+    ///
+    /// Return `None` when the client drop the connection.
+    pub async fn recv(&mut self) -> Option<WsMessage> {
+        self.rx.next().await
+    }
+
+    /// Split into sender and receiver
+    pub fn into_split(self) -> (UnboundedSender<WsMessage>, UnboundedReceiver<WsMessage>) {
+        (self.tx, self.rx)
+    }
+}
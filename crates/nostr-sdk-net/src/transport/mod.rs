@@ -0,0 +1,236 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! WebSocket transport
+//!
+//! The [`WebSocketTransport`] trait allow to replace the WebSocket stack used to connect to relays
+//! (i.e. custom TLS roots, certificate pinning, HTTP CONNECT proxy, embedded Tor client, ...).
+
+use std::fmt;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+pub use async_trait::async_trait;
+use futures_util::{Sink, Stream};
+use url_fork::Url;
+
+mod default;
+mod memory;
+
+pub use self::default::DefaultWebSocketTransport;
+pub use self::memory::{InMemoryConnection, InMemoryListener, InMemoryTransport};
+
+/// WebSocket message
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum WsMessage {
+    /// Text message
+    Text(String),
+    /// Binary message
+    Binary(Vec<u8>),
+    /// Ping message
+    Ping(Vec<u8>),
+    /// Pong message
+    Pong(Vec<u8>),
+    /// Close message, with optional close frame
+    Close(Option<CloseFrame>),
+}
+
+/// Close frame
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CloseFrame {
+    /// Close code (RFC 6455, section 7.4)
+    pub code: u16,
+    /// Close reason
+    pub reason: String,
+}
+
+impl WsMessage {
+    /// Payload length
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Text(text) => text.len(),
+            Self::Binary(data) | Self::Ping(data) | Self::Pong(data) => data.len(),
+            Self::Close(Some(frame)) => 2 + frame.reason.len(),
+            Self::Close(None) => 0,
+        }
+    }
+
+    /// Check if the payload is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Consume the message and get the payload
+    pub fn into_data(self) -> Vec<u8> {
+        match self {
+            Self::Text(text) => text.into_bytes(),
+            Self::Binary(data) | Self::Ping(data) | Self::Pong(data) => data,
+            Self::Close(Some(frame)) => {
+                let mut data: Vec<u8> = frame.code.to_be_bytes().to_vec();
+                data.extend_from_slice(frame.reason.as_bytes());
+                data
+            }
+            Self::Close(None) => Vec::new(),
+        }
+    }
+}
+
+/// Transport error
+#[derive(Debug)]
+pub struct TransportError(Box<dyn std::error::Error + Send + Sync>);
+
+impl std::error::Error for TransportError {}
+
+impl fmt::Display for TransportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl TransportError {
+    /// New error from the transport backend
+    pub fn backend<E>(error: E) -> Self
+    where
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        Self(Box::new(error))
+    }
+
+    /// New error from message
+    pub fn msg<S>(msg: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self(msg.into().into())
+    }
+}
+
+/// Traffic stats of a WebSocket connection, updated by the [`WebSocketTransport`]
+#[derive(Debug, Clone, Default)]
+pub struct TransportStats {
+    bytes_sent: Arc<AtomicUsize>,
+    bytes_received: Arc<AtomicUsize>,
+    compression: Arc<AtomicBool>,
+}
+
+impl TransportStats {
+    /// New empty stats
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bytes sent on the wire (TLS, WebSocket handshake and framing included)
+    pub fn bytes_sent(&self) -> usize {
+        self.bytes_sent.load(Ordering::SeqCst)
+    }
+
+    /// Bytes received from the wire (TLS, WebSocket handshake and framing included)
+    pub fn bytes_received(&self) -> usize {
+        self.bytes_received.load(Ordering::SeqCst)
+    }
+
+    /// Check if `permessage-deflate` was negotiated for the last connection
+    pub fn compression(&self) -> bool {
+        self.compression.load(Ordering::SeqCst)
+    }
+
+    /// Add bytes sent on the wire
+    pub fn add_bytes_sent(&self, size: usize) {
+        self.bytes_sent.fetch_add(size, Ordering::SeqCst);
+    }
+
+    /// Add bytes received from the wire
+    pub fn add_bytes_received(&self, size: usize) {
+        self.bytes_received.fetch_add(size, Ordering::SeqCst);
+    }
+
+    /// Set if `permessage-deflate` was negotiated
+    pub fn set_compression(&self, compression: bool) {
+        self.compression.store(compression, Ordering::SeqCst);
+    }
+}
+
+/// [`WebSocketTransport::connect`] options
+#[derive(Debug, Clone, Default)]
+pub struct ConnectOptions {
+    /// Connection timeout (default: transport default)
+    pub timeout: Option<Duration>,
+    /// Negotiate `permessage-deflate` compression, if supported by both transport and relay (default: false)
+    ///
+    /// Transports without compression support ignore it.
+    pub compression: bool,
+    /// Traffic stats
+    pub stats: TransportStats,
+}
+
+impl ConnectOptions {
+    /// New default options
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set connection timeout
+    pub fn timeout(self, timeout: Option<Duration>) -> Self {
+        Self { timeout, ..self }
+    }
+
+    /// Negotiate `permessage-deflate` compression
+    pub fn compression(self, compression: bool) -> Self {
+        Self {
+            compression,
+            ..self
+        }
+    }
+
+    /// Set [`TransportStats`] to update
+    pub fn stats(self, stats: TransportStats) -> Self {
+        Self { stats, ..self }
+    }
+}
+
+/// Sink half of a WebSocket connection
+#[cfg(not(target_arch = "wasm32"))]
+pub type BoxedSink = Pin<Box<dyn Sink<WsMessage, Error = TransportError> + Send>>;
+/// Sink half of a WebSocket connection
+#[cfg(target_arch = "wasm32")]
+pub type BoxedSink = Pin<Box<dyn Sink<WsMessage, Error = TransportError>>>;
+
+/// Stream half of a WebSocket connection
+#[cfg(not(target_arch = "wasm32"))]
+pub type BoxedStream = Pin<Box<dyn Stream<Item = Result<WsMessage, TransportError>> + Send>>;
+/// Stream half of a WebSocket connection
+#[cfg(target_arch = "wasm32")]
+pub type BoxedStream = Pin<Box<dyn Stream<Item = Result<WsMessage, TransportError>>>>;
+
+/// A type-erased [`WebSocketTransport`]
+pub type DynWebSocketTransport = dyn WebSocketTransport;
+
+/// Super trait of [`WebSocketTransport`]: `Send` and `Sync` are not required on WASM.
+#[cfg(not(target_arch = "wasm32"))]
+pub trait TransportDeps: fmt::Debug + Send + Sync {}
+#[cfg(not(target_arch = "wasm32"))]
+impl<T: fmt::Debug + Send + Sync> TransportDeps for T {}
+
+/// Super trait of [`WebSocketTransport`]: `Send` and `Sync` are not required on WASM.
+#[cfg(target_arch = "wasm32")]
+pub trait TransportDeps: fmt::Debug {}
+#[cfg(target_arch = "wasm32")]
+impl<T: fmt::Debug> TransportDeps for T {}
+
+/// WebSocket transport
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait WebSocketTransport: TransportDeps {
+    /// Open a WebSocket connection to `url`
+    ///
+    /// The transport should update the [`TransportStats`] in `opts` with the bytes exchanged on the wire.
+    /// Proxy and TLS are up to the transport: the SDK rejects them when combined with a custom transport.
+    async fn connect(
+        &self,
+        url: &Url,
+        opts: &ConnectOptions,
+    ) -> Result<(BoxedSink, BoxedStream), TransportError>;
+}
@@ -12,7 +12,8 @@ use ws_stream_wasm::{WsErr, WsMessage, WsMeta, WsStream};
 type Sink = SplitSink<WsStream, WsMessage>;
 type Stream = SplitStream<WsStream>;
 
-pub async fn connect(url: &Url) -> Result<(Sink, Stream), WsErr> {
-    let (_ws, stream) = WsMeta::connect(url, None).await?;
-    Ok(stream.split())
+pub async fn connect(url: &Url) -> Result<(WsMeta, Sink, Stream), WsErr> {
+    let (meta, stream) = WsMeta::connect(url, None).await?;
+    let (sink, stream) = stream.split();
+    Ok((meta, sink, stream))
 }
@@ -0,0 +1,39 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+use std::time::Duration;
+
+use nostr_sdk::prelude::*;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    tracing_subscriber::fmt::init();
+
+    // In-process relay: OKs are delayed by 1 sec
+    let opts = MockRelayOptions::new().ok_delay(Duration::from_secs(1));
+    let mock = MockRelay::run_with_opts(opts);
+
+    let my_keys = Keys::generate();
+    let opts = Options::new().wait_for_connection(true);
+    let client = Client::with_opts(&my_keys, opts);
+    client
+        .add_relay_with_opts(mock.url(), None, mock.relay_opts())
+        .await?;
+
+    client.connect().await;
+
+    let event_id = client.publish_text_note("Hello mock relay", []).await?;
+    println!("Published {event_id}");
+
+    let filter = Filter::new().author(my_keys.public_key());
+    let events = client
+        .get_events_of(vec![filter], Some(Duration::from_secs(5)))
+        .await?;
+    println!("Received {} events", events.len());
+
+    // Drop the connection: the client will reconnect
+    mock.disconnect_all();
+
+    Ok(())
+}
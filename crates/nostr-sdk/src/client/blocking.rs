@@ -21,7 +21,10 @@ use tokio::sync::broadcast;
 #[cfg(feature = "nip46")]
 use super::signer::remote::RemoteSigner;
 use super::{Error, Options, TryIntoUrl};
-use crate::relay::{pool, Relay, RelayOptions, RelayPoolNotification};
+use crate::relay::{
+    pool, EventCount, ListenerOptions, PoolEventListener, Reconciliation, Relay, RelayOptions,
+    RelayPoolNotification,
+};
 use crate::{ClientBuilder, NegentropyOptions, RUNTIME};
 
 #[derive(Debug, Clone)]
@@ -79,6 +82,11 @@ impl Client {
         self.client.update_difficulty(difficulty);
     }
 
+    /// Cancel the POW mining in progress
+    pub fn cancel_pow(&self) {
+        RUNTIME.block_on(async { self.client.cancel_pow().await })
+    }
+
     /// Get current [`Keys`]
     pub fn keys(&self) -> Keys {
         RUNTIME.block_on(async { self.client.keys().await })
@@ -118,6 +126,11 @@ impl Client {
         self.client.notifications()
     }
 
+    /// Get new [`PoolEvent`] listener
+    pub fn listen(&self, opts: ListenerOptions) -> PoolEventListener {
+        RUNTIME.block_on(async { self.client.listen(opts).await })
+    }
+
     /// Get relays
     pub fn relays(&self) -> HashMap<Url, Relay> {
         RUNTIME.block_on(async { self.client.relays().await })
@@ -216,6 +229,14 @@ impl Client {
         RUNTIME.block_on(async { self.client.get_events_of(filters, timeout).await })
     }
 
+    pub fn count(
+        &self,
+        filters: Vec<Filter>,
+        timeout: Option<Duration>,
+    ) -> Result<EventCount, Error> {
+        RUNTIME.block_on(async { self.client.count(filters, timeout).await })
+    }
+
     pub fn req_events_of(&self, filters: Vec<Filter>, timeout: Option<Duration>) {
         RUNTIME.block_on(async {
             self.client.req_events_of(filters, timeout).await;
@@ -429,10 +450,19 @@ impl Client {
     }
 
     /// Negentropy reconciliation
-    pub fn reconcile(&self, filter: Filter, opts: NegentropyOptions) -> Result<(), Error> {
+    pub fn reconcile(
+        &self,
+        filter: Filter,
+        opts: NegentropyOptions,
+    ) -> Result<Reconciliation, Error> {
         RUNTIME.block_on(async move { self.client.reconcile(filter, opts).await })
     }
 
+    /// Sync filter across all relays
+    pub fn sync(&self, filter: Filter, opts: NegentropyOptions) -> Result<Reconciliation, Error> {
+        RUNTIME.block_on(async move { self.client.sync(filter, opts).await })
+    }
+
     pub fn get_channels(&self, timeout: Option<Duration>) -> Result<Vec<Event>, Error> {
         RUNTIME.block_on(async { self.client.get_channels(timeout).await })
     }
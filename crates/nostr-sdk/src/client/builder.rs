@@ -6,12 +6,15 @@
 
 use std::sync::Arc;
 
+use nostr::event::pipeline::{DelegationHook, EventBuilderPipeline, EventHook};
+use nostr::nips::nip26::DelegationTag;
 use nostr::Keys;
 use nostr_database::memory::MemoryDatabase;
 use nostr_database::{DynNostrDatabase, IntoNostrDatabase};
 
 #[cfg(feature = "nip46")]
 use super::RemoteSigner;
+use crate::relay::WebSocketTransport;
 use crate::{Client, Options};
 
 /// Client builder
@@ -20,6 +23,7 @@ pub struct ClientBuilder {
     pub(super) keys: Keys,
     pub(super) database: Arc<DynNostrDatabase>,
     pub(super) opts: Options,
+    pub(super) pipeline: EventBuilderPipeline,
     #[cfg(feature = "nip46")]
     pub(super) remote_signer: Option<RemoteSigner>,
 }
@@ -31,6 +35,7 @@ impl ClientBuilder {
             keys: keys.clone(),
             database: Arc::new(MemoryDatabase::default()),
             opts: Options::default(),
+            pipeline: EventBuilderPipeline::new(),
             #[cfg(feature = "nip46")]
             remote_signer: None,
         }
@@ -51,6 +56,44 @@ impl ClientBuilder {
         self
     }
 
+    /// Set default [`WebSocketTransport`] of the relays
+    ///
+    /// See [`RelayPoolOptions::transport`](crate::RelayPoolOptions::transport).
+    pub fn transport<T>(mut self, transport: T) -> Self
+    where
+        T: WebSocketTransport + 'static,
+    {
+        self.opts.pool = self.opts.pool.transport(transport);
+        self
+    }
+
+    /// Set [`EventBuilderPipeline`]
+    ///
+    /// The hooks are executed on every event built by the [`Client`], before signing.
+    pub fn pipeline(mut self, pipeline: EventBuilderPipeline) -> Self {
+        self.pipeline = pipeline;
+        self
+    }
+
+    /// Add pre-sign hook at the end of the [`EventBuilderPipeline`]
+    pub fn hook<H>(mut self, hook: H) -> Self
+    where
+        H: EventHook + 'static,
+    {
+        self.pipeline.push(hook);
+        self
+    }
+
+    /// Sign events on behalf of a delegator
+    ///
+    /// Add a [`DelegationHook`] to the [`EventBuilderPipeline`]: the NIP26 `delegation` tag is added
+    /// to every event built by the [`Client`] and events not allowed by the delegation conditions are vetoed.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/26.md>
+    pub fn delegation(self, tag: DelegationTag) -> Self {
+        self.hook(DelegationHook::new(tag))
+    }
+
     /// Set remote signer
     #[cfg(feature = "nip46")]
     pub fn remote_signer(mut self, remote_signer: RemoteSigner) -> Self {
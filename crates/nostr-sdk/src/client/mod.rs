@@ -13,6 +13,8 @@ use std::time::Duration;
 
 use async_utility::thread;
 use nostr::event::builder::Error as EventBuilderError;
+use nostr::event::pipeline::{Error as PipelineError, EventBuilderPipeline};
+use nostr::event::pow::{CancellationToken, PowOptions};
 use nostr::key::XOnlyPublicKey;
 #[cfg(feature = "nip46")]
 use nostr::nips::nip46::{NostrConnectMetadata, NostrConnectURI, Request, Response};
@@ -20,8 +22,8 @@ use nostr::nips::nip94::FileMetadata;
 use nostr::types::metadata::Error as MetadataError;
 use nostr::url::Url;
 use nostr::{
-    ChannelId, ClientMessage, Contact, Event, EventBuilder, EventId, Filter, JsonUtil, Keys, Kind,
-    Metadata, Result, Tag, Timestamp,
+    ChannelId, ClientMessage, Contact, Event, EventBuilder, EventId, Filter, Keys, Kind, Metadata,
+    Result, Tag, Timestamp,
 };
 use nostr_database::DynNostrDatabase;
 use nostr_sdk_net::futures_util::Future;
@@ -31,18 +33,21 @@ use tokio::sync::{broadcast, RwLock};
 pub mod blocking;
 pub mod builder;
 pub mod options;
+pub mod profile;
 #[cfg(feature = "nip46")]
 pub mod signer;
 
 pub use self::builder::ClientBuilder;
-pub use self::options::Options;
+pub use self::options::{Options, ProfileCacheOptions};
+pub use self::profile::{CachedProfile, ProfileCache, ProfileNotification};
 #[cfg(feature = "nip46")]
 pub use self::signer::remote::RemoteSigner;
 use crate::relay::pool::{self, Error as RelayPoolError, RelayPool};
 use crate::relay::{
-    FilterOptions, NegentropyOptions, Relay, RelayOptions, RelayPoolNotification, RelaySendOptions,
+    EventCount, FilterOptions, ListenerOptions, NegentropyOptions, PoolEventListener, QueryMode,
+    Reconciliation, Relay, RelayOptions, RelayPoolNotification, RelaySendOptions,
 };
-use crate::util::TryIntoUrl;
+use crate::util::{self, TryIntoUrl};
 
 /// [`Client`] error
 #[derive(Debug, thiserror::Error)]
@@ -62,6 +67,9 @@ pub enum Error {
     /// Unsigned event error
     #[error("unsigned event error: {0}")]
     UnsignedEvent(#[from] nostr::event::unsigned::Error),
+    /// [`EventBuilderPipeline`] error
+    #[error("event pipeline error: {0}")]
+    Pipeline(#[from] PipelineError),
     /// Secp256k1 error
     #[error("secp256k1 error: {0}")]
     Secp256k1(#[from] nostr::secp256k1::Error),
@@ -118,6 +126,9 @@ pub struct Client {
     pool: RelayPool,
     keys: Arc<RwLock<Keys>>,
     opts: Options,
+    pipeline: EventBuilderPipeline,
+    profile_cache: ProfileCache,
+    pow_cancellation: Arc<RwLock<CancellationToken>>,
     dropped: Arc<AtomicBool>,
     #[cfg(feature = "nip46")]
     remote_signer: Option<RemoteSigner>,
@@ -194,21 +205,45 @@ impl Client {
 
     /// Compose [`Client`] from [`ClientBuilder`]
     pub fn from_builder(builder: ClientBuilder) -> Self {
+        let pool = RelayPool::with_database(builder.opts.pool.clone(), builder.database);
         Self {
-            pool: RelayPool::with_database(builder.opts.pool, builder.database),
+            profile_cache: ProfileCache::new(pool.clone(), builder.opts.profile_cache),
+            pool,
             keys: Arc::new(RwLock::new(builder.keys)),
             opts: builder.opts,
+            pipeline: builder.pipeline,
+            pow_cancellation: Arc::new(RwLock::new(CancellationToken::new())),
             dropped: Arc::new(AtomicBool::new(false)),
             #[cfg(feature = "nip46")]
             remote_signer: builder.remote_signer,
         }
     }
 
+    /// Get [`EventBuilderPipeline`]
+    pub fn pipeline(&self) -> &EventBuilderPipeline {
+        &self.pipeline
+    }
+
     /// Update default difficulty for new [`Event`]
     pub fn update_difficulty(&self, difficulty: u8) {
         self.opts.update_difficulty(difficulty);
     }
 
+    /// Cancel the POW mining in progress
+    ///
+    /// Events built after this call are mined with a new cancellation token.
+    pub async fn cancel_pow(&self) {
+        let mut token = self.pow_cancellation.write().await;
+        token.cancel();
+        *token = CancellationToken::new();
+    }
+
+    /// [`PowOptions`] with the cancellation token of the current call
+    async fn pow_opts(&self) -> PowOptions {
+        let token: CancellationToken = self.pow_cancellation.read().await.clone();
+        self.opts.pow.clone().cancellation(token)
+    }
+
     /// Get current [`Keys`]
     pub async fn keys(&self) -> Keys {
         let keys = self.keys.read().await;
@@ -231,6 +266,37 @@ impl Client {
         self.pool.database()
     }
 
+    /// Get [`ProfileCache`]
+    ///
+    /// Shared by all the clones of the [`Client`]: use it to request profiles
+    /// instead of querying the relays from every part of the app.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use nostr_sdk::prelude::*;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let my_keys = Keys::generate();
+    /// #   let client = Client::new(&my_keys);
+    /// let cache = client.profile_cache();
+    /// let mut notifications = cache.notifications();
+    ///
+    /// let profile = cache.get(my_keys.public_key()).await;
+    /// println!("{:?}", profile.metadata);
+    ///
+    /// // Stale profiles are refreshed in background
+    /// while let Ok(notification) = notifications.recv().await {
+    ///     if let ProfileNotification::Metadata { public_key, metadata } = notification {
+    ///         println!("{public_key} updated: {:?}", metadata.name);
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub fn profile_cache(&self) -> ProfileCache {
+        self.profile_cache.clone()
+    }
+
     /// Get NIP46 uri
     #[cfg(feature = "nip46")]
     pub async fn nostr_connect_uri(
@@ -287,6 +353,33 @@ impl Client {
         self.pool.notifications()
     }
 
+    /// Get new [`PoolEvent`] listener
+    ///
+    /// Lossless and typed alternative to [`Client::notifications`], with topic filtering.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use nostr_sdk::prelude::*;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let my_keys = Keys::generate();
+    /// #   let client = Client::new(&my_keys);
+    /// let opts = ListenerOptions::new().topics(Topics::new().kind(Kind::TextNote));
+    /// let mut listener = client.listen(opts).await;
+    /// while let Some(event) = listener.recv().await {
+    ///     match event {
+    ///         PoolEvent::Event { event, .. } => println!("{}", event.content),
+    ///         PoolEvent::Lagged { missed } => println!("Missed {missed} events"),
+    ///         _ => (),
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub async fn listen(&self, opts: ListenerOptions) -> PoolEventListener {
+        self.pool.listen(opts).await
+    }
+
     /// Get relays
     pub async fn relays(&self) -> HashMap<Url, Relay> {
         self.pool.relays().await
@@ -497,6 +590,19 @@ impl Client {
         Ok(())
     }
 
+    /// Reconnect relay now
+    ///
+    /// Reset the reconnection backoff (see [`Relay::reconnect`]).
+    pub async fn reconnect_relay<U>(&self, url: U) -> Result<(), Error>
+    where
+        U: TryIntoUrl,
+        pool::Error: From<<U as TryIntoUrl>::Err>,
+    {
+        let relay: Relay = self.relay(url).await?;
+        relay.reconnect().await;
+        Ok(())
+    }
+
     /// Disconnect relay
     ///
     /// # Example
@@ -646,11 +752,144 @@ impl Client {
         timeout: Option<Duration>,
         opts: FilterOptions,
     ) -> Result<Vec<Event>, Error> {
+        self.get_events_of_with_mode(filters, timeout, opts, self.opts.query_mode)
+            .await
+    }
+
+    /// Get events of filters with [`FilterOptions`] and [`QueryMode`]
+    ///
+    /// With [`QueryMode::CacheFirst`] only the stored events are returned: the newer ones are requested in the background
+    /// (see [`Client::get_events_of_with_callback`]). If nothing is stored, the events of the relays are returned.
+    ///
+    /// If timeout is set to `None`, the default from [`Options`] will be used.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use std::time::Duration;
+    ///
+    /// use nostr_sdk::prelude::*;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let my_keys = Keys::generate();
+    /// #   let client = Client::new(&my_keys);
+    /// // Stored events immediately available offline, the newer ones are notified
+    /// let filter = Filter::new().author(my_keys.public_key()).kind(Kind::TextNote);
+    /// let _events = client
+    ///     .get_events_of_with_mode(
+    ///         vec![filter],
+    ///         Some(Duration::from_secs(10)),
+    ///         FilterOptions::ExitOnEOSE,
+    ///         QueryMode::CacheFirst,
+    ///     )
+    ///     .await
+    ///     .unwrap();
+    /// # }
+    /// ```
+    pub async fn get_events_of_with_mode(
+        &self,
+        filters: Vec<Filter>,
+        timeout: Option<Duration>,
+        opts: FilterOptions,
+        mode: QueryMode,
+    ) -> Result<Vec<Event>, Error> {
+        let timeout: Duration = match timeout {
+            Some(t) => t,
+            None => self.opts.timeout,
+        };
+        Ok(self
+            .pool
+            .get_events_of_with_mode(filters, timeout, opts, mode)
+            .await?)
+    }
+
+    /// Get events of filters with [`FilterOptions`] and [`QueryMode`], delivered with `callback` as soon as available
+    ///
+    /// With [`QueryMode::CacheFirst`] the stored events are delivered immediately, then the newer ones as they are received from relays.
+    ///
+    /// If timeout is set to `None`, the default from [`Options`] will be used.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use std::time::Duration;
+    ///
+    /// use nostr_sdk::prelude::*;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let my_keys = Keys::generate();
+    /// #   let client = Client::new(&my_keys);
+    /// let filter = Filter::new().author(my_keys.public_key()).kind(Kind::TextNote);
+    /// client
+    ///     .get_events_of_with_callback(
+    ///         vec![filter],
+    ///         Some(Duration::from_secs(10)),
+    ///         FilterOptions::ExitOnEOSE,
+    ///         QueryMode::CacheFirst,
+    ///         |event| async move {
+    ///             println!("{}", event.content);
+    ///         },
+    ///     )
+    ///     .await
+    ///     .unwrap();
+    /// # }
+    /// ```
+    pub async fn get_events_of_with_callback<F>(
+        &self,
+        filters: Vec<Filter>,
+        timeout: Option<Duration>,
+        opts: FilterOptions,
+        mode: QueryMode,
+        callback: impl Fn(Event) -> F,
+    ) -> Result<(), Error>
+    where
+        F: Future<Output = ()>,
+    {
         let timeout: Duration = match timeout {
             Some(t) => t,
             None => self.opts.timeout,
         };
-        Ok(self.pool.get_events_of(filters, timeout, opts).await?)
+        Ok(self
+            .pool
+            .get_events_of_with_callback(filters, timeout, opts, mode, callback)
+            .await?)
+    }
+
+    /// Count events of filters (NIP45)
+    ///
+    /// Query the local database and all the read relays: relays without `COUNT` support are asked for the events instead.
+    /// Counts are reported by relay, together with a conservative aggregate (see [`EventCount::total`]).
+    ///
+    /// If timeout is set to `None`, the default from [`Options`] will be used.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use std::time::Duration;
+    ///
+    /// use nostr_sdk::prelude::*;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let my_keys = Keys::generate();
+    /// #   let client = Client::new(&my_keys);
+    /// let filter = Filter::new().pubkey(my_keys.public_key()).kind(Kind::ContactList);
+    /// let count = client
+    ///     .count(vec![filter], Some(Duration::from_secs(10)))
+    ///     .await
+    ///     .unwrap();
+    /// println!("Followers: {}", count.total);
+    /// # }
+    /// ```
+    pub async fn count(
+        &self,
+        filters: Vec<Filter>,
+        timeout: Option<Duration>,
+    ) -> Result<EventCount, Error> {
+        let timeout: Duration = match timeout {
+            Some(t) => t,
+            None => self.opts.timeout,
+        };
+        Ok(self.pool.count(filters, timeout).await?)
     }
 
     /// Request events of filters
@@ -762,9 +1001,17 @@ impl Client {
             let unsigned_event = {
                 let difficulty: u8 = self.opts.get_difficulty();
                 if difficulty > 0 {
-                    builder.to_unsigned_pow_event(signer_public_key, difficulty)
+                    util::unsigned_pow_event(
+                        &self.pipeline,
+                        builder,
+                        signer_public_key,
+                        difficulty,
+                        &self.pow_opts().await,
+                    )
+                    .await?
                 } else {
-                    builder.to_unsigned_event(signer_public_key)
+                    self.pipeline
+                        .to_unsigned_event(builder, signer_public_key)?
                 }
             };
             let res: Response = self
@@ -780,22 +1027,38 @@ impl Client {
             }
         } else {
             let difficulty: u8 = self.opts.get_difficulty();
-            let keys = self.keys.read().await;
+            let keys: Keys = self.keys().await;
             if difficulty > 0 {
-                builder.to_pow_event(&keys, difficulty)?
+                util::unsigned_pow_event(
+                    &self.pipeline,
+                    builder,
+                    keys.public_key(),
+                    difficulty,
+                    &self.pow_opts().await,
+                )
+                .await?
+                .sign(&keys)?
             } else {
-                builder.to_event(&keys)?
+                self.pipeline.to_event(builder, &keys)?
             }
         };
 
         #[cfg(not(feature = "nip46"))]
         let event: Event = {
             let difficulty: u8 = self.opts.get_difficulty();
-            let keys = self.keys.read().await;
+            let keys: Keys = self.keys().await;
             if difficulty > 0 {
-                builder.to_pow_event(&keys, difficulty)?
+                util::unsigned_pow_event(
+                    &self.pipeline,
+                    builder,
+                    keys.public_key(),
+                    difficulty,
+                    &self.pow_opts().await,
+                )
+                .await?
+                .sign(&keys)?
             } else {
-                builder.to_event(&keys)?
+                self.pipeline.to_event(builder, &keys)?
             }
         };
 
@@ -982,35 +1245,25 @@ impl Client {
     }
 
     /// Get contact list [`Metadata`]
+    ///
+    /// Profiles are taken from the [`ProfileCache`].
     pub async fn get_contact_list_metadata(
         &self,
         timeout: Option<Duration>,
     ) -> Result<HashMap<XOnlyPublicKey, Metadata>, Error> {
         let public_keys = self.get_contact_list_public_keys(timeout).await?;
-        let mut contacts: HashMap<XOnlyPublicKey, Metadata> =
-            public_keys.iter().map(|p| (*p, Metadata::new())).collect();
-
-        let chunk_size: usize = self.opts.get_req_filters_chunk_size();
-        for chunk in public_keys.chunks(chunk_size) {
-            let mut filters: Vec<Filter> = Vec::new();
-            for public_key in chunk.iter() {
-                filters.push(
-                    Filter::new()
-                        .author(*public_key)
-                        .kind(Kind::Metadata)
-                        .limit(1),
-                );
-            }
-            let events: Vec<Event> = self.get_events_of(filters, timeout).await?;
-            for event in events.into_iter() {
-                let metadata = Metadata::from_json(&event.content)?;
-                if let Some(m) = contacts.get_mut(&event.pubkey) {
-                    *m = metadata
-                };
+        let profiles = match timeout {
+            Some(timeout) => {
+                self.profile_cache
+                    .get_many_with_timeout(public_keys, timeout)
+                    .await
             }
-        }
-
-        Ok(contacts)
+            None => self.profile_cache.get_many(public_keys).await,
+        };
+        Ok(profiles
+            .into_iter()
+            .map(|(public_key, profile)| (public_key, profile.metadata.unwrap_or_default()))
+            .collect())
     }
 
     /// Send encrypted direct message
@@ -1309,7 +1562,11 @@ impl Client {
     }
 
     /// Negentropy reconciliation
-    pub async fn reconcile(&self, filter: Filter, opts: NegentropyOptions) -> Result<(), Error> {
+    pub async fn reconcile(
+        &self,
+        filter: Filter,
+        opts: NegentropyOptions,
+    ) -> Result<Reconciliation, Error> {
         Ok(self.pool.reconcile(filter, opts).await?)
     }
 
@@ -1319,10 +1576,30 @@ impl Client {
         filter: Filter,
         items: Vec<(EventId, Timestamp)>,
         opts: NegentropyOptions,
-    ) -> Result<(), Error> {
+    ) -> Result<Reconciliation, Error> {
         Ok(self.pool.reconcile_with_items(filter, items, opts).await?)
     }
 
+    /// Negentropy reconciliation of multiple filters
+    pub async fn reconcile_multi(
+        &self,
+        filters: Vec<Filter>,
+        opts: NegentropyOptions,
+    ) -> Result<Reconciliation, Error> {
+        Ok(self.pool.reconcile_multi(filters, opts).await?)
+    }
+
+    /// Sync filter across all relays
+    ///
+    /// Fetch the events missing from the local database and send to each relay the events that it lacks.
+    pub async fn sync(
+        &self,
+        filter: Filter,
+        opts: NegentropyOptions,
+    ) -> Result<Reconciliation, Error> {
+        Ok(self.pool.sync(filter, opts).await?)
+    }
+
     /// Get a list of channels
     pub async fn get_channels(&self, timeout: Option<Duration>) -> Result<Vec<Event>, Error> {
         self.get_events_of(vec![Filter::new().kind(Kind::ChannelCreation)], timeout)
@@ -4,11 +4,15 @@
 
 //! Client Options
 
+#[cfg(not(target_arch = "wasm32"))]
+use std::net::SocketAddr;
 use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 
-use crate::relay::RelayPoolOptions;
+use nostr::event::pow::PowOptions;
+
+use crate::relay::{QueryMode, RelayPoolOptions};
 
 pub(crate) const DEFAULT_SEND_TIMEOUT: Duration = Duration::from_secs(20);
 
@@ -23,8 +27,8 @@ pub struct Options {
     wait_for_subscription: Arc<AtomicBool>,
     /// POW difficulty for all events (default: 0)
     difficulty: Arc<AtomicU8>,
-    /// REQ filters chunk size (default: 10)
-    req_filters_chunk_size: Arc<AtomicU8>,
+    /// POW mining options
+    pub pow: PowOptions,
     /// Skip disconnected relays during send methods (default: true)
     ///
     /// If the relay made just 1 attempt, the relay will not be skipped
@@ -35,6 +39,10 @@ pub struct Options {
     pub timeout: Duration,
     /// Send timeout (default: 20 secs)
     pub send_timeout: Option<Duration>,
+    /// Query mode (default: [`QueryMode::NetworkFirst`])
+    ///
+    /// Used in `get_events_of` and similar as default [`QueryMode`].
+    pub query_mode: QueryMode,
     /// NIP46 timeout (default: 180 secs)
     #[cfg(feature = "nip46")]
     pub nip46_timeout: Option<Duration>,
@@ -42,6 +50,8 @@ pub struct Options {
     pub shutdown_on_drop: bool,
     /// Pool Options
     pub pool: RelayPoolOptions,
+    /// Profile cache options
+    pub profile_cache: ProfileCacheOptions,
 }
 
 impl Default for Options {
@@ -51,14 +61,16 @@ impl Default for Options {
             wait_for_send: Arc::new(AtomicBool::new(true)),
             wait_for_subscription: Arc::new(AtomicBool::new(false)),
             difficulty: Arc::new(AtomicU8::new(0)),
-            req_filters_chunk_size: Arc::new(AtomicU8::new(10)),
+            pow: PowOptions::default(),
             skip_disconnected_relays: Arc::new(AtomicBool::new(true)),
             timeout: Duration::from_secs(60),
             send_timeout: Some(DEFAULT_SEND_TIMEOUT),
+            query_mode: QueryMode::default(),
             #[cfg(feature = "nip46")]
             nip46_timeout: Some(Duration::from_secs(180)),
             shutdown_on_drop: false,
             pool: RelayPoolOptions::default(),
+            profile_cache: ProfileCacheOptions::default(),
         }
     }
 }
@@ -119,18 +131,24 @@ impl Options {
             .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |_| Some(difficulty));
     }
 
+    /// Set POW mining options (threads, deadline, progress, ...)
+    ///
+    /// The cancellation token is replaced by a new one for every event: use [`Client::cancel_pow`](crate::Client::cancel_pow).
+    pub fn pow(self, opts: PowOptions) -> Self {
+        Self { pow: opts, ..self }
+    }
+
     /// Set `REQ` filters chunk size
+    #[deprecated(
+        note = "profiles are fetched by the `ProfileCache`: use `ProfileCacheOptions::batch_size`"
+    )]
     pub fn req_filters_chunk_size(self, size: u8) -> Self {
         Self {
-            req_filters_chunk_size: Arc::new(AtomicU8::new(size)),
+            profile_cache: self.profile_cache.batch_size(size as usize),
             ..self
         }
     }
 
-    pub(crate) fn get_req_filters_chunk_size(&self) -> usize {
-        self.req_filters_chunk_size.load(Ordering::SeqCst) as usize
-    }
-
     /// Skip disconnected relays during send methods (default: true)
     ///
     /// If the relay made just 1 attempt, the relay will not be skipped
@@ -158,6 +176,14 @@ impl Options {
         }
     }
 
+    /// Set default [`QueryMode`]
+    pub fn query_mode(self, mode: QueryMode) -> Self {
+        Self {
+            query_mode: mode,
+            ..self
+        }
+    }
+
     /// Set NIP46 timeout
     #[cfg(feature = "nip46")]
     pub fn nip46_timeout(self, timeout: Option<Duration>) -> Self {
@@ -179,4 +205,129 @@ impl Options {
     pub fn pool(self, opts: RelayPoolOptions) -> Self {
         Self { pool: opts, ..self }
     }
+
+    /// Set profile cache options
+    pub fn profile_cache(self, opts: ProfileCacheOptions) -> Self {
+        Self {
+            profile_cache: opts,
+            ..self
+        }
+    }
+}
+
+/// [`ProfileCache`](super::ProfileCache) options
+#[derive(Debug, Clone, Copy)]
+pub struct ProfileCacheOptions {
+    /// After how long a cached profile is considered stale (default: 1 hour)
+    pub ttl: Duration,
+    /// Interval for the background refresh of the stale profiles (default: 10 min)
+    ///
+    /// Only the profiles accessed within the last `ttl` are refreshed.
+    /// If `None`, stale profiles are refreshed only when requested.
+    pub refresh_interval: Option<Duration>,
+    /// Max number of cached profiles (default: 10000)
+    ///
+    /// When exceeded, the least recently accessed profiles are evicted.
+    pub max_entries: usize,
+    /// After how long a not accessed profile is evicted (default: 1 day)
+    pub idle_timeout: Duration,
+    /// How long to wait for other requests before sending a batch (default: 100 millis)
+    pub batch_delay: Duration,
+    /// Max number of authors per `REQ` (default: 250)
+    pub batch_size: usize,
+    /// Timeout for the relays to reply (default: 10 secs)
+    pub timeout: Duration,
+    /// After how long a NIP05 verification must be repeated (default: 1 day)
+    pub nip05_ttl: Duration,
+    /// Proxy for the NIP05 verification
+    #[cfg(not(target_arch = "wasm32"))]
+    pub proxy: Option<SocketAddr>,
+}
+
+impl Default for ProfileCacheOptions {
+    fn default() -> Self {
+        Self {
+            ttl: Duration::from_secs(60 * 60),
+            refresh_interval: Some(Duration::from_secs(60 * 10)),
+            max_entries: 10_000,
+            idle_timeout: Duration::from_secs(60 * 60 * 24),
+            batch_delay: Duration::from_millis(100),
+            batch_size: 250,
+            timeout: Duration::from_secs(10),
+            nip05_ttl: Duration::from_secs(60 * 60 * 24),
+            #[cfg(not(target_arch = "wasm32"))]
+            proxy: None,
+        }
+    }
+}
+
+impl ProfileCacheOptions {
+    /// New default [`ProfileCacheOptions`]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set TTL
+    pub fn ttl(self, ttl: Duration) -> Self {
+        Self { ttl, ..self }
+    }
+
+    /// Set background refresh interval (`None` to disable it)
+    pub fn refresh_interval(self, interval: Option<Duration>) -> Self {
+        Self {
+            refresh_interval: interval,
+            ..self
+        }
+    }
+
+    /// Set max number of cached profiles
+    pub fn max_entries(self, max: usize) -> Self {
+        Self {
+            max_entries: max,
+            ..self
+        }
+    }
+
+    /// Set after how long a not accessed profile is evicted
+    pub fn idle_timeout(self, timeout: Duration) -> Self {
+        Self {
+            idle_timeout: timeout,
+            ..self
+        }
+    }
+
+    /// Set batch delay
+    pub fn batch_delay(self, delay: Duration) -> Self {
+        Self {
+            batch_delay: delay,
+            ..self
+        }
+    }
+
+    /// Set max number of authors per `REQ`
+    pub fn batch_size(self, size: usize) -> Self {
+        Self {
+            batch_size: size,
+            ..self
+        }
+    }
+
+    /// Set timeout
+    pub fn timeout(self, timeout: Duration) -> Self {
+        Self { timeout, ..self }
+    }
+
+    /// Set NIP05 verification TTL
+    pub fn nip05_ttl(self, ttl: Duration) -> Self {
+        Self {
+            nip05_ttl: ttl,
+            ..self
+        }
+    }
+
+    /// Set proxy for the NIP05 verification
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn proxy(self, proxy: Option<SocketAddr>) -> Self {
+        Self { proxy, ..self }
+    }
 }
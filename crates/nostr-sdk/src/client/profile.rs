@@ -0,0 +1,873 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Profile cache
+//!
+//! Metadata, contact list and relay list (kind 0, 3 and 10002) of the requested public keys.
+//! Profiles are loaded from the [`NostrDatabase`](nostr_database::NostrDatabase),
+//! fetched from relays in deduplicated batches and kept updated by the events received by the pool.
+//! Stale profiles are refreshed in background, as long as they are accessed.
+//! The least recently accessed profiles are evicted (see [`ProfileCacheOptions::max_entries`]).
+
+use std::collections::{HashMap, HashSet};
+use std::future;
+use std::mem;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_utility::{thread, time};
+use nostr::key::XOnlyPublicKey;
+#[cfg(feature = "nip05")]
+use nostr::nips::nip05;
+use nostr::nips::nip65;
+use nostr::{Event, Filter, JsonUtil, Kind, Metadata, RelayMetadata, Timestamp, UncheckedUrl};
+use tokio::sync::{broadcast, oneshot, Mutex, Notify, RwLock};
+
+use super::options::ProfileCacheOptions;
+use crate::relay::pool::RelayPool;
+use crate::relay::{FilterOptions, ListenerOptions, PoolEvent, QueryMode, Topics};
+
+const KINDS: [Kind; 3] = [Kind::Metadata, Kind::ContactList, Kind::RelayList];
+const NOTIFICATION_CHANNEL_SIZE: usize = 1024;
+
+/// Cached profile
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedProfile {
+    /// Public key
+    pub public_key: XOnlyPublicKey,
+    /// Metadata (kind 0)
+    pub metadata: Option<Metadata>,
+    /// Contact list public keys (kind 3)
+    pub contacts: Option<Vec<XOnlyPublicKey>>,
+    /// Relay list (NIP65)
+    pub relays: Option<Vec<(UncheckedUrl, Option<RelayMetadata>)>>,
+    /// NIP05 verification result, if already verified
+    pub nip05_verified: Option<bool>,
+    /// Last time the profile was successfully fetched from relays
+    pub last_fetch: Option<Timestamp>,
+}
+
+impl CachedProfile {
+    fn new(public_key: XOnlyPublicKey) -> Self {
+        Self {
+            public_key,
+            metadata: None,
+            contacts: None,
+            relays: None,
+            nip05_verified: None,
+            last_fetch: None,
+        }
+    }
+
+    /// Check if nothing is known about the profile
+    pub fn is_empty(&self) -> bool {
+        self.metadata.is_none() && self.contacts.is_none() && self.relays.is_none()
+    }
+}
+
+/// Profile cache notification
+#[derive(Debug, Clone)]
+pub enum ProfileNotification {
+    /// Updated metadata
+    Metadata {
+        /// Public key
+        public_key: XOnlyPublicKey,
+        /// Metadata
+        metadata: Box<Metadata>,
+    },
+    /// Updated contact list
+    Contacts {
+        /// Public key
+        public_key: XOnlyPublicKey,
+        /// Contact list public keys
+        contacts: Vec<XOnlyPublicKey>,
+    },
+    /// Updated relay list
+    RelayList {
+        /// Public key
+        public_key: XOnlyPublicKey,
+        /// Relay list
+        relays: Vec<(UncheckedUrl, Option<RelayMetadata>)>,
+    },
+    /// Updated NIP05 verification
+    #[cfg(feature = "nip05")]
+    Nip05 {
+        /// Public key
+        public_key: XOnlyPublicKey,
+        /// NIP05
+        nip05: String,
+        /// Verification result
+        verified: bool,
+    },
+}
+
+impl ProfileNotification {
+    /// Public key of the updated profile
+    pub fn public_key(&self) -> XOnlyPublicKey {
+        match self {
+            Self::Metadata { public_key, .. }
+            | Self::Contacts { public_key, .. }
+            | Self::RelayList { public_key, .. } => *public_key,
+            #[cfg(feature = "nip05")]
+            Self::Nip05 { public_key, .. } => *public_key,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Entry {
+    profile: CachedProfile,
+    created_at: HashMap<Kind, Timestamp>,
+    /// Last fetch attempt, also if failed
+    fetched_at: Option<Timestamp>,
+    /// Last access (UNIX timestamp)
+    accessed_at: AtomicU64,
+    /// Order of the last access, to evict the least recently accessed
+    access_seq: AtomicU64,
+    #[cfg(feature = "nip05")]
+    nip05_checked_at: Option<Timestamp>,
+}
+
+impl Entry {
+    fn new(public_key: XOnlyPublicKey, now: Timestamp, seq: u64) -> Self {
+        Self {
+            profile: CachedProfile::new(public_key),
+            created_at: HashMap::new(),
+            fetched_at: None,
+            accessed_at: AtomicU64::new(now.as_u64()),
+            access_seq: AtomicU64::new(seq),
+            #[cfg(feature = "nip05")]
+            nip05_checked_at: None,
+        }
+    }
+
+    fn is_stale(&self, now: Timestamp, ttl: Duration) -> bool {
+        match self.fetched_at {
+            Some(fetched_at) => fetched_at + ttl <= now,
+            None => true,
+        }
+    }
+
+    fn touch(&self, now: Timestamp, seq: u64) {
+        self.accessed_at.fetch_max(now.as_u64(), Ordering::SeqCst);
+        self.access_seq.fetch_max(seq, Ordering::SeqCst);
+    }
+
+    fn accessed_at(&self) -> Timestamp {
+        Timestamp::from(self.accessed_at.load(Ordering::SeqCst))
+    }
+
+    /// Update the profile if the event is newer than the cached one
+    fn update(&mut self, event: &Event) -> Option<ProfileNotification> {
+        if let Some(created_at) = self.created_at.get(&event.kind) {
+            if event.created_at <= *created_at {
+                return None;
+            }
+        }
+
+        let public_key: XOnlyPublicKey = event.pubkey;
+        let notification = match event.kind {
+            Kind::Metadata => {
+                let metadata = Metadata::from_json(&event.content).ok()?;
+                let old_nip05 = self
+                    .profile
+                    .metadata
+                    .as_ref()
+                    .and_then(|m| m.nip05.as_ref());
+                if old_nip05 != metadata.nip05.as_ref() {
+                    self.profile.nip05_verified = None;
+                    #[cfg(feature = "nip05")]
+                    {
+                        self.nip05_checked_at = None;
+                    }
+                }
+                self.profile.metadata = Some(metadata.clone());
+                ProfileNotification::Metadata {
+                    public_key,
+                    metadata: Box::new(metadata),
+                }
+            }
+            Kind::ContactList => {
+                let contacts: Vec<XOnlyPublicKey> = event.public_keys().copied().collect();
+                self.profile.contacts = Some(contacts.clone());
+                ProfileNotification::Contacts {
+                    public_key,
+                    contacts,
+                }
+            }
+            Kind::RelayList => {
+                let relays = nip65::extract_relay_list(event);
+                self.profile.relays = Some(relays.clone());
+                ProfileNotification::RelayList { public_key, relays }
+            }
+            _ => return None,
+        };
+
+        self.created_at.insert(event.kind, event.created_at);
+
+        Some(notification)
+    }
+}
+
+/// Requests waiting for a batch
+#[derive(Debug, Default)]
+struct Requests {
+    queued: HashMap<XOnlyPublicKey, Vec<oneshot::Sender<()>>>,
+    in_flight: HashMap<XOnlyPublicKey, Vec<oneshot::Sender<()>>>,
+}
+
+#[derive(Debug)]
+struct InternalProfileCache {
+    pool: RelayPool,
+    opts: ProfileCacheOptions,
+    entries: RwLock<HashMap<XOnlyPublicKey, Entry>>,
+    requests: Mutex<Requests>,
+    requests_notify: Notify,
+    notification_sender: broadcast::Sender<ProfileNotification>,
+    access_counter: AtomicU64,
+}
+
+impl InternalProfileCache {
+    fn next_access_seq(&self) -> u64 {
+        self.access_counter.fetch_add(1, Ordering::SeqCst)
+    }
+
+    fn run(self: Arc<Self>) {
+        tracing::debug!("Profile Cache Thread Started");
+        thread::spawn(async move {
+            let opts = ListenerOptions::new().topics(Topics::new().kinds(KINDS));
+            let mut listener = self.pool.listen(opts).await;
+            let mut next_refresh: Option<Timestamp> = self
+                .opts
+                .refresh_interval
+                .map(|interval| Timestamp::now() + interval);
+
+            loop {
+                let refresh = async move {
+                    match next_refresh {
+                        Some(next_refresh) => {
+                            let secs = next_refresh
+                                .as_u64()
+                                .saturating_sub(Timestamp::now().as_u64());
+                            thread::sleep(Duration::from_secs(secs)).await
+                        }
+                        None => future::pending().await,
+                    }
+                };
+
+                tokio::select! {
+                    event = listener.recv() => match event {
+                        Some(PoolEvent::Event { event, .. }) => self.ingest([event.as_ref()]).await,
+                        Some(PoolEvent::Lagged { missed }) => {
+                            // Recover the missed events, if stored by the database
+                            tracing::warn!("Profile cache missed {missed} events: re-syncing from database");
+                            self.resync().await;
+                        }
+                        Some(PoolEvent::Shutdown) | None => break,
+                        _ => (),
+                    },
+                    _ = self.requests_notify.notified() => {
+                        // Fetch off the loop, to keep receiving the events in the meantime
+                        let cache = self.clone();
+                        thread::spawn(async move {
+                            // Give the other callers the time to join the batch
+                            thread::sleep(cache.opts.batch_delay).await;
+                            cache.process_requests().await;
+                        });
+                    }
+                    _ = refresh => (),
+                }
+
+                if let (Some(interval), Some(refresh_at)) =
+                    (self.opts.refresh_interval, next_refresh)
+                {
+                    let now = Timestamp::now();
+                    if refresh_at <= now {
+                        next_refresh = Some(now + interval);
+                        self.evict(now).await;
+                        self.refresh_stale(now).await;
+                    }
+                }
+            }
+
+            tracing::debug!("Exited from Profile Cache thread");
+        });
+    }
+
+    /// Update the cached profiles and send the notifications
+    async fn ingest<'a, I>(&self, events: I)
+    where
+        I: IntoIterator<Item = &'a Event>,
+    {
+        let mut entries = self.entries.write().await;
+        for event in events.into_iter() {
+            if let Some(entry) = entries.get_mut(&event.pubkey) {
+                if let Some(notification) = entry.update(event) {
+                    let _ = self.notification_sender.send(notification);
+                }
+            }
+        }
+    }
+
+    /// Mark the public keys as accessed and add the unknown ones to the cache, loading their profiles from the database
+    async fn load(&self, public_keys: &HashSet<XOnlyPublicKey>) {
+        let now = Timestamp::now();
+        let seq: u64 = self.next_access_seq();
+        let missing: Vec<XOnlyPublicKey> = {
+            let entries = self.entries.read().await;
+            public_keys
+                .iter()
+                .filter(|p| match entries.get(p) {
+                    Some(entry) => {
+                        entry.touch(now, seq);
+                        false
+                    }
+                    None => true,
+                })
+                .copied()
+                .collect()
+        };
+
+        if missing.is_empty() {
+            return;
+        }
+
+        let stored: Vec<Event> = self.query_database(missing.clone()).await;
+
+        {
+            let mut entries = self.entries.write().await;
+            for public_key in missing.into_iter() {
+                entries
+                    .entry(public_key)
+                    .or_insert_with(|| Entry::new(public_key, now, seq));
+            }
+        }
+
+        self.ingest(stored.iter()).await;
+        self.evict(now).await;
+    }
+
+    async fn query_database(&self, public_keys: Vec<XOnlyPublicKey>) -> Vec<Event> {
+        let filter = Filter::new().authors(public_keys).kinds(KINDS);
+        self.pool
+            .database()
+            .query(vec![filter])
+            .await
+            .unwrap_or_default()
+    }
+
+    /// Reload the cached profiles from the database
+    async fn resync(&self) {
+        let public_keys: Vec<XOnlyPublicKey> = {
+            let entries = self.entries.read().await;
+            entries.keys().copied().collect()
+        };
+
+        for chunk in public_keys.chunks(self.opts.batch_size.max(1)) {
+            let stored: Vec<Event> = self.query_database(chunk.to_vec()).await;
+            self.ingest(stored.iter()).await;
+        }
+    }
+
+    /// Evict the idle profiles and, if the cache is full, the least recently accessed ones
+    async fn evict(&self, now: Timestamp) {
+        let mut entries = self.entries.write().await;
+
+        let idle_timeout: Duration = self.opts.idle_timeout;
+        entries.retain(|_, entry| entry.accessed_at() + idle_timeout > now);
+
+        if entries.len() > self.opts.max_entries {
+            let mut by_access: Vec<(u64, XOnlyPublicKey)> = entries
+                .iter()
+                .map(|(public_key, entry)| (entry.access_seq.load(Ordering::SeqCst), *public_key))
+                .collect();
+            by_access.sort_unstable();
+            let excess: usize = entries.len() - self.opts.max_entries;
+            for (_, public_key) in by_access.into_iter().take(excess) {
+                entries.remove(&public_key);
+            }
+        }
+    }
+
+    /// Queue the public keys for the next batch
+    ///
+    /// Return the receivers of the requests to wait for.
+    async fn request<I>(&self, public_keys: I) -> Vec<oneshot::Receiver<()>>
+    where
+        I: IntoIterator<Item = (XOnlyPublicKey, bool)>,
+    {
+        let mut receivers = Vec::new();
+        let mut queued: bool = false;
+
+        let mut requests = self.requests.lock().await;
+        let requests = &mut *requests;
+        for (public_key, wait) in public_keys.into_iter() {
+            // Already requested to relays: just wait for the reply
+            let waiters = match requests.in_flight.get_mut(&public_key) {
+                Some(waiters) => waiters,
+                None => {
+                    queued = true;
+                    requests.queued.entry(public_key).or_default()
+                }
+            };
+
+            if wait {
+                let (tx, rx) = oneshot::channel();
+                waiters.push(tx);
+                receivers.push(rx);
+            }
+        }
+
+        if queued {
+            self.requests_notify.notify_one();
+        }
+
+        receivers
+    }
+
+    /// Fetch the queued profiles from relays
+    async fn process_requests(self: &Arc<Self>) {
+        let public_keys: Vec<XOnlyPublicKey> = {
+            let mut requests = self.requests.lock().await;
+            let requests = &mut *requests;
+            let queued = mem::take(&mut requests.queued);
+            let public_keys = queued.keys().copied().collect();
+            requests.in_flight.extend(queued);
+            public_keys
+        };
+
+        if public_keys.is_empty() {
+            return;
+        }
+
+        tracing::debug!("Fetching {} profiles", public_keys.len());
+
+        let mut handles = Vec::new();
+        for chunk in public_keys.chunks(self.opts.batch_size.max(1)) {
+            let chunk: Vec<XOnlyPublicKey> = chunk.to_vec();
+            let cache = self.clone();
+            let handle = thread::spawn(async move {
+                let filter = Filter::new().authors(chunk.clone()).kinds(KINDS);
+                let res = cache
+                    .pool
+                    .get_events_of_with_mode(
+                        vec![filter],
+                        cache.opts.timeout,
+                        FilterOptions::ExitOnEOSE,
+                        QueryMode::NetworkOnly,
+                    )
+                    .await;
+                (chunk, res)
+            });
+            handles.push(handle);
+        }
+
+        for handle in handles.into_iter().flatten() {
+            if let Ok((chunk, res)) = handle.join().await {
+                let fetched: bool = match res {
+                    Ok(events) => {
+                        self.ingest(events.iter()).await;
+                        true
+                    }
+                    // Retried when stale again, not at every request
+                    Err(e) => {
+                        tracing::warn!("Impossible to fetch profiles: {e}");
+                        false
+                    }
+                };
+
+                let now = Timestamp::now();
+                let mut entries = self.entries.write().await;
+                for public_key in chunk.iter() {
+                    if let Some(entry) = entries.get_mut(public_key) {
+                        entry.fetched_at = Some(now);
+                        if fetched {
+                            entry.profile.last_fetch = Some(now);
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut requests = self.requests.lock().await;
+        for public_key in public_keys.iter() {
+            if let Some(waiters) = requests.in_flight.remove(public_key) {
+                for waiter in waiters.into_iter() {
+                    let _ = waiter.send(());
+                }
+            }
+        }
+    }
+
+    /// Queue the stale profiles accessed within the last TTL for refresh
+    async fn refresh_stale(&self, now: Timestamp) {
+        let ttl: Duration = self.opts.ttl;
+        let stale: Vec<(XOnlyPublicKey, bool)> = {
+            let entries = self.entries.read().await;
+            entries
+                .iter()
+                .filter(|(_, entry)| entry.is_stale(now, ttl) && entry.accessed_at() + ttl > now)
+                .map(|(public_key, _)| (*public_key, false))
+                .collect()
+        };
+
+        if !stale.is_empty() {
+            tracing::debug!("Refreshing {} stale profiles", stale.len());
+            self.request(stale).await;
+        }
+    }
+}
+
+/// Profile cache
+///
+/// Every request for a profile not yet cached (or stale) is queued and merged
+/// with the others into few `REQ`s, without duplicates.
+/// Stale profiles are returned immediately and refreshed in background.
+#[derive(Debug, Clone)]
+pub struct ProfileCache {
+    inner: Arc<InternalProfileCache>,
+}
+
+impl ProfileCache {
+    /// New profile cache
+    ///
+    /// The cache stop when the pool is shutdown.
+    pub fn new(pool: RelayPool, opts: ProfileCacheOptions) -> Self {
+        let (notification_sender, _) = broadcast::channel(NOTIFICATION_CHANNEL_SIZE);
+        let inner = Arc::new(InternalProfileCache {
+            pool,
+            opts,
+            entries: RwLock::new(HashMap::new()),
+            requests: Mutex::new(Requests::default()),
+            requests_notify: Notify::new(),
+            notification_sender,
+            access_counter: AtomicU64::new(0),
+        });
+        inner.clone().run();
+        Self { inner }
+    }
+
+    /// Get new [`ProfileNotification`] listener
+    pub fn notifications(&self) -> broadcast::Receiver<ProfileNotification> {
+        self.inner.notification_sender.subscribe()
+    }
+
+    /// Get cached profile, without querying the database or relays
+    pub async fn peek(&self, public_key: XOnlyPublicKey) -> Option<CachedProfile> {
+        let entries = self.inner.entries.read().await;
+        entries.get(&public_key).map(|e| {
+            e.touch(Timestamp::now(), self.inner.next_access_seq());
+            e.profile.clone()
+        })
+    }
+
+    /// Get profile
+    ///
+    /// See [`ProfileCache::get_many`].
+    pub async fn get(&self, public_key: XOnlyPublicKey) -> CachedProfile {
+        self.get_many([public_key])
+            .await
+            .remove(&public_key)
+            .unwrap_or_else(|| CachedProfile::new(public_key))
+    }
+
+    /// Get profiles
+    ///
+    /// Profiles never seen before (neither in the database) are waited for,
+    /// until the relays reply or the timeout expire ([`ProfileCacheOptions::timeout`]).
+    /// Stale profiles are returned immediately and refreshed in background:
+    /// updates are sent as [`ProfileNotification`].
+    pub async fn get_many<I>(&self, public_keys: I) -> HashMap<XOnlyPublicKey, CachedProfile>
+    where
+        I: IntoIterator<Item = XOnlyPublicKey>,
+    {
+        let timeout: Duration = self.inner.opts.batch_delay + self.inner.opts.timeout;
+        self.get_many_with_timeout(public_keys, timeout).await
+    }
+
+    /// Get profiles, waiting for the profiles never seen before at most `timeout`
+    ///
+    /// See [`ProfileCache::get_many`].
+    pub async fn get_many_with_timeout<I>(
+        &self,
+        public_keys: I,
+        timeout: Duration,
+    ) -> HashMap<XOnlyPublicKey, CachedProfile>
+    where
+        I: IntoIterator<Item = XOnlyPublicKey>,
+    {
+        let public_keys: HashSet<XOnlyPublicKey> = public_keys.into_iter().collect();
+
+        self.inner.load(&public_keys).await;
+
+        let now = Timestamp::now();
+        let stale: Vec<(XOnlyPublicKey, bool)> = {
+            let entries = self.inner.entries.read().await;
+            public_keys
+                .iter()
+                .filter_map(|public_key| {
+                    let entry = entries.get(public_key)?;
+                    if entry.is_stale(now, self.inner.opts.ttl) {
+                        Some((*public_key, entry.profile.is_empty()))
+                    } else {
+                        None
+                    }
+                })
+                .collect()
+        };
+
+        let receivers = self.inner.request(stale).await;
+        if !receivers.is_empty() {
+            time::timeout(Some(timeout), async {
+                for rx in receivers.into_iter() {
+                    let _ = rx.await;
+                }
+            })
+            .await;
+        }
+
+        let entries = self.inner.entries.read().await;
+        public_keys
+            .into_iter()
+            .map(|public_key| {
+                let profile: CachedProfile = entries
+                    .get(&public_key)
+                    .map(|e| e.profile.clone())
+                    .unwrap_or_else(|| CachedProfile::new(public_key));
+                (public_key, profile)
+            })
+            .collect()
+    }
+
+    /// Get contacts profiles of [`XOnlyPublicKey`]
+    pub async fn contacts(
+        &self,
+        public_key: XOnlyPublicKey,
+    ) -> HashMap<XOnlyPublicKey, CachedProfile> {
+        match self.get(public_key).await.contacts {
+            Some(contacts) => self.get_many(contacts).await,
+            None => HashMap::new(),
+        }
+    }
+
+    /// Refresh profiles from relays, without waiting
+    pub async fn refresh<I>(&self, public_keys: I)
+    where
+        I: IntoIterator<Item = XOnlyPublicKey>,
+    {
+        let public_keys: HashSet<XOnlyPublicKey> = public_keys.into_iter().collect();
+        self.inner.load(&public_keys).await;
+        self.inner
+            .request(public_keys.into_iter().map(|p| (p, false)))
+            .await;
+    }
+
+    /// Verify NIP05 of the profile
+    ///
+    /// The result is cached until the NIP05 change or [`ProfileCacheOptions::nip05_ttl`] expire.
+    /// Return `false` if the profile has no NIP05.
+    #[cfg(feature = "nip05")]
+    pub async fn verify_nip05(&self, public_key: XOnlyPublicKey) -> Result<bool, nip05::Error> {
+        let nip05: String = match self.get(public_key).await.metadata.and_then(|m| m.nip05) {
+            Some(nip05) => nip05,
+            None => return Ok(false),
+        };
+
+        let now = Timestamp::now();
+        {
+            let entries = self.inner.entries.read().await;
+            if let Some(entry) = entries.get(&public_key) {
+                if let (Some(verified), Some(checked_at)) =
+                    (entry.profile.nip05_verified, entry.nip05_checked_at)
+                {
+                    if checked_at + self.inner.opts.nip05_ttl > now {
+                        return Ok(verified);
+                    }
+                }
+            }
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let res = nip05::verify(public_key, nip05.clone(), self.inner.opts.proxy).await;
+        #[cfg(target_arch = "wasm32")]
+        let res = nip05::verify(public_key, nip05.clone()).await;
+
+        let verified: bool = match res {
+            Ok(()) => true,
+            Err(nip05::Error::ImpossibleToVerify) | Err(nip05::Error::InvalidFormat) => false,
+            Err(e) => return Err(e),
+        };
+
+        let mut entries = self.inner.entries.write().await;
+        if let Some(entry) = entries.get_mut(&public_key) {
+            // The metadata may be changed in the meantime
+            let current = entry
+                .profile
+                .metadata
+                .as_ref()
+                .and_then(|m| m.nip05.as_ref());
+            if current == Some(&nip05) {
+                let changed: bool = entry.profile.nip05_verified != Some(verified);
+                entry.profile.nip05_verified = Some(verified);
+                entry.nip05_checked_at = Some(now);
+                if changed {
+                    let _ = self
+                        .inner
+                        .notification_sender
+                        .send(ProfileNotification::Nip05 {
+                            public_key,
+                            nip05,
+                            verified,
+                        });
+                }
+            }
+        }
+
+        Ok(verified)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr::{EventBuilder, Keys};
+    use nostr_database::{DatabaseOptions, MemoryDatabase};
+
+    use super::*;
+    use crate::relay::{MockRelay, RelayPoolOptions};
+
+    fn opts() -> ProfileCacheOptions {
+        ProfileCacheOptions::new()
+            .refresh_interval(None)
+            .batch_delay(Duration::from_millis(10))
+            .timeout(Duration::from_secs(2))
+    }
+
+    async fn pool_with_mock(mock: &MockRelay) -> RelayPool {
+        let pool = RelayPool::new(RelayPoolOptions::default());
+        pool.add_relay(mock.url(), None, mock.relay_opts())
+            .await
+            .unwrap();
+        pool.connect(true).await;
+        pool
+    }
+
+    fn metadata_event(keys: &Keys, name: &str) -> Event {
+        EventBuilder::set_metadata(&Metadata::new().name(name))
+            .to_event(keys)
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_fetch_and_update() {
+        let keys = Keys::generate();
+        let mock = MockRelay::run();
+        mock.add_event(
+            EventBuilder::set_contact_list(Vec::new())
+                .to_event(&keys)
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+        let pool = pool_with_mock(&mock).await;
+        let cache = ProfileCache::new(pool.clone(), opts());
+
+        // Never seen before: wait for the relays
+        let profile = cache.get(keys.public_key()).await;
+        assert_eq!(profile.contacts, Some(Vec::new()));
+        assert!(profile.metadata.is_none());
+        assert!(profile.last_fetch.is_some());
+
+        // Refreshed in background and notified
+        let mut notifications = cache.notifications();
+        mock.add_event(metadata_event(&keys, "alice"))
+            .await
+            .unwrap();
+        cache.refresh([keys.public_key()]).await;
+        let notification = time::timeout(Some(Duration::from_secs(5)), notifications.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(matches!(notification, ProfileNotification::Metadata { .. }));
+
+        let profile = cache.peek(keys.public_key()).await.unwrap();
+        assert_eq!(profile.metadata.unwrap().name, Some(String::from("alice")));
+
+        pool.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_failed_fetch_records_attempt() {
+        let public_key = Keys::generate().public_key();
+        let pool = RelayPool::new(RelayPoolOptions::default());
+        let cache = ProfileCache::new(pool.clone(), opts());
+
+        let profile = cache.get(public_key).await;
+        assert!(profile.is_empty());
+        assert!(profile.last_fetch.is_none());
+
+        // Not retried until stale again
+        let entries = cache.inner.entries.read().await;
+        let entry = entries.get(&public_key).unwrap();
+        assert!(entry.fetched_at.is_some());
+        assert!(!entry.is_stale(Timestamp::now(), cache.inner.opts.ttl));
+        drop(entries);
+
+        pool.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_evict_least_recently_accessed() {
+        let pool = RelayPool::new(RelayPoolOptions::default());
+        let cache = ProfileCache::new(pool.clone(), opts().max_entries(2));
+
+        let a = Keys::generate().public_key();
+        let b = Keys::generate().public_key();
+        let c = Keys::generate().public_key();
+
+        cache.get(a).await;
+        cache.get(b).await;
+        cache.peek(a).await;
+        cache.get(c).await;
+
+        let entries = cache.inner.entries.read().await;
+        assert_eq!(entries.len(), 2);
+        assert!(entries.contains_key(&a));
+        assert!(!entries.contains_key(&b));
+        assert!(entries.contains_key(&c));
+        drop(entries);
+
+        // Idle profiles
+        cache
+            .inner
+            .evict(Timestamp::now() + cache.inner.opts.idle_timeout)
+            .await;
+        assert!(cache.inner.entries.read().await.is_empty());
+
+        pool.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_resync_from_database() {
+        let keys = Keys::generate();
+        let database = MemoryDatabase::new(DatabaseOptions::default());
+        let pool = RelayPool::with_database(RelayPoolOptions::default(), database);
+        let cache = ProfileCache::new(pool.clone(), opts());
+
+        assert!(cache.get(keys.public_key()).await.is_empty());
+
+        // Saved by the pool but not delivered to the cache
+        pool.database()
+            .save_event(&metadata_event(&keys, "bob"))
+            .await
+            .unwrap();
+        cache.inner.resync().await;
+
+        let profile = cache.peek(keys.public_key()).await.unwrap();
+        assert_eq!(profile.metadata.unwrap().name, Some(String::from("bob")));
+
+        pool.shutdown().await.unwrap();
+    }
+}
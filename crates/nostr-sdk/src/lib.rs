@@ -40,11 +40,16 @@ pub mod util;
 
 #[cfg(feature = "blocking")]
 pub use self::client::blocking;
-pub use self::client::{Client, ClientBuilder, Options};
+pub use self::client::{
+    CachedProfile, Client, ClientBuilder, Options, ProfileCache, ProfileCacheOptions,
+    ProfileNotification,
+};
 pub use self::relay::{
-    ActiveSubscription, FilterOptions, InternalSubscriptionId, NegentropyOptions, Relay,
-    RelayConnectionStats, RelayOptions, RelayPoolNotification, RelayPoolOptions, RelaySendOptions,
-    RelayStatus,
+    ActiveSubscription, EventCount, ExponentialBackoff, FilterOptions, FixedDelay,
+    InternalSubscriptionId, ListenerOptions, NegentropyOptions, NeverReconnect, OutboxOptions,
+    PoolEvent, PoolEventListener, QueryMode, Reconciliation, ReconnectStrategy, Relay,
+    RelayConnectionStats, RelayOptions, RelayPoolNotification, RelayPoolOptions, RelayScore,
+    RelaySelectionOptions, RelaySendOptions, RelayStatus, TerminationReason, Topics,
 };
 
 #[cfg(feature = "blocking")]
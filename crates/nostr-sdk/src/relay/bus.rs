@@ -0,0 +1,619 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Relay pool event bus
+//!
+//! Each listener has its own bounded queue: a slow listener never makes the others lose [`PoolEvent`]s.
+//! When the queue is full, the pool drop the event and report it with [`PoolEvent::Lagged`] ([`Overflow::Drop`], default)
+//! or wait for the listener ([`Overflow::Wait`], lossless).
+
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use nostr::{Event, EventId, Kind, RelayMessage, SubscriptionId, Url};
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::mpsc::{self, Receiver, Sender};
+use tokio::sync::RwLock;
+
+use super::RelayStatus;
+#[cfg(feature = "blocking")]
+use crate::RUNTIME;
+
+/// Default listener queue capacity
+pub const DEFAULT_LISTENER_CAPACITY: usize = 1024;
+
+/// Relay pool event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PoolEvent {
+    /// Received a new [`Event`]
+    ///
+    /// Emitted only the first time the event is received (from any relay).
+    /// Doesn't include events sent by this client.
+    Event {
+        /// Relay url
+        relay_url: Url,
+        /// Subscription ID
+        subscription_id: SubscriptionId,
+        /// Event
+        event: Box<Event>,
+    },
+    /// `OK` message: the relay accepted or rejected an event
+    Ok {
+        /// Relay url
+        relay_url: Url,
+        /// Event ID
+        event_id: EventId,
+        /// Accepted
+        status: bool,
+        /// Message
+        message: String,
+    },
+    /// End of stored events of a subscription
+    EndOfStoredEvents {
+        /// Relay url
+        relay_url: Url,
+        /// Subscription ID
+        subscription_id: SubscriptionId,
+    },
+    /// Subscription closed by the relay
+    Closed {
+        /// Relay url
+        relay_url: Url,
+        /// Subscription ID
+        subscription_id: SubscriptionId,
+        /// Message
+        message: String,
+    },
+    /// Authentication challenge (NIP42)
+    Auth {
+        /// Relay url
+        relay_url: Url,
+        /// Challenge
+        challenge: String,
+    },
+    /// Notice
+    Notice {
+        /// Relay url
+        relay_url: Url,
+        /// Message
+        message: String,
+    },
+    /// Count result (NIP45)
+    Count {
+        /// Relay url
+        relay_url: Url,
+        /// Subscription ID
+        subscription_id: SubscriptionId,
+        /// Count
+        count: usize,
+        /// The count is approximate
+        approximate: bool,
+    },
+    /// Relay status changed
+    RelayStatus {
+        /// Relay url
+        relay_url: Url,
+        /// Relay Status
+        status: RelayStatus,
+    },
+    /// Relay added to the pool
+    RelayAdded {
+        /// Relay url
+        relay_url: Url,
+    },
+    /// Relay removed from the pool
+    RelayRemoved {
+        /// Relay url
+        relay_url: Url,
+    },
+    /// Events dropped because the listener queue was full (only with [`Overflow::Drop`])
+    Lagged {
+        /// Number of dropped events
+        missed: u64,
+    },
+    /// Stop
+    Stop,
+    /// Shutdown
+    ///
+    /// Last event: the listener is closed.
+    Shutdown,
+}
+
+impl PoolEvent {
+    /// Convert a [`RelayMessage`]
+    ///
+    /// `EVENT` messages return `None`: new events are emitted when saved.
+    /// Negentropy messages return `None` too.
+    pub(crate) fn from_relay_message(relay_url: Url, msg: RelayMessage) -> Option<Self> {
+        match msg {
+            RelayMessage::Ok {
+                event_id,
+                status,
+                message,
+            } => Some(Self::Ok {
+                relay_url,
+                event_id,
+                status,
+                message,
+            }),
+            RelayMessage::EndOfStoredEvents(subscription_id) => Some(Self::EndOfStoredEvents {
+                relay_url,
+                subscription_id,
+            }),
+            RelayMessage::Closed {
+                subscription_id,
+                message,
+            } => Some(Self::Closed {
+                relay_url,
+                subscription_id,
+                message,
+            }),
+            RelayMessage::Auth { challenge } => Some(Self::Auth {
+                relay_url,
+                challenge,
+            }),
+            RelayMessage::Notice { message } => Some(Self::Notice { relay_url, message }),
+            RelayMessage::Count {
+                subscription_id,
+                count,
+                approximate,
+            } => Some(Self::Count {
+                relay_url,
+                subscription_id,
+                count,
+                approximate,
+            }),
+            RelayMessage::Event { .. }
+            | RelayMessage::NegMsg { .. }
+            | RelayMessage::NegErr { .. } => None,
+        }
+    }
+
+    /// Get relay url
+    pub fn relay_url(&self) -> Option<&Url> {
+        match self {
+            Self::Event { relay_url, .. }
+            | Self::Ok { relay_url, .. }
+            | Self::EndOfStoredEvents { relay_url, .. }
+            | Self::Closed { relay_url, .. }
+            | Self::Auth { relay_url, .. }
+            | Self::Notice { relay_url, .. }
+            | Self::Count { relay_url, .. }
+            | Self::RelayStatus { relay_url, .. }
+            | Self::RelayAdded { relay_url }
+            | Self::RelayRemoved { relay_url } => Some(relay_url),
+            Self::Lagged { .. } | Self::Stop | Self::Shutdown => None,
+        }
+    }
+
+    /// Get subscription ID
+    pub fn subscription_id(&self) -> Option<&SubscriptionId> {
+        match self {
+            Self::Event {
+                subscription_id, ..
+            }
+            | Self::EndOfStoredEvents {
+                subscription_id, ..
+            }
+            | Self::Closed {
+                subscription_id, ..
+            }
+            | Self::Count {
+                subscription_id, ..
+            } => Some(subscription_id),
+            _ => None,
+        }
+    }
+
+    /// Get event kind
+    pub fn kind(&self) -> Option<Kind> {
+        match self {
+            Self::Event { event, .. } => Some(event.kind),
+            _ => None,
+        }
+    }
+}
+
+/// Topics of a listener
+///
+/// Each topic only restrict the [`PoolEvent`]s carrying that field: i.e. filtering by kind
+/// doesn't exclude `OK` or relay status events. [`PoolEvent::Lagged`], [`PoolEvent::Stop`]
+/// and [`PoolEvent::Shutdown`] are always delivered.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Topics {
+    relays: Option<HashSet<Url>>,
+    subscriptions: Option<HashSet<SubscriptionId>>,
+    kinds: Option<HashSet<Kind>>,
+}
+
+impl Topics {
+    /// All events
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add relay
+    pub fn relay(mut self, url: Url) -> Self {
+        self.relays.get_or_insert_with(HashSet::new).insert(url);
+        self
+    }
+
+    /// Add relays
+    pub fn relays<I>(mut self, urls: I) -> Self
+    where
+        I: IntoIterator<Item = Url>,
+    {
+        self.relays.get_or_insert_with(HashSet::new).extend(urls);
+        self
+    }
+
+    /// Add subscription
+    pub fn subscription(mut self, id: SubscriptionId) -> Self {
+        self.subscriptions
+            .get_or_insert_with(HashSet::new)
+            .insert(id);
+        self
+    }
+
+    /// Add kind
+    pub fn kind(mut self, kind: Kind) -> Self {
+        self.kinds.get_or_insert_with(HashSet::new).insert(kind);
+        self
+    }
+
+    /// Add kinds
+    pub fn kinds<I>(mut self, kinds: I) -> Self
+    where
+        I: IntoIterator<Item = Kind>,
+    {
+        self.kinds.get_or_insert_with(HashSet::new).extend(kinds);
+        self
+    }
+
+    /// Check if [`PoolEvent`] match the topics
+    pub fn matches(&self, event: &PoolEvent) -> bool {
+        if let (Some(relays), Some(url)) = (&self.relays, event.relay_url()) {
+            if !relays.contains(url) {
+                return false;
+            }
+        }
+
+        if let (Some(subscriptions), Some(id)) = (&self.subscriptions, event.subscription_id()) {
+            if !subscriptions.contains(id) {
+                return false;
+            }
+        }
+
+        if let (Some(kinds), Some(kind)) = (&self.kinds, event.kind()) {
+            if !kinds.contains(&kind) {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// What to do when the listener queue is full
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Overflow {
+    /// Drop the event and report it with [`PoolEvent::Lagged`], before the next delivered event
+    #[default]
+    Drop,
+    /// Wait for the listener to receive (lossless)
+    ///
+    /// The pool stop processing the relay messages until the listener receive:
+    /// a listener that stop receiving without being dropped stall the whole pool,
+    /// and waiting for the pool (i.e. `send_event`) while not receiving is a deadlock!
+    Wait,
+}
+
+/// Listener options
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListenerOptions {
+    capacity: usize,
+    overflow: Overflow,
+    topics: Topics,
+}
+
+impl Default for ListenerOptions {
+    fn default() -> Self {
+        Self {
+            capacity: DEFAULT_LISTENER_CAPACITY,
+            overflow: Overflow::default(),
+            topics: Topics::default(),
+        }
+    }
+}
+
+impl ListenerOptions {
+    /// New default options
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue capacity (default: 1024, min: 1)
+    pub fn capacity(self, capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            ..self
+        }
+    }
+
+    /// Set [`Overflow`] policy (default: [`Overflow::Drop`])
+    pub fn overflow(self, overflow: Overflow) -> Self {
+        Self { overflow, ..self }
+    }
+
+    /// Set [`Topics`] (default: all events)
+    pub fn topics(self, topics: Topics) -> Self {
+        Self { topics, ..self }
+    }
+}
+
+/// Relay pool event listener
+///
+/// Dropping it unregister the listener.
+#[derive(Debug)]
+pub struct PoolEventListener {
+    receiver: Receiver<PoolEvent>,
+}
+
+impl PoolEventListener {
+    /// Receive the next [`PoolEvent`]
+    ///
+    /// Return `None` after [`PoolEvent::Shutdown`].
+    pub async fn recv(&mut self) -> Option<PoolEvent> {
+        self.receiver.recv().await
+    }
+
+    /// Receive the next [`PoolEvent`], if immediately available
+    pub fn try_recv(&mut self) -> Option<PoolEvent> {
+        self.receiver.try_recv().ok()
+    }
+
+    /// Receive the next [`PoolEvent`]
+    #[cfg(feature = "blocking")]
+    pub fn recv_blocking(&mut self) -> Option<PoolEvent> {
+        RUNTIME.block_on(async { self.recv().await })
+    }
+}
+
+#[derive(Debug)]
+struct Listener {
+    sender: Sender<PoolEvent>,
+    overflow: Overflow,
+    topics: Topics,
+    missed: AtomicU64,
+}
+
+impl Listener {
+    /// Return `false` if the listener was dropped
+    async fn send(&self, event: PoolEvent) -> bool {
+        match self.overflow {
+            Overflow::Wait => self.sender.send(event).await.is_ok(),
+            Overflow::Drop => {
+                let missed: u64 = self.missed.load(Ordering::SeqCst);
+                if missed > 0 {
+                    match self.try_send(PoolEvent::Lagged { missed }) {
+                        Ok(()) => {
+                            self.missed.fetch_sub(missed, Ordering::SeqCst);
+                        }
+                        Err(TrySendError::Full(..)) => {
+                            self.missed.fetch_add(1, Ordering::SeqCst);
+                            return true;
+                        }
+                        Err(TrySendError::Closed(..)) => return false,
+                    }
+                }
+
+                match self.try_send(event) {
+                    Ok(()) => true,
+                    Err(TrySendError::Full(..)) => {
+                        self.missed.fetch_add(1, Ordering::SeqCst);
+                        true
+                    }
+                    Err(TrySendError::Closed(..)) => false,
+                }
+            }
+        }
+    }
+
+    /// Send without waiting, leaving the last slot of the queue to [`PoolEvent::Shutdown`]
+    fn try_send(&self, event: PoolEvent) -> Result<(), TrySendError<()>> {
+        let permit = self.sender.try_reserve()?;
+
+        // The reserved slot was taken: release it
+        if self.sender.capacity() == 0 {
+            return Err(TrySendError::Full(()));
+        }
+
+        permit.send(event);
+        Ok(())
+    }
+
+    /// Deliver [`PoolEvent::Shutdown`], also if the queue is full
+    async fn shutdown(&self) {
+        match self.overflow {
+            Overflow::Wait => {
+                let _ = self.sender.send(PoolEvent::Shutdown).await;
+            }
+            Overflow::Drop => {
+                let _ = self.sender.try_send(PoolEvent::Shutdown);
+            }
+        }
+    }
+}
+
+/// Event bus
+#[derive(Debug, Clone, Default)]
+pub(crate) struct EventBus {
+    listeners: Arc<RwLock<Vec<Arc<Listener>>>>,
+}
+
+impl EventBus {
+    /// Register new listener
+    pub async fn listen(&self, opts: ListenerOptions) -> PoolEventListener {
+        // With `Overflow::Drop`, one more slot is reserved to `PoolEvent::Shutdown`
+        let capacity: usize = match opts.overflow {
+            Overflow::Drop => opts.capacity + 1,
+            Overflow::Wait => opts.capacity,
+        };
+        let (sender, receiver) = mpsc::channel(capacity);
+        let mut listeners = self.listeners.write().await;
+        listeners.push(Arc::new(Listener {
+            sender,
+            overflow: opts.overflow,
+            topics: opts.topics,
+            missed: AtomicU64::new(0),
+        }));
+        PoolEventListener { receiver }
+    }
+
+    /// Deliver event to the listeners subscribed to its topics
+    pub async fn publish(&self, event: PoolEvent) {
+        let listeners: Vec<Arc<Listener>> = self.listeners.read().await.clone();
+
+        let mut dropped: bool = false;
+        for listener in listeners.iter() {
+            if listener.topics.matches(&event) && !listener.send(event.clone()).await {
+                dropped = true;
+            }
+        }
+
+        if dropped {
+            let mut listeners = self.listeners.write().await;
+            listeners.retain(|l| !l.sender.is_closed());
+        }
+    }
+
+    /// Deliver [`PoolEvent::Shutdown`] and unregister all listeners
+    pub async fn shutdown(&self) {
+        let listeners: Vec<Arc<Listener>> = self.listeners.read().await.clone();
+        for listener in listeners.iter() {
+            listener.shutdown().await;
+        }
+        let mut listeners = self.listeners.write().await;
+        listeners.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_full_listener_does_not_block() {
+        let bus = EventBus::default();
+        let mut listener = bus.listen(ListenerOptions::new().capacity(1)).await;
+
+        // Never wait for the listener by default
+        bus.publish(PoolEvent::Stop).await;
+        bus.publish(PoolEvent::Stop).await;
+        bus.publish(PoolEvent::Stop).await;
+        assert_eq!(listener.try_recv(), Some(PoolEvent::Stop));
+
+        // Lagged reported before the next event
+        bus.publish(PoolEvent::Stop).await;
+        assert_eq!(listener.try_recv(), Some(PoolEvent::Lagged { missed: 2 }));
+        assert_eq!(listener.try_recv(), None);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_delivered_to_full_listener() {
+        let bus = EventBus::default();
+        let mut listener = bus.listen(ListenerOptions::new().capacity(1)).await;
+
+        bus.publish(PoolEvent::Stop).await;
+        bus.publish(PoolEvent::Stop).await;
+        bus.shutdown().await;
+
+        assert_eq!(listener.recv().await, Some(PoolEvent::Stop));
+        assert_eq!(listener.recv().await, Some(PoolEvent::Shutdown));
+        assert_eq!(listener.recv().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_topics() {
+        let bus = EventBus::default();
+        let url = Url::parse("ws://relay1.example.com").unwrap();
+        let other = Url::parse("ws://relay2.example.com").unwrap();
+        let topics = Topics::new().relay(url.clone());
+        let mut listener = bus.listen(ListenerOptions::new().topics(topics)).await;
+
+        bus.publish(PoolEvent::RelayAdded {
+            relay_url: other.clone(),
+        })
+        .await;
+        bus.publish(PoolEvent::RelayAdded {
+            relay_url: url.clone(),
+        })
+        .await;
+        // Events without relay are always delivered
+        bus.publish(PoolEvent::Stop).await;
+
+        assert_eq!(
+            listener.try_recv(),
+            Some(PoolEvent::RelayAdded { relay_url: url })
+        );
+        assert_eq!(listener.try_recv(), Some(PoolEvent::Stop));
+        assert_eq!(listener.try_recv(), None);
+    }
+
+    #[test]
+    fn test_topics_matches() {
+        let url = Url::parse("ws://relay.example.com").unwrap();
+        let sub = SubscriptionId::new("sub");
+        let topics = Topics::new().subscription(sub.clone()).kind(Kind::TextNote);
+
+        let eose = |subscription_id: SubscriptionId| PoolEvent::EndOfStoredEvents {
+            relay_url: url.clone(),
+            subscription_id,
+        };
+        assert!(topics.matches(&eose(sub.clone())));
+        assert!(!topics.matches(&eose(SubscriptionId::new("other"))));
+
+        // Filtering by kind doesn't exclude events without kind
+        assert!(topics.matches(&PoolEvent::Notice {
+            relay_url: url.clone(),
+            message: String::from("notice"),
+        }));
+    }
+
+    #[tokio::test]
+    async fn test_overflow_wait() {
+        let bus = EventBus::default();
+        let mut listener = bus
+            .listen(ListenerOptions::new().capacity(1).overflow(Overflow::Wait))
+            .await;
+
+        bus.publish(PoolEvent::Stop).await;
+
+        // The second publish wait for the listener
+        let publisher = bus.clone();
+        let handle = tokio::spawn(async move {
+            publisher.publish(PoolEvent::Shutdown).await;
+        });
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(!handle.is_finished());
+
+        // Lossless
+        assert_eq!(listener.recv().await, Some(PoolEvent::Stop));
+        handle.await.unwrap();
+        assert_eq!(listener.recv().await, Some(PoolEvent::Shutdown));
+    }
+
+    #[tokio::test]
+    async fn test_unregister_dropped_listener() {
+        let bus = EventBus::default();
+        let listener = bus.listen(ListenerOptions::new()).await;
+        let _other = bus.listen(ListenerOptions::new()).await;
+        assert_eq!(bus.listeners.read().await.len(), 2);
+
+        drop(listener);
+        bus.publish(PoolEvent::Stop).await;
+        assert_eq!(bus.listeners.read().await.len(), 1);
+    }
+}
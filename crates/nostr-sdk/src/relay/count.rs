@@ -0,0 +1,103 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Event count (NIP45)
+
+use std::collections::{HashMap, HashSet};
+
+use nostr::{EventId, Url};
+
+/// Event count of a relay
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RelayCount {
+    /// Events count
+    pub count: usize,
+    /// The relay reported an approximate count, or some IDs couldn't be fetched (see [`RelayCount::ids`])
+    pub approximate: bool,
+    /// Event IDs, if the relay not support `COUNT` and the IDs were fetched instead
+    ///
+    /// The IDs are fetched with time-windowed `REQ`s, to not be capped by the relay `max_limit`:
+    /// if the relay has more than `max_limit` events with the same timestamp, the IDs are incomplete
+    /// and the count is [`RelayCount::approximate`].
+    pub ids: Option<HashSet<EventId>>,
+}
+
+impl RelayCount {
+    pub(crate) fn new(count: usize, approximate: bool) -> Self {
+        Self {
+            count,
+            approximate,
+            ids: None,
+        }
+    }
+
+    pub(crate) fn from_ids(ids: HashSet<EventId>, approximate: bool) -> Self {
+        Self {
+            count: ids.len(),
+            approximate,
+            ids: Some(ids),
+        }
+    }
+}
+
+/// Event count of the pool
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EventCount {
+    /// Counts, by relay
+    pub relays: HashMap<Url, RelayCount>,
+    /// Events in the local database
+    pub local: usize,
+    /// Conservative aggregate
+    ///
+    /// The counts of different relays can't be added up without counting the shared events many times:
+    /// the aggregate is the max between the distinct event IDs known (local database and relays without `COUNT` support)
+    /// and each relay count. It's a lower bound, unless [`EventCount::approximate`].
+    pub total: usize,
+    /// The aggregate come from an approximate count
+    pub approximate: bool,
+    /// Errors, by relay
+    pub errors: HashMap<Url, String>,
+}
+
+impl EventCount {
+    pub(crate) fn aggregate(
+        mut ids: HashSet<EventId>,
+        relays: HashMap<Url, RelayCount>,
+        errors: HashMap<Url, String>,
+    ) -> Self {
+        let local: usize = ids.len();
+
+        for count in relays.values() {
+            if let Some(relay_ids) = &count.ids {
+                ids.extend(relay_ids.iter().copied());
+            }
+        }
+
+        let mut total: usize = ids.len();
+        let mut approximate: bool = relays
+            .values()
+            .any(|count| count.ids.is_some() && count.approximate);
+        for count in relays.values().filter(|c| c.ids.is_none()) {
+            if count.count > total {
+                total = count.count;
+                approximate = count.approximate;
+            } else if count.count == total && !count.approximate {
+                approximate = false;
+            }
+        }
+
+        Self {
+            relays,
+            local,
+            total,
+            approximate,
+            errors,
+        }
+    }
+
+    /// Check if all relays replied without errors
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
@@ -4,6 +4,9 @@
 
 //! Limits
 
+#[cfg(feature = "nip11")]
+use nostr::nips::nip11::Limitation;
+
 /// Limits
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Limits {
@@ -11,6 +14,8 @@ pub struct Limits {
     pub messages: MessagesLimits,
     /// Events limits
     pub events: EventsLimits,
+    /// Outgoing messages rate limits (default: disabled)
+    pub rate: Option<RateLimits>,
 }
 
 impl Default for Limits {
@@ -18,6 +23,7 @@ impl Default for Limits {
         Self {
             messages: MessagesLimits { max_size: 128_000 },
             events: EventsLimits { max_size: 65_536 },
+            rate: None,
         }
     }
 }
@@ -39,3 +45,74 @@ pub struct EventsLimits {
     /// Maximum size for tag values, in bytes
     pub max_tag_val_size: u16, */
 }
+
+/// Outgoing messages rate limits (token bucket)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimits {
+    /// Max number of messages sent per second
+    pub max_per_sec: u32,
+    /// Max number of messages that can be sent in a burst
+    pub burst: u32,
+}
+
+impl Default for RateLimits {
+    fn default() -> Self {
+        Self {
+            max_per_sec: 10,
+            burst: 50,
+        }
+    }
+}
+
+/// Limitations advertised by the relay (NIP11)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct RelayLimitation {
+    pub max_message_length: Option<usize>,
+    pub max_subscriptions: Option<usize>,
+    pub max_filters: Option<usize>,
+    pub max_limit: Option<usize>,
+}
+
+#[cfg(feature = "nip11")]
+impl From<&Limitation> for RelayLimitation {
+    fn from(limitation: &Limitation) -> Self {
+        // Zero or negative values are ignored
+        fn positive(value: Option<i32>) -> Option<usize> {
+            value.filter(|v| *v > 0).map(|v| v as usize)
+        }
+
+        Self {
+            max_message_length: positive(limitation.max_message_length),
+            max_subscriptions: positive(limitation.max_subscriptions),
+            max_filters: positive(limitation.max_filters),
+            max_limit: positive(limitation.max_limit),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "nip11")]
+    #[test]
+    fn test_relay_limitation_from_nip11() {
+        let limitation = Limitation {
+            max_message_length: Some(16_384),
+            max_subscriptions: Some(0),
+            max_filters: Some(-1),
+            max_limit: Some(500),
+            ..Default::default()
+        };
+        let limitation = RelayLimitation::from(&limitation);
+        assert_eq!(
+            limitation,
+            RelayLimitation {
+                max_message_length: Some(16_384),
+                max_subscriptions: None,
+                max_filters: None,
+                max_limit: Some(500),
+            }
+        );
+    }
+}
@@ -0,0 +1,655 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Mock relay
+//!
+//! In-process relay for deterministic tests: connections go through an [`InMemoryTransport`],
+//! events are stored in a [`MemoryDatabase`] and failures can be injected with [`MockRelayOptions`].
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_utility::thread;
+use nostr::message::relay::NegentropyErrorCode;
+use nostr::negentropy::{self, Bytes, Negentropy};
+use nostr::{
+    ClientMessage, Event, EventId, Filter, FiltersMatchEvent, JsonUtil, RelayMessage,
+    SubscriptionId, Timestamp, Url,
+};
+use nostr_database::{DatabaseError, DatabaseOptions, MemoryDatabase, NostrDatabase};
+use nostr_sdk_net::futures_channel::mpsc::UnboundedSender;
+use nostr_sdk_net::futures_util::StreamExt;
+use nostr_sdk_net::transport::{InMemoryConnection, InMemoryListener, InMemoryTransport};
+use nostr_sdk_net::WsMessage;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::RwLock;
+
+use super::RelayOptions;
+
+static MOCK_RELAY_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// [`MockRelay`] options
+///
+/// Used to inject failures: can be changed while the relay is running with [`MockRelay::update_opts`].
+#[derive(Debug, Clone)]
+pub struct MockRelayOptions {
+    /// Delay before sending `OK` to the client (default: none)
+    pub ok_delay: Option<Duration>,
+    /// Reject every event with `OK` `false` and this message (default: none)
+    pub reject_events: Option<String>,
+    /// Reply to every `REQ` with `CLOSED` and this message (default: none)
+    pub close_subscriptions: Option<String>,
+    /// Send `AUTH` challenge on connection (default: none)
+    pub auth_challenge: Option<String>,
+    /// Number of `NOTICE` sent on connection (default: 0)
+    pub notice_spam: usize,
+    /// Drop the connection after sending N events, also in the middle of a subscription (default: none)
+    pub disconnect_after_events: Option<usize>,
+    /// Support negentropy (default: true)
+    ///
+    /// If `false`, `NEG-OPEN` is answered with a `unknown cmd` notice.
+    pub negentropy: bool,
+    /// Support `COUNT` (default: true)
+    ///
+    /// If `false`, `COUNT` is answered with a `unknown cmd` notice.
+    pub count: bool,
+    /// Answer `COUNT` with approximate counts (default: false)
+    pub approximate_count: bool,
+    /// Close the `REQ` with more filters than this (default: none)
+    pub max_filters: Option<usize>,
+    /// Max number of events sent for each filter of a `REQ` (default: none)
+    pub max_limit: Option<usize>,
+    /// Reply to the WebSocket pings (default: true)
+    pub pong: bool,
+}
+
+impl Default for MockRelayOptions {
+    fn default() -> Self {
+        Self {
+            ok_delay: None,
+            reject_events: None,
+            close_subscriptions: None,
+            auth_challenge: None,
+            notice_spam: 0,
+            disconnect_after_events: None,
+            negentropy: true,
+            count: true,
+            approximate_count: false,
+            max_filters: None,
+            max_limit: None,
+            pong: true,
+        }
+    }
+}
+
+impl MockRelayOptions {
+    /// New default options
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Delay `OK` messages
+    pub fn ok_delay(self, delay: Duration) -> Self {
+        Self {
+            ok_delay: Some(delay),
+            ..self
+        }
+    }
+
+    /// Reject every event
+    pub fn reject_events<S>(self, message: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            reject_events: Some(message.into()),
+            ..self
+        }
+    }
+
+    /// Close every subscription
+    pub fn close_subscriptions<S>(self, message: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            close_subscriptions: Some(message.into()),
+            ..self
+        }
+    }
+
+    /// Send `AUTH` challenge on connection
+    pub fn auth_challenge<S>(self, challenge: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            auth_challenge: Some(challenge.into()),
+            ..self
+        }
+    }
+
+    /// Send `num` notices on connection
+    pub fn notice_spam(self, num: usize) -> Self {
+        Self {
+            notice_spam: num,
+            ..self
+        }
+    }
+
+    /// Drop the connection after sending `num` events
+    pub fn disconnect_after_events(self, num: usize) -> Self {
+        Self {
+            disconnect_after_events: Some(num),
+            ..self
+        }
+    }
+
+    /// Enable/disable negentropy support
+    pub fn negentropy(self, enable: bool) -> Self {
+        Self {
+            negentropy: enable,
+            ..self
+        }
+    }
+
+    /// Enable/disable `COUNT` support
+    pub fn count(self, enable: bool) -> Self {
+        Self {
+            count: enable,
+            ..self
+        }
+    }
+
+    /// Answer `COUNT` with approximate counts
+    pub fn approximate_count(self, approximate: bool) -> Self {
+        Self {
+            approximate_count: approximate,
+            ..self
+        }
+    }
+
+    /// Close the `REQ` with more than `max` filters
+    pub fn max_filters(self, max: usize) -> Self {
+        Self {
+            max_filters: Some(max),
+            ..self
+        }
+    }
+
+    /// Send at most `max` events for each filter of a `REQ`
+    pub fn max_limit(self, max: usize) -> Self {
+        Self {
+            max_limit: Some(max),
+            ..self
+        }
+    }
+
+    /// Enable/disable the replies to the WebSocket pings
+    pub fn pong(self, enable: bool) -> Self {
+        Self {
+            pong: enable,
+            ..self
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Control {
+    Event(Box<Event>),
+    Msg(RelayMessage),
+    Disconnect,
+    Shutdown,
+}
+
+/// In-process mock relay
+///
+/// Add it to a [`Client`](crate::Client) or [`RelayPool`](super::pool::RelayPool) with
+/// [`MockRelay::url`] and [`MockRelay::relay_opts`].
+#[derive(Debug, Clone)]
+pub struct MockRelay {
+    url: Url,
+    transport: InMemoryTransport,
+    database: Arc<MemoryDatabase>,
+    opts: Arc<RwLock<MockRelayOptions>>,
+    control: broadcast::Sender<Control>,
+    reqs: Arc<AtomicUsize>,
+}
+
+impl MockRelay {
+    /// Run mock relay with default options
+    pub fn run() -> Self {
+        Self::run_with_opts(MockRelayOptions::default())
+    }
+
+    /// Run mock relay
+    pub fn run_with_opts(opts: MockRelayOptions) -> Self {
+        let id: usize = MOCK_RELAY_COUNTER.fetch_add(1, Ordering::SeqCst);
+        let url: Url = Url::parse(&format!("ws://mock-relay-{id}.local")).expect("Valid url");
+        let (transport, listener) = InMemoryTransport::new();
+        let (control, _) = broadcast::channel(1024);
+
+        let relay = Self {
+            url,
+            transport,
+            database: Arc::new(MemoryDatabase::new(DatabaseOptions::default())),
+            opts: Arc::new(RwLock::new(opts)),
+            control,
+            reqs: Arc::new(AtomicUsize::new(0)),
+        };
+
+        let this = relay.clone();
+        thread::spawn(async move { this.accept(listener).await });
+
+        relay
+    }
+
+    /// Get relay url
+    pub fn url(&self) -> Url {
+        self.url.clone()
+    }
+
+    /// Get the [`InMemoryTransport`] connected to this relay
+    pub fn transport(&self) -> InMemoryTransport {
+        self.transport.clone()
+    }
+
+    /// Get [`RelayOptions`] with the [`InMemoryTransport`] connected to this relay
+    pub fn relay_opts(&self) -> RelayOptions {
+        RelayOptions::new().transport(self.transport())
+    }
+
+    /// Get relay database
+    pub fn database(&self) -> Arc<MemoryDatabase> {
+        self.database.clone()
+    }
+
+    /// Get number of `REQ` received
+    pub fn reqs(&self) -> usize {
+        self.reqs.load(Ordering::SeqCst)
+    }
+
+    /// Get options
+    pub async fn opts(&self) -> MockRelayOptions {
+        self.opts.read().await.clone()
+    }
+
+    /// Update options
+    pub async fn update_opts(&self, opts: MockRelayOptions) {
+        let mut o = self.opts.write().await;
+        *o = opts;
+    }
+
+    /// Store event and send it to the matching subscriptions
+    pub async fn add_event(&self, event: Event) -> Result<bool, DatabaseError> {
+        let saved: bool = self.database.save_event(&event).await?;
+        if saved {
+            let _ = self.control.send(Control::Event(Box::new(event)));
+        }
+        Ok(saved)
+    }
+
+    /// Send [`RelayMessage`] to all the connected clients
+    pub fn send_msg(&self, msg: RelayMessage) {
+        let _ = self.control.send(Control::Msg(msg));
+    }
+
+    /// Drop all the connections
+    ///
+    /// The clients can reconnect.
+    pub fn disconnect_all(&self) {
+        let _ = self.control.send(Control::Disconnect);
+    }
+
+    /// Drop all the connections and stop accepting new ones
+    pub fn shutdown(&self) {
+        let _ = self.control.send(Control::Shutdown);
+    }
+
+    async fn accept(self, mut listener: InMemoryListener) {
+        tracing::debug!("Mock relay {} started", self.url);
+        let mut control = self.control.subscribe();
+        loop {
+            tokio::select! {
+                conn = listener.accept() => match conn {
+                    Some(conn) => {
+                        let this = self.clone();
+                        let control = self.control.subscribe();
+                        thread::spawn(async move { this.handle_connection(conn, control).await });
+                    }
+                    None => break,
+                },
+                msg = control.recv() => match msg {
+                    Ok(Control::Shutdown) | Err(RecvError::Closed) => break,
+                    _ => (),
+                },
+            }
+        }
+        tracing::debug!("Mock relay {} stopped", self.url);
+    }
+
+    async fn handle_connection(
+        self,
+        conn: InMemoryConnection,
+        mut control: broadcast::Receiver<Control>,
+    ) {
+        tracing::debug!("New connection to mock relay {}", self.url);
+
+        let (tx, mut rx) = conn.into_split();
+        let mut session = Session {
+            tx,
+            subscriptions: HashMap::new(),
+            negentropy: HashMap::new(),
+            sent_events: 0,
+        };
+
+        let opts = self.opts().await;
+        if let Some(challenge) = opts.auth_challenge {
+            session.send(RelayMessage::new_auth(challenge));
+        }
+        for i in 0..opts.notice_spam {
+            session.send(RelayMessage::new_notice(format!("mock notice #{i}")));
+        }
+
+        loop {
+            tokio::select! {
+                msg = rx.next() => match msg {
+                    Some(WsMessage::Text(json)) => {
+                        if !self.handle_msg(&mut session, json).await {
+                            break;
+                        }
+                    }
+                    Some(WsMessage::Ping(data)) => {
+                        if self.opts().await.pong {
+                            session.send_ws(WsMessage::Pong(data));
+                        }
+                    }
+                    Some(WsMessage::Close(_)) | None => break,
+                    Some(_) => (),
+                },
+                msg = control.recv() => match msg {
+                    Ok(Control::Event(event)) => {
+                        let opts = self.opts().await;
+                        if !session.handle_new_event(&event, &opts) {
+                            break;
+                        }
+                    }
+                    Ok(Control::Msg(msg)) => session.send(msg),
+                    Ok(Control::Disconnect) | Ok(Control::Shutdown) | Err(RecvError::Closed) => break,
+                    Err(RecvError::Lagged(_)) => (),
+                },
+            }
+        }
+
+        tracing::debug!("Connection to mock relay {} dropped", self.url);
+    }
+
+    /// Return `false` if the connection must be dropped
+    async fn handle_msg(&self, session: &mut Session, json: String) -> bool {
+        let opts = self.opts().await;
+
+        let msg = match ClientMessage::from_json(json) {
+            Ok(msg) => msg,
+            Err(e) => {
+                session.send(RelayMessage::new_notice(format!("ERROR: bad msg: {e}")));
+                return true;
+            }
+        };
+
+        match msg {
+            ClientMessage::Event(event) => {
+                let (status, message): (bool, String) = if let Err(e) = event.verify() {
+                    (false, format!("invalid: {e}"))
+                } else if let Some(message) = opts.reject_events.clone() {
+                    (false, message)
+                } else {
+                    match self.database.save_event(&event).await {
+                        Ok(true) => {
+                            let _ = self.control.send(Control::Event(event.clone()));
+                            (true, String::new())
+                        }
+                        Ok(false) => (true, String::from("duplicate: already have this event")),
+                        Err(e) => (false, format!("error: {e}")),
+                    }
+                };
+                let ok = RelayMessage::new_ok(event.id, status, message);
+                match opts.ok_delay {
+                    Some(delay) => {
+                        let tx = session.tx.clone();
+                        thread::spawn(async move {
+                            thread::sleep(delay).await;
+                            let _ = tx.unbounded_send(WsMessage::Text(ok.as_json()));
+                        });
+                    }
+                    None => session.send(ok),
+                }
+            }
+            ClientMessage::Req {
+                subscription_id,
+                filters,
+            } => {
+                self.reqs.fetch_add(1, Ordering::SeqCst);
+
+                if let Some(message) = opts.close_subscriptions.clone() {
+                    session.send(RelayMessage::new_closed(subscription_id, message));
+                    return true;
+                }
+
+                if let Some(max) = opts.max_filters {
+                    if filters.len() > max {
+                        session.send(RelayMessage::new_closed(
+                            subscription_id,
+                            "error: too many filters",
+                        ));
+                        return true;
+                    }
+                }
+
+                let query: Vec<Filter> = match opts.max_limit {
+                    Some(max) => filters
+                        .iter()
+                        .cloned()
+                        .map(|mut filter| {
+                            filter.limit = Some(filter.limit.map_or(max, |limit| limit.min(max)));
+                            filter
+                        })
+                        .collect(),
+                    None => filters.clone(),
+                };
+
+                match self.database.query(query).await {
+                    Ok(events) => {
+                        session
+                            .subscriptions
+                            .insert(subscription_id.clone(), filters);
+                        for event in events.into_iter() {
+                            if !session.send_event(&subscription_id, event, &opts) {
+                                return false;
+                            }
+                        }
+                        session.send(RelayMessage::new_eose(subscription_id));
+                    }
+                    Err(e) => {
+                        session.send(RelayMessage::new_closed(
+                            subscription_id,
+                            format!("error: {e}"),
+                        ));
+                    }
+                }
+            }
+            ClientMessage::Count {
+                subscription_id,
+                filters,
+            } => {
+                if !opts.count {
+                    session.send(RelayMessage::new_notice("ERROR: bad msg: unknown cmd"));
+                    return true;
+                }
+
+                match self.database.count(filters).await {
+                    Ok(count) if opts.approximate_count => {
+                        session.send(RelayMessage::new_approximate_count(subscription_id, count))
+                    }
+                    Ok(count) => session.send(RelayMessage::new_count(subscription_id, count)),
+                    Err(e) => session.send(RelayMessage::new_closed(
+                        subscription_id,
+                        format!("error: {e}"),
+                    )),
+                }
+            }
+            ClientMessage::Close(subscription_id) => {
+                session.subscriptions.remove(&subscription_id);
+            }
+            ClientMessage::Auth(event) => {
+                let (status, message) = match event.verify() {
+                    Ok(()) => (true, String::new()),
+                    Err(e) => (false, format!("invalid: {e}")),
+                };
+                session.send(RelayMessage::new_ok(event.id, status, message));
+            }
+            ClientMessage::NegOpen {
+                subscription_id,
+                filter,
+                id_size,
+                initial_message,
+            } => {
+                if !opts.negentropy {
+                    session.send(RelayMessage::new_notice("ERROR: bad msg: unknown cmd"));
+                    return true;
+                }
+
+                let items: Vec<(EventId, Timestamp)> =
+                    match self.database.negentropy_items(*filter).await {
+                        Ok(items) => items,
+                        Err(e) => {
+                            session.send(neg_err(subscription_id, e.to_string()));
+                            return true;
+                        }
+                    };
+
+                let res = negentropy_open(id_size, items).and_then(|mut negentropy| {
+                    let msg = negentropy_reconcile(&mut negentropy, initial_message)?;
+                    Ok((negentropy, msg))
+                });
+                match res {
+                    Ok((negentropy, message)) => {
+                        session
+                            .negentropy
+                            .insert(subscription_id.clone(), negentropy);
+                        session.send(RelayMessage::NegMsg {
+                            subscription_id,
+                            message,
+                        });
+                    }
+                    Err(e) => session.send(neg_err(subscription_id, e.to_string())),
+                }
+            }
+            ClientMessage::NegMsg {
+                subscription_id,
+                message,
+            } => match session.negentropy.get_mut(&subscription_id) {
+                Some(negentropy) => match negentropy_reconcile(negentropy, message) {
+                    Ok(message) => session.send(RelayMessage::NegMsg {
+                        subscription_id,
+                        message,
+                    }),
+                    Err(e) => session.send(neg_err(subscription_id, e.to_string())),
+                },
+                None => session.send(RelayMessage::NegErr {
+                    subscription_id,
+                    code: NegentropyErrorCode::Closed,
+                }),
+            },
+            ClientMessage::NegClose { subscription_id } => {
+                session.negentropy.remove(&subscription_id);
+            }
+        }
+
+        true
+    }
+}
+
+struct Session {
+    tx: UnboundedSender<WsMessage>,
+    subscriptions: HashMap<SubscriptionId, Vec<Filter>>,
+    negentropy: HashMap<SubscriptionId, Negentropy>,
+    sent_events: usize,
+}
+
+impl Session {
+    fn send_ws(&self, msg: WsMessage) {
+        let _ = self.tx.unbounded_send(msg);
+    }
+
+    fn send(&self, msg: RelayMessage) {
+        self.send_ws(WsMessage::Text(msg.as_json()));
+    }
+
+    /// Return `false` if the connection must be dropped
+    fn send_event(
+        &mut self,
+        subscription_id: &SubscriptionId,
+        event: Event,
+        opts: &MockRelayOptions,
+    ) -> bool {
+        if let Some(max) = opts.disconnect_after_events {
+            if self.sent_events >= max {
+                return false;
+            }
+        }
+
+        self.send(RelayMessage::new_event(subscription_id.clone(), event));
+        self.sent_events += 1;
+
+        match opts.disconnect_after_events {
+            Some(max) => self.sent_events < max,
+            None => true,
+        }
+    }
+
+    /// Return `false` if the connection must be dropped
+    fn handle_new_event(&mut self, event: &Event, opts: &MockRelayOptions) -> bool {
+        let ids: Vec<SubscriptionId> = self
+            .subscriptions
+            .iter()
+            .filter(|(_, filters)| filters.match_event(event))
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in ids.iter() {
+            if !self.send_event(id, event.clone(), opts) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+fn negentropy_open(
+    id_size: u8,
+    items: Vec<(EventId, Timestamp)>,
+) -> Result<Negentropy, negentropy::Error> {
+    let mut negentropy = Negentropy::new(id_size as usize, None)?;
+    for (id, timestamp) in items.into_iter() {
+        negentropy.add_item(timestamp.as_u64(), Bytes::from_slice(id.as_bytes()))?;
+    }
+    negentropy.seal()?;
+    Ok(negentropy)
+}
+
+fn negentropy_reconcile(
+    negentropy: &mut Negentropy,
+    message: String,
+) -> Result<String, negentropy::Error> {
+    let query: Bytes = Bytes::from_hex(message)?;
+    Ok(negentropy.reconcile(&query)?.to_hex())
+}
+
+fn neg_err(subscription_id: SubscriptionId, error: String) -> RelayMessage {
+    RelayMessage::NegErr {
+        subscription_id,
+        code: NegentropyErrorCode::Other(error),
+    }
+}
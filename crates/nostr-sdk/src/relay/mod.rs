@@ -4,16 +4,19 @@
 
 //! Relay
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 #[cfg(not(target_arch = "wasm32"))]
 use std::net::SocketAddr;
 use std::ops::Mul;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
 use std::{cmp, fmt};
 
 #[cfg(not(target_arch = "wasm32"))]
+use async_utility::futures_util::future::Abortable;
 use async_utility::futures_util::stream::AbortHandle;
 use async_utility::{futures_util, thread, time};
 use nostr::message::relay::NegentropyErrorCode;
@@ -27,32 +30,61 @@ use nostr::{
     SubscriptionId, Timestamp, Url,
 };
 use nostr_database::{DatabaseError, DynNostrDatabase};
-use nostr_sdk_net::futures_util::{Future, SinkExt, StreamExt};
-use nostr_sdk_net::{self as net, WsMessage};
+use nostr_sdk_net::futures_util::{Future, FutureExt, SinkExt, StreamExt};
+pub use nostr_sdk_net::transport::{
+    self, ConnectOptions, DefaultWebSocketTransport, DynWebSocketTransport, InMemoryTransport,
+    TransportStats, WebSocketTransport,
+};
+#[cfg(not(target_arch = "wasm32"))]
+pub use nostr_sdk_net::TlsConfig;
+use nostr_sdk_net::WsMessage;
 use thiserror::Error;
 use tokio::sync::mpsc::{self, Receiver, Sender};
-use tokio::sync::{broadcast, oneshot, Mutex, RwLock};
+use tokio::sync::{broadcast, oneshot, Mutex, Notify, RwLock};
 
+pub mod bus;
+pub mod count;
 pub mod limits;
+pub mod mock;
 mod options;
+mod outbox;
+pub mod policy;
 pub mod pool;
+#[cfg(not(target_arch = "wasm32"))]
+mod ratelimit;
+pub mod reconciliation;
+pub mod reconnect;
+pub mod score;
 mod stats;
 
-pub use self::limits::Limits;
+pub use self::bus::{ListenerOptions, Overflow, PoolEvent, PoolEventListener, Topics};
+pub use self::count::{EventCount, RelayCount};
+use self::limits::RelayLimitation;
+pub use self::limits::{Limits, RateLimits};
+pub use self::mock::{MockRelay, MockRelayOptions};
 pub use self::options::{
-    FilterOptions, NegentropyOptions, RelayOptions, RelayPoolOptions, RelaySendOptions,
+    FilterOptions, NegentropyOptions, OutboxOptions, QueryMode, RelayOptions, RelayPoolOptions,
+    RelaySelectionOptions, RelaySendOptions,
 };
 use self::options::{MAX_ADJ_RETRY_SEC, MIN_RETRY_SEC};
+pub use self::policy::{AdmissionPolicy, AdmissionStatus};
 pub use self::pool::{RelayPoolMessage, RelayPoolNotification};
+#[cfg(not(target_arch = "wasm32"))]
+use self::ratelimit::RateLimiter;
+pub use self::reconciliation::Reconciliation;
+pub use self::reconnect::{ExponentialBackoff, FixedDelay, NeverReconnect, ReconnectStrategy};
+pub use self::score::RelayScore;
 pub use self::stats::RelayConnectionStats;
+#[cfg(not(target_arch = "wasm32"))]
+pub use self::stats::LATENCY_WINDOW;
 #[cfg(feature = "blocking")]
 use crate::RUNTIME;
 
 type Message = (RelayEvent, Option<oneshot::Sender<bool>>);
 
 const MIN_UPTIME: f64 = 0.90;
-#[cfg(not(target_arch = "wasm32"))]
-const PING_INTERVAL: u64 = 55;
+/// Default page size of `REQ` based reconciliation, if the relay not advertise `max_limit`
+const REQ_FALLBACK_LIMIT: usize = 500;
 
 /// [`Relay`] error
 #[derive(Debug, Error)]
@@ -113,6 +145,14 @@ pub enum Error {
     /// Filters empty
     #[error("filters empty")]
     FiltersEmpty,
+    /// Message too large (NIP11 `max_message_length` limitation)
+    #[error("message too large: size={size}, max_size={max_size}")]
+    MessageTooLarge {
+        /// Message size
+        size: usize,
+        /// Max message size allowed by the relay
+        max_size: usize,
+    },
     /// Reconciliation error
     #[error("negentropy reconciliation error: {0}")]
     NegentropyReconciliation(NegentropyErrorCode),
@@ -122,6 +162,9 @@ pub enum Error {
     /// Unknown negentropy error
     #[error("unknown negentropy error")]
     UnknownNegentropyError,
+    /// Count not supported
+    #[error("count not supported")]
+    CountNotSupported,
 }
 
 /// Relay connection status
@@ -157,6 +200,38 @@ impl fmt::Display for RelayStatus {
     }
 }
 
+/// Why a relay was terminated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TerminationReason {
+    /// Terminated with [`Relay::terminate`]
+    Requested,
+    /// The auto-reconnect loop gave up
+    ///
+    /// Too many consecutive failed attempts (see [`RelayOptions::max_reconnect_attempts`])
+    /// or the [`ReconnectStrategy`] stopped reconnecting.
+    ReconnectGaveUp {
+        /// Consecutive failed connection attempts (`0` if the strategy gave up when an established connection was lost)
+        failures: usize,
+    },
+}
+
+impl fmt::Display for TerminationReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Requested => write!(f, "Requested"),
+            Self::ReconnectGaveUp { failures: 0 } => {
+                write!(f, "Reconnection gave up after the connection was lost")
+            }
+            Self::ReconnectGaveUp { failures: 1 } => {
+                write!(f, "Reconnection gave up after 1 failed attempt")
+            }
+            Self::ReconnectGaveUp { failures } => {
+                write!(f, "Reconnection gave up after {failures} failed attempts")
+            }
+        }
+    }
+}
+
 /// Relay event
 #[derive(Debug)]
 pub enum RelayEvent {
@@ -213,6 +288,45 @@ where
     }
 }
 
+/// Resume cursor of a subscription
+#[derive(Debug, Clone, Copy, Default)]
+struct ResumeCursor {
+    /// Newest event received, up to the last `EOSE`
+    last_event_at: Option<Timestamp>,
+    /// Newest event received since the `REQ` was sent, before `EOSE`
+    backfill: Option<Timestamp>,
+    /// `EOSE` received for the last `REQ`
+    eose: bool,
+}
+
+impl ResumeCursor {
+    fn update(&mut self, created_at: Timestamp) {
+        if self.eose {
+            self.last_event_at = newest(self.last_event_at, created_at);
+        } else {
+            // The stored events may be sent in any order: an interrupted backfill may have gaps
+            self.backfill = newest(self.backfill, created_at);
+        }
+    }
+
+    fn end_of_stored_events(&mut self) {
+        if let Some(backfill) = self.backfill.take() {
+            self.last_event_at = newest(self.last_event_at, backfill);
+        }
+        self.eose = true;
+    }
+
+    /// New `REQ` sent: wait for `EOSE` again
+    fn restart(&mut self) {
+        self.backfill = None;
+        self.eose = false;
+    }
+}
+
+fn newest(last: Option<Timestamp>, created_at: Timestamp) -> Option<Timestamp> {
+    Some(last.map_or(created_at, |last| cmp::max(last, created_at)))
+}
+
 /// Relay instance's actual subscription with its unique id
 #[derive(Debug, Clone)]
 pub struct ActiveSubscription {
@@ -220,6 +334,8 @@ pub struct ActiveSubscription {
     id: SubscriptionId,
     /// Subscriptions filters
     filters: Vec<Filter>,
+    /// Timestamp of the newest event received, up to the last `EOSE`
+    last_event_at: Option<Timestamp>,
 }
 
 impl Default for ActiveSubscription {
@@ -234,6 +350,7 @@ impl ActiveSubscription {
         Self {
             id: SubscriptionId::generate(),
             filters: Vec::new(),
+            last_event_at: None,
         }
     }
 
@@ -242,6 +359,7 @@ impl ActiveSubscription {
         Self {
             id: SubscriptionId::generate(),
             filters,
+            last_event_at: None,
         }
     }
 
@@ -254,6 +372,122 @@ impl ActiveSubscription {
     pub fn filters(&self) -> Vec<Filter> {
         self.filters.clone()
     }
+
+    /// Get the `created_at` of the newest event received
+    ///
+    /// The events received before `EOSE` count only when `EOSE` arrives:
+    /// if the connection is lost during the backfill, the cursor doesn't skip the missing events.
+    /// Tracked only if [`RelayOptions::resume_subscriptions`] is enabled.
+    pub fn last_event_at(&self) -> Option<Timestamp> {
+        self.last_event_at
+    }
+
+    /// Filters with `since` adjusted to the newest event received (minus `skew`)
+    fn resume_filters(&self, skew: u64) -> Vec<Filter> {
+        match self.last_event_at {
+            Some(last_event_at) => {
+                let since: Timestamp = last_event_at - skew;
+                self.filters
+                    .iter()
+                    .cloned()
+                    .map(|mut filter| {
+                        filter.since = Some(match filter.since {
+                            Some(s) => cmp::max(s, since),
+                            None => since,
+                        });
+                        filter
+                    })
+                    .collect()
+            }
+            None => self.filters.clone(),
+        }
+    }
+}
+
+/// `REQ` chunks of the subscriptions split for the `max_filters` limitation
+#[derive(Debug, Default)]
+struct SubscriptionChunks {
+    /// Chunk IDs, by subscription ID (the first chunk keep the subscription ID)
+    chunks: HashMap<SubscriptionId, Vec<SubscriptionId>>,
+    /// Subscription ID, by chunk ID
+    parents: HashMap<SubscriptionId, SubscriptionId>,
+    /// Chunks not replied with `EOSE` yet, by subscription ID
+    pending_eose: HashMap<SubscriptionId, HashSet<SubscriptionId>>,
+}
+
+impl SubscriptionChunks {
+    /// Split the subscription in `n` chunks, reusing the current chunk IDs
+    ///
+    /// Return the chunk IDs and the previous ones no more needed.
+    fn split(
+        &mut self,
+        id: &SubscriptionId,
+        n: usize,
+    ) -> (Vec<SubscriptionId>, Vec<SubscriptionId>) {
+        let current: Vec<SubscriptionId> = self.remove(id);
+        let ids: Vec<SubscriptionId> = (0..n.max(1))
+            .map(|index| match current.get(index) {
+                Some(chunk) => chunk.clone(),
+                None if index == 0 => id.clone(),
+                None => SubscriptionId::generate(),
+            })
+            .collect();
+        let stale: Vec<SubscriptionId> = current
+            .into_iter()
+            .filter(|chunk| !ids.contains(chunk))
+            .collect();
+
+        if ids.len() > 1 {
+            for chunk in ids.iter() {
+                self.parents.insert(chunk.clone(), id.clone());
+            }
+            self.pending_eose
+                .insert(id.clone(), ids.iter().cloned().collect());
+            self.chunks.insert(id.clone(), ids.clone());
+        }
+
+        (ids, stale)
+    }
+
+    /// Remove the subscription and return its chunk IDs
+    fn remove(&mut self, id: &SubscriptionId) -> Vec<SubscriptionId> {
+        self.pending_eose.remove(id);
+        match self.chunks.remove(id) {
+            Some(chunks) => {
+                for chunk in chunks.iter() {
+                    self.parents.remove(chunk);
+                }
+                chunks
+            }
+            None => vec![id.clone()],
+        }
+    }
+
+    /// Get the subscription ID of a chunk
+    fn parent(&self, chunk: &str) -> Option<SubscriptionId> {
+        if self.parents.is_empty() {
+            return None;
+        }
+        self.parents.get(&SubscriptionId::new(chunk)).cloned()
+    }
+
+    /// Mark the chunk as completed (`EOSE` or `CLOSED`)
+    ///
+    /// Return `true` if it was the last chunk of the subscription.
+    fn complete(&mut self, parent: &SubscriptionId, chunk: &str) -> bool {
+        match self.pending_eose.get_mut(parent) {
+            Some(pending) => {
+                pending.remove(&SubscriptionId::new(chunk));
+                if pending.is_empty() {
+                    self.pending_eose.remove(parent);
+                    true
+                } else {
+                    false
+                }
+            }
+            None => false,
+        }
+    }
 }
 
 /// Relay
@@ -270,12 +504,26 @@ pub struct Relay {
     database: Arc<DynNostrDatabase>,
     scheduled_for_stop: Arc<AtomicBool>,
     scheduled_for_termination: Arc<AtomicBool>,
+    termination_reason: Arc<RwLock<Option<TerminationReason>>>,
+    /// Consecutive failed connection attempts
+    reconnect_failures: Arc<AtomicUsize>,
+    /// Wake up the auto-reconnect loop
+    reconnect_notify: Arc<Notify>,
+    /// Reconnection requested with [`Relay::reconnect`]
+    reconnect_requested: Arc<AtomicBool>,
     pool_sender: Sender<RelayPoolMessage>,
     relay_sender: Sender<Message>,
     relay_receiver: Arc<Mutex<Receiver<Message>>>,
     notification_sender: broadcast::Sender<RelayPoolNotification>,
     subscriptions: Arc<RwLock<HashMap<InternalSubscriptionId, ActiveSubscription>>>,
+    open_subscriptions: Arc<RwLock<HashSet<SubscriptionId>>>,
+    queued_subscriptions: Arc<Mutex<VecDeque<InternalSubscriptionId>>>,
+    chunks: Arc<RwLock<SubscriptionChunks>>,
+    last_event_at: Arc<RwLock<HashMap<SubscriptionId, ResumeCursor>>>,
+    subscription_slot: Arc<Notify>,
     limits: Limits,
+    #[cfg(not(target_arch = "wasm32"))]
+    rate_limiter: RateLimiter,
 }
 
 impl PartialEq for Relay {
@@ -303,18 +551,29 @@ impl Relay {
             proxy,
             status: Arc::new(RwLock::new(RelayStatus::Initialized)),
             #[cfg(feature = "nip11")]
-            document: Arc::new(RwLock::new(RelayInformationDocument::new())),
+            document: Arc::new(RwLock::new(opts.get_document().unwrap_or_default())),
             opts,
             stats: RelayConnectionStats::new(),
             database,
             scheduled_for_stop: Arc::new(AtomicBool::new(false)),
             scheduled_for_termination: Arc::new(AtomicBool::new(false)),
+            termination_reason: Arc::new(RwLock::new(None)),
+            reconnect_failures: Arc::new(AtomicUsize::new(0)),
+            reconnect_notify: Arc::new(Notify::new()),
+            reconnect_requested: Arc::new(AtomicBool::new(false)),
             pool_sender,
             relay_sender,
             relay_receiver: Arc::new(Mutex::new(relay_receiver)),
             notification_sender,
             subscriptions: Arc::new(RwLock::new(HashMap::new())),
+            open_subscriptions: Arc::new(RwLock::new(HashSet::new())),
+            queued_subscriptions: Arc::new(Mutex::new(VecDeque::new())),
+            chunks: Arc::new(RwLock::new(SubscriptionChunks::default())),
+            last_event_at: Arc::new(RwLock::new(HashMap::new())),
+            subscription_slot: Arc::new(Notify::new()),
             limits,
+            #[cfg(not(target_arch = "wasm32"))]
+            rate_limiter: RateLimiter::new(limits.rate),
         }
     }
 
@@ -334,18 +593,29 @@ impl Relay {
             url,
             status: Arc::new(RwLock::new(RelayStatus::Initialized)),
             #[cfg(feature = "nip11")]
-            document: Arc::new(RwLock::new(RelayInformationDocument::new())),
+            document: Arc::new(RwLock::new(opts.get_document().unwrap_or_default())),
             opts,
             stats: RelayConnectionStats::new(),
             database,
             scheduled_for_stop: Arc::new(AtomicBool::new(false)),
             scheduled_for_termination: Arc::new(AtomicBool::new(false)),
+            termination_reason: Arc::new(RwLock::new(None)),
+            reconnect_failures: Arc::new(AtomicUsize::new(0)),
+            reconnect_notify: Arc::new(Notify::new()),
+            reconnect_requested: Arc::new(AtomicBool::new(false)),
             pool_sender,
             relay_sender,
             relay_receiver: Arc::new(Mutex::new(relay_receiver)),
             notification_sender,
             subscriptions: Arc::new(RwLock::new(HashMap::new())),
+            open_subscriptions: Arc::new(RwLock::new(HashSet::new())),
+            queued_subscriptions: Arc::new(Mutex::new(VecDeque::new())),
+            chunks: Arc::new(RwLock::new(SubscriptionChunks::default())),
+            last_event_at: Arc::new(RwLock::new(HashMap::new())),
+            subscription_slot: Arc::new(Notify::new()),
             limits,
+            #[cfg(not(target_arch = "wasm32"))]
+            rate_limiter: RateLimiter::new(limits.rate),
         }
     }
 
@@ -391,6 +661,24 @@ impl Relay {
         self.status().await == RelayStatus::Connected
     }
 
+    /// Get [`TerminationReason`]
+    ///
+    /// `None` if the relay isn't terminated.
+    pub async fn termination_reason(&self) -> Option<TerminationReason> {
+        let reason = self.termination_reason.read().await;
+        *reason
+    }
+
+    async fn set_termination_reason(&self, reason: Option<TerminationReason>) {
+        let mut r = self.termination_reason.write().await;
+        *r = reason;
+    }
+
+    /// Get consecutive failed connection attempts
+    pub fn reconnect_failures(&self) -> usize {
+        self.reconnect_failures.load(Ordering::SeqCst)
+    }
+
     /// Get [`RelayInformationDocument`]
     #[cfg(feature = "nip11")]
     pub async fn document(&self) -> RelayInformationDocument {
@@ -412,8 +700,12 @@ impl Relay {
 
     /// Get [`ActiveSubscription`]
     pub async fn subscriptions(&self) -> HashMap<InternalSubscriptionId, ActiveSubscription> {
-        let subscription = self.subscriptions.read().await;
-        subscription.clone()
+        let mut subscriptions = self.subscriptions.read().await.clone();
+        let last_event_at = self.last_event_at.read().await;
+        for sub in subscriptions.values_mut() {
+            sub.last_event_at = last_event_at.get(&sub.id).and_then(|c| c.last_event_at);
+        }
+        subscriptions
     }
 
     /// Get [`ActiveSubscription`] by [`InternalSubscriptionId`]
@@ -421,8 +713,17 @@ impl Relay {
         &self,
         internal_id: &InternalSubscriptionId,
     ) -> Option<ActiveSubscription> {
-        let subscription = self.subscriptions.read().await;
-        subscription.get(internal_id).cloned()
+        let mut sub: ActiveSubscription = {
+            let subscriptions = self.subscriptions.read().await;
+            subscriptions.get(internal_id).cloned()?
+        };
+        sub.last_event_at = self
+            .last_event_at
+            .read()
+            .await
+            .get(&sub.id)
+            .and_then(|c| c.last_event_at);
+        Some(sub)
     }
 
     async fn update_subscription_filters(
@@ -431,9 +732,71 @@ impl Relay {
         filters: Vec<Filter>,
     ) {
         let mut s = self.subscriptions.write().await;
-        s.entry(internal_id)
+        let sub = s
+            .entry(internal_id)
             .and_modify(|sub| sub.filters = filters.clone())
             .or_insert_with(|| ActiveSubscription::with_filters(filters));
+        self.last_event_at.write().await.remove(&sub.id);
+    }
+
+    /// Get relay limitations (NIP11)
+    #[cfg(feature = "nip11")]
+    async fn limitation(&self) -> RelayLimitation {
+        let document = self.document.read().await;
+        document
+            .limitation
+            .as_ref()
+            .map(RelayLimitation::from)
+            .unwrap_or_default()
+    }
+
+    /// Get relay limitations (NIP11)
+    #[cfg(not(feature = "nip11"))]
+    async fn limitation(&self) -> RelayLimitation {
+        RelayLimitation::default()
+    }
+
+    /// Wait for the rate limiter
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn throttle(&self, n: usize) {
+        if self.rate_limiter.acquire(n).await {
+            self.stats.new_rate_limited();
+            tracing::warn!("Rate limit hit for {}: messages throttled", self.url);
+        }
+    }
+
+    /// Wait for the rate limiter
+    #[cfg(target_arch = "wasm32")]
+    async fn throttle(&self, _n: usize) {}
+
+    /// Move the resume cursor of the subscription, if resume is enabled
+    ///
+    /// Must be called only for verified events, also if already received from another relay.
+    pub(crate) async fn update_last_event_at(
+        &self,
+        subscription_id: &SubscriptionId,
+        created_at: Timestamp,
+    ) {
+        if !self.opts.get_resume_subscriptions() {
+            return;
+        }
+
+        // Events from the future can't move the cursor after now
+        let created_at: Timestamp = cmp::min(created_at, Timestamp::now());
+
+        // Cursors are tracked only for the active subscriptions
+        let mut last_event_at = self.last_event_at.write().await;
+        if let Some(cursor) = last_event_at.get_mut(subscription_id) {
+            cursor.update(created_at);
+        }
+    }
+
+    /// Commit the events received before `EOSE` to the resume cursor of the subscription
+    pub(crate) async fn end_of_stored_events(&self, subscription_id: &SubscriptionId) {
+        let mut last_event_at = self.last_event_at.write().await;
+        if let Some(cursor) = last_event_at.get_mut(subscription_id) {
+            cursor.end_of_stored_events();
+        }
     }
 
     /// Get [`RelayOptions`]
@@ -446,6 +809,14 @@ impl Relay {
         self.stats.clone()
     }
 
+    /// Get [`RelayScore`]
+    pub async fn score(&self) -> RelayScore {
+        let score = RelayScore::from_stats(&self.stats).await;
+        #[cfg(feature = "nip11")]
+        let score = score.limitation(&self.document().await);
+        score
+    }
+
     /// Get queue len
     pub fn queue(&self) -> usize {
         self.relay_sender.max_capacity() - self.relay_sender.capacity()
@@ -479,6 +850,9 @@ impl Relay {
         if let RelayStatus::Initialized | RelayStatus::Stopped | RelayStatus::Terminated =
             self.status().await
         {
+            self.set_termination_reason(None).await;
+            self.reconnect_failures.store(0, Ordering::SeqCst);
+
             if self.opts.get_reconnect() {
                 if wait_for_connection {
                     self.try_connect().await
@@ -525,9 +899,61 @@ impl Relay {
 
                         // Check status
                         match relay.status().await {
-                            RelayStatus::Initialized
-                            | RelayStatus::Pending
-                            | RelayStatus::Disconnected => relay.try_connect().await,
+                            RelayStatus::Initialized | RelayStatus::Pending => {
+                                relay.try_connect().await;
+                                continue;
+                            }
+                            RelayStatus::Disconnected => match relay.reconnect_delay() {
+                                Some(delay) => {
+                                    // Reconnection requested during the last attempt: skip the wait
+                                    if !relay.reconnect_requested.swap(false, Ordering::SeqCst) {
+                                        // Drop the wakeup stored while nobody was waiting: it must not skip the backoff
+                                        let _ = relay.reconnect_notify.notified().now_or_never();
+
+                                        if relay.is_scheduled_for_stop()
+                                            || relay.is_scheduled_for_termination()
+                                        {
+                                            continue;
+                                        }
+
+                                        tracing::trace!(
+                                            "{} retry time set to {} secs",
+                                            relay.url,
+                                            delay.as_secs_f64()
+                                        );
+
+                                        // Wait, unless a reconnection (or stop/termination) is requested
+                                        time::timeout(
+                                            Some(delay),
+                                            relay.reconnect_notify.notified(),
+                                        )
+                                        .await;
+
+                                        if relay.is_scheduled_for_stop()
+                                            || relay.is_scheduled_for_termination()
+                                        {
+                                            continue;
+                                        }
+
+                                        relay.reconnect_requested.store(false, Ordering::SeqCst);
+                                    }
+
+                                    relay.try_connect().await;
+                                    continue;
+                                }
+                                None => {
+                                    let reason = TerminationReason::ReconnectGaveUp {
+                                        failures: relay.reconnect_failures(),
+                                    };
+                                    relay.set_termination_reason(Some(reason)).await;
+                                    relay.set_status(RelayStatus::Terminated).await;
+                                    tracing::warn!(
+                                        "Auto connect loop terminated for {}: {reason}",
+                                        relay.url
+                                    );
+                                    break;
+                                }
+                            },
                             RelayStatus::Stopped | RelayStatus::Terminated => {
                                 tracing::debug!("Auto connect loop terminated for {}", relay.url);
                                 break;
@@ -535,23 +961,11 @@ impl Relay {
                             _ => (),
                         };
 
-                        let retry_sec: u64 = if relay.opts.get_adjust_retry_sec() {
-                            let var: u64 =
-                                relay.stats.attempts().saturating_sub(relay.stats.success()) as u64;
-                            if var >= 3 {
-                                let retry_interval: i64 =
-                                    cmp::min(MIN_RETRY_SEC * (1 + var), MAX_ADJ_RETRY_SEC) as i64;
-                                let jitter: i64 = rand::thread_rng().gen_range(-1..=1);
-                                retry_interval.saturating_add(jitter) as u64
-                            } else {
-                                relay.opts().get_retry_sec()
-                            }
-                        } else {
-                            relay.opts().get_retry_sec()
-                        };
-
-                        tracing::trace!("{} retry time set to {retry_sec} secs", relay.url);
-                        thread::sleep(Duration::from_secs(retry_sec)).await;
+                        time::timeout(
+                            Some(Duration::from_secs(1)),
+                            relay.reconnect_notify.notified(),
+                        )
+                        .await;
                     }
                 });
             } else if wait_for_connection {
@@ -563,6 +977,61 @@ impl Relay {
         }
     }
 
+    /// Delay before the next connection attempt
+    ///
+    /// `None` if the auto connect loop must give up.
+    fn reconnect_delay(&self) -> Option<Duration> {
+        let failures: usize = self.reconnect_failures();
+
+        if failures >= self.opts.get_max_reconnect_attempts() {
+            return None;
+        }
+
+        if let Some(strategy) = self.opts.get_reconnect_strategy() {
+            return strategy.next_delay(failures);
+        }
+
+        let retry_sec: u64 = if self.opts.get_adjust_retry_sec() {
+            let var: u64 = self.stats.attempts().saturating_sub(self.stats.success()) as u64;
+            if var >= 3 {
+                let retry_interval: i64 =
+                    cmp::min(MIN_RETRY_SEC * (1 + var), MAX_ADJ_RETRY_SEC) as i64;
+                let jitter: i64 = rand::thread_rng().gen_range(-1..=1);
+                retry_interval.saturating_add(jitter) as u64
+            } else {
+                self.opts.get_retry_sec()
+            }
+        } else {
+            self.opts.get_retry_sec()
+        };
+
+        Some(Duration::from_secs(retry_sec))
+    }
+
+    /// Reconnect now
+    ///
+    /// Reset the backoff of the [`ReconnectStrategy`] and skip the current wait, if any.
+    /// A stopped or terminated relay is connected again. A connected relay is left as is.
+    pub async fn reconnect(&self) {
+        self.reconnect_failures.store(0, Ordering::SeqCst);
+
+        match self.status().await {
+            RelayStatus::Initialized | RelayStatus::Stopped | RelayStatus::Terminated => {
+                self.connect(false).await
+            }
+            RelayStatus::Disconnected if !self.opts.get_reconnect() => {
+                let relay = self.clone();
+                thread::spawn(async move { relay.try_connect().await });
+            }
+            RelayStatus::Pending | RelayStatus::Connecting | RelayStatus::Disconnected => {
+                self.reconnect_requested.store(true, Ordering::SeqCst);
+                self.reconnect_notify.notify_one();
+            }
+            // Nothing to skip: a stored request would skip the wait after the next disconnection
+            RelayStatus::Connected => (),
+        }
+    }
+
     async fn try_connect(&self) {
         self.stats.new_attempt();
 
@@ -572,9 +1041,9 @@ impl Relay {
         self.set_status(RelayStatus::Connecting).await;
         tracing::debug!("Connecting to {}", url);
 
-        // Request `RelayInformationDocument`
+        // Request `RelayInformationDocument`, if not set in options
         #[cfg(feature = "nip11")]
-        {
+        if self.opts.get_document().is_none() {
             let relay = self.clone();
             thread::spawn(async move {
                 #[cfg(not(target_arch = "wasm32"))]
@@ -593,10 +1062,21 @@ impl Relay {
             });
         }
 
-        #[cfg(not(target_arch = "wasm32"))]
-        let connection = net::native::connect(&self.url, self.proxy, None).await;
-        #[cfg(target_arch = "wasm32")]
-        let connection = net::wasm::connect(&self.url).await;
+        let transport: Arc<DynWebSocketTransport> = match self.opts.get_transport() {
+            Some(transport) => transport,
+            #[cfg(not(target_arch = "wasm32"))]
+            None => Arc::new(
+                DefaultWebSocketTransport::new()
+                    .proxy(self.proxy)
+                    .tls(self.opts.get_tls()),
+            ),
+            #[cfg(target_arch = "wasm32")]
+            None => Arc::new(DefaultWebSocketTransport::new()),
+        };
+        let connect_opts = ConnectOptions::new()
+            .compression(self.opts.get_compression())
+            .stats(self.stats.transport_stats());
+        let connection = transport.connect(&self.url, &connect_opts).await;
 
         // Connect
         match connection {
@@ -605,50 +1085,14 @@ impl Relay {
                 tracing::info!("Connected to {}", url);
 
                 self.stats.new_success();
+                self.reconnect_failures.store(0, Ordering::SeqCst);
 
                 #[cfg(not(target_arch = "wasm32"))]
-                let ping_abort_handle: AbortHandle = {
-                    let relay = self.clone();
-                    thread::abortable(async move {
-                        tracing::debug!("Relay Ping Thread Started");
-
-                        loop {
-                            if relay.stats.ping.last_nonce() != 0 && !relay.stats.ping.replied() {
-                                tracing::warn!("{} not replied to ping", relay.url);
-                                relay.stats.ping.reset();
-                                break;
-                            }
-
-                            let nonce: u64 = rand::thread_rng().gen();
-                            if relay.stats.ping.set_last_nonce(nonce)
-                                && relay.stats.ping.set_replied(false)
-                            {
-                                if let Err(e) =
-                                    relay.send_relay_event(RelayEvent::Ping { nonce }, None)
-                                {
-                                    tracing::error!("Impossible to ping {}: {e}", relay.url);
-                                    break;
-                                };
-                            } else {
-                                tracing::warn!(
-                                    "`last_nonce` or `replied` not updated for {}!",
-                                    relay.url
-                                );
-                            }
-
-                            thread::sleep(Duration::from_secs(PING_INTERVAL)).await;
-                        }
-
-                        tracing::debug!("Exited from Ping Thread of {}", relay.url);
-
-                        if let Err(err) = relay.disconnect().await {
-                            tracing::error!("Impossible to disconnect {}: {}", relay.url, err);
-                        }
-                    })
-                };
+                let (ping_abort_handle, ping_abort_registration) = AbortHandle::new_pair();
 
                 let relay = self.clone();
-                thread::spawn(async move {
+                #[cfg_attr(target_arch = "wasm32", allow(unused_variables))]
+                let event_abort_handle: AbortHandle = thread::abortable(async move {
                     tracing::debug!("Relay Event Thread Started");
                     let mut rx = relay.relay_receiver.lock().await;
                     while let Some((relay_event, oneshot_sender)) = rx.recv().await {
@@ -785,7 +1229,8 @@ impl Relay {
                 });
 
                 let relay = self.clone();
-                thread::spawn(async move {
+                #[cfg_attr(target_arch = "wasm32", allow(unused_variables))]
+                let message_abort_handle: AbortHandle = thread::abortable(async move {
                     tracing::debug!("Relay Message Thread Started");
 
                     async fn func(relay: &Relay, data: Vec<u8>) -> bool {
@@ -800,6 +1245,41 @@ impl Relay {
                                         relay.url,
                                         msg
                                     );
+                                    match &msg {
+                                        RawRelayMessage::Ok { message, .. }
+                                        | RawRelayMessage::Notice { message }
+                                            if message.starts_with("rate-limited:") =>
+                                        {
+                                            relay.stats.new_rate_limited();
+                                            tracing::warn!(
+                                                "Rate limited by {}: {message}",
+                                                relay.url
+                                            );
+                                        }
+                                        RawRelayMessage::Closed {
+                                            subscription_id,
+                                            message,
+                                        } => {
+                                            if message.starts_with("rate-limited:") {
+                                                relay.stats.new_rate_limited();
+                                                tracing::warn!(
+                                                    "Rate limited by {}: {message}",
+                                                    relay.url
+                                                );
+                                            }
+                                            relay
+                                                .free_subscription_slot(&SubscriptionId::new(
+                                                    subscription_id,
+                                                ))
+                                                .await;
+                                        }
+                                        _ => (),
+                                    }
+                                    let msg: RawRelayMessage = match relay.resolve_chunk(msg).await
+                                    {
+                                        Some(msg) => msg,
+                                        None => return false,
+                                    };
                                     if let Err(err) = relay
                                         .pool_sender
                                         .send(RelayPoolMessage::ReceivedMsg {
@@ -851,6 +1331,20 @@ impl Relay {
                                     },
                                     Err(e) => tracing::error!("{e}"),
                                 },
+                                WsMessage::Close(frame) => {
+                                    match frame {
+                                        Some(frame) => tracing::debug!(
+                                            "{} closed the connection: code={}, reason={}",
+                                            relay.url,
+                                            frame.code,
+                                            frame.reason
+                                        ),
+                                        None => {
+                                            tracing::debug!("{} closed the connection", relay.url)
+                                        }
+                                    }
+                                    break;
+                                }
                                 _ => {
                                     let data: Vec<u8> = msg.into_data();
                                     let exit: bool = func(&relay, data).await;
@@ -863,8 +1357,8 @@ impl Relay {
                     }
 
                     #[cfg(target_arch = "wasm32")]
-                    while let Some(msg) = ws_rx.next().await {
-                        let data: Vec<u8> = msg.as_ref().to_vec();
+                    while let Some(Ok(msg)) = ws_rx.next().await {
+                        let data: Vec<u8> = msg.into_data();
                         let exit: bool = func(&relay, data).await;
                         if exit {
                             break;
@@ -878,6 +1372,71 @@ impl Relay {
                     }
                 });
 
+                #[cfg(not(target_arch = "wasm32"))]
+                {
+                    let relay = self.clone();
+                    thread::spawn(Abortable::new(
+                        async move {
+                            tracing::debug!("Relay Ping Thread Started");
+
+                            loop {
+                                let nonce: u64 = rand::thread_rng().gen();
+                                if relay.stats.ping.set_last_nonce(nonce)
+                                    && relay.stats.ping.set_replied(false)
+                                {
+                                    if let Err(e) =
+                                        relay.send_relay_event(RelayEvent::Ping { nonce }, None)
+                                    {
+                                        tracing::error!("Impossible to ping {}: {e}", relay.url);
+                                        if let Err(err) = relay.disconnect().await {
+                                            tracing::error!(
+                                                "Impossible to disconnect {}: {}",
+                                                relay.url,
+                                                err
+                                            );
+                                        }
+                                        break;
+                                    };
+                                } else {
+                                    tracing::warn!(
+                                        "`last_nonce` or `replied` not updated for {}!",
+                                        relay.url
+                                    );
+                                }
+
+                                let ping_interval: Duration = relay.opts.get_ping_interval();
+                                let pong_timeout: Duration = relay.opts.get_pong_timeout();
+                                thread::sleep(pong_timeout).await;
+
+                                if !relay.stats.ping.replied() {
+                                    tracing::warn!(
+                                        "{} not replied to ping within {} ms: connection is dead",
+                                        relay.url,
+                                        pong_timeout.as_millis()
+                                    );
+                                    relay.stats.ping.reset();
+
+                                    // The connection may be stuck (i.e. full TCP send buffer):
+                                    // drop it without waiting for the close handshake
+                                    event_abort_handle.abort();
+                                    message_abort_handle.abort();
+                                    relay.set_status(RelayStatus::Disconnected).await;
+                                    break;
+                                }
+
+                                thread::sleep(ping_interval.saturating_sub(pong_timeout)).await;
+                            }
+
+                            tracing::debug!("Exited from Ping Thread of {}", relay.url);
+                        },
+                        ping_abort_registration,
+                    ));
+                }
+
+                // Subscriptions are closed with the previous connection
+                self.open_subscriptions.write().await.clear();
+                self.queued_subscriptions.lock().await.clear();
+
                 // Subscribe to relay
                 if self.opts.get_read() {
                     if let Err(e) = self.resubscribe_all(None).await {
@@ -890,6 +1449,7 @@ impl Relay {
                 }
             }
             Err(err) => {
+                self.reconnect_failures.fetch_add(1, Ordering::SeqCst);
                 self.set_status(RelayStatus::Disconnected).await;
                 tracing::error!("Impossible to connect to {}: {}", url, err);
             }
@@ -921,6 +1481,7 @@ impl Relay {
     /// Disconnect from relay and set status to 'Stopped'
     pub async fn stop(&self) -> Result<(), Error> {
         self.schedule_for_stop(true);
+        self.reconnect_notify.notify_one();
         let status = self.status().await;
         if status.ne(&RelayStatus::Disconnected)
             && status.ne(&RelayStatus::Stopped)
@@ -934,6 +1495,9 @@ impl Relay {
     /// Disconnect from relay and set status to 'Terminated'
     pub async fn terminate(&self) -> Result<(), Error> {
         self.schedule_for_termination(true);
+        self.set_termination_reason(Some(TerminationReason::Requested))
+            .await;
+        self.reconnect_notify.notify_one();
         let status = self.status().await;
         if status.ne(&RelayStatus::Disconnected)
             && status.ne(&RelayStatus::Stopped)
@@ -958,6 +1522,10 @@ impl Relay {
             }
         }
 
+        let limitation: RelayLimitation = self.limitation().await;
+        let msg: ClientMessage = limit_msg(msg, &limitation)?;
+        self.throttle(1).await;
+
         match wait {
             Some(timeout) => {
                 let (tx, rx) = oneshot::channel::<bool>();
@@ -994,6 +1562,13 @@ impl Relay {
             return Err(Error::ReadDisabled);
         }
 
+        let limitation: RelayLimitation = self.limitation().await;
+        let msgs: Vec<ClientMessage> = msgs
+            .into_iter()
+            .map(|msg| limit_msg(msg, &limitation))
+            .collect::<Result<_, _>>()?;
+        self.throttle(msgs.len()).await;
+
         match wait {
             Some(timeout) => {
                 let (tx, rx) = oneshot::channel::<bool>();
@@ -1025,9 +1600,7 @@ impl Relay {
             && self.stats.attempts() > 1
             && self.stats.uptime() < MIN_UPTIME
         {
-            return Err(Error::EventNotPublished(String::from(
-                "relay not connected",
-            )));
+            return Err(Error::NotConnected);
         }
 
         time::timeout(Some(opts.timeout), async {
@@ -1044,6 +1617,7 @@ impl Relay {
                         },
                     ) => {
                         if self.url == url && id == event_id {
+                            self.stats.new_ok(status);
                             if status {
                                 return Ok(event_id);
                             } else {
@@ -1057,9 +1631,7 @@ impl Relay {
                             | RelayStatus::Stopped
                             | RelayStatus::Terminated = status
                             {
-                                return Err(Error::EventNotPublished(String::from(
-                                    "relay not connected (status changed)",
-                                )));
+                                return Err(Error::NotConnected);
                             }
                         }
                     }
@@ -1087,9 +1659,7 @@ impl Relay {
             && self.stats.attempts() > 1
             && self.stats.uptime() < MIN_UPTIME
         {
-            return Err(Error::EventNotPublished(String::from(
-                "relay not connected",
-            )));
+            return Err(Error::NotConnected);
         }
 
         let mut msgs: Vec<ClientMessage> = Vec::with_capacity(events.len());
@@ -1116,6 +1686,7 @@ impl Relay {
                         },
                     ) => {
                         if self.url == url && missing.remove(&event_id) {
+                            self.stats.new_ok(status);
                             if status {
                                 published.insert(event_id);
                             } else {
@@ -1129,9 +1700,7 @@ impl Relay {
                             | RelayStatus::Stopped
                             | RelayStatus::Terminated = status
                             {
-                                return Err(Error::EventNotPublished(String::from(
-                                    "relay not connected (status changed)",
-                                )));
+                                return Err(Error::NotConnected);
                             }
                         }
                     }
@@ -1165,11 +1734,18 @@ impl Relay {
         }
 
         let subscriptions = self.subscriptions().await;
+        let resume: bool = self.opts.get_resume_subscriptions();
+        let skew: u64 = self.opts.get_resume_skew_sec();
 
         for (internal_id, sub) in subscriptions.into_iter() {
             if !sub.filters.is_empty() {
-                self.send_msg(ClientMessage::new_req(sub.id.clone(), sub.filters), wait)
-                    .await?;
+                self.send_subscription(internal_id, &sub, wait).await?;
+
+                if resume && self.opts.get_resume_with_reconciliation() {
+                    if let Some(last_event_at) = sub.last_event_at {
+                        self.fill_gaps(sub.filters, last_event_at - skew);
+                    }
+                }
             } else {
                 tracing::warn!("Subscription '{internal_id}' has empty filters");
             }
@@ -1178,30 +1754,294 @@ impl Relay {
         Ok(())
     }
 
-    async fn resubscribe(
+    /// Send `REQ` for [`ActiveSubscription`], according to relay limitations
+    ///
+    /// Filters are split in more `REQ` if exceed the `max_filters` limitation.
+    /// If the `max_subscriptions` limitation is reached, the subscription is queued
+    /// and sent when another subscription is closed.
+    /// If `wait` is set, the free slots are waited for instead.
+    async fn send_subscription(
         &self,
         internal_id: InternalSubscriptionId,
+        sub: &ActiveSubscription,
         wait: Option<Duration>,
     ) -> Result<(), Error> {
-        if !self.opts.get_read() {
-            return Err(Error::ReadDisabled);
+        let filters: Vec<Filter> = if self.opts.get_resume_subscriptions() {
+            sub.resume_filters(self.opts.get_resume_skew_sec())
+        } else {
+            sub.filters.clone()
+        };
+
+        if self.opts.get_resume_subscriptions() {
+            let mut last_event_at = self.last_event_at.write().await;
+            last_event_at.entry(sub.id.clone()).or_default().restart();
         }
 
-        let sub: ActiveSubscription = self
-            .subscription(&internal_id)
-            .await
-            .ok_or(Error::InternalIdNotFound)?;
-        self.send_msg(ClientMessage::new_req(sub.id, sub.filters), wait)
-            .await?;
+        let limitation: RelayLimitation = self.limitation().await;
+        let chunks: Vec<Vec<Filter>> = split_filters(filters, limitation.max_filters);
+        let (ids, stale) = {
+            let mut subscription_chunks = self.chunks.write().await;
+            subscription_chunks.split(&sub.id, chunks.len())
+        };
 
-        Ok(())
+        // Close chunks of the previous REQ that are no more needed
+        let stale: Vec<SubscriptionId> = {
+            let mut open = self.open_subscriptions.write().await;
+            stale.into_iter().filter(|id| open.remove(id)).collect()
+        };
+        for id in stale.into_iter() {
+            self.send_msg(ClientMessage::close(id), wait).await?;
+        }
+
+        match (limitation.max_subscriptions, wait) {
+            (Some(max), Some(timeout)) => {
+                time::timeout(Some(timeout), async {
+                    while !self.open_subscription_chunks(&ids, Some(max)).await {
+                        tracing::debug!(
+                            "Max subscriptions reached for {}: waiting for a free slot",
+                            self.url
+                        );
+                        self.subscription_slot.notified().await;
+                    }
+                })
+                .await
+                .ok_or(Error::Timeout)?;
+            }
+            (max, None) => {
+                if !self.open_subscription_chunks(&ids, max).await {
+                    tracing::warn!(
+                        "Max subscriptions reached for {}: subscription '{internal_id}' queued",
+                        self.url
+                    );
+                    let mut queue = self.queued_subscriptions.lock().await;
+                    if !queue.contains(&internal_id) {
+                        queue.push_back(internal_id);
+                    }
+                    return Ok(());
+                }
+            }
+            (None, Some(..)) => {
+                self.open_subscription_chunks(&ids, None).await;
+            }
+        }
+
+        let mut msgs: Vec<ClientMessage> = ids
+            .into_iter()
+            .zip(chunks)
+            .map(|(id, filters)| ClientMessage::new_req(id, filters))
+            .collect();
+        if msgs.len() == 1 {
+            self.send_msg(msgs.remove(0), wait).await
+        } else {
+            self.batch_msg(msgs, wait).await
+        }
     }
 
-    /// Subscribe to filters
+    /// Mark the chunks as open, if there are enough free slots
+    async fn open_subscription_chunks(&self, ids: &[SubscriptionId], max: Option<usize>) -> bool {
+        let mut open = self.open_subscriptions.write().await;
+        if let Some(max) = max {
+            let new: usize = ids.iter().filter(|id| !open.contains(id)).count();
+            if open.len() + new > max {
+                return false;
+            }
+        }
+        open.extend(ids.iter().cloned());
+        true
+    }
+
+    /// Replace the chunk ID with the subscription ID
     ///
-    /// Internal Subscription ID set to `InternalSubscriptionId::Default`
-    pub async fn subscribe(
-        &self,
+    /// `EOSE` is forwarded only when all the chunks replied.
+    async fn resolve_chunk(&self, msg: RawRelayMessage) -> Option<RawRelayMessage> {
+        match msg {
+            RawRelayMessage::Event {
+                subscription_id,
+                event,
+            } => {
+                let chunks = self.chunks.read().await;
+                Some(RawRelayMessage::Event {
+                    subscription_id: match chunks.parent(&subscription_id) {
+                        Some(parent) => parent.to_string(),
+                        None => subscription_id,
+                    },
+                    event,
+                })
+            }
+            RawRelayMessage::EndOfStoredEvents(subscription_id) => {
+                let mut chunks = self.chunks.write().await;
+                match chunks.parent(&subscription_id) {
+                    Some(parent) => {
+                        if chunks.complete(&parent, &subscription_id) {
+                            Some(RawRelayMessage::EndOfStoredEvents(parent.to_string()))
+                        } else {
+                            None
+                        }
+                    }
+                    None => Some(RawRelayMessage::EndOfStoredEvents(subscription_id)),
+                }
+            }
+            RawRelayMessage::Closed {
+                subscription_id,
+                message,
+            } => {
+                let mut chunks = self.chunks.write().await;
+                let subscription_id: String = match chunks.parent(&subscription_id) {
+                    Some(parent) => {
+                        chunks.complete(&parent, &subscription_id);
+                        parent.to_string()
+                    }
+                    None => subscription_id,
+                };
+                Some(RawRelayMessage::Closed {
+                    subscription_id,
+                    message,
+                })
+            }
+            msg => Some(msg),
+        }
+    }
+
+    /// Close all the `REQ` of a subscription and free the slots
+    async fn close_subscription(
+        &self,
+        id: &SubscriptionId,
+        wait: Option<Duration>,
+    ) -> Result<(), Error> {
+        let ids: Vec<SubscriptionId> = {
+            let chunks: Vec<SubscriptionId> = self.chunks.write().await.remove(id);
+            let mut open = self.open_subscriptions.write().await;
+            chunks.into_iter().filter(|id| open.remove(id)).collect()
+        };
+
+        if ids.is_empty() {
+            // Queued or not tracked: close it anyway
+            self.send_msg(ClientMessage::close(id.clone()), wait)
+                .await?;
+        } else {
+            for id in ids.into_iter() {
+                self.send_msg(ClientMessage::close(id), wait).await?;
+            }
+        }
+
+        self.subscription_slot_freed().await;
+
+        Ok(())
+    }
+
+    /// Wait for a free subscription slot and take it
+    async fn take_subscription_slot(
+        &self,
+        id: &SubscriptionId,
+        timeout: Duration,
+    ) -> Result<(), Error> {
+        loop {
+            if let Some(max) = self.limitation().await.max_subscriptions {
+                let mut open = self.open_subscriptions.write().await;
+                if open.len() >= max {
+                    drop(open);
+                    tracing::debug!(
+                        "Max subscriptions reached for {}: waiting for a free slot",
+                        self.url
+                    );
+                    time::timeout(Some(timeout), self.subscription_slot.notified())
+                        .await
+                        .ok_or(Error::Timeout)?;
+                    continue;
+                }
+                open.insert(id.clone());
+            }
+            return Ok(());
+        }
+    }
+
+    /// Free a subscription slot (ex. subscription closed by the relay)
+    async fn free_subscription_slot(&self, id: &SubscriptionId) {
+        let mut open = self.open_subscriptions.write().await;
+        if open.remove(id) {
+            drop(open);
+            self.subscription_slot_freed().await;
+        }
+    }
+
+    /// Notify waiters and send the first queued subscription
+    async fn subscription_slot_freed(&self) {
+        self.subscription_slot.notify_one();
+
+        let internal_id: Option<InternalSubscriptionId> = {
+            let mut queue = self.queued_subscriptions.lock().await;
+            queue.pop_front()
+        };
+
+        // Sent in background: this is also called by the reading task
+        // and the rate limiter may wait before sending the `REQ`
+        if let Some(internal_id) = internal_id {
+            let relay = self.clone();
+            thread::spawn(async move {
+                if let Some(sub) = relay.subscription(&internal_id).await {
+                    if let Err(e) = relay.send_subscription(internal_id, &sub, None).await {
+                        tracing::error!("Impossible to send queued subscription: {e}");
+                    }
+                }
+            });
+        }
+    }
+
+    /// Reconcile events older than the resume point in background
+    ///
+    /// Only with negentropy: filters have no `since`, so a `REQ` based reconciliation would walk the whole history.
+    fn fill_gaps(&self, filters: Vec<Filter>, until: Timestamp) {
+        let relay = self.clone();
+        thread::spawn(async move {
+            for filter in filters.into_iter() {
+                let mut filter: Filter = filter;
+                filter.until = Some(match filter.until {
+                    Some(u) => cmp::min(u, until),
+                    None => until,
+                });
+                let items = match relay.database.negentropy_items(filter.clone()).await {
+                    Ok(items) => items,
+                    Err(e) => {
+                        tracing::error!("Impossible to get negentropy items: {e}");
+                        continue;
+                    }
+                };
+                match relay
+                    .reconcile(filter, items, NegentropyOptions::new().req_fallback(false))
+                    .await
+                {
+                    Ok(..) => (),
+                    Err(Error::NegentropyNotSupported) | Err(Error::UnknownNegentropyError) => {
+                        tracing::debug!("{} not support negentropy: gaps not filled", relay.url);
+                        break;
+                    }
+                    Err(e) => tracing::error!("Impossible to fill gaps for {}: {e}", relay.url),
+                }
+            }
+        });
+    }
+
+    async fn resubscribe(
+        &self,
+        internal_id: InternalSubscriptionId,
+        wait: Option<Duration>,
+    ) -> Result<(), Error> {
+        if !self.opts.get_read() {
+            return Err(Error::ReadDisabled);
+        }
+
+        let sub: ActiveSubscription = self
+            .subscription(&internal_id)
+            .await
+            .ok_or(Error::InternalIdNotFound)?;
+        self.send_subscription(internal_id, &sub, wait).await
+    }
+
+    /// Subscribe to filters
+    ///
+    /// Internal Subscription ID set to `InternalSubscriptionId::Default`
+    pub async fn subscribe(
+        &self,
         filters: Vec<Filter>,
         wait: Option<Duration>,
     ) -> Result<(), Error> {
@@ -1251,9 +2091,11 @@ impl Relay {
         let subscription = subscriptions
             .remove(&internal_id)
             .ok_or(Error::InternalIdNotFound)?;
-        self.send_msg(ClientMessage::close(subscription.id), wait)
-            .await?;
-        Ok(())
+        self.queued_subscriptions
+            .lock()
+            .await
+            .retain(|id| id != &internal_id);
+        self.close_subscription(&subscription.id, wait).await
     }
 
     /// Unsubscribe from all subscriptions
@@ -1265,8 +2107,7 @@ impl Relay {
         let subscriptions = self.subscriptions().await;
 
         for sub in subscriptions.into_values() {
-            self.send_msg(ClientMessage::close(sub.id.clone()), wait)
-                .await?;
+            self.close_subscription(&sub.id, wait).await?;
         }
 
         Ok(())
@@ -1376,18 +2217,45 @@ impl Relay {
             return Err(Error::ReadDisabled);
         }
 
-        let id = SubscriptionId::generate();
+        // Filters that exceed the `max_filters` limitation are requested in parallel,
+        // within the same `timeout`
+        let limitation: RelayLimitation = self.limitation().await;
+        let chunks: Vec<Vec<Filter>> = split_filters(filters, limitation.max_filters);
 
-        self.send_msg(ClientMessage::new_req(id.clone(), filters), None)
-            .await?;
+        // The wait after EOSE is not part of the timeout
+        let after_eose: Duration = match opts {
+            FilterOptions::WaitDurationAfterEOSE(duration) => duration,
+            _ => Duration::ZERO,
+        };
 
-        self.handle_events_of(id.clone(), timeout, opts, callback)
-            .await?;
+        let opened: Mutex<Vec<SubscriptionId>> = Mutex::new(Vec::new());
+        let res = {
+            let opened = &opened;
+            let callback = &callback;
+            let requests = chunks.into_iter().map(|filters| async move {
+                let id = SubscriptionId::generate();
+                self.take_subscription_slot(&id, timeout).await?;
+                opened.lock().await.push(id.clone());
+                self.send_msg(ClientMessage::new_req(id.clone(), filters), None)
+                    .await?;
+                self.handle_events_of(id, timeout, opts, callback).await
+            });
+            time::timeout(
+                Some(timeout + after_eose),
+                futures_util::future::join_all(requests),
+            )
+            .await
+        };
 
         // Unsubscribe
-        self.send_msg(ClientMessage::close(id), None).await?;
+        for id in opened.into_inner().into_iter() {
+            self.close_subscription(&id, None).await?;
+        }
 
-        Ok(())
+        match res {
+            Some(res) => res.into_iter().collect(),
+            None => Err(Error::Timeout),
+        }
     }
 
     /// Get events of filters
@@ -1422,34 +2290,11 @@ impl Relay {
 
         let relay = self.clone();
         thread::spawn(async move {
-            let id = SubscriptionId::generate();
-
-            // Subscribe
             if let Err(e) = relay
-                .send_msg(ClientMessage::new_req(id.clone(), filters), None)
+                .get_events_of_with_callback(filters, timeout, opts, |_| async {})
                 .await
             {
-                tracing::error!(
-                    "Impossible to send REQ to {}: {}",
-                    relay.url(),
-                    e.to_string()
-                );
-            };
-
-            if let Err(e) = relay
-                .handle_events_of(id.clone(), timeout, opts, |_| async {})
-                .await
-            {
-                tracing::error!("{e}");
-            }
-
-            // Unsubscribe
-            if let Err(e) = relay.send_msg(ClientMessage::close(id), None).await {
-                tracing::error!(
-                    "Impossible to close subscription with {}: {}",
-                    relay.url(),
-                    e.to_string()
-                );
+                tracing::error!("Impossible to request events to {}: {e}", relay.url());
             }
         });
     }
@@ -1460,50 +2305,157 @@ impl Relay {
         filters: Vec<Filter>,
         timeout: Duration,
     ) -> Result<usize, Error> {
+        let count: RelayCount = self.send_count(filters, timeout).await?;
+        Ok(count.count)
+    }
+
+    /// Count events of filters
+    ///
+    /// If the relay not support `COUNT` (NIP45), fallback to fetch the event IDs with time-windowed `REQ`s
+    /// (see [`RelayCount::ids`]). The fallback ignore the `limit` of the filters.
+    pub async fn count(
+        &self,
+        filters: Vec<Filter>,
+        timeout: Duration,
+    ) -> Result<RelayCount, Error> {
+        if self.supports_count().await {
+            match self.send_count(filters.clone(), timeout).await {
+                Ok(count) => return Ok(count),
+                Err(Error::CountNotSupported) => {
+                    tracing::info!(
+                        "{} not support count: fallback to fetch event IDs",
+                        self.url
+                    );
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        let limit: usize = self
+            .limitation()
+            .await
+            .max_limit
+            .unwrap_or(REQ_FALLBACK_LIMIT);
+        let mut ids: HashSet<EventId> = HashSet::new();
+        let mut approximate: bool = false;
+        for mut filter in filters.into_iter() {
+            filter.limit = None;
+            let windows: WindowedIds = self.get_ids_by_windows(filter, limit, timeout).await?;
+            ids.extend(windows.ids);
+            approximate |= windows.truncated;
+        }
+        Ok(RelayCount::from_ids(ids, approximate))
+    }
+
+    /// Check if the relay support `COUNT`, according to its [`RelayInformationDocument`]
+    ///
+    /// Return `true` if unknown.
+    async fn supports_count(&self) -> bool {
+        #[cfg(feature = "nip11")]
+        if let Some(nips) = self.document().await.supported_nips {
+            return nips.contains(&45);
+        }
+
+        true
+    }
+
+    async fn send_count(
+        &self,
+        filters: Vec<Filter>,
+        timeout: Duration,
+    ) -> Result<RelayCount, Error> {
         let id = SubscriptionId::generate();
+        let mut notifications = self.notification_sender.subscribe();
         self.send_msg(ClientMessage::new_count(id.clone(), filters), None)
             .await?;
 
-        let mut count = 0;
-
-        let mut notifications = self.notification_sender.subscribe();
-        time::timeout(Some(timeout), async {
+        let res: Result<RelayCount, Error> = time::timeout(Some(timeout), async {
             while let Ok(notification) = notifications.recv().await {
-                if let RelayPoolNotification::Message(
-                    url,
-                    RelayMessage::Count {
-                        subscription_id,
-                        count: c,
-                    },
-                ) = notification
-                {
-                    if subscription_id == id && url == self.url {
-                        count = c;
-                        break;
+                if let RelayPoolNotification::Message(url, msg) = notification {
+                    if url != self.url {
+                        continue;
+                    }
+
+                    match msg {
+                        RelayMessage::Count {
+                            subscription_id,
+                            count,
+                            approximate,
+                        } if subscription_id == id => {
+                            return Ok(RelayCount::new(count, approximate));
+                        }
+                        RelayMessage::Closed {
+                            subscription_id, ..
+                        } if subscription_id == id => return Err(Error::CountNotSupported),
+                        RelayMessage::Notice { message } if message.contains("unknown cmd") => {
+                            return Err(Error::CountNotSupported)
+                        }
+                        _ => (),
                     }
                 }
             }
+
+            Err(Error::Timeout)
         })
         .await
-        .ok_or(Error::Timeout)?;
+        .unwrap_or(Err(Error::Timeout));
 
-        // Unsubscribe
-        self.send_msg(ClientMessage::close(id), None).await?;
+        // Unsubscribe, also on timeout or error
+        let close: Result<(), Error> = self.send_msg(ClientMessage::close(id), None).await;
 
+        let count: RelayCount = res?;
+        close?;
         Ok(count)
     }
 
     /// Negentropy reconciliation
+    ///
+    /// If the relay not support negentropy, fallback to `REQ` based reconciliation
+    /// (see [`NegentropyOptions::req_fallback`]).
+    ///
+    /// If reads are disabled, only a `bidirectional` negentropy reconciliation is allowed:
+    /// the relay receive the events that it lacks, but nothing is fetched from it.
     pub async fn reconcile(
         &self,
         filter: Filter,
         items: Vec<(EventId, Timestamp)>,
         opts: NegentropyOptions,
-    ) -> Result<(), Error> {
-        if !self.opts.get_read() {
+    ) -> Result<Reconciliation, Error> {
+        if !opts.req_fallback {
+            return self.reconcile_with_negentropy(filter, items, opts).await;
+        }
+
+        match self
+            .reconcile_with_negentropy(filter.clone(), items.clone(), opts)
+            .await
+        {
+            Err(Error::NegentropyNotSupported) | Err(Error::UnknownNegentropyError) => {
+                tracing::info!(
+                    "{} not support negentropy: fallback to REQ based reconciliation",
+                    self.url
+                );
+                self.reconcile_with_req(filter, items, opts).await
+            }
+            res => res,
+        }
+    }
+
+    async fn reconcile_with_negentropy(
+        &self,
+        filter: Filter,
+        items: Vec<(EventId, Timestamp)>,
+        opts: NegentropyOptions,
+    ) -> Result<Reconciliation, Error> {
+        // Relays with reads disabled can only receive the events that they lack
+        let read: bool = self.opts.get_read();
+        if !read && !opts.bidirectional {
             return Err(Error::ReadDisabled);
         }
 
+        #[cfg(not(target_arch = "wasm32"))]
+        let start = Instant::now();
+        let mut report = Reconciliation::new();
+
         if !self.is_connected().await
             && self.stats.attempts() > 1
             && self.stats.uptime() < MIN_UPTIME
@@ -1525,12 +2477,13 @@ impl Relay {
         let sub_id = SubscriptionId::generate();
         let open_msg = ClientMessage::neg_open(&mut negentropy, &sub_id, filter)?;
 
-        self.send_msg(open_msg, Some(Duration::from_secs(10)))
-            .await?;
-
+        // Subscribe before sending, to not miss the reply
         let mut notifications = self.notification_sender.subscribe();
         let mut temp_notifications = self.notification_sender.subscribe();
 
+        self.send_msg(open_msg, Some(Duration::from_secs(10)))
+            .await?;
+
         // Check if negentropy it's supported
         time::timeout(Some(opts.initial_timeout), async {
             while let Ok(notification) = temp_notifications.recv().await {
@@ -1582,6 +2535,8 @@ impl Relay {
                                 message,
                             } => {
                                 if subscription_id == sub_id {
+                                    report.rounds += 1;
+
                                     let query: Bytes = Bytes::from_hex(message)?;
                                     let mut have_ids: Vec<Bytes> = Vec::new();
                                     let mut need_ids: Vec<Bytes> = Vec::new();
@@ -1591,25 +2546,28 @@ impl Relay {
                                         &mut need_ids,
                                     )?;
 
-                                    if opts.bidirectional {
-                                        let ids = have_ids
-                                            .into_iter()
-                                            .filter_map(|id| EventId::from_slice(&id).ok());
-                                        let filter = Filter::new().ids(ids);
-                                        let events: Vec<Event> =
-                                            self.database.query(vec![filter]).await?;
-                                        let msgs: Vec<ClientMessage> = events
-                                            .into_iter()
-                                            .map(ClientMessage::new_event)
-                                            .collect();
-                                        if let Err(e) = self
-                                            .batch_msg(msgs, Some(opts.batch_send_timeout))
+                                    let have_ids: Vec<EventId> = have_ids
+                                        .into_iter()
+                                        .filter_map(|id| EventId::from_slice(&id).ok())
+                                        .collect();
+                                    report.local.extend(have_ids.iter().copied());
+
+                                    if opts.bidirectional && !have_ids.is_empty() {
+                                        match self
+                                            .send_stored_events(have_ids, opts.batch_send_timeout)
                                             .await
                                         {
-                                            tracing::error!("negentropy reconciliation: impossible to batch events to {}: {e}", self.url);
+                                            Ok(sent) => report.sent.extend(sent),
+                                            Err(e) => tracing::error!("negentropy reconciliation: impossible to batch events to {}: {e}", self.url),
                                         }
                                     }
 
+                                    report.remote.extend(
+                                        need_ids
+                                            .iter()
+                                            .filter_map(|id| EventId::from_slice(id).ok()),
+                                    );
+
                                     if need_ids.is_empty() {
                                         tracing::info!(
                                             "Negentropy reconciliation terminated for {}",
@@ -1622,17 +2580,21 @@ impl Relay {
                                         .into_iter()
                                         .filter_map(|id| EventId::from_slice(&id).ok());
                                     let filter = Filter::new().ids(ids);
-                                    if !filter.ids.is_empty() {
+                                    if !read {
+                                        tracing::debug!("negentropy reconciliation: reads disabled, skip events needed from {}", self.url);
+                                    } else if !filter.ids.is_empty() {
                                         let timeout: Duration = opts.static_get_events_timeout
                                             + opts
                                                 .relative_get_events_timeout
                                                 .mul(filter.ids.len() as u32);
-                                        self.get_events_of(
-                                            vec![filter],
-                                            timeout,
-                                            FilterOptions::ExitOnEOSE,
-                                        )
-                                        .await?;
+                                        let events: Vec<Event> = self
+                                            .get_events_of(
+                                                vec![filter],
+                                                timeout,
+                                                FilterOptions::ExitOnEOSE,
+                                            )
+                                            .await?;
+                                        report.received.extend(events.into_iter().map(|e| e.id));
                                     } else {
                                         tracing::warn!("negentropy reconciliation: tried to send empty filters to {}", self.url);
                                     }
@@ -1689,7 +2651,254 @@ impl Relay {
         };
         self.send_msg(close_msg, None).await?;
 
-        Ok(())
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            report.duration = start.elapsed();
+        }
+
+        Ok(report)
+    }
+
+    /// Reconciliation with time-windowed `REQ`s
+    ///
+    /// Windows that hit the relay `max_limit` are split in half,
+    /// then the received IDs are compared with the local `items`.
+    async fn reconcile_with_req(
+        &self,
+        filter: Filter,
+        items: Vec<(EventId, Timestamp)>,
+        opts: NegentropyOptions,
+    ) -> Result<Reconciliation, Error> {
+        #[cfg(not(target_arch = "wasm32"))]
+        let start = Instant::now();
+        let mut report = Reconciliation::new();
+
+        let limit: usize = self
+            .limitation()
+            .await
+            .max_limit
+            .unwrap_or(REQ_FALLBACK_LIMIT);
+        let timeout: Duration =
+            opts.static_get_events_timeout + opts.relative_get_events_timeout.mul(limit as u32);
+
+        let windows: WindowedIds = self.get_ids_by_windows(filter, limit, timeout).await?;
+        report.rounds += windows.rounds;
+        let remote_ids: HashSet<EventId> = windows.ids;
+
+        let local_ids: HashSet<EventId> = items.into_iter().map(|(id, _)| id).collect();
+
+        // Events received with the REQs are already saved into the database
+        report.remote = remote_ids.difference(&local_ids).copied().collect();
+        report.received = report.remote.clone();
+        report.local = local_ids.difference(&remote_ids).copied().collect();
+
+        if opts.bidirectional && !report.local.is_empty() {
+            let ids: Vec<EventId> = report.local.iter().copied().collect();
+            match self.send_stored_events(ids, opts.batch_send_timeout).await {
+                Ok(sent) => report.sent.extend(sent),
+                Err(e) => tracing::error!(
+                    "REQ based reconciliation: impossible to batch events to {}: {e}",
+                    self.url
+                ),
+            }
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            report.duration = start.elapsed();
+        }
+
+        Ok(report)
+    }
+
+    /// Send the events with these IDs, taken from the database, and wait for the `OK` messages
+    ///
+    /// Return the IDs of the events accepted by the relay, within `timeout`.
+    pub(crate) async fn send_stored_events<I>(
+        &self,
+        ids: I,
+        timeout: Duration,
+    ) -> Result<HashSet<EventId>, Error>
+    where
+        I: IntoIterator<Item = EventId>,
+    {
+        let filter = Filter::new().ids(ids);
+        if filter.ids.is_empty() {
+            return Ok(HashSet::new());
+        }
+
+        let events: Vec<Event> = self.database.query(vec![filter]).await?;
+        if events.is_empty() {
+            return Ok(HashSet::new());
+        }
+
+        let mut missing: HashSet<EventId> = events.iter().map(|e| e.id).collect();
+        let mut accepted: HashSet<EventId> = HashSet::new();
+
+        // Subscribe before sending, to not miss the `OK` messages
+        let mut notifications = self.notification_sender.subscribe();
+        let msgs: Vec<ClientMessage> = events.into_iter().map(ClientMessage::new_event).collect();
+        self.batch_msg(msgs, Some(timeout)).await?;
+
+        time::timeout(Some(timeout), async {
+            while let Ok(notification) = notifications.recv().await {
+                match notification {
+                    RelayPoolNotification::Message(
+                        url,
+                        RelayMessage::Ok {
+                            event_id, status, ..
+                        },
+                    ) if url == self.url && missing.remove(&event_id) => {
+                        self.stats.new_ok(status);
+                        if status {
+                            accepted.insert(event_id);
+                        }
+                    }
+                    RelayPoolNotification::RelayStatus { url, status }
+                        if url == self.url && status != RelayStatus::Connected =>
+                    {
+                        break
+                    }
+                    RelayPoolNotification::Stop | RelayPoolNotification::Shutdown => break,
+                    _ => (),
+                }
+
+                if missing.is_empty() {
+                    break;
+                }
+            }
+        })
+        .await;
+
+        Ok(accepted)
+    }
+
+    /// Get the IDs of the events matching `filter` with time-windowed `REQ`s
+    ///
+    /// Windows that hit `limit` are split in half.
+    /// Relays may silently send less events than `limit`: a window with at least as many events
+    /// as any previous one is checked for older events and, if truncated, its size is used as limit.
+    async fn get_ids_by_windows(
+        &self,
+        filter: Filter,
+        limit: usize,
+        timeout: Duration,
+    ) -> Result<WindowedIds, Error> {
+        let mut res = WindowedIds::default();
+        let mut windows: Vec<(u64, u64)> = vec![(
+            filter.since.map(|t| t.as_u64()).unwrap_or_default(),
+            filter.until.unwrap_or_else(Timestamp::now).as_u64(),
+        )];
+
+        // Max number of events sent by the relay for a `REQ`: `limit` or less, if silently capped
+        let mut cap: usize = limit;
+        // The relay cap is at least the max number of events received for a window
+        let mut max_received: usize = 0;
+
+        while let Some((since, until)) = windows.pop() {
+            res.rounds += 1;
+            let items: Vec<(EventId, Timestamp)> = self
+                .get_window(&filter, since, until, limit, timeout)
+                .await?;
+            let len: usize = items.len();
+
+            let mut truncated: bool = len >= cap;
+            if !truncated && len > 0 && len >= max_received {
+                // Check if there are events older than the received ones
+                let oldest: u64 = items.iter().map(|(_, t)| t.as_u64()).min().unwrap_or(since);
+                if oldest > since {
+                    res.rounds += 1;
+                    let older = self
+                        .get_window(&filter, since, oldest - 1, 1, timeout)
+                        .await?;
+                    if !older.is_empty() {
+                        tracing::debug!("{} silently cap REQs to {len} events", self.url);
+                        cap = len;
+                        truncated = true;
+                    }
+                }
+            }
+            max_received = cmp::max(max_received, len);
+
+            if truncated {
+                if until > since {
+                    // Window truncated by limit: split it
+                    let mid: u64 = since + (until - since) / 2;
+                    windows.push((since, mid));
+                    windows.push((mid + 1, until));
+                } else {
+                    tracing::warn!(
+                        "More than {cap} events at timestamp {since} on {}",
+                        self.url
+                    );
+                    res.truncated = true;
+                }
+            }
+
+            res.ids.extend(items.into_iter().map(|(id, _)| id));
+        }
+
+        Ok(res)
+    }
+
+    /// Get the IDs and timestamps of at most `limit` events matching `filter` between `since` and `until`
+    async fn get_window(
+        &self,
+        filter: &Filter,
+        since: u64,
+        until: u64,
+        limit: usize,
+        timeout: Duration,
+    ) -> Result<Vec<(EventId, Timestamp)>, Error> {
+        let mut f: Filter = filter.clone();
+        f.since = Some(Timestamp::from(since));
+        f.until = Some(Timestamp::from(until));
+
+        let items: Mutex<HashMap<EventId, Timestamp>> = Mutex::new(HashMap::new());
+        self.get_events_of_with_callback(
+            vec![f.clone().limit(limit)],
+            timeout,
+            FilterOptions::ExitOnEOSE,
+            |event| {
+                let items = &items;
+                async move {
+                    let mut items = items.lock().await;
+                    items.insert(event.id, event.created_at);
+                }
+            },
+        )
+        .await?;
+        let mut items: HashMap<EventId, Timestamp> = items.into_inner();
+
+        // Events already saved into the database are not notified again: take the ones seen on this relay
+        let stored: Vec<Event> = self.database.query(vec![f]).await.unwrap_or_default();
+        for event in stored.into_iter() {
+            if let Ok(Some(urls)) = self.database.event_seen_on_relays(event.id).await {
+                if urls.contains(&self.url) {
+                    items.insert(event.id, event.created_at);
+                }
+            }
+        }
+
+        Ok(items.into_iter().collect())
+    }
+
+    /// Negentropy reconciliation of multiple filters
+    ///
+    /// Filters are reconciled in sequence. Stop at the first error.
+    pub async fn reconcile_multi(
+        &self,
+        items: Vec<(Filter, Vec<(EventId, Timestamp)>)>,
+        opts: NegentropyOptions,
+    ) -> Result<Reconciliation, Error> {
+        let mut report = Reconciliation::new();
+        for (filter, items) in items.into_iter() {
+            let r: Reconciliation = self.reconcile(filter, items, opts).await?;
+            let duration: Duration = report.duration + r.duration;
+            report.merge(r);
+            report.duration = duration;
+        }
+        Ok(report)
     }
 
     /// Check if relay support negentropy protocol
@@ -1697,7 +2906,7 @@ impl Relay {
         let pk = Keys::generate();
         let filter = Filter::new().author(pk.public_key());
         match self
-            .reconcile(
+            .reconcile_with_negentropy(
                 filter,
                 Vec::new(),
                 NegentropyOptions::new().initial_timeout(Duration::from_secs(5)),
@@ -1705,8 +2914,179 @@ impl Relay {
             .await
         {
             Ok(_) => Ok(true),
-            Err(Error::NegentropyNotSupported) => Ok(false),
+            Err(Error::NegentropyNotSupported) | Err(Error::UnknownNegentropyError) => Ok(false),
             Err(e) => Err(e),
         }
     }
 }
+
+/// Event IDs received with time-windowed `REQ`s
+#[derive(Default)]
+struct WindowedIds {
+    ids: HashSet<EventId>,
+    /// Number of `REQ`s
+    rounds: usize,
+    /// Some windows were truncated by the limit (too many events with the same timestamp)
+    truncated: bool,
+}
+
+/// Split filters in chunks of `max_filters`
+fn split_filters(filters: Vec<Filter>, max_filters: Option<usize>) -> Vec<Vec<Filter>> {
+    match max_filters {
+        Some(max) if filters.len() > max => filters.chunks(max).map(|c| c.to_vec()).collect(),
+        _ => vec![filters],
+    }
+}
+
+/// Apply relay limitations to [`ClientMessage`]
+fn limit_msg(msg: ClientMessage, limitation: &RelayLimitation) -> Result<ClientMessage, Error> {
+    let msg: ClientMessage = match (msg, limitation.max_limit) {
+        (
+            ClientMessage::Req {
+                subscription_id,
+                filters,
+            },
+            Some(max_limit),
+        ) => ClientMessage::Req {
+            subscription_id,
+            filters: clamp_limit(filters, max_limit),
+        },
+        (
+            ClientMessage::Count {
+                subscription_id,
+                filters,
+            },
+            Some(max_limit),
+        ) => ClientMessage::Count {
+            subscription_id,
+            filters: clamp_limit(filters, max_limit),
+        },
+        (msg, _) => msg,
+    };
+
+    if let Some(max_size) = limitation.max_message_length {
+        let size: usize = msg.as_json().len();
+        if size > max_size {
+            return Err(Error::MessageTooLarge { size, max_size });
+        }
+    }
+
+    Ok(msg)
+}
+
+/// Clamp `limit` of filters
+fn clamp_limit(filters: Vec<Filter>, max_limit: usize) -> Vec<Filter> {
+    filters
+        .into_iter()
+        .map(|mut filter| {
+            // Filters without limit are left as they are: the relay apply its own default
+            if let Some(limit) = filter.limit {
+                filter.limit = Some(cmp::min(limit, max_limit));
+            }
+            filter
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filters(n: usize) -> Vec<Filter> {
+        (0..n).map(|i| Filter::new().limit(i + 1)).collect()
+    }
+
+    #[test]
+    fn test_split_filters() {
+        assert_eq!(split_filters(filters(5), None), vec![filters(5)]);
+        assert_eq!(split_filters(filters(2), Some(2)), vec![filters(2)]);
+
+        let chunks = split_filters(filters(5), Some(2));
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks.concat(), filters(5));
+        assert_eq!(
+            chunks.iter().map(|c| c.len()).collect::<Vec<_>>(),
+            [2, 2, 1]
+        );
+    }
+
+    #[test]
+    fn test_clamp_limit() {
+        let limitation = RelayLimitation {
+            max_limit: Some(2),
+            ..Default::default()
+        };
+        let id = SubscriptionId::new("test");
+        let filters = vec![
+            Filter::new().limit(1),
+            Filter::new().limit(10),
+            Filter::new(),
+        ];
+
+        let msg = limit_msg(
+            ClientMessage::new_req(id.clone(), filters.clone()),
+            &limitation,
+        )
+        .unwrap();
+        assert_eq!(
+            msg,
+            ClientMessage::new_req(
+                id.clone(),
+                vec![
+                    Filter::new().limit(1),
+                    Filter::new().limit(2),
+                    Filter::new()
+                ]
+            )
+        );
+
+        // COUNT are clamped too
+        let msg = limit_msg(
+            ClientMessage::new_count(id.clone(), filters.clone()),
+            &limitation,
+        )
+        .unwrap();
+        assert_eq!(
+            msg,
+            ClientMessage::new_count(
+                id,
+                vec![
+                    Filter::new().limit(1),
+                    Filter::new().limit(2),
+                    Filter::new()
+                ]
+            )
+        );
+
+        // Without limitations the message is left as it is
+        let msg = ClientMessage::new_req(SubscriptionId::new("test"), filters);
+        assert_eq!(
+            limit_msg(msg.clone(), &RelayLimitation::default()).unwrap(),
+            msg
+        );
+    }
+
+    #[test]
+    fn test_message_too_large() {
+        let msg = ClientMessage::new_req(SubscriptionId::new("test"), filters(3));
+        let size: usize = msg.as_json().len();
+
+        let limitation = RelayLimitation {
+            max_message_length: Some(size),
+            ..Default::default()
+        };
+        assert_eq!(limit_msg(msg.clone(), &limitation).unwrap(), msg);
+
+        let limitation = RelayLimitation {
+            max_message_length: Some(size - 1),
+            ..Default::default()
+        };
+        match limit_msg(msg, &limitation) {
+            Err(Error::MessageTooLarge { size: s, max_size }) => {
+                assert_eq!(s, size);
+                assert_eq!(max_size, size - 1);
+            }
+            res => panic!("Unexpected result: {res:?}"),
+        }
+    }
+}
@@ -2,15 +2,28 @@
 // Copyright (c) 2023-2024 Rust Nostr Developers
 // Distributed under the MIT software license
 
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 
+#[cfg(feature = "nip11")]
+use nostr::nips::nip11::RelayInformationDocument;
+#[cfg(not(target_arch = "wasm32"))]
+use nostr_sdk_net::TlsConfig;
+use nostr_sdk_net::{DynWebSocketTransport, WebSocketTransport};
+
+use super::limits::Limits;
+use super::policy::{AdmissionPolicy, DynAdmissionPolicy};
+use super::reconnect::{DynReconnectStrategy, ReconnectStrategy};
 use crate::client::options::DEFAULT_SEND_TIMEOUT;
 
 pub const DEFAULT_RETRY_SEC: u64 = 10;
 pub const MIN_RETRY_SEC: u64 = 5;
 pub const MAX_ADJ_RETRY_SEC: u64 = 60;
+pub const DEFAULT_RESUME_SKEW_SEC: u64 = 60;
+pub const DEFAULT_PING_INTERVAL: Duration = Duration::from_secs(55);
+pub const DEFAULT_PONG_TIMEOUT: Duration = Duration::from_secs(20);
+pub const MIN_PONG_TIMEOUT: Duration = Duration::from_millis(100);
 
 /// [`Relay`](super::Relay) options
 #[derive(Debug, Clone)]
@@ -27,6 +40,30 @@ pub struct RelayOptions {
     retry_sec: Arc<AtomicU64>,
     /// Automatically adjust retry seconds based on success/attempts (default: true)
     adjust_retry_sec: Arc<AtomicBool>,
+    /// Reconnection strategy (default: [`RelayPoolOptions::reconnect_strategy`] or `retry_sec`/`adjust_retry_sec`)
+    reconnect_strategy: Option<Arc<DynReconnectStrategy>>,
+    /// Max consecutive failed connection attempts before terminating the relay (default: `usize::MAX`, unlimited)
+    max_reconnect_attempts: Arc<AtomicUsize>,
+    /// Resume subscriptions from the newest event received, on reconnect (default: false)
+    resume_subscriptions: Arc<AtomicBool>,
+    /// Seconds subtracted to the newest event timestamp when resuming subscriptions (default: 60 secs)
+    resume_skew_sec: Arc<AtomicU64>,
+    /// Fill gaps with negentropy reconciliation when resuming subscriptions (default: false)
+    resume_with_reconciliation: Arc<AtomicBool>,
+    /// Negotiate `permessage-deflate` compression, if supported by the relay (default: true)
+    compression: Arc<AtomicBool>,
+    /// Ping interval, in milliseconds (default: 55 secs)
+    ping_interval: Arc<AtomicU64>,
+    /// Max time to wait for the pong, in milliseconds (default: 20 secs)
+    pong_timeout: Arc<AtomicU64>,
+    /// TLS config of the default transport (default: [`RelayPoolOptions::tls`] or `webpki` roots)
+    #[cfg(not(target_arch = "wasm32"))]
+    tls: Option<TlsConfig>,
+    /// WebSocket transport (default: [`RelayPoolOptions::transport`] or tungstenite/`ws_stream_wasm`)
+    transport: Option<Arc<DynWebSocketTransport>>,
+    /// Information document used instead of fetching it from the relay (default: none)
+    #[cfg(feature = "nip11")]
+    document: Option<RelayInformationDocument>,
 }
 
 impl Default for RelayOptions {
@@ -37,6 +74,19 @@ impl Default for RelayOptions {
             reconnect: Arc::new(AtomicBool::new(true)),
             retry_sec: Arc::new(AtomicU64::new(DEFAULT_RETRY_SEC)),
             adjust_retry_sec: Arc::new(AtomicBool::new(true)),
+            reconnect_strategy: None,
+            max_reconnect_attempts: Arc::new(AtomicUsize::new(usize::MAX)),
+            resume_subscriptions: Arc::new(AtomicBool::new(false)),
+            resume_skew_sec: Arc::new(AtomicU64::new(DEFAULT_RESUME_SKEW_SEC)),
+            resume_with_reconciliation: Arc::new(AtomicBool::new(false)),
+            compression: Arc::new(AtomicBool::new(true)),
+            ping_interval: Arc::new(AtomicU64::new(DEFAULT_PING_INTERVAL.as_millis() as u64)),
+            pong_timeout: Arc::new(AtomicU64::new(DEFAULT_PONG_TIMEOUT.as_millis() as u64)),
+            #[cfg(not(target_arch = "wasm32"))]
+            tls: None,
+            transport: None,
+            #[cfg(feature = "nip11")]
+            document: None,
         }
     }
 }
@@ -152,6 +202,273 @@ impl RelayOptions {
                 Some(adjust_retry_sec)
             });
     }
+
+    /// Set [`ReconnectStrategy`]
+    ///
+    /// Override `retry_sec` and `adjust_retry_sec` options.
+    pub fn reconnect_strategy<S>(self, strategy: S) -> Self
+    where
+        S: ReconnectStrategy + 'static,
+    {
+        Self {
+            reconnect_strategy: Some(Arc::new(strategy)),
+            ..self
+        }
+    }
+
+    pub(crate) fn get_reconnect_strategy(&self) -> Option<Arc<DynReconnectStrategy>> {
+        self.reconnect_strategy.clone()
+    }
+
+    /// Use `strategy` if not already set
+    pub(crate) fn default_reconnect_strategy(
+        self,
+        strategy: Option<Arc<DynReconnectStrategy>>,
+    ) -> Self {
+        Self {
+            reconnect_strategy: self.reconnect_strategy.or(strategy),
+            ..self
+        }
+    }
+
+    /// Max consecutive failed connection attempts (default: unlimited)
+    ///
+    /// When reached, the auto-reconnect loop give up and the relay is terminated
+    /// (see [`TerminationReason::ReconnectGaveUp`](super::TerminationReason::ReconnectGaveUp)).
+    pub fn max_reconnect_attempts(self, max: Option<usize>) -> Self {
+        Self {
+            max_reconnect_attempts: Arc::new(AtomicUsize::new(max.unwrap_or(usize::MAX))),
+            ..self
+        }
+    }
+
+    pub(crate) fn get_max_reconnect_attempts(&self) -> usize {
+        self.max_reconnect_attempts.load(Ordering::SeqCst)
+    }
+
+    /// Set `max_reconnect_attempts` option
+    pub fn update_max_reconnect_attempts(&self, max: Option<usize>) {
+        let _ =
+            self.max_reconnect_attempts
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |_| {
+                    Some(max.unwrap_or(usize::MAX))
+                });
+    }
+
+    /// Resume subscriptions from the newest event received, on reconnect (default: false)
+    ///
+    /// The `since` field of the filters is adjusted to the `created_at` of the newest event
+    /// received for the subscription (minus the `resume_skew_sec`).
+    pub fn resume_subscriptions(self, resume_subscriptions: bool) -> Self {
+        Self {
+            resume_subscriptions: Arc::new(AtomicBool::new(resume_subscriptions)),
+            ..self
+        }
+    }
+
+    pub(crate) fn get_resume_subscriptions(&self) -> bool {
+        self.resume_subscriptions.load(Ordering::SeqCst)
+    }
+
+    /// Set `resume_subscriptions` option
+    pub fn update_resume_subscriptions(&self, resume_subscriptions: bool) {
+        let _ = self
+            .resume_subscriptions
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |_| {
+                Some(resume_subscriptions)
+            });
+    }
+
+    /// Seconds subtracted to the newest event timestamp when resuming subscriptions (default: 60 secs)
+    pub fn resume_skew_sec(self, resume_skew_sec: u64) -> Self {
+        Self {
+            resume_skew_sec: Arc::new(AtomicU64::new(resume_skew_sec)),
+            ..self
+        }
+    }
+
+    pub(crate) fn get_resume_skew_sec(&self) -> u64 {
+        self.resume_skew_sec.load(Ordering::SeqCst)
+    }
+
+    /// Set `resume_skew_sec` option
+    pub fn update_resume_skew_sec(&self, resume_skew_sec: u64) {
+        let _ = self
+            .resume_skew_sec
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |_| {
+                Some(resume_skew_sec)
+            });
+    }
+
+    /// Fill gaps with negentropy reconciliation when resuming subscriptions (default: false)
+    ///
+    /// Events older than the resume point, that the relay received while disconnected, are fetched.
+    pub fn resume_with_reconciliation(self, resume_with_reconciliation: bool) -> Self {
+        Self {
+            resume_with_reconciliation: Arc::new(AtomicBool::new(resume_with_reconciliation)),
+            ..self
+        }
+    }
+
+    pub(crate) fn get_resume_with_reconciliation(&self) -> bool {
+        self.resume_with_reconciliation.load(Ordering::SeqCst)
+    }
+
+    /// Set `resume_with_reconciliation` option
+    pub fn update_resume_with_reconciliation(&self, resume_with_reconciliation: bool) {
+        let _ = self.resume_with_reconciliation.fetch_update(
+            Ordering::SeqCst,
+            Ordering::SeqCst,
+            |_| Some(resume_with_reconciliation),
+        );
+    }
+
+    /// Negotiate `permessage-deflate` compression, if supported by the relay (default: true)
+    ///
+    /// Not supported on WASM: the browser negotiate it automatically.
+    pub fn compression(self, compression: bool) -> Self {
+        Self {
+            compression: Arc::new(AtomicBool::new(compression)),
+            ..self
+        }
+    }
+
+    pub(crate) fn get_compression(&self) -> bool {
+        self.compression.load(Ordering::SeqCst)
+    }
+
+    /// Set `compression` option (applied on next connection)
+    pub fn update_compression(&self, compression: bool) {
+        let _ = self
+            .compression
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |_| Some(compression));
+    }
+
+    /// Set ping interval (default: 55 secs)
+    ///
+    /// Not supported on WASM: the browsers don't expose the WebSocket ping.
+    pub fn ping_interval(self, interval: Duration) -> Self {
+        Self {
+            ping_interval: Arc::new(AtomicU64::new(interval.as_millis() as u64)),
+            ..self
+        }
+    }
+
+    pub(crate) fn get_ping_interval(&self) -> Duration {
+        Duration::from_millis(self.ping_interval.load(Ordering::SeqCst))
+    }
+
+    /// Set `ping_interval` option
+    pub fn update_ping_interval(&self, interval: Duration) {
+        let _ = self
+            .ping_interval
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |_| {
+                Some(interval.as_millis() as u64)
+            });
+    }
+
+    /// Set max time to wait for the pong (default: 20 secs)
+    ///
+    /// If the relay not reply to the ping in time, the connection is considered dead:
+    /// the relay is disconnected (and reconnected, if `reconnect` is enabled).
+    ///
+    /// Clamped to half the ping interval, so the next ping is sent after the pong deadline (min: 100 ms).
+    pub fn pong_timeout(self, timeout: Duration) -> Self {
+        Self {
+            pong_timeout: Arc::new(AtomicU64::new(timeout.as_millis() as u64)),
+            ..self
+        }
+    }
+
+    pub(crate) fn get_pong_timeout(&self) -> Duration {
+        let timeout = Duration::from_millis(self.pong_timeout.load(Ordering::SeqCst));
+        timeout
+            .min(self.get_ping_interval() / 2)
+            .max(MIN_PONG_TIMEOUT)
+    }
+
+    /// Set `pong_timeout` option
+    pub fn update_pong_timeout(&self, timeout: Duration) {
+        let _ = self
+            .pong_timeout
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |_| {
+                Some(timeout.as_millis() as u64)
+            });
+    }
+
+    /// Set [`TlsConfig`] (root certificates, pinning, client certificate, ...)
+    ///
+    /// Can't be combined with a custom [`WebSocketTransport`].
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn tls(self, tls: TlsConfig) -> Self {
+        Self {
+            tls: Some(tls),
+            ..self
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn get_tls(&self) -> TlsConfig {
+        self.tls.clone().unwrap_or_default()
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn has_tls(&self) -> bool {
+        self.tls.is_some()
+    }
+
+    /// Use `tls` if not already set
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn default_tls(self, tls: Option<TlsConfig>) -> Self {
+        Self {
+            tls: self.tls.or(tls),
+            ..self
+        }
+    }
+
+    /// Set custom [`WebSocketTransport`]
+    ///
+    /// Useful to bring your own WebSocket stack (custom TLS roots, certificate pinning, HTTP proxy, ...).
+    ///
+    /// The transport opens the connection on its own: proxy and [`TlsConfig`] can't be combined with it
+    /// and compression is used only if supported by the transport.
+    pub fn transport<T>(self, transport: T) -> Self
+    where
+        T: WebSocketTransport + 'static,
+    {
+        Self {
+            transport: Some(Arc::new(transport)),
+            ..self
+        }
+    }
+
+    pub(crate) fn get_transport(&self) -> Option<Arc<DynWebSocketTransport>> {
+        self.transport.clone()
+    }
+
+    /// Use `transport` if not already set
+    pub(crate) fn default_transport(self, transport: Option<Arc<DynWebSocketTransport>>) -> Self {
+        Self {
+            transport: self.transport.or(transport),
+            ..self
+        }
+    }
+
+    /// Set [`RelayInformationDocument`] (NIP11)
+    ///
+    /// The document is not fetched from the relay: its limitations are applied from the first connection.
+    #[cfg(feature = "nip11")]
+    pub fn document(self, document: RelayInformationDocument) -> Self {
+        Self {
+            document: Some(document),
+            ..self
+        }
+    }
+
+    #[cfg(feature = "nip11")]
+    pub(crate) fn get_document(&self) -> Option<RelayInformationDocument> {
+        self.document.clone()
+    }
 }
 
 /// [`Relay`](super::Relay) send options
@@ -209,8 +526,33 @@ pub enum FilterOptions {
     WaitDurationAfterEOSE(Duration),
 }
 
+/// Where to get the events from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum QueryMode {
+    /// Get the events from the local database immediately, then only the newer ones from relays
+    ///
+    /// The `since` of each filter is set to the `created_at` of the newest stored event that match it.
+    /// The stored events are returned without waiting for the relays: the newer events are delivered
+    /// with the notifications, or with the callback of `get_events_of_with_callback`.
+    /// If nothing is stored, the relays are waited as with [`QueryMode::NetworkFirst`].
+    CacheFirst,
+    /// Get the events from relays, merged with the ones in the local database
+    ///
+    /// If no relay is reachable, only the stored events are returned.
+    #[default]
+    NetworkFirst,
+    /// Get the events only from the local database: no request is sent to relays
+    CacheOnly,
+    /// Get the events only from relays
+    ///
+    /// Events already in the local database aren't received again from relays: they are included if seen
+    /// on one of the relays that replied, also if seen with a previous query (the relay may have deleted them since).
+    /// Fail if no relay replied.
+    NetworkOnly,
+}
+
 /// Relay Pool Options
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct RelayPoolOptions {
     /// Notification channel size (default: 1024)
     pub notification_channel_size: usize,
@@ -218,6 +560,29 @@ pub struct RelayPoolOptions {
     pub task_channel_size: usize,
     /// Shutdown on [RelayPool](super::pool::RelayPool) drop
     pub shutdown_on_drop: bool,
+    /// Admission policies for incoming events (default: empty)
+    ///
+    /// Checked in order: the first policy that reject the event stop the check.
+    pub admission_policies: Vec<Arc<DynAdmissionPolicy>>,
+    /// Relay selection options
+    pub relay_selection: RelaySelectionOptions,
+    /// Outbox options
+    pub outbox: OutboxOptions,
+    /// Relays limits
+    pub limits: Limits,
+    /// Default WebSocket transport of the relays (default: tungstenite on native, `ws_stream_wasm` on WASM)
+    ///
+    /// Overridden by [`RelayOptions::transport`].
+    pub transport: Option<Arc<DynWebSocketTransport>>,
+    /// Default reconnection strategy of the relays (default: `retry_sec`/`adjust_retry_sec` relay options)
+    ///
+    /// Overridden by [`RelayOptions::reconnect_strategy`].
+    pub reconnect_strategy: Option<Arc<DynReconnectStrategy>>,
+    /// Default TLS config of the relays (default: `webpki` roots)
+    ///
+    /// Overridden by [`RelayOptions::tls`].
+    #[cfg(not(target_arch = "wasm32"))]
+    pub tls: Option<TlsConfig>,
 }
 
 impl Default for RelayPoolOptions {
@@ -226,6 +591,14 @@ impl Default for RelayPoolOptions {
             notification_channel_size: 1024,
             task_channel_size: 1024,
             shutdown_on_drop: false,
+            admission_policies: Vec::new(),
+            relay_selection: RelaySelectionOptions::default(),
+            outbox: OutboxOptions::default(),
+            limits: Limits::default(),
+            transport: None,
+            reconnect_strategy: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            tls: None,
         }
     }
 }
@@ -243,6 +616,192 @@ impl RelayPoolOptions {
             ..self
         }
     }
+
+    /// Add [`AdmissionPolicy`] for incoming events
+    pub fn admission_policy<P>(mut self, policy: P) -> Self
+    where
+        P: AdmissionPolicy + 'static,
+    {
+        self.admission_policies.push(Arc::new(policy));
+        self
+    }
+
+    /// Set [`RelaySelectionOptions`]
+    pub fn relay_selection(self, relay_selection: RelaySelectionOptions) -> Self {
+        Self {
+            relay_selection,
+            ..self
+        }
+    }
+
+    /// Set [`OutboxOptions`]
+    pub fn outbox(self, outbox: OutboxOptions) -> Self {
+        Self { outbox, ..self }
+    }
+
+    /// Set relays [`Limits`] (messages size, events size and rate limits)
+    pub fn limits(self, limits: Limits) -> Self {
+        Self { limits, ..self }
+    }
+
+    /// Set default [`WebSocketTransport`] of the relays
+    pub fn transport<T>(self, transport: T) -> Self
+    where
+        T: WebSocketTransport + 'static,
+    {
+        Self {
+            transport: Some(Arc::new(transport)),
+            ..self
+        }
+    }
+
+    /// Set default [`ReconnectStrategy`] of the relays
+    pub fn reconnect_strategy<S>(self, strategy: S) -> Self
+    where
+        S: ReconnectStrategy + 'static,
+    {
+        Self {
+            reconnect_strategy: Some(Arc::new(strategy)),
+            ..self
+        }
+    }
+
+    /// Set default [`TlsConfig`] of the relays
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn tls(self, tls: TlsConfig) -> Self {
+        Self {
+            tls: Some(tls),
+            ..self
+        }
+    }
+}
+
+/// Score-based relay selection options
+///
+/// See [`RelayScore`](super::RelayScore) for details about the score.
+#[derive(Debug, Clone, Copy)]
+pub struct RelaySelectionOptions {
+    /// Select relays for reads and writes by score (default: false)
+    pub enabled: bool,
+    /// Relays with a score lower than or equal to this are skipped (default: 0.5)
+    ///
+    /// If all relays have a lower score, the best one is used anyway.
+    pub min_score: f64,
+    /// Max number of relays used for reads (default: all)
+    pub max_read_relays: Option<usize>,
+    /// Max number of relays used for writes (default: all)
+    pub max_write_relays: Option<usize>,
+    /// Save scores into the database on stop/shutdown and load them on connect (default: false)
+    pub persist_scores: bool,
+}
+
+impl Default for RelaySelectionOptions {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_score: 0.5,
+            max_read_relays: None,
+            max_write_relays: None,
+            persist_scores: false,
+        }
+    }
+}
+
+impl RelaySelectionOptions {
+    /// New default [`RelaySelectionOptions`]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Select relays for reads and writes by score (default: false)
+    pub fn enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    /// Relays with a score lower than or equal to this are skipped (default: 0.5)
+    pub fn min_score(mut self, min_score: f64) -> Self {
+        self.min_score = min_score;
+        self
+    }
+
+    /// Max number of relays used for reads (default: all)
+    pub fn max_read_relays(mut self, max: Option<usize>) -> Self {
+        self.max_read_relays = max;
+        self
+    }
+
+    /// Max number of relays used for writes (default: all)
+    pub fn max_write_relays(mut self, max: Option<usize>) -> Self {
+        self.max_write_relays = max;
+        self
+    }
+
+    /// Save scores into the database on stop/shutdown and load them on connect (default: false)
+    pub fn persist_scores(mut self, persist_scores: bool) -> Self {
+        self.persist_scores = persist_scores;
+        self
+    }
+}
+
+/// Outbox options
+///
+/// When enabled, outgoing events are saved into the database before being sent
+/// and retried in background until the relay reply with an `OK` message or the item expire.
+#[derive(Debug, Clone, Copy)]
+pub struct OutboxOptions {
+    /// Enable outbox (default: false)
+    pub enabled: bool,
+    /// Time after which a pending event is discarded (default: 7 days)
+    pub ttl: Duration,
+    /// Min retry interval (default: 5 secs)
+    pub min_retry_interval: Duration,
+    /// Max retry interval (default: 5 min)
+    ///
+    /// The retry interval is doubled after every failed attempt, up to this value.
+    pub max_retry_interval: Duration,
+}
+
+impl Default for OutboxOptions {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ttl: Duration::from_secs(60 * 60 * 24 * 7),
+            min_retry_interval: Duration::from_secs(5),
+            max_retry_interval: Duration::from_secs(60 * 5),
+        }
+    }
+}
+
+impl OutboxOptions {
+    /// New default [`OutboxOptions`]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enable outbox (default: false)
+    pub fn enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    /// Time after which a pending event is discarded (default: 7 days)
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Min retry interval (default: 5 secs)
+    pub fn min_retry_interval(mut self, interval: Duration) -> Self {
+        self.min_retry_interval = interval;
+        self
+    }
+
+    /// Max retry interval (default: 5 min)
+    pub fn max_retry_interval(mut self, interval: Duration) -> Self {
+        self.max_retry_interval = interval;
+        self
+    }
 }
 
 /// Negentropy reconciliation options
@@ -268,6 +827,8 @@ pub struct NegentropyOptions {
     ///
     /// If `true`, perform the set reconciliation on each side.
     pub bidirectional: bool,
+    /// Fallback to `REQ` based reconciliation if the relay not support negentropy (default: true)
+    pub req_fallback: bool,
 }
 
 impl Default for NegentropyOptions {
@@ -279,6 +840,7 @@ impl Default for NegentropyOptions {
             relative_get_events_timeout: Duration::from_millis(250),
             batch_send_timeout: Duration::from_secs(30),
             bidirectional: false,
+            req_fallback: true,
         }
     }
 }
@@ -332,4 +894,31 @@ impl NegentropyOptions {
         self.bidirectional = bidirectional;
         self
     }
+
+    /// Fallback to `REQ` based reconciliation if the relay not support negentropy (default: true)
+    ///
+    /// The filter is synced with time-windowed `REQ`s: windows that hit the limit are split in half.
+    pub fn req_fallback(mut self, req_fallback: bool) -> Self {
+        self.req_fallback = req_fallback;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pong_timeout_clamped() {
+        let opts = RelayOptions::new()
+            .ping_interval(Duration::from_secs(10))
+            .pong_timeout(Duration::from_secs(30));
+        assert_eq!(opts.get_pong_timeout(), Duration::from_secs(5));
+
+        opts.update_pong_timeout(Duration::ZERO);
+        assert_eq!(opts.get_pong_timeout(), MIN_PONG_TIMEOUT);
+
+        opts.update_pong_timeout(Duration::from_secs(2));
+        assert_eq!(opts.get_pong_timeout(), Duration::from_secs(2));
+    }
 }
@@ -0,0 +1,238 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Outbox
+//!
+//! Outgoing events are saved into the [`NostrDatabase`](nostr_database::NostrDatabase)
+//! before being sent and removed when the relay reply with an `OK` message,
+//! unless the event was rejected for rate limit or missing auth.
+//! Pending events are retried in background, with exponential backoff, until they expire.
+
+use std::cmp;
+use std::collections::HashMap;
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Weak};
+
+use async_utility::{thread, time};
+use nostr::{EventId, Timestamp, Url};
+use nostr_database::{DatabaseError, DynNostrDatabase, OutboxEvent};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, RwLock};
+
+use super::options::{OutboxOptions, RelaySendOptions};
+use super::{Error as RelayError, Relay, RelayPoolNotification, RelayStatus};
+
+type Backoff = HashMap<(EventId, Url), (u32, Timestamp)>;
+
+/// `OK` message prefixes of rejections that may succeed later
+const RETRYABLE_PREFIXES: [&str; 2] = ["rate-limited:", "auth-required:"];
+
+fn is_retryable(message: &str) -> bool {
+    RETRYABLE_PREFIXES
+        .iter()
+        .any(|prefix| message.starts_with(prefix))
+}
+
+/// Get the events for which the relay gave a final answer (`OK` message)
+///
+/// Rejections that may succeed later (rate limit, auth required) are not final.
+fn resolved<T>(ids: &[EventId], res: &Result<T, RelayError>) -> Vec<EventId> {
+    match res {
+        Ok(..) => ids.to_vec(),
+        Err(RelayError::EventNotPublished(message)) => {
+            if is_retryable(message) {
+                Vec::new()
+            } else {
+                ids.to_vec()
+            }
+        }
+        Err(RelayError::EventsNotPublished(not_published)) => ids
+            .iter()
+            .filter(|id| not_published.get(id).map_or(true, |m| !is_retryable(m)))
+            .copied()
+            .collect(),
+        Err(RelayError::PartialPublish { not_published, .. }) => ids
+            .iter()
+            .filter(|id| not_published.get(id).map_or(true, |m| !is_retryable(m)))
+            .copied()
+            .collect(),
+        Err(..) => Vec::new(),
+    }
+}
+
+/// Remove events from outbox if the relay gave a final answer
+pub(crate) async fn resolve<T>(
+    database: &Arc<DynNostrDatabase>,
+    ids: &[EventId],
+    relay_url: &Url,
+    res: &Result<T, RelayError>,
+) {
+    for id in resolved(ids, res).into_iter() {
+        if let Err(e) = database.remove_outbox_event(id, relay_url.clone()).await {
+            tracing::error!("Impossible to remove event {id} from outbox: {e}");
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct OutboxTask {
+    database: Arc<DynNostrDatabase>,
+    relays: Arc<RwLock<HashMap<Url, Relay>>>,
+    notifications: broadcast::Receiver<RelayPoolNotification>,
+    /// Shared by all the `RelayPool` clones: released when the last one is dropped
+    pool: Weak<AtomicBool>,
+    opts: OutboxOptions,
+}
+
+impl OutboxTask {
+    pub fn new(
+        database: Arc<DynNostrDatabase>,
+        relays: Arc<RwLock<HashMap<Url, Relay>>>,
+        notifications: broadcast::Receiver<RelayPoolNotification>,
+        pool: Weak<AtomicBool>,
+        opts: OutboxOptions,
+    ) -> Self {
+        Self {
+            database,
+            relays,
+            notifications,
+            pool,
+            opts,
+        }
+    }
+
+    pub fn run(mut self) {
+        tracing::debug!("Outbox Thread Started");
+        thread::spawn(async move {
+            let mut backoff: Backoff = HashMap::new();
+            let mut last_retry: Option<Timestamp> = None;
+
+            loop {
+                // The relay pool was dropped without shutdown
+                if self.pool.strong_count() == 0 {
+                    break;
+                }
+
+                let mut reconnected: bool = false;
+
+                match time::timeout(
+                    Some(self.opts.min_retry_interval),
+                    self.notifications.recv(),
+                )
+                .await
+                {
+                    Some(Ok(RelayPoolNotification::RelayStatus {
+                        url,
+                        status: RelayStatus::Connected,
+                    })) => {
+                        // Relay reconnected: retry immediately
+                        let now = Timestamp::now();
+                        for ((_, relay_url), (attempts, next_try)) in backoff.iter_mut() {
+                            if relay_url == &url {
+                                *attempts = 0;
+                                *next_try = now;
+                            }
+                        }
+                        reconnected = true;
+                    }
+                    Some(Ok(RelayPoolNotification::Shutdown)) | Some(Err(RecvError::Closed)) => {
+                        break;
+                    }
+                    _ => (),
+                }
+
+                // Avoid to check the outbox at every notification
+                let due: bool = match last_retry {
+                    Some(last_retry) => {
+                        last_retry + self.opts.min_retry_interval <= Timestamp::now()
+                    }
+                    None => true,
+                };
+
+                if reconnected || due {
+                    last_retry = Some(Timestamp::now());
+                    if let Err(e) = self.retry(&mut backoff).await {
+                        tracing::error!("Impossible to retry outbox events: {e}");
+                    }
+                }
+            }
+
+            tracing::debug!("Exited from Outbox thread");
+        });
+    }
+
+    async fn retry(&self, backoff: &mut Backoff) -> Result<(), DatabaseError> {
+        let now = Timestamp::now();
+        let relays = self.relays.read().await.clone();
+        let mut handles = Vec::new();
+
+        for item in self.database.outbox_events().await?.into_iter() {
+            let OutboxEvent {
+                event, relay_url, ..
+            } = item.clone();
+            let key = (event.id, relay_url.clone());
+
+            if item.is_expired() {
+                tracing::debug!("Outbox event {} for {relay_url} expired", event.id);
+                self.database
+                    .remove_outbox_event(event.id, relay_url)
+                    .await?;
+                backoff.remove(&key);
+                continue;
+            }
+
+            // Events never seen before may still be in flight: wait for the first interval
+            let (attempts, next_try) = *backoff
+                .entry(key.clone())
+                .or_insert((0, now + self.opts.min_retry_interval));
+            if next_try > now {
+                continue;
+            }
+
+            let relay: Relay = match relays.get(&relay_url) {
+                Some(relay) => relay.clone(),
+                None => continue,
+            };
+
+            if !relay.is_connected().await {
+                continue;
+            }
+
+            let handle = thread::spawn(async move {
+                let res = relay.send_event(event, RelaySendOptions::new()).await;
+                let resolved: bool = !resolved(&[key.0], &res).is_empty();
+                (key, attempts, resolved)
+            });
+            handles.push(handle);
+        }
+
+        for handle in handles.into_iter().flatten() {
+            if let Ok(((event_id, relay_url), attempts, resolved)) = handle.join().await {
+                if resolved {
+                    backoff.remove(&(event_id, relay_url.clone()));
+                    self.database
+                        .remove_outbox_event(event_id, relay_url)
+                        .await?;
+                } else {
+                    let interval = cmp::min(
+                        self.opts
+                            .min_retry_interval
+                            .saturating_mul(2u32.saturating_pow(attempts)),
+                        self.opts.max_retry_interval,
+                    );
+                    tracing::debug!(
+                        "Impossible to send outbox event {event_id} to {relay_url}: retry in {} secs",
+                        interval.as_secs()
+                    );
+                    backoff.insert(
+                        (event_id, relay_url),
+                        (attempts.saturating_add(1), Timestamp::now() + interval),
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
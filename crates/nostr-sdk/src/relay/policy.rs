@@ -0,0 +1,365 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Admission policies
+//!
+//! Policies are checked by the [`RelayPool`](super::pool::RelayPool) for every incoming event,
+//! after the ID and the signature have been verified and before the event is saved into the database.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use nostr::key::XOnlyPublicKey;
+use nostr::nips::nip13;
+use nostr::{Event, JsonUtil, Kind, SubscriptionId, Url};
+use nostr_database::{async_trait, AsyncTraitDeps};
+
+use super::limits::{EventsLimits, Limits};
+
+/// A type-erased [`AdmissionPolicy`]
+pub type DynAdmissionPolicy = dyn AdmissionPolicy;
+
+/// Admission status
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum AdmissionStatus {
+    /// Save the event into the database and send notifications
+    Accept,
+    /// Send notifications but don't save the event into the database
+    AcceptWithoutStore,
+    /// Discard the event
+    Reject {
+        /// Reason
+        reason: String,
+    },
+}
+
+impl AdmissionStatus {
+    /// Reject with reason
+    pub fn reject<S>(reason: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self::Reject {
+            reason: reason.into(),
+        }
+    }
+
+    /// Check if event is accepted (with or without store)
+    pub fn is_accepted(&self) -> bool {
+        !self.is_rejected()
+    }
+
+    /// Check if event is rejected
+    pub fn is_rejected(&self) -> bool {
+        matches!(self, Self::Reject { .. })
+    }
+}
+
+/// Admission policy for incoming events
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait AdmissionPolicy: AsyncTraitDeps {
+    /// Check if [`Event`], received from `relay_url` for `subscription_id`, can be admitted
+    async fn admit_event(
+        &self,
+        relay_url: &Url,
+        subscription_id: &SubscriptionId,
+        event: &Event,
+    ) -> AdmissionStatus;
+}
+
+/// Check policies in order
+///
+/// The first [`AdmissionStatus::Reject`] stop the check.
+/// If at least one policy return [`AdmissionStatus::AcceptWithoutStore`], the event will not be saved.
+pub(crate) async fn admit_event(
+    policies: &[Arc<DynAdmissionPolicy>],
+    relay_url: &Url,
+    subscription_id: &SubscriptionId,
+    event: &Event,
+) -> AdmissionStatus {
+    let mut status = AdmissionStatus::Accept;
+    for policy in policies.iter() {
+        match policy.admit_event(relay_url, subscription_id, event).await {
+            AdmissionStatus::Accept => (),
+            AdmissionStatus::AcceptWithoutStore => status = AdmissionStatus::AcceptWithoutStore,
+            reject => return reject,
+        }
+    }
+    status
+}
+
+/// Minimum NIP13 difficulty policy
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/13.md>
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PowPolicy {
+    difficulty: u8,
+}
+
+impl PowPolicy {
+    /// New minimum POW policy
+    pub fn new(difficulty: u8) -> Self {
+        Self { difficulty }
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl AdmissionPolicy for PowPolicy {
+    async fn admit_event(&self, _: &Url, _: &SubscriptionId, event: &Event) -> AdmissionStatus {
+        let difficulty: u8 = nip13::get_leading_zero_bits(event.id.inner());
+        if difficulty >= self.difficulty {
+            AdmissionStatus::Accept
+        } else {
+            AdmissionStatus::reject(format!(
+                "pow: difficulty {difficulty} is less than {}",
+                self.difficulty
+            ))
+        }
+    }
+}
+
+/// Max event size policy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MaxEventSizePolicy {
+    max_size: u32,
+}
+
+impl Default for MaxEventSizePolicy {
+    fn default() -> Self {
+        Self::from(Limits::default())
+    }
+}
+
+impl From<EventsLimits> for MaxEventSizePolicy {
+    fn from(limits: EventsLimits) -> Self {
+        Self::new(limits.max_size)
+    }
+}
+
+impl From<Limits> for MaxEventSizePolicy {
+    fn from(limits: Limits) -> Self {
+        Self::from(limits.events)
+    }
+}
+
+impl MaxEventSizePolicy {
+    /// New max event size policy (size of normalised JSON, in bytes)
+    pub fn new(max_size: u32) -> Self {
+        Self { max_size }
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl AdmissionPolicy for MaxEventSizePolicy {
+    async fn admit_event(&self, _: &Url, _: &SubscriptionId, event: &Event) -> AdmissionStatus {
+        let size: usize = event.as_json().as_bytes().len();
+        if size <= self.max_size as usize {
+            AdmissionStatus::Accept
+        } else {
+            AdmissionStatus::reject(format!(
+                "event too large: size={size}, max_size={}",
+                self.max_size
+            ))
+        }
+    }
+}
+
+/// Web of Trust policy
+///
+/// Accept only events authored by the allowed public keys.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WotPolicy {
+    allowed: HashSet<XOnlyPublicKey>,
+}
+
+impl WotPolicy {
+    /// New WoT policy
+    pub fn new<I>(allowed: I) -> Self
+    where
+        I: IntoIterator<Item = XOnlyPublicKey>,
+    {
+        Self {
+            allowed: allowed.into_iter().collect(),
+        }
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl AdmissionPolicy for WotPolicy {
+    async fn admit_event(&self, _: &Url, _: &SubscriptionId, event: &Event) -> AdmissionStatus {
+        if self.allowed.contains(&event.pubkey) {
+            AdmissionStatus::Accept
+        } else {
+            AdmissionStatus::reject("wot: author not allowed")
+        }
+    }
+}
+
+/// Kind allowlist policy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KindPolicy {
+    allowed: HashSet<Kind>,
+}
+
+impl KindPolicy {
+    /// New kind allowlist policy
+    pub fn new<I>(allowed: I) -> Self
+    where
+        I: IntoIterator<Item = Kind>,
+    {
+        Self {
+            allowed: allowed.into_iter().collect(),
+        }
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl AdmissionPolicy for KindPolicy {
+    async fn admit_event(&self, _: &Url, _: &SubscriptionId, event: &Event) -> AdmissionStatus {
+        if self.allowed.contains(&event.kind) {
+            AdmissionStatus::Accept
+        } else {
+            AdmissionStatus::reject(format!("kind {} not allowed", event.kind))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr::{EventBuilder, Keys};
+
+    use super::*;
+
+    #[derive(Debug)]
+    struct AcceptWithoutStorePolicy;
+
+    #[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+    #[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+    impl AdmissionPolicy for AcceptWithoutStorePolicy {
+        async fn admit_event(&self, _: &Url, _: &SubscriptionId, _: &Event) -> AdmissionStatus {
+            AdmissionStatus::AcceptWithoutStore
+        }
+    }
+
+    async fn check<P>(policy: &P, event: &Event) -> AdmissionStatus
+    where
+        P: AdmissionPolicy,
+    {
+        let url = Url::parse("wss://relay.example.com").unwrap();
+        policy
+            .admit_event(&url, &SubscriptionId::new("test"), event)
+            .await
+    }
+
+    fn text_note(keys: &Keys) -> Event {
+        EventBuilder::new_text_note("hello", [])
+            .to_event(keys)
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_pow_policy() {
+        let keys = Keys::generate();
+        let policy = PowPolicy::new(8);
+
+        let event = EventBuilder::new_text_note("hello", [])
+            .to_pow_event(&keys, 8)
+            .unwrap();
+        assert_eq!(check(&policy, &event).await, AdmissionStatus::Accept);
+
+        // Find an event with less than 8 leading zero bits
+        let event = (0..)
+            .map(|i| {
+                EventBuilder::new_text_note(format!("hello {i}"), [])
+                    .to_event(&keys)
+                    .unwrap()
+            })
+            .find(|e| nip13::get_leading_zero_bits(e.id.inner()) < 8)
+            .unwrap();
+        assert!(check(&policy, &event).await.is_rejected());
+    }
+
+    #[tokio::test]
+    async fn test_max_event_size_policy() {
+        let keys = Keys::generate();
+        let event = text_note(&keys);
+        let size = event.as_json().len() as u32;
+
+        let policy = MaxEventSizePolicy::new(size);
+        assert_eq!(check(&policy, &event).await, AdmissionStatus::Accept);
+
+        let policy = MaxEventSizePolicy::new(size - 1);
+        assert!(check(&policy, &event).await.is_rejected());
+    }
+
+    #[tokio::test]
+    async fn test_wot_policy() {
+        let allowed = Keys::generate();
+        let other = Keys::generate();
+        let policy = WotPolicy::new([allowed.public_key()]);
+
+        assert_eq!(
+            check(&policy, &text_note(&allowed)).await,
+            AdmissionStatus::Accept
+        );
+        assert!(check(&policy, &text_note(&other)).await.is_rejected());
+    }
+
+    #[tokio::test]
+    async fn test_kind_policy() {
+        let keys = Keys::generate();
+        let policy = KindPolicy::new([Kind::TextNote]);
+
+        assert_eq!(
+            check(&policy, &text_note(&keys)).await,
+            AdmissionStatus::Accept
+        );
+
+        let note = text_note(&keys);
+        let reaction = EventBuilder::new_reaction(note.id, note.pubkey, "+")
+            .to_event(&keys)
+            .unwrap();
+        assert!(check(&policy, &reaction).await.is_rejected());
+    }
+
+    #[tokio::test]
+    async fn test_admit_event_in_order() {
+        let keys = Keys::generate();
+        let event = text_note(&keys);
+        let url = Url::parse("wss://relay.example.com").unwrap();
+        let subscription_id = SubscriptionId::new("test");
+
+        // No policies
+        assert_eq!(
+            admit_event(&[], &url, &subscription_id, &event).await,
+            AdmissionStatus::Accept
+        );
+
+        // Accept without store propagate
+        let policies: Vec<Arc<DynAdmissionPolicy>> = vec![
+            Arc::new(AcceptWithoutStorePolicy),
+            Arc::new(KindPolicy::new([Kind::TextNote])),
+        ];
+        assert_eq!(
+            admit_event(&policies, &url, &subscription_id, &event).await,
+            AdmissionStatus::AcceptWithoutStore
+        );
+
+        // The first reject stop the check
+        let policies: Vec<Arc<DynAdmissionPolicy>> = vec![
+            Arc::new(AcceptWithoutStorePolicy),
+            Arc::new(KindPolicy::new([Kind::Metadata])),
+            Arc::new(WotPolicy::new([])),
+        ];
+        assert_eq!(
+            admit_event(&policies, &url, &subscription_id, &event).await,
+            AdmissionStatus::reject("kind 1 not allowed")
+        );
+    }
+}
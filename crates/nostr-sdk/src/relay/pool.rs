@@ -4,12 +4,16 @@
 
 //! Relay Pool
 
-use std::collections::HashMap;
+use std::cmp;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
 #[cfg(not(target_arch = "wasm32"))]
 use std::net::SocketAddr;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
 
 use async_utility::thread;
 use nostr::message::MessageHandleError;
@@ -17,15 +21,21 @@ use nostr::{
     event, ClientMessage, Event, EventId, Filter, JsonUtil, MissingPartialEvent, PartialEvent,
     RawRelayMessage, RelayMessage, SubscriptionId, Timestamp, Url,
 };
-use nostr_database::{DatabaseError, DynNostrDatabase, IntoNostrDatabase, MemoryDatabase};
+use nostr_database::{
+    DatabaseError, DynNostrDatabase, IntoNostrDatabase, MemoryDatabase, OutboxEvent,
+};
 use thiserror::Error;
 use tokio::sync::mpsc::{self, Receiver, Sender};
 use tokio::sync::{broadcast, Mutex, RwLock};
 
+use super::bus::{EventBus, ListenerOptions, PoolEvent, PoolEventListener};
+use super::count::{EventCount, RelayCount};
 use super::options::RelayPoolOptions;
+use super::outbox::{self, OutboxTask};
+use super::policy::{self, AdmissionStatus, DynAdmissionPolicy};
 use super::{
-    Error as RelayError, FilterOptions, InternalSubscriptionId, Limits, NegentropyOptions, Relay,
-    RelayOptions, RelaySendOptions, RelayStatus,
+    Error as RelayError, FilterOptions, InternalSubscriptionId, NegentropyOptions, QueryMode,
+    Reconciliation, Relay, RelayOptions, RelayScore, RelaySendOptions, RelayStatus,
 };
 use crate::util::TryIntoUrl;
 
@@ -74,6 +84,12 @@ pub enum Error {
     /// Event expired
     #[error("event expired")]
     EventExpired,
+    /// No relay replied
+    #[error("no relay replied")]
+    NoRelayReplied,
+    /// Proxy or TLS config combined with a custom transport
+    #[error("proxy and TLS config can't be used with a custom transport")]
+    UnsupportedTransportOptions,
 }
 
 /// Relay Pool Message
@@ -103,6 +119,8 @@ pub enum RelayPoolMessage {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum RelayPoolNotification {
     /// Received an [`Event`]. Does not include events sent by this client.
+    ///
+    /// The NIP26 delegation is not required to be valid: check it with [`Event::delegator`].
     Event(Url, Event),
     /// Received a [`RelayMessage`]. Includes messages wrapping events that were sent by this client.
     Message(Url, RelayMessage),
@@ -122,21 +140,30 @@ pub enum RelayPoolNotification {
 #[derive(Debug, Clone)]
 struct RelayPoolTask {
     database: Arc<DynNostrDatabase>,
+    relays: Arc<RwLock<HashMap<Url, Relay>>>,
     receiver: Arc<Mutex<Receiver<RelayPoolMessage>>>,
     notification_sender: broadcast::Sender<RelayPoolNotification>,
+    bus: EventBus,
+    admission_policies: Vec<Arc<DynAdmissionPolicy>>,
     running: Arc<AtomicBool>,
 }
 
 impl RelayPoolTask {
     pub fn new(
         database: Arc<DynNostrDatabase>,
+        relays: Arc<RwLock<HashMap<Url, Relay>>>,
         pool_task_receiver: Receiver<RelayPoolMessage>,
         notification_sender: broadcast::Sender<RelayPoolNotification>,
+        bus: EventBus,
+        admission_policies: Vec<Arc<DynAdmissionPolicy>>,
     ) -> Self {
         Self {
             database,
+            relays,
             receiver: Arc::new(Mutex::new(pool_task_receiver)),
             notification_sender,
+            bus,
+            admission_policies,
             running: Arc::new(AtomicBool::new(false)),
         }
     }
@@ -165,6 +192,13 @@ impl RelayPoolTask {
                         RelayPoolMessage::ReceivedMsg { relay_url, msg } => {
                             match this.handle_relay_message(relay_url.clone(), msg).await {
                                 Ok(Some(msg)) => {
+                                    if let Some(event) = PoolEvent::from_relay_message(
+                                        relay_url.clone(),
+                                        msg.clone(),
+                                    ) {
+                                        this.bus.publish(event).await;
+                                    }
+
                                     let _ = this.notification_sender.send(
                                         RelayPoolNotification::Message(
                                             relay_url.clone(),
@@ -193,6 +227,12 @@ impl RelayPoolTask {
                             }
                         }
                         RelayPoolMessage::RelayStatus { url, status } => {
+                            this.bus
+                                .publish(PoolEvent::RelayStatus {
+                                    relay_url: url.clone(),
+                                    status,
+                                })
+                                .await;
                             let _ = this
                                 .notification_sender
                                 .send(RelayPoolNotification::RelayStatus { url, status });
@@ -200,6 +240,7 @@ impl RelayPoolTask {
                         RelayPoolMessage::Stop => {
                             tracing::debug!("Received stop msg");
                             this.set_running_to(false);
+                            this.bus.publish(PoolEvent::Stop).await;
                             if let Err(e) =
                                 this.notification_sender.send(RelayPoolNotification::Stop)
                             {
@@ -211,6 +252,7 @@ impl RelayPoolTask {
                             tracing::debug!("Received shutdown msg");
                             this.set_running_to(false);
                             receiver.close();
+                            this.bus.shutdown().await;
                             if let Err(e) = this
                                 .notification_sender
                                 .send(RelayPoolNotification::Shutdown)
@@ -227,6 +269,16 @@ impl RelayPoolTask {
         }
     }
 
+    async fn set_event_seen(&self, event_id: EventId, relay_url: &Url) {
+        if let Err(e) = self
+            .database
+            .event_id_seen(event_id, relay_url.clone())
+            .await
+        {
+            tracing::error!("Impossible to set event {event_id} as seen by relay: {e}");
+        }
+    }
+
     async fn handle_relay_message(
         &self,
         relay_url: Url,
@@ -246,28 +298,6 @@ impl RelayPoolTask {
                     .has_event_already_been_seen(partial_event.id)
                     .await?;
 
-                // Set event as seen by relay
-                if let Err(e) = self
-                    .database
-                    .event_id_seen(partial_event.id, relay_url.clone())
-                    .await
-                {
-                    tracing::error!(
-                        "Impossible to set event {} as seen by relay: {e}",
-                        partial_event.id
-                    );
-                }
-
-                // Check if event was already saved
-                if self
-                    .database
-                    .has_event_already_been_saved(partial_event.id)
-                    .await?
-                {
-                    tracing::trace!("Event {} already saved into database", partial_event.id);
-                    return Ok(None);
-                }
-
                 // Verify signature
                 partial_event.verify_signature()?;
 
@@ -278,19 +308,72 @@ impl RelayPoolTask {
                 // Compose full event
                 let event: Event = partial_event.merge(missing);
 
+                // Verify event ID
+                event.verify_id()?;
+
+                let subscription_id = SubscriptionId::new(subscription_id);
+
+                // Move the resume cursor of this relay, also if the event was received from another relay
+                if let Some(relay) = self.relays.read().await.get(&relay_url) {
+                    relay
+                        .update_last_event_at(&subscription_id, event.created_at)
+                        .await;
+                }
+
+                // Check if event was already saved
+                if self.database.has_event_already_been_saved(event.id).await? {
+                    tracing::trace!("Event {} already saved into database", event.id);
+                    self.set_event_seen(event.id, &relay_url).await;
+                    return Ok(None);
+                }
+
                 // Check if it's expired
                 if event.is_expired() {
                     return Err(Error::EventExpired);
                 }
 
-                // Verify event ID
-                event.verify_id()?;
+                // An invalid NIP26 delegation doesn't invalidate the event: the event is kept
+                // and its delegation status is available with `Event::delegator`
+                if let Err(e) = event.delegator() {
+                    tracing::debug!("Event {} has an invalid delegation: {e}", event.id);
+                }
+
+                // Check admission policies
+                let status: AdmissionStatus = policy::admit_event(
+                    &self.admission_policies,
+                    &relay_url,
+                    &subscription_id,
+                    &event,
+                )
+                .await;
+
+                match status {
+                    AdmissionStatus::Accept => {
+                        // Save event
+                        self.database.save_event(&event).await?;
+                    }
+                    AdmissionStatus::AcceptWithoutStore => {
+                        tracing::trace!("Event {} accepted without store", event.id);
+                    }
+                    AdmissionStatus::Reject { reason } => {
+                        // A rejected event is not set as seen by the relay
+                        tracing::debug!("Event {} from {relay_url} rejected: {reason}", event.id);
+                        return Ok(None);
+                    }
+                }
 
-                // Save event
-                self.database.save_event(&event).await?;
+                // Set event as seen by relay
+                self.set_event_seen(event.id, &relay_url).await;
 
                 // If not seed, send RelayPoolNotification::Event
                 if !seen {
+                    self.bus
+                        .publish(PoolEvent::Event {
+                            relay_url: relay_url.clone(),
+                            subscription_id: subscription_id.clone(),
+                            event: Box::new(event.clone()),
+                        })
+                        .await;
                     let _ = self
                         .notification_sender
                         .send(RelayPoolNotification::Event(relay_url, event.clone()));
@@ -298,15 +381,55 @@ impl RelayPoolTask {
 
                 // Compose RelayMessage
                 Ok(Some(RelayMessage::Event {
-                    subscription_id: SubscriptionId::new(subscription_id),
+                    subscription_id,
                     event: Box::new(event),
                 }))
             }
+            RawRelayMessage::EndOfStoredEvents(subscription_id) => {
+                let subscription_id = SubscriptionId::new(subscription_id);
+
+                // The events received before EOSE are now complete
+                if let Some(relay) = self.relays.read().await.get(&relay_url) {
+                    relay.end_of_stored_events(&subscription_id).await;
+                }
+
+                Ok(Some(RelayMessage::EndOfStoredEvents(subscription_id)))
+            }
             m => Ok(Some(RelayMessage::try_from(m)?)),
         }
     }
 }
 
+/// Deduplicated events, in insertion order
+#[derive(Default)]
+struct Events {
+    ids: HashSet<EventId>,
+    events: Vec<Event>,
+}
+
+impl Events {
+    fn push(&mut self, event: Event) {
+        if self.ids.insert(event.id) {
+            self.events.push(event);
+        }
+    }
+
+    fn into_inner(self) -> Vec<Event> {
+        self.events
+    }
+}
+
+impl Extend<Event> for Events {
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = Event>,
+    {
+        for event in iter.into_iter() {
+            self.push(event);
+        }
+    }
+}
+
 /// Relay Pool
 #[derive(Debug, Clone)]
 pub struct RelayPool {
@@ -314,7 +437,9 @@ pub struct RelayPool {
     relays: Arc<RwLock<HashMap<Url, Relay>>>,
     pool_task_sender: Sender<RelayPoolMessage>,
     notification_sender: broadcast::Sender<RelayPoolNotification>,
+    bus: EventBus,
     filters: Arc<RwLock<Vec<Filter>>>,
+    persisted_scores: Arc<RwLock<HashMap<Url, f64>>>,
     pool_task: RelayPoolTask,
     opts: RelayPoolOptions,
     dropped: Arc<AtomicBool>,
@@ -356,22 +481,43 @@ impl RelayPool {
         let (pool_task_sender, pool_task_receiver) = mpsc::channel(opts.task_channel_size);
 
         let database: Arc<DynNostrDatabase> = database.into_nostr_database();
+        let bus = EventBus::default();
+
+        let relays = Arc::new(RwLock::new(HashMap::new()));
 
         let relay_pool_task = RelayPoolTask::new(
             database.clone(),
+            relays.clone(),
             pool_task_receiver,
             notification_sender.clone(),
+            bus.clone(),
+            opts.admission_policies.clone(),
         );
 
+        let dropped = Arc::new(AtomicBool::new(false));
+
+        if opts.outbox.enabled {
+            OutboxTask::new(
+                database.clone(),
+                relays.clone(),
+                notification_sender.subscribe(),
+                Arc::downgrade(&dropped),
+                opts.outbox,
+            )
+            .run();
+        }
+
         let pool = Self {
             database,
-            relays: Arc::new(RwLock::new(HashMap::new())),
+            relays,
             pool_task_sender,
             notification_sender,
+            bus,
             filters: Arc::new(RwLock::new(Vec::new())),
+            persisted_scores: Arc::new(RwLock::new(HashMap::new())),
             pool_task: relay_pool_task,
             opts,
-            dropped: Arc::new(AtomicBool::new(false)),
+            dropped,
         };
 
         pool.start();
@@ -386,6 +532,12 @@ impl RelayPool {
 
     /// Stop
     pub async fn stop(&self) -> Result<(), Error> {
+        if self.opts.relay_selection.persist_scores {
+            if let Err(e) = self.save_relay_scores().await {
+                tracing::error!("Impossible to save relay scores: {e}");
+            }
+        }
+
         let relays = self.relays().await;
         for relay in relays.values() {
             relay.stop().await?;
@@ -403,6 +555,12 @@ impl RelayPool {
 
     /// Completely shutdown pool
     pub async fn shutdown(self) -> Result<(), Error> {
+        if self.opts.relay_selection.persist_scores {
+            if let Err(e) = self.save_relay_scores().await {
+                tracing::error!("Impossible to save relay scores: {e}");
+            }
+        }
+
         self.disconnect().await?;
         thread::spawn(async move {
             thread::sleep(Duration::from_secs(3)).await;
@@ -412,10 +570,19 @@ impl RelayPool {
     }
 
     /// Get new notification listener
+    ///
+    /// Slow receivers lose notifications: use [`RelayPool::listen`] for a lossless, typed alternative.
     pub fn notifications(&self) -> broadcast::Receiver<RelayPoolNotification> {
         self.notification_sender.subscribe()
     }
 
+    /// Get new [`PoolEvent`] listener
+    ///
+    /// Each listener has its own bounded queue (see [`ListenerOptions`]).
+    pub async fn listen(&self, opts: ListenerOptions) -> PoolEventListener {
+        self.bus.listen(opts).await
+    }
+
     /// Get database
     pub fn database(&self) -> Arc<DynNostrDatabase> {
         self.database.clone()
@@ -438,6 +605,101 @@ impl RelayPool {
         relays.get(&url).cloned().ok_or(Error::RelayNotFound)
     }
 
+    /// Get relay scores
+    ///
+    /// If a score was loaded from the database, it's merged with the live one (see [`RelayScore::merge`]).
+    pub async fn relay_scores(&self) -> HashMap<Url, f64> {
+        let relays = self.relays().await;
+        let persisted_scores = self.persisted_scores.read().await;
+        let mut scores: HashMap<Url, f64> = HashMap::with_capacity(relays.len());
+        for (url, relay) in relays.into_iter() {
+            let score: RelayScore = relay.score().await;
+            let score: f64 = match persisted_scores.get(&url) {
+                Some(persisted) => score.merge(*persisted, relay.stats().attempts()),
+                None => score.value(),
+            };
+            scores.insert(url, score);
+        }
+        scores
+    }
+
+    /// Save relay scores into database
+    pub async fn save_relay_scores(&self) -> Result<(), Error> {
+        for (url, score) in self.relay_scores().await.into_iter() {
+            self.database.save_relay_score(url, score).await?;
+        }
+        Ok(())
+    }
+
+    /// Load relay scores from database
+    pub async fn load_relay_scores(&self) -> Result<(), Error> {
+        let scores: HashMap<Url, f64> = self.database.relay_scores().await?;
+        let mut persisted_scores = self.persisted_scores.write().await;
+        *persisted_scores = scores;
+        Ok(())
+    }
+
+    /// Get relays to use for reads
+    async fn read_relays(&self) -> HashMap<Url, Relay> {
+        self.select_relays(false).await
+    }
+
+    /// Get relays to use for writes
+    async fn write_relays(&self) -> HashMap<Url, Relay> {
+        self.select_relays(true).await
+    }
+
+    async fn select_relays(&self, write: bool) -> HashMap<Url, Relay> {
+        let relays: HashMap<Url, Relay> = self.relays().await;
+        let opts = self.opts.relay_selection;
+
+        if !opts.enabled {
+            return relays;
+        }
+
+        let scores: HashMap<Url, f64> = self.relay_scores().await;
+        let mut candidates: Vec<(Url, Relay, f64)> = relays
+            .into_iter()
+            .filter(|(_, relay)| {
+                if write {
+                    relay.opts().get_write()
+                } else {
+                    relay.opts().get_read()
+                }
+            })
+            .map(|(url, relay)| {
+                let score: f64 = scores.get(&url).copied().unwrap_or_default();
+                (url, relay, score)
+            })
+            .collect();
+        candidates.sort_by(|a, b| b.2.partial_cmp(&a.2).unwrap_or(cmp::Ordering::Equal));
+
+        let max: Option<usize> = if write {
+            opts.max_write_relays
+        } else {
+            opts.max_read_relays
+        };
+
+        let mut selected: HashMap<Url, Relay> = HashMap::new();
+        for (index, (url, relay, score)) in candidates.into_iter().enumerate() {
+            if let Some(max) = max {
+                if selected.len() >= max {
+                    break;
+                }
+            }
+
+            // Demote flaky relays, but keep at least the best one
+            if score <= opts.min_score && index > 0 {
+                tracing::debug!("Skipping {url}: score {score:.2} <= {:.2}", opts.min_score);
+                continue;
+            }
+
+            selected.insert(url, relay);
+        }
+
+        selected
+    }
+
     /// Get subscription filters
     pub async fn subscription_filters(&self) -> Vec<Filter> {
         self.filters.read().await.clone()
@@ -462,6 +724,22 @@ impl RelayPool {
         Error: From<<U as TryIntoUrl>::Err>,
     {
         let url: Url = url.try_into_url()?;
+
+        // A custom transport opens the connection on its own: proxy and TLS config would be ignored
+        let own_transport: bool = opts.get_transport().is_some();
+        let opts: RelayOptions = opts
+            .default_transport(self.opts.transport.clone())
+            .default_reconnect_strategy(self.opts.reconnect_strategy.clone());
+        let opts: RelayOptions = if opts.get_transport().is_some() {
+            let pool_tls: bool = !own_transport && self.opts.tls.is_some();
+            if proxy.is_some() || opts.has_tls() || pool_tls {
+                return Err(Error::UnsupportedTransportOptions);
+            }
+            opts
+        } else {
+            opts.default_tls(self.opts.tls.clone())
+        };
+
         let mut relays = self.relays.write().await;
         if !relays.contains_key(&url) {
             let relay = Relay::new(
@@ -471,9 +749,12 @@ impl RelayPool {
                 self.notification_sender.clone(),
                 proxy,
                 opts,
-                Limits::default(),
+                self.opts.limits,
             );
-            relays.insert(relay.url(), relay);
+            let relay_url: Url = relay.url();
+            relays.insert(relay_url.clone(), relay);
+            drop(relays);
+            self.bus.publish(PoolEvent::RelayAdded { relay_url }).await;
             Ok(true)
         } else {
             Ok(false)
@@ -495,10 +776,14 @@ impl RelayPool {
                 self.database.clone(),
                 self.pool_task_sender.clone(),
                 self.notification_sender.clone(),
-                opts,
-                Limits::default(),
+                opts.default_transport(self.opts.transport.clone())
+                    .default_reconnect_strategy(self.opts.reconnect_strategy.clone()),
+                self.opts.limits,
             );
-            relays.insert(relay.url(), relay);
+            let relay_url: Url = relay.url();
+            relays.insert(relay_url.clone(), relay);
+            drop(relays);
+            self.bus.publish(PoolEvent::RelayAdded { relay_url }).await;
             Ok(true)
         } else {
             Ok(false)
@@ -514,7 +799,11 @@ impl RelayPool {
         let url: Url = url.try_into_url()?;
         let mut relays = self.relays.write().await;
         if let Some(relay) = relays.remove(&url) {
+            drop(relays);
             self.disconnect_relay(&relay).await?;
+            self.bus
+                .publish(PoolEvent::RelayRemoved { relay_url: url })
+                .await;
         }
         Ok(())
     }
@@ -637,13 +926,15 @@ impl RelayPool {
 
     /// Send event and wait for `OK` relay msg
     pub async fn send_event(&self, event: Event, opts: RelaySendOptions) -> Result<EventId, Error> {
-        let relays = self.relays().await;
+        let relays = self.write_relays().await;
 
         if relays.is_empty() {
             return Err(Error::NoRelays);
         }
 
         self.database.save_event(&event).await?;
+        self.save_outbox_events(std::slice::from_ref(&event), relays.keys())
+            .await;
 
         let sent_to_at_least_one_relay: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
         let mut handles = Vec::new();
@@ -653,8 +944,14 @@ impl RelayPool {
         for (url, relay) in relays.into_iter() {
             let event = event.clone();
             let sent = sent_to_at_least_one_relay.clone();
+            let database = self.database.clone();
+            let use_outbox: bool = self.opts.outbox.enabled;
             let handle = thread::spawn(async move {
-                match relay.send_event(event, opts).await {
+                let res = relay.send_event(event, opts).await;
+                if use_outbox {
+                    outbox::resolve(&database, &[event_id], &url, &res).await;
+                }
+                match res {
                     Ok(_) => {
                         let _ =
                             sent.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |_| Some(true));
@@ -682,7 +979,7 @@ impl RelayPool {
         events: Vec<Event>,
         opts: RelaySendOptions,
     ) -> Result<(), Error> {
-        let relays = self.relays().await;
+        let relays = self.write_relays().await;
 
         if relays.is_empty() {
             return Err(Error::NoRelays);
@@ -692,16 +989,25 @@ impl RelayPool {
         for event in events.iter() {
             self.database.save_event(event).await?;
         }
+        self.save_outbox_events(&events, relays.keys()).await;
 
+        let ids: Vec<EventId> = events.iter().map(|e| e.id).collect();
         let sent_to_at_least_one_relay: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
         let mut handles = Vec::new();
 
         for (url, relay) in relays.into_iter() {
             let len = events.len();
             let events = events.clone();
+            let ids = ids.clone();
             let sent = sent_to_at_least_one_relay.clone();
+            let database = self.database.clone();
+            let use_outbox: bool = self.opts.outbox.enabled;
             let handle = thread::spawn(async move {
-                match relay.batch_event(events, opts).await {
+                let res = relay.batch_event(events, opts).await;
+                if use_outbox {
+                    outbox::resolve(&database, &ids, &url, &res).await;
+                }
+                match res {
                     Ok(_) => {
                         let _ =
                             sent.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |_| Some(true));
@@ -723,6 +1029,30 @@ impl RelayPool {
         Ok(())
     }
 
+    /// Get events waiting to be sent
+    pub async fn outbox(&self) -> Result<Vec<OutboxEvent>, Error> {
+        Ok(self.database.outbox_events().await?)
+    }
+
+    async fn save_outbox_events<'a, I>(&self, events: &[Event], urls: I)
+    where
+        I: Iterator<Item = &'a Url>,
+    {
+        if !self.opts.outbox.enabled {
+            return;
+        }
+
+        let expiration: Timestamp = Timestamp::now() + self.opts.outbox.ttl;
+        for url in urls {
+            for event in events.iter() {
+                let item = OutboxEvent::new(event.clone(), url.clone(), expiration);
+                if let Err(e) = self.database.save_outbox_event(item).await {
+                    tracing::error!("Impossible to save event {} into outbox: {e}", event.id);
+                }
+            }
+        }
+    }
+
     /// Send event to a single relay
     pub async fn send_event_to<U>(
         &self,
@@ -738,7 +1068,14 @@ impl RelayPool {
         self.database.save_event(&event).await?;
         let relays = self.relays().await;
         if let Some(relay) = relays.get(&url) {
-            Ok(relay.send_event(event, opts).await?)
+            let event_id: EventId = event.id;
+            self.save_outbox_events(std::slice::from_ref(&event), [&url].into_iter())
+                .await;
+            let res = relay.send_event(event, opts).await;
+            if self.opts.outbox.enabled {
+                outbox::resolve(&self.database, &[event_id], &url, &res).await;
+            }
+            Ok(res?)
         } else {
             Err(Error::RelayNotFound)
         }
@@ -784,35 +1121,241 @@ impl RelayPool {
         timeout: Duration,
         opts: FilterOptions,
     ) -> Result<Vec<Event>, Error> {
-        let stored_events: Vec<Event> = self
-            .database
-            .query(filters.clone())
+        self.get_events_of_with_mode(filters, timeout, opts, QueryMode::default())
             .await
-            .unwrap_or_default();
-        let events: Arc<Mutex<Vec<Event>>> = Arc::new(Mutex::new(stored_events));
+    }
+
+    /// Get events of filters with [`QueryMode`]
+    ///
+    /// Events are deduplicated.
+    ///
+    /// With [`QueryMode::CacheFirst`], the stored events are returned immediately and the newer ones are requested
+    /// in the background: they are saved into the database and delivered with the notifications, not merged
+    /// into the returned events. Use [`RelayPool::get_events_of_with_callback`] to receive them directly.
+    /// If nothing is stored, the events are requested to the relays and returned like [`QueryMode::NetworkFirst`].
+    pub async fn get_events_of_with_mode(
+        &self,
+        filters: Vec<Filter>,
+        timeout: Duration,
+        opts: FilterOptions,
+        mode: QueryMode,
+    ) -> Result<Vec<Event>, Error> {
+        if let QueryMode::CacheFirst = mode {
+            let (stored, newer_filters) = self.query_cache_first(filters.clone()).await;
+            if !stored.is_empty() {
+                self.req_events_of(newer_filters, timeout, opts).await;
+                return Ok(stored);
+            }
+        }
+
+        let events: Mutex<Vec<Event>> = Mutex::new(Vec::new());
+        self.get_events_of_with_callback(filters, timeout, opts, mode, |event| {
+            let events = &events;
+            async move {
+                events.lock().await.push(event);
+            }
+        })
+        .await?;
+        Ok(events.into_inner())
+    }
+
+    /// Get events of filters with [`QueryMode`], delivered with `callback` as soon as available
+    ///
+    /// Events are deduplicated. With [`QueryMode::CacheFirst`], the stored events are delivered
+    /// before sending the `REQ`s, then the newer ones as they are received from the relays.
+    pub async fn get_events_of_with_callback<F>(
+        &self,
+        filters: Vec<Filter>,
+        timeout: Duration,
+        opts: FilterOptions,
+        mode: QueryMode,
+        callback: impl Fn(Event) -> F,
+    ) -> Result<(), Error>
+    where
+        F: Future<Output = ()>,
+    {
+        let seen: Mutex<HashSet<EventId>> = Mutex::new(HashSet::new());
+        let callback = |event: Event| {
+            let seen = &seen;
+            let callback = &callback;
+            async move {
+                if seen.lock().await.insert(event.id) {
+                    callback(event).await;
+                }
+            }
+        };
+
+        match mode {
+            QueryMode::CacheOnly => {
+                for event in self.database.query(filters).await.unwrap_or_default() {
+                    callback(event).await;
+                }
+            }
+            QueryMode::CacheFirst => {
+                let (stored, newer_filters) = self.query_cache_first(filters).await;
+                for event in stored.into_iter() {
+                    callback(event).await;
+                }
+                self.fetch_events_of(newer_filters, timeout, opts, callback)
+                    .await?;
+            }
+            QueryMode::NetworkFirst => {
+                self.fetch_events_of(filters.clone(), timeout, opts, callback)
+                    .await?;
+                for event in self.database.query(filters).await.unwrap_or_default() {
+                    callback(event).await;
+                }
+            }
+            QueryMode::NetworkOnly => {
+                let replied: HashSet<Url> = self
+                    .fetch_events_of(filters.clone(), timeout, opts, callback)
+                    .await?;
+
+                if replied.is_empty() {
+                    return Err(Error::NoRelayReplied);
+                }
+
+                // Events already stored aren't received again: take them from the database
+                let stored: Vec<Event> = self.database.query(filters).await.unwrap_or_default();
+                for event in stored.into_iter() {
+                    if let Ok(Some(relays)) = self.database.event_seen_on_relays(event.id).await {
+                        if relays.iter().any(|url| replied.contains(url)) {
+                            callback(event).await;
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Get the stored events of filters
+    ///
+    /// Return the events and the filters to top up each filter from its newest stored event.
+    async fn query_cache_first(&self, filters: Vec<Filter>) -> (Vec<Event>, Vec<Filter>) {
+        let mut events: Events = Events::default();
+        let mut newer_filters: Vec<Filter> = Vec::with_capacity(filters.len());
+        for filter in filters.into_iter() {
+            let stored: Vec<Event> = self
+                .database
+                .query(vec![filter.clone()])
+                .await
+                .unwrap_or_default();
+            let newest: Option<Timestamp> = stored.iter().map(|e| e.created_at).max();
+            events.extend(stored);
+            newer_filters.push(match newest {
+                Some(newest) => {
+                    let since: Timestamp = filter.since.map_or(newest, |s| s.max(newest));
+                    filter.since(since)
+                }
+                None => filter,
+            });
+        }
+        (events.into_inner(), newer_filters)
+    }
+
+    /// Get events of filters from the read relays, delivered with `callback` as they are received
+    ///
+    /// Return the relays that replied.
+    async fn fetch_events_of<F>(
+        &self,
+        filters: Vec<Filter>,
+        timeout: Duration,
+        opts: FilterOptions,
+        callback: impl Fn(Event) -> F,
+    ) -> Result<HashSet<Url>, Error>
+    where
+        F: Future<Output = ()>,
+    {
+        let (tx, mut rx) = mpsc::channel::<Event>(self.opts.notification_channel_size);
+        let replied: Arc<Mutex<HashSet<Url>>> = Arc::new(Mutex::new(HashSet::new()));
         let mut handles = Vec::new();
-        let relays = self.relays().await;
+        let relays = self.read_relays().await;
         for (url, relay) in relays.into_iter() {
             let filters = filters.clone();
-            let events = events.clone();
+            let tx = tx.clone();
+            let replied = replied.clone();
             let handle = thread::spawn(async move {
-                if let Err(e) = relay
-                    .get_events_of_with_callback(filters, timeout, opts, |event| async {
-                        events.lock().await.push(event);
+                match relay
+                    .get_events_of_with_callback(filters, timeout, opts, |event| {
+                        let tx = tx.clone();
+                        async move {
+                            let _ = tx.send(event).await;
+                        }
                     })
                     .await
                 {
-                    tracing::error!("Failed to get events from {url}: {e}");
+                    Ok(()) => {
+                        replied.lock().await.insert(url);
+                    }
+                    Err(e) => tracing::error!("Failed to get events from {url}: {e}"),
                 }
             });
             handles.push(handle);
         }
 
+        // Closed when all the relays completed
+        drop(tx);
+        while let Some(event) = rx.recv().await {
+            callback(event).await;
+        }
+
         for handle in handles.into_iter().flatten() {
             handle.join().await?;
         }
 
-        Ok(events.lock_owned().await.clone())
+        let replied: HashSet<Url> = replied.lock_owned().await.clone();
+        Ok(replied)
+    }
+
+    /// Count events of filters
+    ///
+    /// Query the local database and all the read relays, in parallel.
+    /// Errors are reported, by relay, in [`EventCount::errors`].
+    pub async fn count(
+        &self,
+        filters: Vec<Filter>,
+        timeout: Duration,
+    ) -> Result<EventCount, Error> {
+        let ids: Vec<EventId> = self
+            .database
+            .event_ids_by_filters(filters.clone())
+            .await
+            .unwrap_or_default();
+        let counts: Arc<Mutex<HashMap<Url, RelayCount>>> = Arc::new(Mutex::new(HashMap::new()));
+        let errors: Arc<Mutex<HashMap<Url, String>>> = Arc::new(Mutex::new(HashMap::new()));
+        let mut handles = Vec::new();
+        let relays = self.read_relays().await;
+        for (url, relay) in relays.into_iter() {
+            let filters = filters.clone();
+            let counts = counts.clone();
+            let errors = errors.clone();
+            let handle = thread::spawn(async move {
+                match relay.count(filters, timeout).await {
+                    Ok(count) => {
+                        counts.lock().await.insert(url, count);
+                    }
+                    Err(e) => {
+                        tracing::error!("Failed to count events of {url}: {e}");
+                        errors.lock().await.insert(url, e.to_string());
+                    }
+                }
+            });
+            handles.push(handle);
+        }
+
+        for handle in handles.into_iter().flatten() {
+            handle.join().await?;
+        }
+
+        let counts: HashMap<Url, RelayCount> = counts.lock_owned().await.clone();
+        let errors: HashMap<Url, String> = errors.lock_owned().await.clone();
+        Ok(EventCount::aggregate(
+            ids.into_iter().collect(),
+            counts,
+            errors,
+        ))
     }
 
     /// Request events of filter.
@@ -825,7 +1368,7 @@ impl RelayPool {
         timeout: Duration,
         opts: FilterOptions,
     ) {
-        let relays = self.relays().await;
+        let relays = self.read_relays().await;
         for relay in relays.values() {
             relay.req_events_of(filters.clone(), timeout, opts);
         }
@@ -833,6 +1376,12 @@ impl RelayPool {
 
     /// Connect to all added relays and keep connection alive
     pub async fn connect(&self, wait_for_connection: bool) {
+        if self.opts.relay_selection.persist_scores {
+            if let Err(e) = self.load_relay_scores().await {
+                tracing::error!("Impossible to load relay scores: {e}");
+            }
+        }
+
         let relays: HashMap<Url, Relay> = self.relays().await;
 
         if wait_for_connection {
@@ -883,7 +1432,11 @@ impl RelayPool {
     }
 
     /// Negentropy reconciliation
-    pub async fn reconcile(&self, filter: Filter, opts: NegentropyOptions) -> Result<(), Error> {
+    pub async fn reconcile(
+        &self,
+        filter: Filter,
+        opts: NegentropyOptions,
+    ) -> Result<Reconciliation, Error> {
         let items: Vec<(EventId, Timestamp)> =
             self.database.negentropy_items(filter.clone()).await?;
         self.reconcile_with_items(filter, items, opts).await
@@ -895,24 +1448,199 @@ impl RelayPool {
         filter: Filter,
         items: Vec<(EventId, Timestamp)>,
         opts: NegentropyOptions,
-    ) -> Result<(), Error> {
+    ) -> Result<Reconciliation, Error> {
+        self.reconcile_multi_with_items(vec![(filter, items)], opts)
+            .await
+    }
+
+    /// Negentropy reconciliation of multiple filters
+    pub async fn reconcile_multi(
+        &self,
+        filters: Vec<Filter>,
+        opts: NegentropyOptions,
+    ) -> Result<Reconciliation, Error> {
+        let mut items = Vec::with_capacity(filters.len());
+        for filter in filters.into_iter() {
+            let i: Vec<(EventId, Timestamp)> =
+                self.database.negentropy_items(filter.clone()).await?;
+            items.push((filter, i));
+        }
+        self.reconcile_multi_with_items(items, opts).await
+    }
+
+    /// Negentropy reconciliation of multiple filters with custom items
+    ///
+    /// Relays are reconciled in parallel. Errors are reported, by relay, in [`Reconciliation::errors`].
+    pub async fn reconcile_multi_with_items(
+        &self,
+        items: Vec<(Filter, Vec<(EventId, Timestamp)>)>,
+        opts: NegentropyOptions,
+    ) -> Result<Reconciliation, Error> {
+        let relays = self.read_relays().await;
+
+        if relays.is_empty() {
+            return Err(Error::NoRelays);
+        }
+
+        let mut report = Reconciliation::new();
+        for r in self
+            .reconcile_relays(relays, items, opts)
+            .await?
+            .into_values()
+        {
+            report.merge(r);
+        }
+        Ok(report)
+    }
+
+    /// Reconcile relays in parallel
+    ///
+    /// Failed reconciliations are reported in [`Reconciliation::errors`].
+    async fn reconcile_relays(
+        &self,
+        relays: HashMap<Url, Relay>,
+        items: Vec<(Filter, Vec<(EventId, Timestamp)>)>,
+        opts: NegentropyOptions,
+    ) -> Result<HashMap<Url, Reconciliation>, Error> {
         let mut handles = Vec::new();
-        let relays = self.relays().await;
+
         for (url, relay) in relays.into_iter() {
-            let filter = filter.clone();
-            let my_items = items.clone();
+            let items = items.clone();
             let handle = thread::spawn(async move {
-                if let Err(e) = relay.reconcile(filter, my_items, opts).await {
-                    tracing::error!("Failed to get reconcile with {url}: {e}");
-                }
+                let r: Reconciliation = match relay.reconcile_multi(items, opts).await {
+                    Ok(r) => r,
+                    Err(e) => {
+                        tracing::error!("Failed to get reconcile with {url}: {e}");
+                        let mut r = Reconciliation::new();
+                        r.errors.insert(url.clone(), e.to_string());
+                        r
+                    }
+                };
+                (url, r)
             });
             handles.push(handle);
         }
 
+        let mut reports: HashMap<Url, Reconciliation> = HashMap::with_capacity(handles.len());
         for handle in handles.into_iter().flatten() {
-            handle.join().await?;
+            let (url, r) = handle.join().await?;
+            reports.insert(url, r);
         }
+        Ok(reports)
+    }
 
-        Ok(())
+    /// Sync filter across all relays
+    ///
+    /// First, the events missing from the local database are fetched from the read relays.
+    /// Then, each write relay receive the events that it lacks:
+    /// the diff of the first phase is reused for relays used for both reads and writes,
+    /// the other ones are reconciled bidirectionally.
+    /// [`Reconciliation::sent`] contains only the events accepted by at least one relay (`OK` message).
+    pub async fn sync(
+        &self,
+        filter: Filter,
+        opts: NegentropyOptions,
+    ) -> Result<Reconciliation, Error> {
+        let mut read_relays: HashMap<Url, Relay> = self.read_relays().await;
+        read_relays.retain(|_, relay| relay.opts().get_read());
+        let mut write_relays: HashMap<Url, Relay> = self.write_relays().await;
+        write_relays.retain(|_, relay| relay.opts().get_write());
+
+        if read_relays.is_empty() && write_relays.is_empty() {
+            return Err(Error::NoRelays);
+        }
+
+        // Download
+        let items: Vec<(EventId, Timestamp)> =
+            self.database.negentropy_items(filter.clone()).await?;
+        let downloads: HashMap<Url, Reconciliation> = self
+            .reconcile_relays(
+                read_relays,
+                vec![(filter.clone(), items)],
+                opts.bidirectional(false),
+            )
+            .await?;
+
+        // Events missing from the local database, now fetched
+        let remote: HashSet<EventId> = downloads
+            .values()
+            .flat_map(|r| r.remote.iter().copied())
+            .collect();
+
+        let mut report = Reconciliation::new();
+        let mut download_duration: Duration = Duration::default();
+        for r in downloads.values() {
+            download_duration = download_duration.max(r.duration);
+        }
+
+        // Upload
+        let items: Vec<(EventId, Timestamp)> =
+            self.database.negentropy_items(filter.clone()).await?;
+        let mut handles = Vec::new();
+        for (url, relay) in write_relays.into_iter() {
+            let handle = match downloads.get(&url) {
+                Some(download) if download.is_ok() => {
+                    // Events that the relay lacks: the local ones and the ones fetched from other relays
+                    let missing: HashSet<EventId> = download
+                        .local
+                        .iter()
+                        .chain(remote.difference(&download.remote))
+                        .copied()
+                        .collect();
+                    thread::spawn(async move {
+                        #[cfg(not(target_arch = "wasm32"))]
+                        let start = Instant::now();
+                        let mut r = Reconciliation::new();
+                        match relay
+                            .send_stored_events(missing, opts.batch_send_timeout)
+                            .await
+                        {
+                            Ok(sent) => r.sent = sent,
+                            Err(e) => {
+                                tracing::error!("Failed to send events to {url}: {e}");
+                                r.errors.insert(url, e.to_string());
+                            }
+                        }
+                        #[cfg(not(target_arch = "wasm32"))]
+                        {
+                            r.duration = start.elapsed();
+                        }
+                        r
+                    })
+                }
+                // Download failed: the error is already reported
+                Some(..) => continue,
+                None => {
+                    let items = vec![(filter.clone(), items.clone())];
+                    thread::spawn(async move {
+                        match relay.reconcile_multi(items, opts.bidirectional(true)).await {
+                            Ok(r) => r,
+                            Err(e) => {
+                                tracing::error!("Failed to get reconcile with {url}: {e}");
+                                let mut r = Reconciliation::new();
+                                r.errors.insert(url, e.to_string());
+                                r
+                            }
+                        }
+                    })
+                }
+            };
+            handles.push(handle);
+        }
+
+        for r in downloads.into_values() {
+            report.merge(r);
+        }
+
+        let mut upload_duration: Duration = Duration::default();
+        for handle in handles.into_iter().flatten() {
+            let r: Reconciliation = handle.join().await?;
+            upload_duration = upload_duration.max(r.duration);
+            report.merge(r);
+        }
+
+        report.duration = download_duration + upload_duration;
+
+        Ok(report)
     }
 }
@@ -0,0 +1,144 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Rate limiter
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_utility::thread;
+use tokio::sync::Mutex;
+
+use super::limits::RateLimits;
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+/// Token bucket rate limiter
+#[derive(Debug, Clone)]
+pub(crate) struct RateLimiter {
+    limits: Option<RateLimits>,
+    bucket: Arc<Mutex<Option<Bucket>>>,
+}
+
+impl RateLimiter {
+    pub fn new(limits: Option<RateLimits>) -> Self {
+        Self {
+            limits,
+            bucket: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Wait until `n` messages can be sent
+    ///
+    /// Return `true` if the rate limit was hit.
+    pub async fn acquire(&self, n: usize) -> bool {
+        let limits: RateLimits = match self.limits {
+            Some(limits) => limits,
+            None => return false,
+        };
+
+        let rate: f64 = limits.max_per_sec.max(1) as f64;
+        let burst: f64 = limits.burst.max(1) as f64;
+
+        // Big batches can't wait for more tokens than the bucket can hold:
+        // the bucket goes in debt and next messages will wait.
+        let needed: f64 = (n as f64).min(burst);
+
+        let mut limited: bool = false;
+
+        loop {
+            let wait: f64 = {
+                let mut bucket = self.bucket.lock().await;
+                let now = Instant::now();
+                let bucket = bucket.get_or_insert_with(|| Bucket {
+                    tokens: burst,
+                    last_refill: now,
+                });
+
+                let elapsed: f64 = now.duration_since(bucket.last_refill).as_secs_f64();
+                bucket.tokens = (bucket.tokens + elapsed * rate).min(burst);
+                bucket.last_refill = now;
+
+                if bucket.tokens >= needed {
+                    bucket.tokens -= n as f64;
+                    return limited;
+                }
+
+                (needed - bucket.tokens) / rate
+            };
+
+            // Sleep without holding the lock
+            limited = true;
+            thread::sleep(Duration::from_secs_f64(wait)).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(max_per_sec: u32, burst: u32) -> RateLimiter {
+        RateLimiter::new(Some(RateLimits { max_per_sec, burst }))
+    }
+
+    #[tokio::test]
+    async fn test_disabled() {
+        let limiter = RateLimiter::new(None);
+        for _ in 0..1000 {
+            assert!(!limiter.acquire(1).await);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_burst() {
+        let limiter = limiter(10, 5);
+        let start = Instant::now();
+        for _ in 0..5 {
+            assert!(!limiter.acquire(1).await);
+        }
+        assert!(start.elapsed() < Duration::from_millis(100));
+    }
+
+    #[tokio::test]
+    async fn test_throttle() {
+        let limiter = limiter(20, 2);
+        assert!(!limiter.acquire(2).await);
+
+        // Bucket empty: wait for 1 token (50 ms)
+        let start = Instant::now();
+        assert!(limiter.acquire(1).await);
+        assert!(start.elapsed() >= Duration::from_millis(40));
+    }
+
+    #[tokio::test]
+    async fn test_refill() {
+        let limiter = limiter(100, 5);
+        assert!(!limiter.acquire(5).await);
+
+        // 5 tokens refilled in 50 ms
+        thread::sleep(Duration::from_millis(60)).await;
+        assert!(!limiter.acquire(5).await);
+
+        // The bucket can't hold more than the burst
+        thread::sleep(Duration::from_millis(200)).await;
+        assert!(!limiter.acquire(5).await);
+        assert!(limiter.acquire(1).await);
+    }
+
+    #[tokio::test]
+    async fn test_batch_bigger_than_burst() {
+        let limiter = limiter(100, 5);
+
+        // The bucket goes in debt: the next message wait for 6 tokens
+        assert!(!limiter.acquire(10).await);
+        let start = Instant::now();
+        assert!(limiter.acquire(1).await);
+        assert!(start.elapsed() >= Duration::from_millis(50));
+    }
+}
@@ -0,0 +1,100 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Negentropy reconciliation report
+
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use nostr::{EventId, Url};
+
+/// Negentropy reconciliation report
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Reconciliation {
+    /// Events that the local database have and the relay not
+    pub local: HashSet<EventId>,
+    /// Events that the relay have and the local database not
+    pub remote: HashSet<EventId>,
+    /// Events sent to relay and accepted with an `OK` message (only if bidirectional)
+    pub sent: HashSet<EventId>,
+    /// Events received from relay
+    pub received: HashSet<EventId>,
+    /// Errors, by relay
+    pub errors: HashMap<Url, String>,
+    /// Number of negentropy rounds
+    pub rounds: usize,
+    /// Reconciliation duration
+    pub duration: Duration,
+}
+
+impl Reconciliation {
+    /// New empty [`Reconciliation`] report
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Merge another report into this one
+    ///
+    /// Rounds are added. The duration is the longest one, since relays are reconciled in parallel.
+    pub fn merge(&mut self, other: Reconciliation) {
+        self.local.extend(other.local);
+        self.remote.extend(other.remote);
+        self.sent.extend(other.sent);
+        self.received.extend(other.received);
+        self.errors.extend(other.errors);
+        self.rounds += other.rounds;
+        self.duration = self.duration.max(other.duration);
+    }
+
+    /// Check if the reconciliation completed without errors
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(n: u8) -> EventId {
+        EventId::from_slice(&[n; 32]).unwrap()
+    }
+
+    #[test]
+    fn test_merge() {
+        let url1 = Url::parse("wss://relay1.example.com").unwrap();
+        let url2 = Url::parse("wss://relay2.example.com").unwrap();
+
+        let mut report = Reconciliation {
+            local: HashSet::from([id(1)]),
+            remote: HashSet::from([id(2)]),
+            sent: HashSet::from([id(1)]),
+            received: HashSet::from([id(2)]),
+            errors: HashMap::new(),
+            rounds: 2,
+            duration: Duration::from_secs(3),
+        };
+        assert!(report.is_ok());
+
+        report.merge(Reconciliation {
+            local: HashSet::from([id(1), id(3)]),
+            remote: HashSet::from([id(4)]),
+            sent: HashSet::new(),
+            received: HashSet::from([id(4)]),
+            errors: HashMap::from([(url2.clone(), String::from("timeout"))]),
+            rounds: 1,
+            duration: Duration::from_secs(1),
+        });
+
+        assert_eq!(report.local, HashSet::from([id(1), id(3)]));
+        assert_eq!(report.remote, HashSet::from([id(2), id(4)]));
+        assert_eq!(report.sent, HashSet::from([id(1)]));
+        assert_eq!(report.received, HashSet::from([id(2), id(4)]));
+        assert_eq!(report.rounds, 3);
+        assert_eq!(report.duration, Duration::from_secs(3));
+        assert!(!report.is_ok());
+        assert!(report.errors.contains_key(&url2));
+        assert!(!report.errors.contains_key(&url1));
+    }
+}
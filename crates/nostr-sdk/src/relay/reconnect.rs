@@ -0,0 +1,178 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Reconnection strategies
+//!
+//! A [`ReconnectStrategy`] decide how long the [`Relay`](super::Relay) auto-reconnect loop wait
+//! before the next connection attempt, or if it must give up.
+
+use std::fmt;
+use std::time::Duration;
+
+use nostr::secp256k1::rand::{self, Rng};
+
+/// Upper bound of [`ExponentialBackoff::max`] (~136 years): avoid `Duration` overflows
+const MAX_DELAY: Duration = Duration::from_secs(u32::MAX as u64);
+
+/// A type-erased [`ReconnectStrategy`]
+pub type DynReconnectStrategy = dyn ReconnectStrategy;
+
+/// Reconnection strategy
+pub trait ReconnectStrategy: fmt::Debug + Send + Sync {
+    /// Delay before the next connection attempt
+    ///
+    /// `failures` is the number of consecutive failed connection attempts (`0` when an established connection is lost).
+    /// Return `None` to give up: the relay will be terminated.
+    fn next_delay(&self, failures: usize) -> Option<Duration>;
+}
+
+/// Reconnect after a fixed delay
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FixedDelay {
+    delay: Duration,
+}
+
+impl FixedDelay {
+    /// New fixed delay strategy
+    pub fn new(delay: Duration) -> Self {
+        Self { delay }
+    }
+}
+
+impl ReconnectStrategy for FixedDelay {
+    fn next_delay(&self, _failures: usize) -> Option<Duration> {
+        Some(self.delay)
+    }
+}
+
+/// Exponential backoff with jitter
+///
+/// The delay is `initial * multiplier^failures`, capped to `max`, minus a random part of it (up to `jitter`).
+/// The jitter spread the reconnections of many clients after a relay restart.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExponentialBackoff {
+    initial: Duration,
+    max: Duration,
+    multiplier: f64,
+    jitter: f64,
+}
+
+impl Default for ExponentialBackoff {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_secs(5),
+            max: Duration::from_secs(300),
+            multiplier: 2.0,
+            jitter: 0.5,
+        }
+    }
+}
+
+impl ExponentialBackoff {
+    /// New exponential backoff strategy
+    ///
+    /// Default: 5 secs initial delay, 5 min max delay, multiplier 2, jitter 50%.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Delay after a lost connection
+    pub fn initial(self, initial: Duration) -> Self {
+        Self { initial, ..self }
+    }
+
+    /// Max delay
+    pub fn max(self, max: Duration) -> Self {
+        Self {
+            max: max.min(MAX_DELAY),
+            ..self
+        }
+    }
+
+    /// Multiplier applied for each failed attempt (min: 1)
+    pub fn multiplier(self, multiplier: f64) -> Self {
+        Self {
+            multiplier: multiplier.max(1.0),
+            ..self
+        }
+    }
+
+    /// Max fraction of the delay removed at random (from 0 to 1, `NaN` is 0)
+    pub fn jitter(self, jitter: f64) -> Self {
+        Self {
+            jitter: if jitter.is_nan() {
+                0.0
+            } else {
+                jitter.clamp(0.0, 1.0)
+            },
+            ..self
+        }
+    }
+}
+
+impl ReconnectStrategy for ExponentialBackoff {
+    fn next_delay(&self, failures: usize) -> Option<Duration> {
+        let exp: i32 = failures.min(i32::MAX as usize) as i32;
+        let delay: f64 = self.initial.as_secs_f64() * self.multiplier.powi(exp);
+        // `0 * inf` is NaN
+        let delay: f64 = if delay.is_nan() {
+            0.0
+        } else {
+            delay.min(self.max.as_secs_f64())
+        };
+        let jitter: f64 = rand::thread_rng().gen_range(0.0..=self.jitter);
+        Some(Duration::from_secs_f64(delay * (1.0 - jitter)))
+    }
+}
+
+/// Never reconnect: the relay is terminated when the connection is lost or can't be established
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct NeverReconnect;
+
+impl ReconnectStrategy for NeverReconnect {
+    fn next_delay(&self, _failures: usize) -> Option<Duration> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exponential_backoff() {
+        let backoff = ExponentialBackoff::new()
+            .initial(Duration::from_secs(1))
+            .max(Duration::from_secs(10))
+            .jitter(0.0);
+        assert_eq!(backoff.next_delay(0), Some(Duration::from_secs(1)));
+        assert_eq!(backoff.next_delay(3), Some(Duration::from_secs(8)));
+        assert_eq!(backoff.next_delay(4), Some(Duration::from_secs(10)));
+        assert_eq!(
+            backoff.next_delay(usize::MAX),
+            Some(Duration::from_secs(10))
+        );
+    }
+
+    #[test]
+    fn test_exponential_backoff_overflow() {
+        let backoff = ExponentialBackoff::new().max(Duration::MAX).jitter(0.0);
+        assert_eq!(backoff.next_delay(usize::MAX), Some(MAX_DELAY));
+
+        let backoff = ExponentialBackoff::new()
+            .initial(Duration::ZERO)
+            .multiplier(f64::MAX)
+            .jitter(0.0);
+        assert_eq!(backoff.next_delay(usize::MAX), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn test_exponential_backoff_nan() {
+        let backoff = ExponentialBackoff::new()
+            .initial(Duration::from_secs(1))
+            .multiplier(f64::NAN)
+            .jitter(f64::NAN);
+        assert_eq!(backoff.next_delay(3), Some(Duration::from_secs(1)));
+    }
+}
@@ -0,0 +1,183 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Relay Score
+
+#[cfg(feature = "nip11")]
+use nostr::nips::nip11::RelayInformationDocument;
+
+use super::RelayConnectionStats;
+
+const UPTIME_WEIGHT: f64 = 0.5;
+const ACCEPTANCE_WEIGHT: f64 = 0.2;
+const LATENCY_WEIGHT: f64 = 0.15;
+const LIMITATION_WEIGHT: f64 = 0.15;
+/// Weight of a persisted score, as number of connection attempts
+const PERSISTED_SCORE_ATTEMPTS: f64 = 10.0;
+
+/// Relay score
+///
+/// Every component is between `0.0` (worst) and `1.0` (best).
+/// Components without enough data are set to `1.0`, to give a chance to new relays.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RelayScore {
+    /// Connection success rate
+    pub uptime: f64,
+    /// Rate of events accepted by the relay (`OK` messages)
+    pub acceptance: f64,
+    /// Latency score
+    pub latency: f64,
+    /// NIP11 limitations score (auth or payment required)
+    pub limitation: f64,
+}
+
+impl Default for RelayScore {
+    fn default() -> Self {
+        Self {
+            uptime: 1.0,
+            acceptance: 1.0,
+            latency: 1.0,
+            limitation: 1.0,
+        }
+    }
+}
+
+impl RelayScore {
+    /// Compose score from [`RelayConnectionStats`]
+    pub async fn from_stats(stats: &RelayConnectionStats) -> Self {
+        let mut score = Self::default();
+
+        if stats.attempts() > 0 {
+            score.uptime = stats.uptime();
+        }
+
+        let accepted: usize = stats.ok_accepted();
+        let rejected: usize = stats.ok_rejected();
+        let total: usize = accepted + rejected;
+        if total > 0 {
+            score.acceptance = accepted as f64 / total as f64;
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            if let Some(latency) = stats.latency().await {
+                score.latency = 1.0 / (1.0 + latency.as_secs_f64());
+            }
+        }
+
+        score
+    }
+
+    /// Apply NIP11 limitations
+    #[cfg(feature = "nip11")]
+    pub fn limitation(mut self, document: &RelayInformationDocument) -> Self {
+        if let Some(limitation) = &document.limitation {
+            let mut value: f64 = 1.0;
+            if limitation.auth_required.unwrap_or_default() {
+                value *= 0.75;
+            }
+            if limitation.payment_required.unwrap_or_default() {
+                value *= 0.25;
+            }
+            self.limitation = value;
+        }
+        self
+    }
+
+    /// Weighted score, between `0.0` and `1.0`
+    pub fn value(&self) -> f64 {
+        let value: f64 = self.uptime * UPTIME_WEIGHT
+            + self.acceptance * ACCEPTANCE_WEIGHT
+            + self.latency * LATENCY_WEIGHT
+            + self.limitation * LIMITATION_WEIGHT;
+        value.clamp(0.0, 1.0)
+    }
+
+    /// Merge the weighted score with a score persisted in a previous session
+    ///
+    /// The persisted score counts as 10 connection attempts,
+    /// so the live score prevails as the `attempts` of this session grow.
+    pub fn merge(&self, persisted: f64, attempts: usize) -> f64 {
+        let attempts: f64 = attempts as f64;
+        let weight: f64 = attempts / (attempts + PERSISTED_SCORE_ATTEMPTS);
+        let value: f64 = self.value() * weight + persisted * (1.0 - weight);
+        value.clamp(0.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "nip11")]
+    use nostr::nips::nip11::Limitation;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_score_without_data() {
+        let stats = RelayConnectionStats::new();
+        let score = RelayScore::from_stats(&stats).await;
+        assert_eq!(score, RelayScore::default());
+        assert_eq!(score.value(), 1.0);
+    }
+
+    #[tokio::test]
+    async fn test_score_from_stats() {
+        let stats = RelayConnectionStats::new();
+        for _ in 0..4 {
+            stats.new_attempt();
+        }
+        stats.new_success();
+        stats.new_ok(true);
+        stats.new_ok(false);
+        stats.new_ok(false);
+        stats.new_ok(false);
+
+        let score = RelayScore::from_stats(&stats).await;
+        assert_eq!(score.uptime, 0.25);
+        assert_eq!(score.acceptance, 0.25);
+        assert_eq!(score.latency, 1.0);
+        assert_eq!(score.value(), 0.25 * 0.5 + 0.25 * 0.2 + 0.15 + 0.15);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_latency_score() {
+        let stats = RelayConnectionStats::new();
+        stats.save_latency(std::time::Duration::from_secs(1)).await;
+        let score = RelayScore::from_stats(&stats).await;
+        assert_eq!(score.latency, 0.5);
+    }
+
+    #[cfg(feature = "nip11")]
+    #[test]
+    fn test_limitation_score() {
+        let document = RelayInformationDocument {
+            limitation: Some(Limitation {
+                auth_required: Some(true),
+                payment_required: Some(true),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let score = RelayScore::default().limitation(&document);
+        assert_eq!(score.limitation, 0.75 * 0.25);
+
+        // No limitation
+        let score = RelayScore::default().limitation(&RelayInformationDocument::default());
+        assert_eq!(score.limitation, 1.0);
+    }
+
+    #[test]
+    fn test_merge_persisted_score() {
+        let score = RelayScore::default();
+
+        // Without attempts, only the persisted score is used
+        assert_eq!(score.merge(0.2, 0), 0.2);
+
+        // The live score prevails as the attempts grow
+        assert_eq!(score.merge(0.2, 10), 0.6);
+        let merged = score.merge(0.2, 1000);
+        assert!(merged > 0.99 && merged < 1.0);
+    }
+}
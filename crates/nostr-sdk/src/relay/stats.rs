@@ -14,12 +14,17 @@ use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use nostr::Timestamp;
+use nostr_sdk_net::TransportStats;
 #[cfg(not(target_arch = "wasm32"))]
 use tokio::sync::RwLock;
 
 #[cfg(feature = "blocking")]
 use crate::RUNTIME;
 
+/// Number of ping latencies kept in [`RelayConnectionStats`]
+#[cfg(not(target_arch = "wasm32"))]
+pub const LATENCY_WINDOW: usize = 10;
+
 /// Ping Stats
 #[cfg(not(target_arch = "wasm32"))]
 #[derive(Debug, Clone)]
@@ -92,6 +97,10 @@ pub struct RelayConnectionStats {
     success: Arc<AtomicUsize>,
     bytes_sent: Arc<AtomicUsize>,
     bytes_received: Arc<AtomicUsize>,
+    wire: TransportStats,
+    ok_accepted: Arc<AtomicUsize>,
+    ok_rejected: Arc<AtomicUsize>,
+    rate_limited: Arc<AtomicUsize>,
     connected_at: Arc<AtomicU64>,
     first_connection_timestamp: Arc<AtomicU64>,
     #[cfg(not(target_arch = "wasm32"))]
@@ -114,6 +123,10 @@ impl RelayConnectionStats {
             success: Arc::new(AtomicUsize::new(0)),
             bytes_sent: Arc::new(AtomicUsize::new(0)),
             bytes_received: Arc::new(AtomicUsize::new(0)),
+            wire: TransportStats::new(),
+            ok_accepted: Arc::new(AtomicUsize::new(0)),
+            ok_rejected: Arc::new(AtomicUsize::new(0)),
+            rate_limited: Arc::new(AtomicUsize::new(0)),
             connected_at: Arc::new(AtomicU64::new(0)),
             first_connection_timestamp: Arc::new(AtomicU64::new(0)),
             #[cfg(not(target_arch = "wasm32"))]
@@ -144,16 +157,50 @@ impl RelayConnectionStats {
         }
     }
 
-    /// Bytes sent
+    /// Bytes sent (uncompressed messages)
     pub fn bytes_sent(&self) -> usize {
         self.bytes_sent.load(Ordering::SeqCst)
     }
 
-    /// Bytes received
+    /// Bytes received (uncompressed messages)
     pub fn bytes_received(&self) -> usize {
         self.bytes_received.load(Ordering::SeqCst)
     }
 
+    /// Bytes sent on the wire (compressed, including TLS, WebSocket handshake and framing)
+    ///
+    /// On WASM the wire size is not exposed by the browser: always `0`.
+    pub fn wire_bytes_sent(&self) -> usize {
+        self.wire.bytes_sent()
+    }
+
+    /// Bytes received from the wire (compressed, including TLS, WebSocket handshake and framing)
+    ///
+    /// On WASM the wire size is not exposed by the browser: always `0`.
+    pub fn wire_bytes_received(&self) -> usize {
+        self.wire.bytes_received()
+    }
+
+    /// Check if `permessage-deflate` compression is negotiated for the current connection
+    pub fn compression(&self) -> bool {
+        self.wire.compression()
+    }
+
+    /// Number of events accepted by the relay (`OK` with status `true`)
+    pub fn ok_accepted(&self) -> usize {
+        self.ok_accepted.load(Ordering::SeqCst)
+    }
+
+    /// Number of events rejected by the relay (`OK` with status `false`)
+    pub fn ok_rejected(&self) -> usize {
+        self.ok_rejected.load(Ordering::SeqCst)
+    }
+
+    /// Number of times the rate limit was hit (local throttling or `rate-limited` relay replies)
+    pub fn rate_limited(&self) -> usize {
+        self.rate_limited.load(Ordering::SeqCst)
+    }
+
     /// Get UNIX timestamp of the last connection
     pub fn connected_at(&self) -> Timestamp {
         Timestamp::from(self.connected_at.load(Ordering::SeqCst))
@@ -164,7 +211,7 @@ impl RelayConnectionStats {
         Timestamp::from(self.first_connection_timestamp.load(Ordering::SeqCst))
     }
 
-    /// Calculate latency
+    /// Calculate latency (average of the last [`LATENCY_WINDOW`] pings)
     #[cfg(not(target_arch = "wasm32"))]
     pub async fn latency(&self) -> Option<Duration> {
         let latencies = self.latencies.read().await;
@@ -172,12 +219,25 @@ impl RelayConnectionStats {
         sum.checked_div(latencies.len() as u32)
     }
 
-    /// Calculate latency
+    /// Calculate latency (average of the last [`LATENCY_WINDOW`] pings)
     #[cfg(all(not(target_arch = "wasm32"), feature = "blocking"))]
     pub fn latency_blocking(&self) -> Option<Duration> {
         RUNTIME.block_on(async { self.latency().await })
     }
 
+    /// Latencies of the last [`LATENCY_WINDOW`] pings (newest first)
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn latencies(&self) -> Vec<Duration> {
+        let latencies = self.latencies.read().await;
+        latencies.iter().copied().collect()
+    }
+
+    /// Latencies of the last [`LATENCY_WINDOW`] pings (newest first)
+    #[cfg(all(not(target_arch = "wasm32"), feature = "blocking"))]
+    pub fn latencies_blocking(&self) -> Vec<Duration> {
+        RUNTIME.block_on(async { self.latencies().await })
+    }
+
     pub(crate) fn new_attempt(&self) {
         self.attempts.fetch_add(1, Ordering::SeqCst);
     }
@@ -200,6 +260,22 @@ impl RelayConnectionStats {
         }
     }
 
+    pub(crate) fn new_ok(&self, status: bool) {
+        if status {
+            self.ok_accepted.fetch_add(1, Ordering::SeqCst);
+        } else {
+            self.ok_rejected.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    pub(crate) fn new_rate_limited(&self) {
+        self.rate_limited.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub(crate) fn transport_stats(&self) -> TransportStats {
+        self.wire.clone()
+    }
+
     pub(crate) fn add_bytes_sent(&self, size: usize) {
         self.bytes_sent.fetch_add(size, Ordering::SeqCst);
     }
@@ -213,7 +289,7 @@ impl RelayConnectionStats {
     #[cfg(not(target_arch = "wasm32"))]
     pub(crate) async fn save_latency(&self, latency: Duration) {
         let mut latencies = self.latencies.write().await;
-        if latencies.len() >= 5 {
+        if latencies.len() >= LATENCY_WINDOW {
             latencies.pop_back();
         }
         latencies.push_front(latency)
@@ -4,7 +4,13 @@
 
 //! Util
 
+use nostr::event::pipeline::{Error as PipelineError, EventBuilderPipeline};
+use nostr::event::pow::PowOptions;
+#[cfg(not(target_arch = "wasm32"))]
+use nostr::event::{builder, pow};
+use nostr::key::XOnlyPublicKey;
 use nostr::url::{ParseError, Url};
+use nostr::{EventBuilder, UnsignedEvent};
 
 /// Try into [`Url`]
 pub trait TryIntoUrl {
@@ -41,3 +47,39 @@ impl TryIntoUrl for &str {
         Url::parse(self)
     }
 }
+
+/// Build unsigned POW [`Event`](nostr::Event) without blocking the async runtime
+///
+/// The [`EventBuilderPipeline`] hooks are executed before mining.
+/// Mining run on a blocking thread, using the threads configured in [`PowOptions`].
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn unsigned_pow_event(
+    pipeline: &EventBuilderPipeline,
+    builder: EventBuilder,
+    pubkey: XOnlyPublicKey,
+    difficulty: u8,
+    opts: &PowOptions,
+) -> Result<UnsignedEvent, PipelineError> {
+    let pipeline: EventBuilderPipeline = pipeline.clone();
+    let opts: PowOptions = opts.clone();
+    tokio::task::spawn_blocking(move || {
+        pipeline.to_unsigned_pow_event_with_opts(builder, pubkey, difficulty, &opts)
+    })
+    .await
+    .map_err(|_| PipelineError::Builder(builder::Error::Pow(pow::Error::JoinHandleError)))?
+}
+
+/// Build unsigned POW [`Event`](nostr::Event)
+///
+/// The [`EventBuilderPipeline`] hooks are executed before mining.
+/// Threads are not available on WASM: [`PowOptions`] are ignored and mining run on the current thread.
+#[cfg(target_arch = "wasm32")]
+pub async fn unsigned_pow_event(
+    pipeline: &EventBuilderPipeline,
+    builder: EventBuilder,
+    pubkey: XOnlyPublicKey,
+    difficulty: u8,
+    _opts: &PowOptions,
+) -> Result<UnsignedEvent, PipelineError> {
+    pipeline.to_unsigned_pow_event(builder, pubkey, difficulty)
+}
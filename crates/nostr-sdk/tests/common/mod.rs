@@ -0,0 +1,100 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Shared helpers of the integration tests
+
+#![allow(dead_code)]
+
+use std::future::Future;
+use std::time::Duration;
+
+use nostr_sdk::database::{DatabaseOptions, MemoryDatabase};
+use nostr_sdk::prelude::*;
+use nostr_sdk::relay::pool::RelayPool;
+
+pub const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// New pool connected to the mock relays
+pub async fn connect_pool(relays: &[&MockRelay], opts: RelayPoolOptions) -> RelayPool {
+    let pool = RelayPool::new(opts);
+    add_relays(&pool, relays).await;
+    pool
+}
+
+/// New pool, with a database that store the events, connected to the mock relays
+pub async fn connect_pool_with_database(relays: &[&MockRelay]) -> RelayPool {
+    let database = MemoryDatabase::new(DatabaseOptions::default());
+    let pool = RelayPool::with_database(RelayPoolOptions::default(), database);
+    add_relays(&pool, relays).await;
+    pool
+}
+
+async fn add_relays(pool: &RelayPool, relays: &[&MockRelay]) {
+    for relay in relays.iter() {
+        pool.add_relay(relay.url(), None, relay.relay_opts())
+            .await
+            .unwrap();
+    }
+    pool.connect(true).await;
+}
+
+/// Relay options of the mock relay, advertising a NIP11 limitation
+pub fn relay_opts_with_limitation(relay: &MockRelay, limitation: Limitation) -> RelayOptions {
+    let document = RelayInformationDocument {
+        limitation: Some(limitation),
+        ..Default::default()
+    };
+    relay.relay_opts().document(document)
+}
+
+/// Text note created at `created_at`
+pub fn note(keys: &Keys, content: &str, created_at: u64) -> Event {
+    let created_at = Timestamp::from(created_at);
+    let kind = Kind::TextNote;
+    let tags: Vec<Tag> = Vec::new();
+    let pubkey = keys.public_key();
+    UnsignedEvent {
+        id: EventId::new(&pubkey, created_at, &kind, &tags, content),
+        pubkey,
+        created_at,
+        kind,
+        tags,
+        content: content.to_string(),
+    }
+    .sign(keys)
+    .unwrap()
+}
+
+/// Poll `condition` until `true` or [`TIMEOUT`]
+pub async fn wait_until<F, Fut>(condition: F) -> bool
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = bool>,
+{
+    tokio::time::timeout(TIMEOUT, async {
+        while !condition().await {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .is_ok()
+}
+
+/// Receive the first [`PoolEvent`] that match `f`, within [`TIMEOUT`]
+pub async fn recv_until<F>(listener: &mut PoolEventListener, f: F) -> Option<PoolEvent>
+where
+    F: Fn(&PoolEvent) -> bool,
+{
+    tokio::time::timeout(TIMEOUT, async {
+        while let Some(event) = listener.recv().await {
+            if f(&event) {
+                return Some(event);
+            }
+        }
+        None
+    })
+    .await
+    .ok()
+    .flatten()
+}
@@ -0,0 +1,153 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+use nostr_sdk::prelude::*;
+use nostr_sdk::relay::pool::RelayPool;
+
+mod common;
+
+use self::common::*;
+
+async fn add_notes(relay: &MockRelay, keys: &Keys, num: u64) {
+    for i in 0..num {
+        relay
+            .add_event(note(keys, "count me", 1_700_000_000 + i))
+            .await
+            .unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_count() {
+    let keys = Keys::generate();
+    let relay = MockRelay::run();
+    add_notes(&relay, &keys, 3).await;
+    let pool: RelayPool = connect_pool(&[&relay], RelayPoolOptions::default()).await;
+
+    let count = pool
+        .count(vec![Filter::new().author(keys.public_key())], TIMEOUT)
+        .await
+        .unwrap();
+    let relay_count = &count.relays[&relay.url()];
+    assert_eq!(relay_count.count, 3);
+    assert!(!relay_count.approximate);
+    assert!(relay_count.ids.is_none());
+    assert_eq!(count.total, 3);
+    assert!(!count.approximate);
+    assert!(count.errors.is_empty());
+
+    pool.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_count_approximate() {
+    let keys = Keys::generate();
+    let relay = MockRelay::run_with_opts(MockRelayOptions::new().approximate_count(true));
+    add_notes(&relay, &keys, 2).await;
+    let pool: RelayPool = connect_pool(&[&relay], RelayPoolOptions::default()).await;
+
+    let count = pool
+        .count(vec![Filter::new().author(keys.public_key())], TIMEOUT)
+        .await
+        .unwrap();
+    assert_eq!(count.relays[&relay.url()].count, 2);
+    assert!(count.relays[&relay.url()].approximate);
+    assert_eq!(count.total, 2);
+    assert!(count.approximate);
+
+    pool.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_count_fallback_paginated() {
+    let keys = Keys::generate();
+    let relay = MockRelay::run_with_opts(MockRelayOptions::new().count(false).max_limit(2));
+    add_notes(&relay, &keys, 5).await;
+
+    let limitation = Limitation {
+        max_limit: Some(2),
+        ..Default::default()
+    };
+    let pool = RelayPool::new(RelayPoolOptions::default());
+    pool.add_relay(
+        relay.url(),
+        None,
+        relay_opts_with_limitation(&relay, limitation),
+    )
+    .await
+    .unwrap();
+    pool.connect(true).await;
+
+    // Relay without COUNT: the IDs are fetched in time windows of at most `max_limit` events
+    let count = pool
+        .count(vec![Filter::new().author(keys.public_key())], TIMEOUT)
+        .await
+        .unwrap();
+    let relay_count = &count.relays[&relay.url()];
+    assert_eq!(relay_count.count, 5);
+    assert_eq!(relay_count.ids.as_ref().map(|ids| ids.len()), Some(5));
+    assert!(!relay_count.approximate);
+    assert_eq!(count.total, 5);
+    assert!(relay.reqs() > 1);
+
+    pool.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_count_aggregate_relays() {
+    let keys = Keys::generate();
+    let relay1 = MockRelay::run();
+    add_notes(&relay1, &keys, 2).await;
+    let relay2 = MockRelay::run();
+    add_notes(&relay2, &keys, 4).await;
+    let pool: RelayPool = connect_pool(&[&relay1, &relay2], RelayPoolOptions::default()).await;
+
+    // Shared events can't be added up: the aggregate is the max
+    let count = pool
+        .count(vec![Filter::new().author(keys.public_key())], TIMEOUT)
+        .await
+        .unwrap();
+    assert_eq!(count.relays.len(), 2);
+    assert_eq!(count.total, 4);
+
+    pool.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_count_fallback_truncated() {
+    let relay = MockRelay::run_with_opts(MockRelayOptions::new().count(false).max_limit(2));
+    let keys = Keys::generate();
+    for i in 0..3 {
+        relay
+            .add_event(note(&keys, &format!("same second {i}"), 1_700_000_000))
+            .await
+            .unwrap();
+    }
+
+    let limitation = Limitation {
+        max_limit: Some(2),
+        ..Default::default()
+    };
+    let pool = RelayPool::new(RelayPoolOptions::default());
+    pool.add_relay(
+        relay.url(),
+        None,
+        relay_opts_with_limitation(&relay, limitation),
+    )
+    .await
+    .unwrap();
+    pool.connect(true).await;
+
+    // More than `max_limit` events with the same timestamp: the window can't be split
+    let count = pool
+        .count(vec![Filter::new().author(keys.public_key())], TIMEOUT)
+        .await
+        .unwrap();
+    let relay_count = &count.relays[&relay.url()];
+    assert_eq!(relay_count.count, 2);
+    assert!(relay_count.approximate);
+    assert!(count.approximate);
+
+    pool.shutdown().await.unwrap();
+}
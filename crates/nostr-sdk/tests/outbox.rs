@@ -0,0 +1,98 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use nostr_sdk::database::{MemoryDatabase, NostrDatabase};
+use nostr_sdk::prelude::*;
+use nostr_sdk::relay::pool::RelayPool;
+
+mod common;
+
+use self::common::*;
+
+fn opts() -> RelayPoolOptions {
+    RelayPoolOptions::new().outbox(
+        OutboxOptions::new()
+            .enabled(true)
+            .min_retry_interval(Duration::from_millis(100))
+            .max_retry_interval(Duration::from_millis(200)),
+    )
+}
+
+#[tokio::test]
+async fn test_outbox_retry_until_accepted() {
+    let keys = Keys::generate();
+    let relay =
+        MockRelay::run_with_opts(MockRelayOptions::new().reject_events("rate-limited: slow down"));
+    let pool: RelayPool = connect_pool(&[&relay], opts()).await;
+
+    let event = note(&keys, "retry me", 1_700_000_000);
+    assert!(pool
+        .send_event(event.clone(), RelaySendOptions::new())
+        .await
+        .is_err());
+
+    // Rate limited: kept in outbox
+    let outbox = pool.outbox().await.unwrap();
+    assert_eq!(outbox.len(), 1);
+    assert_eq!(outbox[0].event, event);
+    assert_eq!(outbox[0].relay_url, relay.url());
+
+    // Retried in background once the relay accept events
+    relay.update_opts(MockRelayOptions::new()).await;
+    assert!(wait_until(|| async { pool.outbox().await.unwrap().is_empty() }).await);
+    let count = relay
+        .database()
+        .count(vec![Filter::new().id(event.id)])
+        .await
+        .unwrap();
+    assert_eq!(count, 1);
+
+    pool.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_outbox_final_rejection() {
+    let keys = Keys::generate();
+    let relay = MockRelay::run_with_opts(MockRelayOptions::new().reject_events("blocked: spam"));
+    let pool: RelayPool = connect_pool(&[&relay], opts()).await;
+
+    let event = note(&keys, "spam", 1_700_000_000);
+    assert!(pool
+        .send_event(event.clone(), RelaySendOptions::new())
+        .await
+        .is_err());
+
+    // Not retryable: removed from outbox
+    assert!(pool.outbox().await.unwrap().is_empty());
+
+    pool.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_outbox_accepted() {
+    let keys = Keys::generate();
+    let relay = MockRelay::run();
+    let pool: RelayPool = connect_pool(&[&relay], opts()).await;
+
+    pool.send_event(note(&keys, "ok", 1_700_000_000), RelaySendOptions::new())
+        .await
+        .unwrap();
+    assert!(pool.outbox().await.unwrap().is_empty());
+
+    pool.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_outbox_task_exit_on_drop() {
+    let database = Arc::new(MemoryDatabase::default());
+    let pool = RelayPool::with_database(opts(), database.clone());
+    assert!(Arc::strong_count(&database) > 1);
+
+    // Dropped without shutdown: the tasks release the database
+    drop(pool);
+    assert!(wait_until(|| async { Arc::strong_count(&database) == 1 }).await);
+}
@@ -0,0 +1,226 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+use std::collections::HashSet;
+
+use nostr_sdk::database::NostrDatabase;
+use nostr_sdk::prelude::*;
+use nostr_sdk::relay::policy::KindPolicy;
+use nostr_sdk::relay::pool::{Error, RelayPool};
+
+mod common;
+
+use self::common::*;
+
+#[tokio::test]
+async fn test_send_event_to_all_relays() {
+    let keys = Keys::generate();
+    let relay1 = MockRelay::run();
+    let relay2 = MockRelay::run();
+    let pool: RelayPool = connect_pool(&[&relay1, &relay2], RelayPoolOptions::default()).await;
+
+    let event = note(&keys, "hello", 1_700_000_000);
+    let event_id = pool
+        .send_event(event.clone(), RelaySendOptions::new())
+        .await
+        .unwrap();
+    assert_eq!(event_id, event.id);
+
+    for relay in [&relay1, &relay2] {
+        let events = relay
+            .database()
+            .query(vec![Filter::new().id(event.id)])
+            .await
+            .unwrap();
+        assert_eq!(events, vec![event.clone()]);
+    }
+
+    pool.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_send_event_rejected_by_all_relays() {
+    let keys = Keys::generate();
+    let relay = MockRelay::run_with_opts(MockRelayOptions::new().reject_events("blocked: test"));
+    let pool: RelayPool = connect_pool(&[&relay], RelayPoolOptions::default()).await;
+
+    let event = note(&keys, "hello", 1_700_000_000);
+    let res = pool
+        .send_event(event.clone(), RelaySendOptions::new())
+        .await;
+    assert!(matches!(res, Err(Error::EventNotPublished(id)) if id == event.id));
+
+    pool.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_get_events_of_deduplicate_relays() {
+    let keys = Keys::generate();
+    let shared = note(&keys, "shared", 1_700_000_000);
+    let only2 = note(&keys, "only on relay 2", 1_700_000_001);
+
+    let relay1 = MockRelay::run();
+    relay1.add_event(shared.clone()).await.unwrap();
+    let relay2 = MockRelay::run();
+    relay2.add_event(shared.clone()).await.unwrap();
+    relay2.add_event(only2.clone()).await.unwrap();
+
+    let pool: RelayPool = connect_pool(&[&relay1, &relay2], RelayPoolOptions::default()).await;
+
+    let events = pool
+        .get_events_of(
+            vec![Filter::new().author(keys.public_key())],
+            TIMEOUT,
+            FilterOptions::ExitOnEOSE,
+        )
+        .await
+        .unwrap();
+    let ids: HashSet<EventId> = events.iter().map(|e| e.id).collect();
+    assert_eq!(events.len(), 2);
+    assert_eq!(ids, HashSet::from([shared.id, only2.id]));
+
+    pool.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_subscription_receive_new_events() {
+    let keys = Keys::generate();
+    let relay = MockRelay::run();
+    let pool: RelayPool = connect_pool(&[&relay], RelayPoolOptions::default()).await;
+    let mut listener = pool.listen(ListenerOptions::new()).await;
+
+    pool.subscribe(vec![Filter::new().author(keys.public_key())], None)
+        .await;
+    recv_until(&mut listener, |e| {
+        matches!(e, PoolEvent::EndOfStoredEvents { .. })
+    })
+    .await
+    .unwrap();
+
+    let event = note(&keys, "live", 1_700_000_000);
+    relay.add_event(event.clone()).await.unwrap();
+
+    let received = recv_until(&mut listener, |e| matches!(e, PoolEvent::Event { .. })).await;
+    match received {
+        Some(PoolEvent::Event {
+            relay_url,
+            event: e,
+            ..
+        }) => {
+            assert_eq!(relay_url, relay.url());
+            assert_eq!(*e, event);
+        }
+        e => panic!("Unexpected event: {e:?}"),
+    }
+
+    pool.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_filters_chunked_by_max_filters() {
+    let relay = MockRelay::run_with_opts(MockRelayOptions::new().max_filters(1));
+    let mut filters: Vec<Filter> = Vec::new();
+    let mut ids: HashSet<EventId> = HashSet::new();
+    for i in 0..3 {
+        let keys = Keys::generate();
+        let event = note(&keys, "chunked", 1_700_000_000 + i);
+        relay.add_event(event.clone()).await.unwrap();
+        filters.push(Filter::new().author(keys.public_key()));
+        ids.insert(event.id);
+    }
+
+    let limitation = Limitation {
+        max_filters: Some(1),
+        ..Default::default()
+    };
+    let pool = RelayPool::new(RelayPoolOptions::default());
+    pool.add_relay(
+        relay.url(),
+        None,
+        relay_opts_with_limitation(&relay, limitation),
+    )
+    .await
+    .unwrap();
+    pool.connect(true).await;
+
+    // The relay close the REQ with more than 1 filter: one REQ per filter
+    let events = pool
+        .get_events_of(filters, TIMEOUT, FilterOptions::ExitOnEOSE)
+        .await
+        .unwrap();
+    assert_eq!(events.iter().map(|e| e.id).collect::<HashSet<_>>(), ids);
+    assert_eq!(relay.reqs(), 3);
+
+    pool.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_subscription_chunked_by_max_filters() {
+    let relay = MockRelay::run_with_opts(MockRelayOptions::new().max_filters(1));
+    let keys1 = Keys::generate();
+    let keys2 = Keys::generate();
+
+    let limitation = Limitation {
+        max_filters: Some(1),
+        ..Default::default()
+    };
+    let pool = RelayPool::new(RelayPoolOptions::default());
+    pool.add_relay(
+        relay.url(),
+        None,
+        relay_opts_with_limitation(&relay, limitation),
+    )
+    .await
+    .unwrap();
+    pool.connect(true).await;
+    let mut listener = pool.listen(ListenerOptions::new()).await;
+
+    pool.subscribe(
+        vec![
+            Filter::new().author(keys1.public_key()),
+            Filter::new().author(keys2.public_key()),
+        ],
+        None,
+    )
+    .await;
+    assert!(wait_until(|| async { relay.reqs() == 2 }).await);
+
+    // Events of the second chunk are delivered too
+    let event = note(&keys2, "second chunk", 1_700_000_000);
+    relay.add_event(event.clone()).await.unwrap();
+    let received = recv_until(&mut listener, |e| matches!(e, PoolEvent::Event { .. })).await;
+    assert!(matches!(received, Some(PoolEvent::Event { event: e, .. }) if *e == event));
+
+    pool.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_rejected_event_not_seen() {
+    let keys = Keys::generate();
+    let event = note(&keys, "not allowed", 1_700_000_000);
+    let relay = MockRelay::run();
+    relay.add_event(event.clone()).await.unwrap();
+
+    let opts = RelayPoolOptions::default().admission_policy(KindPolicy::new([Kind::Metadata]));
+    let pool: RelayPool = connect_pool(&[&relay], opts).await;
+
+    let events = pool
+        .get_events_of(
+            vec![Filter::new().author(keys.public_key())],
+            TIMEOUT,
+            FilterOptions::ExitOnEOSE,
+        )
+        .await
+        .unwrap();
+    assert!(events.is_empty());
+
+    let database = pool.database();
+    assert!(!database
+        .has_event_already_been_seen(event.id)
+        .await
+        .unwrap());
+    assert_eq!(database.event_seen_on_relays(event.id).await.unwrap(), None);
+
+    pool.shutdown().await.unwrap();
+}
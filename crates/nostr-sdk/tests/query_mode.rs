@@ -0,0 +1,165 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+use std::collections::HashSet;
+
+use nostr_sdk::prelude::*;
+use nostr_sdk::relay::pool::{Error, RelayPool};
+use tokio::sync::Mutex;
+
+mod common;
+
+use self::common::*;
+
+/// Pool with `stored` in the database and a relay with `stored` and `remote`
+async fn setup(keys: &Keys) -> (RelayPool, MockRelay, Event, Event) {
+    let stored = note(keys, "stored", 1_700_000_000);
+    let remote = note(keys, "remote", 1_700_000_100);
+
+    let relay = MockRelay::run();
+    relay.add_event(stored.clone()).await.unwrap();
+    relay.add_event(remote.clone()).await.unwrap();
+
+    let pool: RelayPool = connect_pool_with_database(&[&relay]).await;
+    pool.database().save_event(&stored).await.unwrap();
+
+    (pool, relay, stored, remote)
+}
+
+async fn get_ids(
+    pool: &RelayPool,
+    keys: &Keys,
+    mode: QueryMode,
+) -> Result<HashSet<EventId>, Error> {
+    let events = pool
+        .get_events_of_with_mode(
+            vec![Filter::new().author(keys.public_key())],
+            TIMEOUT,
+            FilterOptions::ExitOnEOSE,
+            mode,
+        )
+        .await?;
+    Ok(events.into_iter().map(|e| e.id).collect())
+}
+
+#[tokio::test]
+async fn test_cache_only() {
+    let keys = Keys::generate();
+    let (pool, relay, stored, _) = setup(&keys).await;
+
+    let ids = get_ids(&pool, &keys, QueryMode::CacheOnly).await.unwrap();
+    assert_eq!(ids, HashSet::from([stored.id]));
+    assert_eq!(relay.reqs(), 0);
+
+    pool.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_network_first() {
+    let keys = Keys::generate();
+    let (pool, relay, stored, remote) = setup(&keys).await;
+    let local = note(&keys, "local only", 1_700_000_050);
+    pool.database().save_event(&local).await.unwrap();
+
+    // Relay events merged with the stored ones
+    let ids = get_ids(&pool, &keys, QueryMode::NetworkFirst)
+        .await
+        .unwrap();
+    assert_eq!(ids, HashSet::from([stored.id, remote.id, local.id]));
+    assert_eq!(relay.reqs(), 1);
+
+    pool.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_network_only() {
+    let keys = Keys::generate();
+    let (pool, _relay, stored, remote) = setup(&keys).await;
+    let local = note(&keys, "local only", 1_700_000_050);
+    pool.database().save_event(&local).await.unwrap();
+
+    // Stored events not seen on the relay are excluded
+    let ids = get_ids(&pool, &keys, QueryMode::NetworkOnly).await.unwrap();
+    assert_eq!(ids, HashSet::from([stored.id, remote.id]));
+
+    pool.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_network_only_no_relay_replied() {
+    let keys = Keys::generate();
+    let pool: RelayPool = connect_pool_with_database(&[]).await;
+
+    let res = get_ids(&pool, &keys, QueryMode::NetworkOnly).await;
+    assert!(res.is_err());
+
+    pool.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_cache_first_return_stored_immediately() {
+    let keys = Keys::generate();
+    let (pool, _relay, stored, remote) = setup(&keys).await;
+
+    let ids = get_ids(&pool, &keys, QueryMode::CacheFirst).await.unwrap();
+    assert_eq!(ids, HashSet::from([stored.id]));
+
+    // The newer events are fetched in background and saved
+    let database = pool.database();
+    assert!(
+        wait_until(|| async {
+            database
+                .count(vec![Filter::new().id(remote.id)])
+                .await
+                .unwrap()
+                == 1
+        })
+        .await
+    );
+
+    pool.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_cache_first_empty_cache() {
+    let keys = Keys::generate();
+    let remote = note(&keys, "remote", 1_700_000_100);
+    let relay = MockRelay::run();
+    relay.add_event(remote.clone()).await.unwrap();
+    let pool: RelayPool = connect_pool_with_database(&[&relay]).await;
+
+    // Nothing stored: wait for the relays
+    let ids = get_ids(&pool, &keys, QueryMode::CacheFirst).await.unwrap();
+    assert_eq!(ids, HashSet::from([remote.id]));
+    assert_eq!(relay.reqs(), 1);
+
+    pool.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_cache_first_callback() {
+    let keys = Keys::generate();
+    let (pool, _relay, stored, remote) = setup(&keys).await;
+
+    let received: Mutex<Vec<EventId>> = Mutex::new(Vec::new());
+    pool.get_events_of_with_callback(
+        vec![Filter::new().author(keys.public_key())],
+        TIMEOUT,
+        FilterOptions::ExitOnEOSE,
+        QueryMode::CacheFirst,
+        |event| {
+            let received = &received;
+            async move {
+                received.lock().await.push(event.id);
+            }
+        },
+    )
+    .await
+    .unwrap();
+
+    // Stored event first, then the newer one from the relay, without duplicates
+    assert_eq!(received.into_inner(), vec![stored.id, remote.id]);
+
+    pool.shutdown().await.unwrap();
+}
@@ -0,0 +1,159 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+use std::collections::HashSet;
+
+use nostr_sdk::database::{DatabaseOptions, MemoryDatabase};
+use nostr_sdk::prelude::*;
+use nostr_sdk::relay::pool::RelayPool;
+use nostr_sdk::relay::Error as RelayError;
+
+mod common;
+
+use self::common::*;
+
+/// Add text notes created at consecutive timestamps
+async fn add_notes(relay: &MockRelay, keys: &Keys, num: u64) -> HashSet<EventId> {
+    let mut ids = HashSet::new();
+    for i in 0..num {
+        let event = note(keys, "reconcile me", 1_700_000_000 + i);
+        ids.insert(event.id);
+        relay.add_event(event).await.unwrap();
+    }
+    ids
+}
+
+#[tokio::test]
+async fn test_support_negentropy() {
+    let relay = MockRelay::run();
+    let without = MockRelay::run_with_opts(MockRelayOptions::new().negentropy(false));
+    let pool: RelayPool = connect_pool(&[&relay, &without], RelayPoolOptions::default()).await;
+
+    let r: Relay = pool.relay(relay.url()).await.unwrap();
+    assert!(r.support_negentropy().await.unwrap());
+
+    // Not fallback to REQ
+    let r: Relay = pool.relay(without.url()).await.unwrap();
+    assert!(!r.support_negentropy().await.unwrap());
+    assert_eq!(without.reqs(), 0);
+
+    pool.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_reconcile_with_req() {
+    let keys = Keys::generate();
+    let filter = Filter::new().author(keys.public_key());
+    let relay = MockRelay::run_with_opts(MockRelayOptions::new().negentropy(false));
+    let remote = add_notes(&relay, &keys, 2).await;
+    let local = note(&keys, "local", 1_700_000_010);
+
+    let pool: RelayPool = connect_pool_with_database(&[&relay]).await;
+    pool.database().save_event(&local).await.unwrap();
+
+    let report = pool
+        .reconcile(filter.clone(), NegentropyOptions::new().bidirectional(true))
+        .await
+        .unwrap();
+    assert!(report.is_ok());
+    assert_eq!(report.remote, remote);
+    assert_eq!(report.received, remote);
+    assert_eq!(report.local, HashSet::from([local.id]));
+    assert_eq!(report.sent, HashSet::from([local.id]));
+    assert!(relay.reqs() > 0);
+
+    // Without fallback
+    let res = pool
+        .relay(relay.url())
+        .await
+        .unwrap()
+        .reconcile(
+            filter,
+            Vec::new(),
+            NegentropyOptions::new().req_fallback(false),
+        )
+        .await;
+    assert!(matches!(res, Err(RelayError::NegentropyNotSupported)));
+
+    pool.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_req_windows_split_by_max_limit() {
+    let keys = Keys::generate();
+    let relay = MockRelay::run_with_opts(MockRelayOptions::new().negentropy(false).max_limit(2));
+    let remote = add_notes(&relay, &keys, 5).await;
+
+    let limitation = Limitation {
+        max_limit: Some(2),
+        ..Default::default()
+    };
+    let database = MemoryDatabase::new(DatabaseOptions::default());
+    let pool = RelayPool::with_database(RelayPoolOptions::default(), database);
+    pool.add_relay(
+        relay.url(),
+        None,
+        relay_opts_with_limitation(&relay, limitation),
+    )
+    .await
+    .unwrap();
+    pool.connect(true).await;
+
+    let report = pool
+        .reconcile(
+            Filter::new().author(keys.public_key()),
+            NegentropyOptions::new(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(report.remote, remote);
+    assert!(report.rounds > 1);
+    assert_eq!(relay.reqs(), report.rounds);
+
+    pool.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_req_windows_silently_capped() {
+    let keys = Keys::generate();
+    let relay = MockRelay::run_with_opts(MockRelayOptions::new().negentropy(false).max_limit(2));
+    let remote = add_notes(&relay, &keys, 5).await;
+
+    // The relay not advertise `max_limit`
+    let pool: RelayPool = connect_pool_with_database(&[&relay]).await;
+
+    let report = pool
+        .reconcile(
+            Filter::new().author(keys.public_key()),
+            NegentropyOptions::new(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(report.remote, remote);
+
+    pool.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_req_windows_not_split_if_complete() {
+    let keys = Keys::generate();
+    let relay = MockRelay::run_with_opts(MockRelayOptions::new().negentropy(false));
+    let remote = add_notes(&relay, &keys, 3).await;
+    let pool: RelayPool = connect_pool_with_database(&[&relay]).await;
+
+    let report = pool
+        .reconcile(
+            Filter::new().author(keys.public_key()),
+            NegentropyOptions::new(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(report.remote, remote);
+
+    // 1 window and 1 check for older events
+    assert_eq!(report.rounds, 2);
+    assert_eq!(relay.reqs(), 2);
+
+    pool.shutdown().await.unwrap();
+}
@@ -0,0 +1,287 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+use std::time::Duration;
+
+use nostr_sdk::prelude::*;
+use nostr_sdk::relay::pool::RelayPool;
+
+mod common;
+
+use self::common::*;
+
+async fn connect(relay: &MockRelay, opts: RelayOptions) -> (RelayPool, Relay) {
+    let pool = RelayPool::new(RelayPoolOptions::default());
+    pool.add_relay(relay.url(), None, opts).await.unwrap();
+    pool.connect(true).await;
+    let r: Relay = pool.relay(relay.url()).await.unwrap();
+    (pool, r)
+}
+
+fn is_status(event: &PoolEvent, expected: RelayStatus) -> bool {
+    matches!(event, PoolEvent::RelayStatus { status, .. } if *status == expected)
+}
+
+#[tokio::test]
+async fn test_reconnect_after_disconnection() {
+    let relay = MockRelay::run();
+    let opts = relay
+        .relay_opts()
+        .reconnect_strategy(FixedDelay::new(Duration::from_millis(50)));
+    let (pool, r) = connect(&relay, opts).await;
+    assert_eq!(r.status().await, RelayStatus::Connected);
+    let mut listener = pool.listen(ListenerOptions::new()).await;
+
+    relay.disconnect_all();
+    recv_until(&mut listener, |e| is_status(e, RelayStatus::Disconnected))
+        .await
+        .unwrap();
+    recv_until(&mut listener, |e| is_status(e, RelayStatus::Connected))
+        .await
+        .unwrap();
+    assert_eq!(r.status().await, RelayStatus::Connected);
+
+    pool.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_reconnect_while_connected_keeps_backoff() {
+    let relay = MockRelay::run();
+    let opts = relay
+        .relay_opts()
+        .reconnect_strategy(FixedDelay::new(Duration::from_millis(500)));
+    let (pool, r) = connect(&relay, opts).await;
+    let mut listener = pool.listen(ListenerOptions::new()).await;
+
+    // Nothing to skip while connected
+    r.reconnect().await;
+    assert_eq!(r.status().await, RelayStatus::Connected);
+
+    // The wait after the next disconnection is not skipped
+    relay.disconnect_all();
+    recv_until(&mut listener, |e| is_status(e, RelayStatus::Disconnected))
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(r.status().await, RelayStatus::Disconnected);
+    recv_until(&mut listener, |e| is_status(e, RelayStatus::Connected))
+        .await
+        .unwrap();
+
+    pool.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_reconnect_after_missed_pong() {
+    let relay = MockRelay::run();
+    let opts = relay
+        .relay_opts()
+        .ping_interval(Duration::from_millis(400))
+        .pong_timeout(Duration::from_millis(100))
+        .reconnect_strategy(FixedDelay::new(Duration::from_millis(50)));
+    let (pool, r) = connect(&relay, opts).await;
+    assert_eq!(r.status().await, RelayStatus::Connected);
+    let mut listener = pool.listen(ListenerOptions::new()).await;
+
+    // The connection is dead for the relay: the pings are no longer answered
+    relay.update_opts(MockRelayOptions::new().pong(false)).await;
+    recv_until(&mut listener, |e| is_status(e, RelayStatus::Disconnected))
+        .await
+        .unwrap();
+
+    relay.update_opts(MockRelayOptions::new()).await;
+    recv_until(&mut listener, |e| is_status(e, RelayStatus::Connected))
+        .await
+        .unwrap();
+    assert_eq!(r.status().await, RelayStatus::Connected);
+
+    pool.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_subscription_restored_after_reconnection() {
+    let keys = Keys::generate();
+    let relay = MockRelay::run();
+    let opts = relay
+        .relay_opts()
+        .reconnect_strategy(FixedDelay::new(Duration::from_millis(50)))
+        .resume_subscriptions(true);
+    let (pool, _) = connect(&relay, opts).await;
+    let mut listener = pool.listen(ListenerOptions::new()).await;
+
+    pool.subscribe(vec![Filter::new().author(keys.public_key())], None)
+        .await;
+    assert!(wait_until(|| async { relay.reqs() == 1 }).await);
+
+    // Connection dropped in the middle of the subscription
+    relay.disconnect_all();
+    recv_until(&mut listener, |e| is_status(e, RelayStatus::Disconnected))
+        .await
+        .unwrap();
+
+    // Subscription sent again on reconnect: the event published in the meantime is received
+    let event = note(&keys, "missed", Timestamp::now().as_u64());
+    relay.add_event(event.clone()).await.unwrap();
+    let received = recv_until(&mut listener, |e| matches!(e, PoolEvent::Event { .. })).await;
+    assert!(matches!(received, Some(PoolEvent::Event { event: e, .. }) if *e == event));
+    assert_eq!(relay.reqs(), 2);
+
+    pool.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_disconnect_after_events() {
+    let keys = Keys::generate();
+    let relay = MockRelay::run_with_opts(MockRelayOptions::new().disconnect_after_events(1));
+    relay
+        .add_event(note(&keys, "first", 1_700_000_000))
+        .await
+        .unwrap();
+    relay
+        .add_event(note(&keys, "second", 1_700_000_001))
+        .await
+        .unwrap();
+    let opts = relay.relay_opts().reconnect_strategy(NeverReconnect);
+    let (pool, r) = connect(&relay, opts).await;
+
+    // Connection dropped before EOSE: partial result within the timeout
+    let events = pool
+        .get_events_of(
+            vec![Filter::new().author(keys.public_key())],
+            Duration::from_secs(1),
+            FilterOptions::ExitOnEOSE,
+        )
+        .await
+        .unwrap();
+    assert_eq!(events.len(), 1);
+    assert!(wait_until(|| async { r.status().await == RelayStatus::Terminated }).await);
+
+    pool.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_give_up_after_max_attempts() {
+    let relay = MockRelay::run();
+    let opts = relay
+        .relay_opts()
+        .reconnect_strategy(FixedDelay::new(Duration::from_millis(20)))
+        .max_reconnect_attempts(Some(2));
+    let (pool, r) = connect(&relay, opts).await;
+
+    // The relay stop accepting connections
+    relay.shutdown();
+    assert!(wait_until(|| async { r.status().await == RelayStatus::Terminated }).await);
+    assert!(r.termination_reason().await.is_some());
+
+    pool.shutdown().await.unwrap();
+}
+
+async fn last_event_at(relay: &Relay) -> Option<Timestamp> {
+    relay
+        .subscriptions()
+        .await
+        .into_values()
+        .next()
+        .and_then(|sub| sub.last_event_at())
+}
+
+#[tokio::test]
+async fn test_resume_cursor_of_each_relay() {
+    let keys = Keys::generate();
+    let event = note(&keys, "shared", 1_700_000_000);
+
+    let relay1 = MockRelay::run();
+    relay1.add_event(event.clone()).await.unwrap();
+    let relay2 = MockRelay::run();
+    relay2.add_event(event.clone()).await.unwrap();
+
+    let pool = RelayPool::new(RelayPoolOptions::default());
+    for relay in [&relay1, &relay2] {
+        let opts = relay.relay_opts().resume_subscriptions(true);
+        pool.add_relay(relay.url(), None, opts).await.unwrap();
+    }
+    pool.connect(true).await;
+
+    pool.subscribe(vec![Filter::new().author(keys.public_key())], None)
+        .await;
+
+    // The event is saved once, but it moves the cursor of both relays
+    for relay in [&relay1, &relay2] {
+        let r: Relay = pool.relay(relay.url()).await.unwrap();
+        assert!(wait_until(|| async { last_event_at(&r).await == Some(event.created_at) }).await);
+    }
+
+    pool.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_resume_cursor_not_moved_by_interrupted_backfill() {
+    let keys = Keys::generate();
+    let relay = MockRelay::run_with_opts(MockRelayOptions::new().disconnect_after_events(1));
+    relay
+        .add_event(note(&keys, "old", 1_700_000_000))
+        .await
+        .unwrap();
+    relay
+        .add_event(note(&keys, "new", 1_700_000_100))
+        .await
+        .unwrap();
+
+    let opts = relay
+        .relay_opts()
+        .resume_subscriptions(true)
+        .reconnect_strategy(NeverReconnect);
+    let (pool, r) = connect(&relay, opts).await;
+    let mut listener = pool.listen(ListenerOptions::new()).await;
+
+    pool.subscribe(vec![Filter::new().author(keys.public_key())], None)
+        .await;
+
+    // Connection lost before EOSE: the missing events must be requested again on resume
+    recv_until(&mut listener, |e| matches!(e, PoolEvent::Event { .. }))
+        .await
+        .unwrap();
+    assert!(wait_until(|| async { r.status().await == RelayStatus::Terminated }).await);
+    assert_eq!(last_event_at(&r).await, None);
+
+    pool.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_fill_gaps_without_negentropy() {
+    let keys = Keys::generate();
+    let relay = MockRelay::run_with_opts(MockRelayOptions::new().negentropy(false));
+    let opts = relay
+        .relay_opts()
+        .reconnect_strategy(FixedDelay::new(Duration::from_millis(50)))
+        .resume_subscriptions(true)
+        .resume_with_reconciliation(true);
+    let (pool, _) = connect(&relay, opts).await;
+    let mut listener = pool.listen(ListenerOptions::new()).await;
+
+    pool.subscribe(vec![Filter::new().author(keys.public_key())], None)
+        .await;
+    recv_until(&mut listener, |e| {
+        matches!(e, PoolEvent::EndOfStoredEvents { .. })
+    })
+    .await
+    .unwrap();
+    let event = note(&keys, "live", Timestamp::now().as_u64());
+    relay.add_event(event).await.unwrap();
+    recv_until(&mut listener, |e| matches!(e, PoolEvent::Event { .. }))
+        .await
+        .unwrap();
+
+    relay.disconnect_all();
+    recv_until(&mut listener, |e| is_status(e, RelayStatus::Connected))
+        .await
+        .unwrap();
+    assert!(wait_until(|| async { relay.reqs() == 2 }).await);
+
+    // The gaps are not filled with a REQ based reconciliation of the whole history
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert_eq!(relay.reqs(), 2);
+
+    pool.shutdown().await.unwrap();
+}
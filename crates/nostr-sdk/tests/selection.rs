@@ -0,0 +1,118 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use nostr_sdk::database::{MemoryDatabase, NostrDatabase};
+use nostr_sdk::prelude::*;
+use nostr_sdk::relay::pool::RelayPool;
+
+mod common;
+
+use self::common::*;
+
+fn opts(persist_scores: bool) -> RelayPoolOptions {
+    RelayPoolOptions::new().relay_selection(
+        RelaySelectionOptions::new()
+            .enabled(true)
+            .min_score(0.9)
+            .persist_scores(persist_scores),
+    )
+}
+
+#[tokio::test]
+async fn test_skip_relays_below_min_score() {
+    let keys = Keys::generate();
+    let good = MockRelay::run();
+    let bad = MockRelay::run_with_opts(MockRelayOptions::new().reject_events("blocked: test"));
+    let pool: RelayPool = connect_pool(&[&good, &bad], opts(false)).await;
+
+    // Both relays are used until the first rejection
+    pool.send_event(note(&keys, "first", 1_700_000_000), RelaySendOptions::new())
+        .await
+        .unwrap();
+    let bad_relay: Relay = pool.relay(bad.url()).await.unwrap();
+    assert_eq!(bad_relay.stats().ok_rejected(), 1);
+
+    let scores = pool.relay_scores().await;
+    assert!(scores[&bad.url()] <= 0.9);
+    assert!(scores[&good.url()] > 0.9);
+
+    // The relay with a low score is skipped
+    let event = note(&keys, "second", 1_700_000_001);
+    pool.send_event(event.clone(), RelaySendOptions::new())
+        .await
+        .unwrap();
+    assert_eq!(bad_relay.stats().ok_rejected(), 1);
+    let events = good
+        .database()
+        .query(vec![Filter::new().id(event.id)])
+        .await
+        .unwrap();
+    assert_eq!(events, vec![event]);
+
+    pool.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_keep_best_relay_below_min_score() {
+    let keys = Keys::generate();
+    let bad = MockRelay::run_with_opts(MockRelayOptions::new().reject_events("blocked: test"));
+    let pool: RelayPool = connect_pool(&[&bad], opts(false)).await;
+    let bad_relay: Relay = pool.relay(bad.url()).await.unwrap();
+
+    for i in 0..2 {
+        let event = note(&keys, "rejected", 1_700_000_000 + i);
+        assert!(pool
+            .send_event(event, RelaySendOptions::new())
+            .await
+            .is_err());
+    }
+
+    // The only relay is used anyway
+    assert_eq!(bad_relay.stats().ok_rejected(), 2);
+
+    pool.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_persisted_scores_round_trip() {
+    let keys = Keys::generate();
+    let bad = MockRelay::run_with_opts(MockRelayOptions::new().reject_events("blocked: test"));
+    let database = Arc::new(MemoryDatabase::default());
+
+    // First session: the relay reject an event
+    let pool = RelayPool::with_database(opts(true), database.clone());
+    pool.add_relay(bad.url(), None, bad.relay_opts())
+        .await
+        .unwrap();
+    pool.connect(true).await;
+    let event = note(&keys, "rejected", 1_700_000_000);
+    assert!(pool
+        .send_event(event, RelaySendOptions::new())
+        .await
+        .is_err());
+    let score: f64 = pool.relay_scores().await[&bad.url()];
+    pool.shutdown().await.unwrap();
+
+    let persisted: HashMap<Url, f64> = database.relay_scores().await.unwrap();
+    assert_eq!(persisted.get(&bad.url()), Some(&score));
+    assert!(score <= 0.9);
+
+    // Second session: the persisted score is merged with the live one
+    let pool = RelayPool::with_database(opts(true), database.clone());
+    pool.add_relay(bad.url(), None, bad.relay_opts())
+        .await
+        .unwrap();
+    pool.connect(true).await;
+    let relay: Relay = pool.relay(bad.url()).await.unwrap();
+    let live: f64 = relay.score().await.value();
+    let merged: f64 = pool.relay_scores().await[&bad.url()];
+    assert!(live > 0.9);
+    assert!(merged > score && merged < live);
+    assert!(merged <= 0.9);
+
+    pool.shutdown().await.unwrap();
+}
@@ -0,0 +1,163 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+use std::collections::HashSet;
+
+use nostr_sdk::database::NostrDatabase;
+use nostr_sdk::prelude::*;
+use nostr_sdk::relay::pool::RelayPool;
+
+mod common;
+
+use self::common::*;
+
+async fn ids_of(relay: &MockRelay, filter: Filter) -> HashSet<EventId> {
+    relay
+        .database()
+        .query(vec![filter])
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|e| e.id)
+        .collect()
+}
+
+#[tokio::test]
+async fn test_sync_union_of_relays() {
+    let keys = Keys::generate();
+    let filter = Filter::new().author(keys.public_key());
+    let a = note(&keys, "a", 1_700_000_000);
+    let b = note(&keys, "b", 1_700_000_001);
+    let c = note(&keys, "c", 1_700_000_002);
+
+    let relay1 = MockRelay::run();
+    relay1.add_event(a.clone()).await.unwrap();
+    let relay2 = MockRelay::run();
+    relay2.add_event(b.clone()).await.unwrap();
+
+    let pool: RelayPool = connect_pool_with_database(&[&relay1, &relay2]).await;
+    pool.database().save_event(&c).await.unwrap();
+
+    let report = pool
+        .sync(filter.clone(), NegentropyOptions::new())
+        .await
+        .unwrap();
+    assert!(report.is_ok());
+    assert_eq!(report.local, HashSet::from([c.id]));
+    assert_eq!(report.remote, HashSet::from([a.id, b.id]));
+    assert_eq!(report.received, HashSet::from([a.id, b.id]));
+    assert_eq!(report.sent, HashSet::from([a.id, b.id, c.id]));
+
+    let all = HashSet::from([a.id, b.id, c.id]);
+    assert_eq!(ids_of(&relay1, filter.clone()).await, all);
+    assert_eq!(ids_of(&relay2, filter.clone()).await, all);
+    let local: HashSet<EventId> = pool
+        .database()
+        .query(vec![filter])
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|e| e.id)
+        .collect();
+    assert_eq!(local, all);
+
+    // Only 1 reconciliation per relay: the upload reuse the diff of the download
+    assert_eq!(report.rounds, 2);
+
+    pool.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_sync_write_only_relay() {
+    let keys = Keys::generate();
+    let filter = Filter::new().author(keys.public_key());
+    let a = note(&keys, "a", 1_700_000_000);
+    let d = note(&keys, "d", 1_700_000_003);
+
+    let reader = MockRelay::run();
+    reader.add_event(a.clone()).await.unwrap();
+    let writer = MockRelay::run();
+    writer.add_event(d.clone()).await.unwrap();
+
+    let pool: RelayPool = connect_pool_with_database(&[&reader]).await;
+    pool.add_relay(writer.url(), None, writer.relay_opts().read(false))
+        .await
+        .unwrap();
+    pool.connect(true).await;
+
+    let report = pool
+        .sync(filter.clone(), NegentropyOptions::new())
+        .await
+        .unwrap();
+    assert!(report.is_ok(), "{:?}", report.errors);
+
+    // The write-only relay receive the events, but nothing is fetched from it
+    assert_eq!(report.received, HashSet::from([a.id]));
+    assert_eq!(
+        ids_of(&writer, filter.clone()).await,
+        HashSet::from([a.id, d.id])
+    );
+    assert!(pool
+        .database()
+        .query(vec![Filter::new().id(d.id)])
+        .await
+        .unwrap()
+        .is_empty());
+
+    pool.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_sync_rejected_events_not_sent() {
+    let keys = Keys::generate();
+    let filter = Filter::new().author(keys.public_key());
+    let c = note(&keys, "c", 1_700_000_000);
+
+    let relay = MockRelay::run_with_opts(MockRelayOptions::new().reject_events("blocked: test"));
+    let pool: RelayPool = connect_pool_with_database(&[&relay]).await;
+    pool.database().save_event(&c).await.unwrap();
+
+    let report = pool.sync(filter, NegentropyOptions::new()).await.unwrap();
+    assert_eq!(report.local, HashSet::from([c.id]));
+    assert!(report.sent.is_empty());
+
+    pool.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_reconcile_multi_with_items() {
+    let keys = Keys::generate();
+    let a = note(&keys, "a", 1_700_000_000);
+    let b = note(&keys, "b", 1_700_000_001);
+    let other = Keys::generate();
+    let e = note(&other, "e", 1_700_000_002);
+
+    let relay = MockRelay::run();
+    for event in [&a, &b, &e] {
+        relay.add_event(event.clone()).await.unwrap();
+    }
+    let pool: RelayPool = connect_pool_with_database(&[&relay]).await;
+
+    // Custom items: `a` is already known
+    let report = pool
+        .reconcile_multi_with_items(
+            vec![
+                (
+                    Filter::new().author(keys.public_key()),
+                    vec![(a.id, a.created_at)],
+                ),
+                (Filter::new().author(other.public_key()), Vec::new()),
+            ],
+            NegentropyOptions::new(),
+        )
+        .await
+        .unwrap();
+    assert!(report.is_ok());
+    assert!(report.local.is_empty());
+    assert_eq!(report.remote, HashSet::from([b.id, e.id]));
+    assert_eq!(report.received, HashSet::from([b.id, e.id]));
+    assert!(report.sent.is_empty());
+
+    pool.shutdown().await.unwrap();
+}
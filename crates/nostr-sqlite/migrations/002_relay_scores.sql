@@ -0,0 +1,6 @@
+PRAGMA user_version = 2; -- Schema version
+
+CREATE TABLE IF NOT EXISTS relay_scores (
+    relay_url TEXT PRIMARY KEY NOT NULL,
+    score REAL NOT NULL
+);
@@ -0,0 +1,9 @@
+PRAGMA user_version = 3; -- Schema version
+
+CREATE TABLE IF NOT EXISTS outbox (
+    event_id BLOB NOT NULL,
+    relay_url TEXT NOT NULL,
+    event BLOB NOT NULL,
+    expiration INTEGER NOT NULL,
+    PRIMARY KEY (event_id, relay_url)
+);
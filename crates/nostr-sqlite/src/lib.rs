@@ -8,7 +8,7 @@
 #![warn(missing_docs)]
 #![warn(rustdoc::bare_urls)]
 
-use std::collections::{BTreeSet, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::path::Path;
 use std::sync::Arc;
 
@@ -20,7 +20,7 @@ use deadpool_sqlite::{Config, Object, Pool, Runtime};
 use nostr::{Event, EventId, Filter, Timestamp, Url};
 use nostr_database::{
     Backend, DatabaseIndexes, DatabaseOptions, EventIndexResult, FlatBufferBuilder,
-    FlatBufferDecode, FlatBufferEncode, NostrDatabase, RawEvent,
+    FlatBufferDecode, FlatBufferEncode, NostrDatabase, OutboxEvent, RawEvent,
 };
 use rusqlite::config::DbConfig;
 use tokio::sync::RwLock;
@@ -35,6 +35,7 @@ use self::migration::STARTUP_SQL;
 #[derive(Debug, Clone)]
 pub struct SQLiteDatabase {
     db: Pool,
+    opts: DatabaseOptions,
     indexes: DatabaseIndexes,
     fbb: Arc<RwLock<FlatBufferBuilder<'static>>>,
 }
@@ -42,6 +43,14 @@ pub struct SQLiteDatabase {
 impl SQLiteDatabase {
     /// Open SQLite store
     pub async fn open<P>(path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        Self::open_with_opts(path, DatabaseOptions::default()).await
+    }
+
+    /// Open SQLite store with [`DatabaseOptions`]
+    pub async fn open_with_opts<P>(path: P, opts: DatabaseOptions) -> Result<Self, Error>
     where
         P: AsRef<Path>,
     {
@@ -54,7 +63,8 @@ impl SQLiteDatabase {
 
         let this = Self {
             db: pool,
-            indexes: DatabaseIndexes::new(),
+            opts,
+            indexes: DatabaseIndexes::new().delegated_authors(opts.delegated_authors),
             fbb: Arc::new(RwLock::new(FlatBufferBuilder::with_capacity(70_000))),
         };
 
@@ -116,7 +126,7 @@ impl NostrDatabase for SQLiteDatabase {
     }
 
     fn opts(&self) -> DatabaseOptions {
-        DatabaseOptions::default()
+        self.opts
     }
 
     #[tracing::instrument(skip_all, level = "trace")]
@@ -299,6 +309,96 @@ impl NostrDatabase for SQLiteDatabase {
         .await?
     }
 
+    async fn save_relay_score(&self, relay_url: Url, score: f64) -> Result<(), Self::Err> {
+        let conn = self.acquire().await?;
+        conn.interact(move |conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO relay_scores (relay_url, score) VALUES (?, ?);",
+                (relay_url.to_string(), score),
+            )
+        })
+        .await??;
+        Ok(())
+    }
+
+    async fn relay_scores(&self) -> Result<HashMap<Url, f64>, Self::Err> {
+        let conn = self.acquire().await?;
+        conn.interact(move |conn| {
+            let mut stmt = conn.prepare_cached("SELECT relay_url, score FROM relay_scores;")?;
+            let mut rows = stmt.query([])?;
+            let mut scores = HashMap::new();
+            while let Ok(Some(row)) = rows.next() {
+                let url: String = row.get(0)?;
+                let score: f64 = row.get(1)?;
+                scores.insert(Url::parse(&url)?, score);
+            }
+            Ok(scores)
+        })
+        .await?
+    }
+
+    async fn save_outbox_event(&self, item: OutboxEvent) -> Result<(), Self::Err> {
+        // Acquire FlatBuffers Builder
+        let mut fbb = self.fbb.write().await;
+
+        // Encode
+        let event_id: EventId = item.event.id;
+        let value: Vec<u8> = item.event.encode(&mut fbb).to_vec();
+
+        let conn = self.acquire().await?;
+        conn.interact(move |conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO outbox (event_id, relay_url, event, expiration) VALUES (?, ?, ?, ?);",
+                (
+                    event_id.to_hex(),
+                    item.relay_url.to_string(),
+                    value,
+                    item.expiration.as_i64(),
+                ),
+            )
+        })
+        .await??;
+        Ok(())
+    }
+
+    async fn outbox_events(&self) -> Result<Vec<OutboxEvent>, Self::Err> {
+        let conn = self.acquire().await?;
+        conn.interact(move |conn| {
+            let mut stmt =
+                conn.prepare_cached("SELECT event, relay_url, expiration FROM outbox;")?;
+            let mut rows = stmt.query([])?;
+            let mut items = Vec::new();
+            while let Ok(Some(row)) = rows.next() {
+                let buf: Vec<u8> = row.get(0)?;
+                let url: String = row.get(1)?;
+                let expiration: i64 = row.get(2)?;
+                items.push(OutboxEvent::new(
+                    Event::decode(&buf)?,
+                    Url::parse(&url)?,
+                    Timestamp::from(expiration as u64),
+                ));
+            }
+            Ok(items)
+        })
+        .await?
+    }
+
+    async fn remove_outbox_event(
+        &self,
+        event_id: EventId,
+        relay_url: Url,
+    ) -> Result<(), Self::Err> {
+        let conn = self.acquire().await?;
+        conn.interact(move |conn| {
+            conn.execute(
+                "DELETE FROM outbox WHERE event_id = ? AND relay_url = ?;",
+                (event_id.to_hex(), relay_url.to_string()),
+            )
+        })
+        .await??;
+        Ok(())
+    }
+
     async fn wipe(&self) -> Result<(), Self::Err> {
         let conn = self.acquire().await?;
 
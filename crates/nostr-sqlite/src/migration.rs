@@ -11,7 +11,7 @@ use thiserror::Error;
 use super::Error;
 
 /// Latest database version
-pub const DB_VERSION: usize = 1;
+pub const DB_VERSION: usize = 3;
 
 /// Startup DB Pragmas
 pub const STARTUP_SQL: &str = r##"
@@ -55,7 +55,7 @@ pub(crate) async fn run(conn: &Object) -> Result<(), Error> {
 
                 // for initialized but out-of-date schemas, proceed to
                 // upgrade sequentially until we are current.
-                /* if curr_version == 1 {
+                if curr_version == 1 {
                     curr_version = mig_1_to_2(conn)?;
                 }
 
@@ -63,7 +63,7 @@ pub(crate) async fn run(conn: &Object) -> Result<(), Error> {
                     curr_version = mig_2_to_3(conn)?;
                 }
 
-                if curr_version == 3 {
+                /* if curr_version == 3 {
                     curr_version = mig_3_to_4(conn)?;
                 }
 
@@ -109,8 +109,14 @@ fn mig_init(conn: &mut Connection) -> Result<usize, Error> {
     Ok(1)
 }
 
-/* fn mig_1_to_2(conn: &mut Connection) -> Result<usize, Error> {
-    conn.execute_batch(include_str!("../../migrations/002_notifications.sql"))?;
+fn mig_1_to_2(conn: &mut Connection) -> Result<usize, Error> {
+    conn.execute_batch(include_str!("../migrations/002_relay_scores.sql"))?;
     tracing::info!("database schema upgraded v1 -> v2");
     Ok(2)
-} */
+}
+
+fn mig_2_to_3(conn: &mut Connection) -> Result<usize, Error> {
+    conn.execute_batch(include_str!("../migrations/003_outbox.sql"))?;
+    tracing::info!("database schema upgraded v2 -> v3");
+    Ok(3)
+}
@@ -15,10 +15,11 @@ use core::panic::PanicInfo;
 use alloc_cortex_m::CortexMHeap;
 use cortex_m_rt::entry;
 use cortex_m_semihosting::{debug, hprintln};
-use nostr::secp256k1::rand::{self, RngCore};
-use nostr::secp256k1::{Secp256k1, SecretKey};
-use nostr::{FromBech32, Keys, ToBech32};
 use nostr::nips::nip06::FromMnemonic;
+use nostr::secp256k1::rand::{self, CryptoRng, RngCore};
+use nostr::secp256k1::{Secp256k1, SecretKey};
+use nostr::types::time::FixedTime;
+use nostr::{EventBuilder, FromBech32, JsonUtil, Keys, Timestamp, ToBech32};
 
 // this is the allocator the application will use
 #[global_allocator]
@@ -49,6 +50,9 @@ impl RngCore for FakeRng {
     }
 }
 
+// NOT cryptographically secure: use a hardware TRNG on real devices
+impl CryptoRng for FakeRng {}
+
 #[entry]
 fn main() -> ! {
     hprintln!("heap size {}\n", HEAP_SIZE).unwrap();
@@ -76,6 +80,17 @@ fn main() -> ! {
     hprintln!("\nRandom keys (using FakeRng):").unwrap();
     print_keys(&keys);
 
+    // Sign event (time must be provided by an external source, i.e. RTC)
+    let time = FixedTime::from(Timestamp::from(1700000000));
+    let event = EventBuilder::new_text_note("Hello from bare metal!", [])
+        .to_event_with_ctx(&secp, &mut FakeRng, &time, &keys)
+        .unwrap();
+    hprintln!("\nSigned event (using FakeRng):").unwrap();
+    hprintln!("- ID: {}", event.id).unwrap();
+    hprintln!("- Signature: {}", event.sig).unwrap();
+    hprintln!("- Valid: {}", event.verify_with_ctx(&secp).is_ok()).unwrap();
+    hprintln!("- JSON: {}", event.as_json()).unwrap();
+
     // exit QEMU
     // NOTE do not run this on hardware; it can corrupt OpenOCD state
     debug::exit(debug::EXIT_SUCCESS);
@@ -0,0 +1,401 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Borrowed Event
+//!
+//! Zero-copy event for high-throughput consumers: strings are borrowed from the JSON input
+//! (only escaped strings are allocated) and can be converted to [`Event`] only when needed.
+
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use bitcoin::hashes::sha256::Hash as Sha256Hash;
+use bitcoin::hashes::Hash;
+use bitcoin::secp256k1::schnorr::Signature;
+use bitcoin::secp256k1::{self, Message, Secp256k1, Verification, XOnlyPublicKey};
+use serde::de::{Deserializer, IgnoredAny, SeqAccess, Visitor};
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::tag;
+#[cfg(feature = "std")]
+use crate::SECP256K1;
+use crate::{Event, EventId, Kind, Tag, TagIndexes, Timestamp};
+
+/// [`EventRef`] error
+#[derive(Debug)]
+pub enum Error {
+    /// Invalid signature
+    InvalidSignature,
+    /// Invalid event id
+    InvalidId,
+    /// Not an `EVENT` message
+    NotEventMessage,
+    /// Error serializing or deserializing JSON data
+    Json(serde_json::Error),
+    /// Secp256k1 error
+    Secp256k1(secp256k1::Error),
+    /// Tag error
+    Tag(tag::Error),
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidSignature => write!(f, "Invalid signature"),
+            Self::InvalidId => write!(f, "Invalid event id"),
+            Self::NotEventMessage => write!(f, "Not an EVENT message"),
+            Self::Json(e) => write!(f, "Json: {e}"),
+            Self::Secp256k1(e) => write!(f, "Secp256k1: {e}"),
+            Self::Tag(e) => write!(f, "Tag: {e}"),
+        }
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Json(e)
+    }
+}
+
+impl From<secp256k1::Error> for Error {
+    fn from(e: secp256k1::Error) -> Self {
+        Self::Secp256k1(e)
+    }
+}
+
+impl From<tag::Error> for Error {
+    fn from(e: tag::Error) -> Self {
+        Self::Tag(e)
+    }
+}
+
+/// Borrowed [`Event`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventRef<'a> {
+    /// Id
+    pub id: EventId,
+    /// Author
+    pub pubkey: XOnlyPublicKey,
+    /// Timestamp (seconds)
+    pub created_at: Timestamp,
+    /// Kind
+    pub kind: Kind,
+    /// Raw tags
+    #[serde(borrow, deserialize_with = "deserialize_tags")]
+    pub tags: Vec<Vec<Cow<'a, str>>>,
+    /// Content
+    #[serde(borrow)]
+    pub content: Cow<'a, str>,
+    /// Signature
+    pub sig: Signature,
+}
+
+impl<'a> EventRef<'a> {
+    /// Deserialize [`EventRef`] from JSON, borrowing the strings from `json`
+    ///
+    /// **This method NOT verify the signature!**
+    pub fn from_json<T>(json: &'a T) -> Result<Self, Error>
+    where
+        T: AsRef<[u8]> + ?Sized,
+    {
+        Ok(serde_json::from_slice(json.as_ref())?)
+    }
+
+    /// Deserialize [`EventRef`] from [`Value`], borrowing the strings from `value`
+    ///
+    /// **This method NOT verify the signature!**
+    pub fn from_value(value: &'a Value) -> Result<Self, Error> {
+        Ok(Self::deserialize(value)?)
+    }
+
+    /// Verify both [`EventId`] and [`Signature`]
+    #[cfg(feature = "std")]
+    pub fn verify(&self) -> Result<(), Error> {
+        self.verify_with_ctx(&SECP256K1)
+    }
+
+    /// Verify [`EventId`] and [`Signature`]
+    pub fn verify_with_ctx<C>(&self, secp: &Secp256k1<C>) -> Result<(), Error>
+    where
+        C: Verification,
+    {
+        self.verify_id()?;
+        self.verify_signature_with_ctx(secp)
+    }
+
+    /// Verify if the [`EventId`] it's composed correctly
+    ///
+    /// The ID is computed over the tags as received, without parsing them.
+    pub fn verify_id(&self) -> Result<(), Error> {
+        let data = (
+            0,
+            &self.pubkey,
+            self.created_at,
+            self.kind,
+            &self.tags,
+            &self.content,
+        );
+        let json: Vec<u8> = serde_json::to_vec(&data)?;
+        let id = EventId::from_hash(Sha256Hash::hash(&json));
+        if id == self.id {
+            Ok(())
+        } else {
+            Err(Error::InvalidId)
+        }
+    }
+
+    /// Verify only event [`Signature`]
+    #[cfg(feature = "std")]
+    pub fn verify_signature(&self) -> Result<(), Error> {
+        self.verify_signature_with_ctx(&SECP256K1)
+    }
+
+    /// Verify event [`Signature`]
+    pub fn verify_signature_with_ctx<C>(&self, secp: &Secp256k1<C>) -> Result<(), Error>
+    where
+        C: Verification,
+    {
+        let message = Message::from_slice(self.id.as_bytes())?;
+        secp.verify_schnorr(&self.sig, &message, &self.pubkey)
+            .map_err(|_| Error::InvalidSignature)
+    }
+
+    /// Build tags index
+    pub fn build_tags_index(&self) -> TagIndexes {
+        TagIndexes::from(
+            self.tags
+                .iter()
+                .map(|t| t.iter().map(|s| s.as_ref()).collect::<Vec<&str>>()),
+        )
+    }
+
+    /// Parse tags and compose [`Event`]
+    pub fn to_event(&self) -> Result<Event, Error> {
+        self.clone().into_event()
+    }
+
+    /// Parse tags and compose [`Event`], consuming the borrowed data
+    pub fn into_event(self) -> Result<Event, Error> {
+        let tags: Vec<Tag> = self
+            .tags
+            .into_iter()
+            .map(Tag::parse)
+            .collect::<Result<Vec<Tag>, _>>()?;
+        Ok(Event {
+            id: self.id,
+            pubkey: self.pubkey,
+            created_at: self.created_at,
+            kind: self.kind,
+            tags,
+            content: self.content.into_owned(),
+            sig: self.sig,
+        })
+    }
+}
+
+impl<'a> EventRef<'a> {
+    /// Deserialize [`EventRef`] from a relay `EVENT` message, borrowing the strings from `json`
+    ///
+    /// Return the subscription ID and the event.
+    ///
+    /// **This method NOT verify the signature!**
+    pub fn from_relay_message(json: &'a str) -> Result<(Cow<'a, str>, Self), Error> {
+        let msg: Option<(Cow<'a, str>, Self)> =
+            serde_json::from_str::<RelayEventMessage<'a>>(json)?.0;
+        msg.ok_or(Error::NotEventMessage)
+    }
+}
+
+/// Relay `EVENT` message (`None` for the other messages)
+struct RelayEventMessage<'a>(Option<(Cow<'a, str>, EventRef<'a>)>);
+
+impl<'de: 'a, 'a> Deserialize<'de> for RelayEventMessage<'a> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct RelayEventMessageVisitor<'a>(core::marker::PhantomData<&'a ()>);
+
+        impl<'de: 'a, 'a> Visitor<'de> for RelayEventMessageVisitor<'a> {
+            type Value = RelayEventMessage<'a>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a relay message")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                let msg_type: Option<CowStr<'a>> = seq.next_element()?;
+                if msg_type.as_ref().map(|t| t.0.as_ref()) != Some("EVENT") {
+                    while seq.next_element::<IgnoredAny>()?.is_some() {}
+                    return Ok(RelayEventMessage(None));
+                }
+
+                let subscription_id: CowStr<'a> = seq
+                    .next_element()?
+                    .ok_or_else(|| serde::de::Error::invalid_length(1, &self))?;
+                let event: EventRef<'a> = seq
+                    .next_element()?
+                    .ok_or_else(|| serde::de::Error::invalid_length(2, &self))?;
+                Ok(RelayEventMessage(Some((subscription_id.0, event))))
+            }
+        }
+
+        deserializer.deserialize_seq(RelayEventMessageVisitor(core::marker::PhantomData))
+    }
+}
+
+fn deserialize_tags<'de, 'a, D>(deserializer: D) -> Result<Vec<Vec<Cow<'a, str>>>, D::Error>
+where
+    'de: 'a,
+    D: Deserializer<'de>,
+{
+    let tags: Vec<Vec<CowStr<'a>>> = Vec::deserialize(deserializer)?;
+    Ok(tags
+        .into_iter()
+        .map(|tag| tag.into_iter().map(|s| s.0).collect())
+        .collect())
+}
+
+/// String borrowed when possible (not escaped)
+struct CowStr<'a>(Cow<'a, str>);
+
+impl<'de: 'a, 'a> Deserialize<'de> for CowStr<'a> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct CowStrVisitor;
+
+        impl<'de> Visitor<'de> for CowStrVisitor {
+            type Value = Cow<'de, str>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a string")
+            }
+
+            fn visit_borrowed_str<E>(self, v: &'de str) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Ok(Cow::Borrowed(v))
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Ok(Cow::Owned(String::from(v)))
+            }
+
+            fn visit_string<E>(self, v: String) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Ok(Cow::Owned(v))
+            }
+        }
+
+        Ok(Self(deserializer.deserialize_str(CowStrVisitor)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Alphabet, Filter, FiltersMatchEvent, JsonUtil};
+
+    const EVENT_JSON: &str = r#"{"id":"2be17aa3031bdcb006f0fce80c146dea9c1c0268b0af2398bb673365c6444d45","pubkey":"f86c44a2de95d9149b51c6a29afeabba264c18e2fa7c49de93424a0c56947785","created_at":1640839235,"kind":4,"tags":[["p","13adc511de7e1cfcf1c6b7f6365fb5a03442d7bcacf565ea57fa7770912c023d"]],"content":"uRuvYr585B80L6rSJiHocw==?iv=oh6LVqdsYYol3JfFnXTbPA==","sig":"a5d9290ef9659083c490b303eb7ee41356d8778ff19f2f91776c8dc4443388a64ffcf336e61af4c25c05ac3ae952d1ced889ed655b67790891222aaa15b99fdd"}"#;
+
+    #[test]
+    fn test_event_ref_borrowed() {
+        let event = EventRef::from_json(EVENT_JSON).unwrap();
+        assert!(matches!(event.content, Cow::Borrowed(_)));
+        assert!(matches!(event.tags[0][1], Cow::Borrowed(_)));
+        assert_eq!(event.kind, Kind::EncryptedDirectMessage);
+
+        let owned = event.to_event().unwrap();
+        assert_eq!(owned, Event::from_json(EVENT_JSON).unwrap());
+    }
+
+    #[test]
+    fn test_event_ref_from_relay_message() {
+        let msg = format!(r#"["EVENT","sub",{EVENT_JSON}]"#);
+        let (subscription_id, event) = EventRef::from_relay_message(&msg).unwrap();
+        assert_eq!(subscription_id, "sub");
+        assert!(matches!(subscription_id, Cow::Borrowed(_)));
+        assert!(matches!(event.content, Cow::Borrowed(_)));
+        assert!(matches!(event.tags[0][1], Cow::Borrowed(_)));
+        assert_eq!(
+            event.into_event().unwrap(),
+            Event::from_json(EVENT_JSON).unwrap()
+        );
+
+        assert!(matches!(
+            EventRef::from_relay_message(r#"["EOSE","sub"]"#),
+            Err(Error::NotEventMessage)
+        ));
+        assert!(matches!(
+            EventRef::from_relay_message(r#"["EVENT","sub"]"#),
+            Err(Error::Json(_))
+        ));
+    }
+
+    #[test]
+    fn test_event_ref_filter_match() {
+        let event = EventRef::from_json(EVENT_JSON).unwrap();
+        let pubkey = event.pubkey;
+
+        let filter = Filter::new()
+            .kind(Kind::EncryptedDirectMessage)
+            .author(pubkey)
+            .custom_tag(
+                Alphabet::P,
+                ["13adc511de7e1cfcf1c6b7f6365fb5a03442d7bcacf565ea57fa7770912c023d"],
+            );
+        assert!(filter.match_event(&event));
+
+        let filter = Filter::new().kind(Kind::TextNote);
+        assert!(!filter.match_event(&event));
+
+        let filters = vec![filter, Filter::new().author(pubkey)];
+        assert!(filters.match_matchable_event(&event));
+
+        // Keep the trait object safe
+        let filters: &dyn FiltersMatchEvent = &filters;
+        assert!(filters.match_event(&event.to_event().unwrap()));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_event_ref_verify() {
+        use crate::{EventBuilder, Keys};
+
+        let keys = Keys::generate();
+        let event = EventBuilder::new_text_note(
+            "escaped \"content\"\n",
+            [Tag::Hashtag(String::from("nostr"))],
+        )
+        .to_event(&keys)
+        .unwrap();
+        let json = event.as_json();
+
+        let event_ref = EventRef::from_json(&json).unwrap();
+        assert!(matches!(event_ref.content, Cow::Owned(_)));
+        assert!(event_ref.verify().is_ok());
+        assert_eq!(event_ref.to_event().unwrap(), event);
+
+        let mut tampered = event_ref.clone();
+        tampered.content = Cow::Borrowed("tampered");
+        assert!(matches!(tampered.verify(), Err(Error::InvalidId)));
+    }
+}
@@ -17,6 +17,8 @@ use serde_json::{json, Value};
 use url_fork::Url;
 
 use super::kind::{Kind, NIP90_JOB_REQUEST_RANGE, NIP90_JOB_RESULT_RANGE};
+#[cfg(feature = "std")]
+use super::pow::{self, PowOptions};
 use super::tag::ImageDimensions;
 use super::{Event, EventId, Marker, Tag, TagKind, UnsignedEvent};
 use crate::key::{self, Keys};
@@ -78,6 +80,9 @@ pub enum Error {
     NIP04(nip04::Error),
     /// NIP58 error
     NIP58(nip58::Error),
+    /// Proof of Work error
+    #[cfg(feature = "std")]
+    Pow(pow::Error),
     /// Wrong kind
     WrongKind {
         /// The received wrong kind
@@ -102,6 +107,8 @@ impl fmt::Display for Error {
             #[cfg(feature = "nip04")]
             Self::NIP04(e) => write!(f, "NIP04: {e}"),
             Self::NIP58(e) => write!(f, "NIP58: {e}"),
+            #[cfg(feature = "std")]
+            Self::Pow(e) => write!(f, "POW: {e}"),
             Self::WrongKind { received, expected } => {
                 write!(f, "Wrong kind: received={received}, expected={expected}")
             }
@@ -153,6 +160,13 @@ impl From<nip58::Error> for Error {
     }
 }
 
+#[cfg(feature = "std")]
+impl From<pow::Error> for Error {
+    fn from(e: pow::Error) -> Self {
+        Self::Pow(e)
+    }
+}
+
 /// [`Event`] builder
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct EventBuilder {
@@ -313,6 +327,42 @@ impl EventBuilder {
     pub fn to_unsigned_pow_event(self, pubkey: XOnlyPublicKey, difficulty: u8) -> UnsignedEvent {
         self.to_unsigned_pow_event_with_supplier(&Instant::now(), pubkey, difficulty)
     }
+
+    /// Build unsigned POW [`Event`] with [`PowOptions`]
+    ///
+    /// Mine with multiple threads, with progress report, cancellation and deadline.
+    #[cfg(feature = "std")]
+    pub fn to_unsigned_pow_event_with_opts(
+        self,
+        pubkey: XOnlyPublicKey,
+        difficulty: u8,
+        opts: &PowOptions,
+    ) -> Result<UnsignedEvent, Error> {
+        Ok(pow::mine(
+            pubkey,
+            Timestamp::now(),
+            self.kind,
+            self.tags,
+            self.content,
+            difficulty,
+            opts,
+        )?)
+    }
+
+    /// Build POW [`Event`] with [`PowOptions`]
+    ///
+    /// Mine with multiple threads, with progress report, cancellation and deadline.
+    #[cfg(feature = "std")]
+    pub fn to_pow_event_with_opts(
+        self,
+        keys: &Keys,
+        difficulty: u8,
+        opts: &PowOptions,
+    ) -> Result<Event, Error> {
+        let unsigned: UnsignedEvent =
+            self.to_unsigned_pow_event_with_opts(keys.public_key(), difficulty, opts)?;
+        Ok(unsigned.sign(keys)?)
+    }
 }
 
 impl EventBuilder {
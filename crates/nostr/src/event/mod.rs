@@ -13,20 +13,29 @@ use bitcoin::secp256k1::schnorr::Signature;
 use bitcoin::secp256k1::{self, Message, Secp256k1, Verification, XOnlyPublicKey};
 use serde_json::Value;
 
+pub mod borrowed;
 pub mod builder;
 pub mod id;
 pub mod kind;
 pub mod partial;
+pub mod pipeline;
+#[cfg(feature = "std")]
+pub mod pow;
 pub mod tag;
+pub mod typed;
 pub mod unsigned;
 
+pub use self::borrowed::EventRef;
 pub use self::builder::EventBuilder;
 pub use self::id::EventId;
 pub use self::kind::Kind;
 pub use self::partial::{MissingPartialEvent, PartialEvent};
+pub use self::pipeline::{EventBuilderPipeline, EventHook};
 pub use self::tag::{Marker, Tag, TagIndexValues, TagIndexes, TagKind};
+pub use self::typed::TypedEvent;
 pub use self::unsigned::UnsignedEvent;
 use crate::nips::nip01::Coordinate;
+use crate::nips::nip26::{self, DelegationTag, EventProperties};
 #[cfg(feature = "std")]
 use crate::types::time::Instant;
 use crate::types::time::TimeSupplier;
@@ -284,6 +293,76 @@ impl Event {
         })
     }
 
+    /// Extract NIP26 [`DelegationTag`], if exists
+    ///
+    /// **This method NOT validate the delegation!** Use [`Event::delegator`] for that.
+    pub fn delegation_tag(&self) -> Option<DelegationTag> {
+        self.tags
+            .iter()
+            .find_map(|t| DelegationTag::try_from(t).ok())
+    }
+
+    /// Get delegator public key
+    ///
+    /// Return `Ok(None)` if the event has no delegation tag or an error if the delegation tag
+    /// is not valid (invalid signature or conditions not satisfied by event kind and `created_at`).
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/26.md>
+    #[cfg(feature = "std")]
+    pub fn delegator(&self) -> Result<Option<XOnlyPublicKey>, nip26::Error> {
+        self.delegator_with_ctx(&SECP256K1)
+    }
+
+    /// Get delegator public key
+    ///
+    /// Return `Ok(None)` if the event has no delegation tag or an error if the delegation tag
+    /// is not valid (invalid signature or conditions not satisfied by event kind and `created_at`).
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/26.md>
+    pub fn delegator_with_ctx<C>(
+        &self,
+        secp: &Secp256k1<C>,
+    ) -> Result<Option<XOnlyPublicKey>, nip26::Error>
+    where
+        C: Verification,
+    {
+        match self.delegation_tag() {
+            Some(tag) => {
+                tag.validate_with_ctx(secp, self.pubkey, &EventProperties::from_event(self))?;
+                Ok(Some(tag.delegator_pubkey()))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Get effective author
+    ///
+    /// Return the delegator if the event has a valid delegation tag, otherwise the event `pubkey`.
+    #[cfg(feature = "std")]
+    pub fn author(&self) -> XOnlyPublicKey {
+        self.author_with_ctx(&SECP256K1)
+    }
+
+    /// Get effective author
+    ///
+    /// Return the delegator if the event has a valid delegation tag, otherwise the event `pubkey`.
+    pub fn author_with_ctx<C>(&self, secp: &Secp256k1<C>) -> XOnlyPublicKey
+    where
+        C: Verification,
+    {
+        match self.delegator_with_ctx(secp) {
+            Ok(Some(delegator)) => delegator,
+            _ => self.pubkey,
+        }
+    }
+
+    /// Get strongly typed view of the event, with parsed tags
+    ///
+    /// Return [`typed::Error::UnsupportedKind`] if there isn't a typed view for the event [`Kind`].
+    pub fn as_typed(&self) -> Result<TypedEvent, typed::Error> {
+        TypedEvent::try_from(self)
+    }
+
     /// Build tags index
     pub fn build_tags_index(&self) -> TagIndexes {
         TagIndexes::from(self.tags.iter().map(|t| t.as_vec()))
@@ -337,7 +416,8 @@ impl Event {
 #[cfg(test)]
 mod tests {
     use super::*;
-    #[cfg(feature = "std")]
+    use crate::types::time::FixedTime;
+    use crate::util::TestRng;
     use crate::Keys;
 
     #[test]
@@ -404,4 +484,69 @@ mod tests {
             .unwrap();
         assert!(!&event.is_expired());
     }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_event_delegator() {
+        use core::str::FromStr;
+
+        use crate::nips::nip26::{Conditions, Error as Nip26Error, ValidationError};
+
+        let delegator_keys = Keys::generate();
+        let delegatee_keys = Keys::generate();
+        let conditions = Conditions::from_str("kind=1").unwrap();
+        let tag =
+            DelegationTag::new(&delegator_keys, delegatee_keys.public_key(), conditions).unwrap();
+
+        // Valid delegation
+        let event = EventBuilder::new_text_note("delegated", [Tag::from(tag.clone())])
+            .to_event(&delegatee_keys)
+            .unwrap();
+        assert_eq!(event.delegation_tag(), Some(tag.clone()));
+        assert_eq!(event.delegator(), Ok(Some(delegator_keys.public_key())));
+        assert_eq!(event.author(), delegator_keys.public_key());
+
+        // Kind not allowed by conditions
+        let event = EventBuilder::new(Kind::Reaction, "+", [Tag::from(tag)])
+            .to_event(&delegatee_keys)
+            .unwrap();
+        assert_eq!(
+            event.delegator(),
+            Err(Nip26Error::ConditionsValidation(
+                ValidationError::InvalidKind
+            ))
+        );
+        assert_eq!(event.author(), delegatee_keys.public_key());
+
+        // Not delegated
+        let event = EventBuilder::new_text_note("not delegated", [])
+            .to_event(&delegatee_keys)
+            .unwrap();
+        assert_eq!(event.delegator(), Ok(None));
+        assert_eq!(event.author(), delegatee_keys.public_key());
+    }
+
+    #[test]
+    fn test_event_sign_verify_with_ctx() {
+        let secp = Secp256k1::new();
+        let mut rng = TestRng::default();
+        let supplier = FixedTime::from(Timestamp::from(1700000000));
+
+        let keys = Keys::generate_with_ctx(&secp, &mut rng);
+        let event = EventBuilder::new_text_note(
+            "signed on bare metal",
+            [Tag::Expiration(Timestamp::from(1700000100))],
+        )
+        .to_event_with_ctx(&secp, &mut rng, &supplier, &keys)
+        .unwrap();
+
+        assert_eq!(event.pubkey, keys.public_key());
+        assert_eq!(event.created_at, Timestamp::from(1700000000));
+        assert!(event.verify_with_ctx(&secp).is_ok());
+        assert!(!event.is_expired_with_supplier(&supplier));
+        assert!(event.is_expired_with_supplier(&FixedTime::from(Timestamp::from(1700000200))));
+
+        let event = Event::from_json(event.as_json()).unwrap();
+        assert!(event.verify_with_ctx(&secp).is_ok());
+    }
 }
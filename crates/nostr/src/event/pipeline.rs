@@ -0,0 +1,602 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Event builder pipeline
+//!
+//! Chain of hooks executed on the [`UnsignedEvent`] before signing.
+//! Each hook can edit the event (tags, content, ...) or veto it.
+
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt;
+use core::time::Duration;
+
+#[cfg(feature = "std")]
+use bitcoin::secp256k1::rand;
+use bitcoin::secp256k1::rand::{CryptoRng, Rng};
+use bitcoin::secp256k1::{Secp256k1, SecretKey, Signing, XOnlyPublicKey};
+
+#[cfg(feature = "std")]
+use super::pow::{self, PowOptions};
+use super::{builder, unsigned, Event, EventBuilder, EventId, Tag, TagKind, UnsignedEvent};
+use crate::nips::nip19::FromBech32;
+use crate::nips::nip26::{DelegationTag, EventProperties};
+#[cfg(feature = "std")]
+use crate::types::time::Instant;
+use crate::types::time::TimeSupplier;
+use crate::Keys;
+#[cfg(feature = "std")]
+use crate::SECP256K1;
+
+/// [`EventBuilderPipeline`] error
+#[derive(Debug)]
+pub enum Error {
+    /// Event vetoed by a hook
+    Vetoed(String),
+    /// Event builder error
+    Builder(builder::Error),
+    /// Unsigned event error
+    Unsigned(unsigned::Error),
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Vetoed(reason) => write!(f, "Event vetoed: {reason}"),
+            Self::Builder(e) => write!(f, "Event builder: {e}"),
+            Self::Unsigned(e) => write!(f, "Unsigned event: {e}"),
+        }
+    }
+}
+
+impl From<builder::Error> for Error {
+    fn from(e: builder::Error) -> Self {
+        Self::Builder(e)
+    }
+}
+
+impl From<unsigned::Error> for Error {
+    fn from(e: unsigned::Error) -> Self {
+        Self::Unsigned(e)
+    }
+}
+
+/// Pre-sign hook
+///
+/// Edit the [`UnsignedEvent`] in place or return [`Error::Vetoed`] to block it.
+/// The event ID is recomputed by the pipeline after all the hooks are executed.
+pub trait EventHook: Send + Sync {
+    /// Process [`UnsignedEvent`]
+    fn process(&self, event: &mut UnsignedEvent) -> Result<(), Error>;
+}
+
+impl<F> EventHook for F
+where
+    F: Fn(&mut UnsignedEvent) -> Result<(), Error> + Send + Sync,
+{
+    fn process(&self, event: &mut UnsignedEvent) -> Result<(), Error> {
+        self(event)
+    }
+}
+
+/// Event builder pipeline
+#[derive(Clone, Default)]
+pub struct EventBuilderPipeline {
+    hooks: Vec<Arc<dyn EventHook>>,
+}
+
+impl fmt::Debug for EventBuilderPipeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventBuilderPipeline")
+            .field("hooks", &self.hooks.len())
+            .finish()
+    }
+}
+
+impl EventBuilderPipeline {
+    /// New empty [`EventBuilderPipeline`]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add hook at the end of the chain
+    pub fn hook<H>(mut self, hook: H) -> Self
+    where
+        H: EventHook + 'static,
+    {
+        self.push(hook);
+        self
+    }
+
+    /// Add hook at the end of the chain
+    pub fn push<H>(&mut self, hook: H)
+    where
+        H: EventHook + 'static,
+    {
+        self.hooks.push(Arc::new(hook));
+    }
+
+    /// Number of hooks
+    pub fn len(&self) -> usize {
+        self.hooks.len()
+    }
+
+    /// Check if pipeline has no hooks
+    pub fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+
+    /// Execute the hooks and recompute the event ID
+    pub fn process(&self, mut event: UnsignedEvent) -> Result<UnsignedEvent, Error> {
+        if self.hooks.is_empty() {
+            return Ok(event);
+        }
+
+        for hook in self.hooks.iter() {
+            hook.process(&mut event)?;
+        }
+
+        event.id = EventId::new(
+            &event.pubkey,
+            event.created_at,
+            &event.kind,
+            &event.tags,
+            &event.content,
+        );
+
+        Ok(event)
+    }
+
+    /// Build and process [`UnsignedEvent`]
+    pub fn to_unsigned_event_with_supplier<T>(
+        &self,
+        supplier: &T,
+        builder: EventBuilder,
+        pubkey: XOnlyPublicKey,
+    ) -> Result<UnsignedEvent, Error>
+    where
+        T: TimeSupplier,
+    {
+        let unsigned: UnsignedEvent = builder.to_unsigned_event_with_supplier(supplier, pubkey);
+        self.process(unsigned)
+    }
+
+    /// Build and process unsigned POW [`Event`]
+    ///
+    /// The hooks are executed before mining, so the POW is not invalidated.
+    #[cfg(feature = "std")]
+    pub fn to_unsigned_pow_event_with_supplier<T>(
+        &self,
+        supplier: &T,
+        builder: EventBuilder,
+        pubkey: XOnlyPublicKey,
+        difficulty: u8,
+    ) -> Result<UnsignedEvent, Error>
+    where
+        T: TimeSupplier,
+    {
+        let unsigned: UnsignedEvent =
+            self.to_unsigned_event_with_supplier(supplier, builder, pubkey)?;
+        mine(unsigned, difficulty, &PowOptions::new())
+    }
+
+    /// Build, process and sign [`Event`]
+    pub fn to_event_with_ctx<C, R, T>(
+        &self,
+        secp: &Secp256k1<C>,
+        rng: &mut R,
+        supplier: &T,
+        builder: EventBuilder,
+        keys: &Keys,
+    ) -> Result<Event, Error>
+    where
+        C: Signing,
+        R: Rng + CryptoRng,
+        T: TimeSupplier,
+    {
+        let unsigned: UnsignedEvent =
+            self.to_unsigned_event_with_supplier(supplier, builder, keys.public_key())?;
+        Ok(unsigned.sign_with_ctx(secp, rng, keys)?)
+    }
+
+    /// Build, process and sign POW [`Event`]
+    #[cfg(feature = "std")]
+    pub fn to_pow_event_with_ctx<C, R, T>(
+        &self,
+        secp: &Secp256k1<C>,
+        rng: &mut R,
+        supplier: &T,
+        builder: EventBuilder,
+        keys: &Keys,
+        difficulty: u8,
+    ) -> Result<Event, Error>
+    where
+        C: Signing,
+        R: Rng + CryptoRng,
+        T: TimeSupplier,
+    {
+        let unsigned: UnsignedEvent = self.to_unsigned_pow_event_with_supplier(
+            supplier,
+            builder,
+            keys.public_key(),
+            difficulty,
+        )?;
+        Ok(unsigned.sign_with_ctx(secp, rng, keys)?)
+    }
+}
+
+#[cfg(feature = "std")]
+impl EventBuilderPipeline {
+    /// Build and process [`UnsignedEvent`]
+    pub fn to_unsigned_event(
+        &self,
+        builder: EventBuilder,
+        pubkey: XOnlyPublicKey,
+    ) -> Result<UnsignedEvent, Error> {
+        self.to_unsigned_event_with_supplier(&Instant::now(), builder, pubkey)
+    }
+
+    /// Build and process unsigned POW [`Event`]
+    pub fn to_unsigned_pow_event(
+        &self,
+        builder: EventBuilder,
+        pubkey: XOnlyPublicKey,
+        difficulty: u8,
+    ) -> Result<UnsignedEvent, Error> {
+        self.to_unsigned_pow_event_with_supplier(&Instant::now(), builder, pubkey, difficulty)
+    }
+
+    /// Build and process unsigned POW [`Event`], mining with [`PowOptions`]
+    pub fn to_unsigned_pow_event_with_opts(
+        &self,
+        builder: EventBuilder,
+        pubkey: XOnlyPublicKey,
+        difficulty: u8,
+        opts: &PowOptions,
+    ) -> Result<UnsignedEvent, Error> {
+        let unsigned: UnsignedEvent = self.to_unsigned_event(builder, pubkey)?;
+        mine(unsigned, difficulty, opts)
+    }
+
+    /// Build, process and sign POW [`Event`], mining with [`PowOptions`]
+    pub fn to_pow_event_with_opts(
+        &self,
+        builder: EventBuilder,
+        keys: &Keys,
+        difficulty: u8,
+        opts: &PowOptions,
+    ) -> Result<Event, Error> {
+        let unsigned: UnsignedEvent =
+            self.to_unsigned_pow_event_with_opts(builder, keys.public_key(), difficulty, opts)?;
+        Ok(unsigned.sign(keys)?)
+    }
+
+    /// Build, process and sign [`Event`]
+    pub fn to_event(&self, builder: EventBuilder, keys: &Keys) -> Result<Event, Error> {
+        self.to_event_with_ctx(
+            &SECP256K1,
+            &mut rand::thread_rng(),
+            &Instant::now(),
+            builder,
+            keys,
+        )
+    }
+
+    /// Build, process and sign POW [`Event`]
+    pub fn to_pow_event(
+        &self,
+        builder: EventBuilder,
+        keys: &Keys,
+        difficulty: u8,
+    ) -> Result<Event, Error> {
+        self.to_pow_event_with_ctx(
+            &SECP256K1,
+            &mut rand::thread_rng(),
+            &Instant::now(),
+            builder,
+            keys,
+            difficulty,
+        )
+    }
+}
+
+/// Mine the processed [`UnsignedEvent`], keeping its `created_at`, tags and content
+///
+/// A `nonce` tag already set by the builder or by a hook is replaced.
+#[cfg(feature = "std")]
+fn mine(
+    unsigned: UnsignedEvent,
+    difficulty: u8,
+    opts: &PowOptions,
+) -> Result<UnsignedEvent, Error> {
+    let tags: Vec<Tag> = unsigned
+        .tags
+        .into_iter()
+        .filter(|t| t.kind() != TagKind::Nonce)
+        .collect();
+    Ok(pow::mine(
+        unsigned.pubkey,
+        unsigned.created_at,
+        unsigned.kind,
+        tags,
+        unsigned.content,
+        difficulty,
+        opts,
+    )
+    .map_err(builder::Error::from)?)
+}
+
+fn has_tag(event: &UnsignedEvent, kind: &TagKind) -> bool {
+    event.tags.iter().any(|t| &t.kind() == kind)
+}
+
+/// Add NIP89 `client` tag, if missing
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/89.md>
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientTagHook {
+    name: String,
+}
+
+impl ClientTagHook {
+    /// New [`ClientTagHook`]
+    pub fn new<S>(name: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self { name: name.into() }
+    }
+}
+
+impl EventHook for ClientTagHook {
+    fn process(&self, event: &mut UnsignedEvent) -> Result<(), Error> {
+        let kind = TagKind::Custom(String::from("client"));
+        if !has_tag(event, &kind) {
+            event.tags.push(Tag::Generic(kind, vec![self.name.clone()]));
+        }
+        Ok(())
+    }
+}
+
+/// Add `expiration` tag, if missing
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/40.md>
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExpirationHook {
+    after: Duration,
+}
+
+impl ExpirationHook {
+    /// New [`ExpirationHook`]: event expire `after` its creation
+    pub fn new(after: Duration) -> Self {
+        Self { after }
+    }
+}
+
+impl EventHook for ExpirationHook {
+    fn process(&self, event: &mut UnsignedEvent) -> Result<(), Error> {
+        if !has_tag(event, &TagKind::Expiration) {
+            event
+                .tags
+                .push(Tag::Expiration(event.created_at + self.after));
+        }
+        Ok(())
+    }
+}
+
+/// Add `content-warning` tag, if missing
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/36.md>
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContentWarningHook {
+    reason: Option<String>,
+}
+
+impl ContentWarningHook {
+    /// New [`ContentWarningHook`]
+    pub fn new(reason: Option<String>) -> Self {
+        Self { reason }
+    }
+}
+
+impl EventHook for ContentWarningHook {
+    fn process(&self, event: &mut UnsignedEvent) -> Result<(), Error> {
+        if !has_tag(event, &TagKind::ContentWarning) {
+            event.tags.push(Tag::ContentWarning {
+                reason: self.reason.clone(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Sign on behalf of a delegator: add NIP26 `delegation` tag, if missing
+///
+/// Veto the event if the delegation conditions are not satisfied by its kind and `created_at`.
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/26.md>
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DelegationHook {
+    tag: DelegationTag,
+}
+
+impl DelegationHook {
+    /// New [`DelegationHook`]
+    pub fn new(tag: DelegationTag) -> Self {
+        Self { tag }
+    }
+}
+
+impl EventHook for DelegationHook {
+    fn process(&self, event: &mut UnsignedEvent) -> Result<(), Error> {
+        let properties = EventProperties::new(event.kind.as_u64(), event.created_at.as_u64());
+
+        #[cfg(feature = "std")]
+        self.tag
+            .validate(event.pubkey, &properties)
+            .map_err(|e| Error::Vetoed(format!("delegation: {e}")))?;
+
+        #[cfg(not(feature = "std"))]
+        self.tag
+            .conditions()
+            .evaluate(&properties)
+            .map_err(|e| Error::Vetoed(format!("delegation: {e}")))?;
+
+        if !has_tag(event, &TagKind::Delegation) {
+            event.tags.push(Tag::from(self.tag.clone()));
+        }
+
+        Ok(())
+    }
+}
+
+/// Veto events that contain a bech32 encoded secret key (`nsec`) in the content
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SecretKeyLeakHook;
+
+impl EventHook for SecretKeyLeakHook {
+    fn process(&self, event: &mut UnsignedEvent) -> Result<(), Error> {
+        let leak: bool = event
+            .content
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .filter(|word| word.starts_with("nsec1"))
+            .any(|word| SecretKey::from_bech32(word).is_ok());
+        if leak {
+            return Err(Error::Vetoed("content contains a secret key".to_string()));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nips::nip13;
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_pipeline_hooks() {
+        let keys = Keys::generate();
+        let pipeline = EventBuilderPipeline::new()
+            .hook(ClientTagHook::new("rust-nostr"))
+            .hook(ContentWarningHook::new(None));
+
+        let event = pipeline
+            .to_event(EventBuilder::new_text_note("hello", []), &keys)
+            .unwrap();
+        assert!(event.verify().is_ok());
+        assert!(event.tags.contains(&Tag::Generic(
+            TagKind::Custom(String::from("client")),
+            vec![String::from("rust-nostr")]
+        )));
+        assert!(event.tags.contains(&Tag::ContentWarning { reason: None }));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_pipeline_veto() {
+        let keys = Keys::generate();
+        let pipeline = EventBuilderPipeline::new().hook(SecretKeyLeakHook);
+
+        let content = "my key: nsec1ufnus6pju578ste3v90xd5m2decpuzpql2295m3sknqcjzyys9ls0qlc85";
+        let res = pipeline.to_event(EventBuilder::new_text_note(content, []), &keys);
+        assert!(matches!(res, Err(Error::Vetoed(..))));
+
+        let res = pipeline.to_event(EventBuilder::new_text_note("nsec1 is a prefix", []), &keys);
+        assert!(res.is_ok());
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_pipeline_pow() {
+        let keys = Keys::generate();
+        let pipeline =
+            EventBuilderPipeline::new().hook(ExpirationHook::new(Duration::from_secs(60)));
+
+        let event = pipeline
+            .to_pow_event(EventBuilder::new_text_note("hello", []), &keys, 8)
+            .unwrap();
+        assert!(event.verify().is_ok());
+        assert!(nip13::get_leading_zero_bits(event.id.as_bytes()) >= 8);
+        assert!(event.tags.iter().any(|t| t.kind() == TagKind::Expiration));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_pipeline_pow_replace_nonce() {
+        let keys = Keys::generate();
+        let pipeline = EventBuilderPipeline::new().hook(|event: &mut UnsignedEvent| {
+            event.tags.push(Tag::POW {
+                nonce: 1,
+                difficulty: 0,
+            });
+            Ok(())
+        });
+
+        let event = pipeline
+            .to_pow_event(EventBuilder::new_text_note("hello", []), &keys, 8)
+            .unwrap();
+        let nonces: Vec<&Tag> = event
+            .tags
+            .iter()
+            .filter(|t| t.kind() == TagKind::Nonce)
+            .collect();
+        assert_eq!(nonces.len(), 1);
+        assert!(nip13::get_leading_zero_bits(event.id.as_bytes()) >= 8);
+        assert!(event.verify().is_ok());
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_pipeline_pow_keeps_processed_event() {
+        let keys = Keys::generate();
+        let created_at = crate::Timestamp::from(1_700_000_000);
+        let pipeline = EventBuilderPipeline::new().hook(move |event: &mut UnsignedEvent| {
+            event.created_at = created_at;
+            Ok(())
+        });
+
+        let event = pipeline
+            .to_pow_event(EventBuilder::new_text_note("hello", []), &keys, 8)
+            .unwrap();
+        assert_eq!(event.created_at, created_at);
+        assert!(event.verify().is_ok());
+
+        let opts = PowOptions::new().threads(2);
+        let event = pipeline
+            .to_pow_event_with_opts(EventBuilder::new_text_note("hello", []), &keys, 8, &opts)
+            .unwrap();
+        assert_eq!(event.created_at, created_at);
+        assert!(nip13::get_leading_zero_bits(event.id.as_bytes()) >= 8);
+        assert!(event.verify().is_ok());
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_pipeline_delegation() {
+        use core::str::FromStr;
+
+        use crate::nips::nip26::Conditions;
+        use crate::Kind;
+
+        let delegator_keys = Keys::generate();
+        let keys = Keys::generate();
+        let conditions = Conditions::from_str("kind=1").unwrap();
+        let tag = DelegationTag::new(&delegator_keys, keys.public_key(), conditions).unwrap();
+        let pipeline = EventBuilderPipeline::new().hook(DelegationHook::new(tag));
+
+        let event = pipeline
+            .to_event(EventBuilder::new_text_note("hello", []), &keys)
+            .unwrap();
+        assert!(event.verify().is_ok());
+        assert_eq!(event.author(), delegator_keys.public_key());
+
+        let res = pipeline.to_event(EventBuilder::new(Kind::Reaction, "+", []), &keys);
+        assert!(matches!(res, Err(Error::Vetoed(..))));
+    }
+}
@@ -0,0 +1,494 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Proof of Work engine
+//!
+//! Multi-threaded NIP13 mining, with progress report, cancellation and deadline.
+//!
+//! <https://github.com/nostr-protocol/nips/blob/master/13.md>
+
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt;
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use core::time::Duration;
+use std::sync::mpsc::{sync_channel, RecvTimeoutError};
+use std::thread;
+
+use bitcoin::secp256k1::XOnlyPublicKey;
+
+use super::{EventId, Kind, Tag, UnsignedEvent};
+use crate::nips::nip13;
+use crate::types::time::Instant;
+use crate::Timestamp;
+
+const DEFAULT_PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Proof of Work error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// Mining cancelled
+    Cancelled {
+        /// Nonce to use to resume mining
+        resume_nonce: u128,
+        /// Timestamp of the mined event, to use to resume mining
+        created_at: Timestamp,
+    },
+    /// Deadline exceeded
+    DeadlineExceeded {
+        /// Nonce to use to resume mining
+        resume_nonce: u128,
+        /// Timestamp of the mined event, to use to resume mining
+        created_at: Timestamp,
+    },
+    /// Thread Join failed
+    JoinHandleError,
+}
+
+impl std::error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Cancelled {
+                resume_nonce,
+                created_at,
+            } => write!(
+                f,
+                "mining cancelled (resume nonce: {resume_nonce}, created at: {created_at})"
+            ),
+            Self::DeadlineExceeded {
+                resume_nonce,
+                created_at,
+            } => write!(
+                f,
+                "mining deadline exceeded (resume nonce: {resume_nonce}, created at: {created_at})"
+            ),
+            Self::JoinHandleError => write!(f, "impossible to join threads"),
+        }
+    }
+}
+
+/// Cancellation token
+///
+/// Clones share the same state: cancelling one of them stop the mining.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    /// New [`CancellationToken`]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Check if cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+/// Mining progress
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PowProgress {
+    /// Number of hashes computed
+    pub attempts: u64,
+    /// Time elapsed since the start of the mining
+    pub elapsed: Duration,
+    /// Hashes per second
+    pub hash_rate: f64,
+    /// Expected remaining time, at current hash rate
+    ///
+    /// Mining is memoryless: the expected remaining time doesn't decrease with the elapsed time.
+    pub eta: Option<Duration>,
+}
+
+/// Progress callback
+pub type PowProgressCallback = Arc<dyn Fn(PowProgress) + Send + Sync>;
+
+/// Proof of Work options
+#[derive(Clone)]
+pub struct PowOptions {
+    threads: usize,
+    start_nonce: u128,
+    created_at: Option<Timestamp>,
+    deadline: Option<Duration>,
+    cancellation: Option<CancellationToken>,
+    progress: Option<PowProgressCallback>,
+    progress_interval: Duration,
+}
+
+impl fmt::Debug for PowOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PowOptions")
+            .field("threads", &self.threads)
+            .field("start_nonce", &self.start_nonce)
+            .field("created_at", &self.created_at)
+            .field("deadline", &self.deadline)
+            .field("cancellation", &self.cancellation)
+            .field("progress_interval", &self.progress_interval)
+            .finish()
+    }
+}
+
+impl Default for PowOptions {
+    fn default() -> Self {
+        Self {
+            threads: thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1),
+            start_nonce: 0,
+            created_at: None,
+            deadline: None,
+            cancellation: None,
+            progress: None,
+            progress_interval: DEFAULT_PROGRESS_INTERVAL,
+        }
+    }
+}
+
+impl PowOptions {
+    /// New default [`PowOptions`]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of threads (default: available parallelism)
+    pub fn threads(self, threads: usize) -> Self {
+        Self {
+            threads: threads.max(1),
+            ..self
+        }
+    }
+
+    /// Resume mining from a known nonce (see [`Error::Cancelled`] and [`Error::DeadlineExceeded`])
+    ///
+    /// The nonces already tried are valid only for the same `created_at`: set it with [`PowOptions::created_at`].
+    pub fn start_nonce(self, start_nonce: u128) -> Self {
+        Self {
+            start_nonce,
+            ..self
+        }
+    }
+
+    /// Mine the event with this `created_at`, to resume a previous mining
+    pub fn created_at(self, created_at: Timestamp) -> Self {
+        Self {
+            created_at: Some(created_at),
+            ..self
+        }
+    }
+
+    /// Stop mining after `deadline`
+    pub fn deadline(self, deadline: Duration) -> Self {
+        Self {
+            deadline: Some(deadline),
+            ..self
+        }
+    }
+
+    /// Set [`CancellationToken`]
+    pub fn cancellation(self, token: CancellationToken) -> Self {
+        Self {
+            cancellation: Some(token),
+            ..self
+        }
+    }
+
+    /// Progress callback, called on the calling thread every `progress_interval` (default: 1 sec)
+    pub fn progress<F>(self, callback: F) -> Self
+    where
+        F: Fn(PowProgress) + Send + Sync + 'static,
+    {
+        Self {
+            progress: Some(Arc::new(callback)),
+            ..self
+        }
+    }
+
+    /// Progress callback interval
+    pub fn progress_interval(self, interval: Duration) -> Self {
+        Self {
+            progress_interval: interval,
+            ..self
+        }
+    }
+}
+
+fn progress(attempts: u64, elapsed: Duration, difficulty: u8) -> PowProgress {
+    let secs: f64 = elapsed.as_secs_f64();
+    let hash_rate: f64 = if secs > 0.0 {
+        attempts as f64 / secs
+    } else {
+        0.0
+    };
+    let eta: Option<Duration> = if hash_rate > 0.0 {
+        let secs: f64 = 2f64.powi(difficulty as i32) / hash_rate;
+        if secs.is_finite() && secs < u64::MAX as f64 {
+            Some(Duration::from_secs_f64(secs))
+        } else {
+            None
+        }
+    } else {
+        None
+    };
+    PowProgress {
+        attempts,
+        elapsed,
+        hash_rate,
+        eta,
+    }
+}
+
+/// Mine an [`UnsignedEvent`] with the requested `difficulty`
+///
+/// Each thread tries the nonces `start_nonce + i`, `start_nonce + i + threads`, ...
+/// The `created_at` doesn't change while mining and is replaced by [`PowOptions::created_at`], if set.
+pub fn mine(
+    pubkey: XOnlyPublicKey,
+    created_at: Timestamp,
+    kind: Kind,
+    tags: Vec<Tag>,
+    content: String,
+    difficulty: u8,
+    opts: &PowOptions,
+) -> Result<UnsignedEvent, Error> {
+    let threads: usize = opts.threads.max(1);
+    let stride: u128 = threads as u128;
+    let start_nonce: u128 = opts.start_nonce;
+    let created_at: Timestamp = opts.created_at.unwrap_or(created_at);
+    let now = Instant::now();
+
+    let (tx, rx) = sync_channel::<UnsignedEvent>(threads);
+    let stop = Arc::new(AtomicBool::new(false));
+    let attempts = Arc::new(AtomicU64::new(0));
+    let mut handles = Vec::with_capacity(threads);
+
+    for i in 0..threads {
+        let tx = tx.clone();
+        let stop = stop.clone();
+        let attempts = attempts.clone();
+        let mut tags = tags.clone();
+        let content = content.clone();
+        let handle = thread::spawn(move || {
+            // Next nonce to try
+            let mut nonce: u128 = start_nonce.wrapping_add(i as u128);
+            loop {
+                if stop.load(Ordering::Relaxed) {
+                    return nonce;
+                }
+
+                tags.push(Tag::POW { nonce, difficulty });
+
+                let id = EventId::new(&pubkey, created_at, &kind, &tags, &content);
+                attempts.fetch_add(1, Ordering::Relaxed);
+
+                if nip13::get_leading_zero_bits(id.inner()) >= difficulty {
+                    let _ = tx.try_send(UnsignedEvent {
+                        id,
+                        pubkey,
+                        created_at,
+                        kind,
+                        tags,
+                        content,
+                    });
+                    stop.store(true, Ordering::SeqCst);
+                    return nonce;
+                }
+
+                tags.pop();
+                nonce = nonce.wrapping_add(stride);
+            }
+        });
+        handles.push(handle);
+    }
+
+    drop(tx);
+
+    let res: Result<UnsignedEvent, Error> = loop {
+        let interval: Duration = match opts.deadline {
+            Some(deadline) => opts
+                .progress_interval
+                .min(deadline.saturating_sub(now.elapsed())),
+            None => opts.progress_interval,
+        };
+
+        match rx.recv_timeout(interval) {
+            Ok(event) => break Ok(event),
+            Err(RecvTimeoutError::Timeout) => {
+                if let Some(callback) = &opts.progress {
+                    callback(progress(
+                        attempts.load(Ordering::Relaxed),
+                        now.elapsed(),
+                        difficulty,
+                    ));
+                }
+
+                if let Some(token) = &opts.cancellation {
+                    if token.is_cancelled() {
+                        break Err(Error::Cancelled {
+                            resume_nonce: 0,
+                            created_at,
+                        });
+                    }
+                }
+
+                if let Some(deadline) = opts.deadline {
+                    if now.elapsed() >= deadline {
+                        break Err(Error::DeadlineExceeded {
+                            resume_nonce: 0,
+                            created_at,
+                        });
+                    }
+                }
+            }
+            Err(RecvTimeoutError::Disconnected) => break Err(Error::JoinHandleError),
+        }
+    };
+
+    stop.store(true, Ordering::SeqCst);
+
+    // Smallest nonce not yet tried by all the threads
+    let mut resume_nonce: Option<u128> = None;
+    for handle in handles.into_iter() {
+        let next: u128 = handle.join().map_err(|_| Error::JoinHandleError)?;
+        resume_nonce = Some(resume_nonce.map_or(next, |n| n.min(next)));
+    }
+    let resume_nonce: u128 = resume_nonce.unwrap_or(opts.start_nonce);
+
+    let attempts: u64 = attempts.load(Ordering::Relaxed);
+    tracing::debug!(
+        "{attempts} iterations in {} ms with {threads} threads. Avg rate {} hashes/second",
+        now.elapsed().as_millis(),
+        attempts as u128 * 1000 / core::cmp::max(1, now.elapsed().as_millis())
+    );
+
+    match res {
+        Ok(event) => Ok(event),
+        Err(Error::Cancelled { .. }) => Err(Error::Cancelled {
+            resume_nonce,
+            created_at,
+        }),
+        Err(Error::DeadlineExceeded { .. }) => Err(Error::DeadlineExceeded {
+            resume_nonce,
+            created_at,
+        }),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Keys;
+
+    #[test]
+    fn test_mine() {
+        let keys = Keys::generate();
+        let opts = PowOptions::new().threads(2);
+        let event = mine(
+            keys.public_key(),
+            Timestamp::now(),
+            Kind::TextNote,
+            Vec::new(),
+            String::from("hello"),
+            8,
+            &opts,
+        )
+        .unwrap();
+        assert!(nip13::get_leading_zero_bits(event.id.inner()) >= 8);
+        assert!(event.sign(&keys).is_ok());
+    }
+
+    #[test]
+    fn test_mine_cancelled() {
+        let keys = Keys::generate();
+        let token = CancellationToken::new();
+        token.cancel();
+        let opts = PowOptions::new()
+            .threads(2)
+            .start_nonce(10)
+            .cancellation(token)
+            .progress_interval(Duration::from_millis(10));
+        let res = mine(
+            keys.public_key(),
+            Timestamp::now(),
+            Kind::TextNote,
+            Vec::new(),
+            String::from("hello"),
+            255,
+            &opts,
+        );
+        match res {
+            Err(Error::Cancelled { resume_nonce, .. }) => assert!(resume_nonce >= 10),
+            _ => panic!("expected cancellation"),
+        }
+    }
+
+    #[test]
+    fn test_mine_deadline() {
+        let keys = Keys::generate();
+        let opts = PowOptions::new()
+            .threads(1)
+            .deadline(Duration::from_millis(50));
+        let res = mine(
+            keys.public_key(),
+            Timestamp::now(),
+            Kind::TextNote,
+            Vec::new(),
+            String::from("hello"),
+            255,
+            &opts,
+        );
+        assert!(matches!(res, Err(Error::DeadlineExceeded { .. })));
+    }
+
+    #[test]
+    fn test_mine_resume() {
+        let keys = Keys::generate();
+        let opts = PowOptions::new()
+            .threads(1)
+            .deadline(Duration::from_millis(20));
+        let res = mine(
+            keys.public_key(),
+            Timestamp::now(),
+            Kind::TextNote,
+            Vec::new(),
+            String::from("hello"),
+            255,
+            &opts,
+        );
+        let (resume_nonce, created_at) = match res {
+            Err(Error::DeadlineExceeded {
+                resume_nonce,
+                created_at,
+            }) => (resume_nonce, created_at),
+            _ => panic!("expected deadline exceeded"),
+        };
+
+        let opts = PowOptions::new()
+            .threads(2)
+            .start_nonce(resume_nonce)
+            .created_at(created_at);
+        let event = mine(
+            keys.public_key(),
+            Timestamp::now() + 60u64,
+            Kind::TextNote,
+            Vec::new(),
+            String::from("hello"),
+            8,
+            &opts,
+        )
+        .unwrap();
+        assert_eq!(event.created_at, created_at);
+        assert!(nip13::get_leading_zero_bits(event.id.inner()) >= 8);
+    }
+}
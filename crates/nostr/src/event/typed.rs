@@ -0,0 +1,719 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Typed event views
+//!
+//! Strongly typed wrappers, with parsed tags, for the supported [`Kind`]s.
+//! Every view can be converted back to an [`EventBuilder`].
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use bitcoin::secp256k1::XOnlyPublicKey;
+
+use super::{Event, EventBuilder, EventId, Kind, Marker, Tag, TagKind};
+use crate::nips::nip53::{self, LiveEvent};
+use crate::nips::nip94::{self, FileMetadata};
+use crate::nips::{nip01, nip65};
+use crate::{ImageDimensions, JsonUtil, RelayMetadata, Timestamp, UncheckedUrl};
+
+const MENTION_MARKER: &str = "mention";
+
+/// Typed event error
+#[derive(Debug)]
+pub enum Error {
+    /// Kind not supported
+    UnsupportedKind(Kind),
+    /// Tag not found
+    TagNotFound(TagKind),
+    /// Event error
+    Event(super::Error),
+    /// NIP53 error
+    NIP53(nip53::Error),
+    /// NIP94 error
+    NIP94(nip94::Error),
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedKind(kind) => write!(f, "Unsupported kind: {kind}"),
+            Self::TagNotFound(kind) => write!(f, "Tag not found: {kind}"),
+            Self::Event(e) => write!(f, "Event: {e}"),
+            Self::NIP53(e) => write!(f, "NIP53: {e}"),
+            Self::NIP94(e) => write!(f, "NIP94: {e}"),
+        }
+    }
+}
+
+impl From<super::Error> for Error {
+    fn from(e: super::Error) -> Self {
+        Self::Event(e)
+    }
+}
+
+impl From<nip53::Error> for Error {
+    fn from(e: nip53::Error) -> Self {
+        Self::NIP53(e)
+    }
+}
+
+impl From<nip94::Error> for Error {
+    fn from(e: nip94::Error) -> Self {
+        Self::NIP94(e)
+    }
+}
+
+/// Event pointer: event ID and optional relay URL
+pub type EventPointer = (EventId, Option<UncheckedUrl>);
+
+/// Text note
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/10.md>
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextNote {
+    /// Content
+    pub content: String,
+    /// Root of the thread
+    pub root: Option<EventPointer>,
+    /// Event directly replied to, if different from `root`
+    pub reply: Option<EventPointer>,
+    /// Mentioned events
+    pub mentions: Vec<EventPointer>,
+    /// Mentioned or involved public keys
+    pub public_keys: Vec<XOnlyPublicKey>,
+    /// Other tags
+    pub tags: Vec<Tag>,
+}
+
+impl TextNote {
+    /// Check if it's a reply
+    pub fn is_reply(&self) -> bool {
+        self.root.is_some() || self.reply.is_some()
+    }
+
+    /// Get the ID of the event directly replied to
+    pub fn replied_event_id(&self) -> Option<EventId> {
+        self.reply
+            .as_ref()
+            .or(self.root.as_ref())
+            .map(|(id, ..)| *id)
+    }
+}
+
+impl TryFrom<&Event> for TextNote {
+    type Error = Error;
+
+    fn try_from(event: &Event) -> Result<Self, Self::Error> {
+        let mut root: Option<EventPointer> = None;
+        let mut reply: Option<EventPointer> = None;
+        let mut mentions: Vec<EventPointer> = Vec::new();
+        let mut positional: Vec<EventPointer> = Vec::new();
+        let mut public_keys: Vec<XOnlyPublicKey> = Vec::new();
+        let mut tags: Vec<Tag> = Vec::new();
+
+        for tag in event.tags.iter() {
+            match tag {
+                Tag::Event {
+                    event_id,
+                    relay_url,
+                    marker,
+                } => {
+                    let pointer: EventPointer = (*event_id, relay_url.clone());
+                    match marker {
+                        Some(Marker::Root) => root = Some(pointer),
+                        Some(Marker::Reply) => reply = Some(pointer),
+                        Some(Marker::Custom(m)) if m == MENTION_MARKER => mentions.push(pointer),
+                        Some(Marker::Custom(..)) => tags.push(tag.clone()),
+                        None => positional.push(pointer),
+                    }
+                }
+                Tag::PublicKey {
+                    public_key,
+                    relay_url: None,
+                    alias: None,
+                } => public_keys.push(*public_key),
+                _ => tags.push(tag.clone()),
+            }
+        }
+
+        // Deprecated positional `e` tags: first is the root, last is the reply, others are mentions
+        if root.is_none() && reply.is_none() && !positional.is_empty() {
+            let mut positional = positional.into_iter();
+            root = positional.next();
+            reply = positional.next_back();
+            mentions.extend(positional);
+        } else {
+            mentions.extend(positional);
+        }
+
+        Ok(Self {
+            content: event.content.clone(),
+            root,
+            reply,
+            mentions,
+            public_keys,
+            tags,
+        })
+    }
+}
+
+impl From<TextNote> for EventBuilder {
+    fn from(note: TextNote) -> Self {
+        let TextNote {
+            content,
+            root,
+            reply,
+            mentions,
+            public_keys,
+            tags: others,
+        } = note;
+
+        let mut tags: Vec<Tag> = Vec::new();
+
+        let markers = root
+            .map(|p| (p, Marker::Root))
+            .into_iter()
+            .chain(reply.map(|p| (p, Marker::Reply)))
+            .chain(
+                mentions
+                    .into_iter()
+                    .map(|p| (p, Marker::Custom(String::from(MENTION_MARKER)))),
+            );
+        for ((event_id, relay_url), marker) in markers {
+            tags.push(Tag::Event {
+                event_id,
+                relay_url,
+                marker: Some(marker),
+            });
+        }
+
+        tags.extend(public_keys.into_iter().map(Tag::public_key));
+        tags.extend(others);
+
+        Self::new_text_note(content, tags)
+    }
+}
+
+/// Reaction
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/25.md>
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reaction {
+    /// Content (`+`, `-` or emoji)
+    pub content: String,
+    /// Target event
+    pub event_id: EventId,
+    /// Target event relay URL
+    pub relay_url: Option<UncheckedUrl>,
+    /// Target event author
+    pub public_key: Option<XOnlyPublicKey>,
+    /// NIP30 custom emoji (shortcode and image URL)
+    pub emoji: Option<(String, UncheckedUrl)>,
+    /// Other tags
+    pub tags: Vec<Tag>,
+}
+
+impl Reaction {
+    /// Check if it's a like (`+` or empty content)
+    pub fn is_like(&self) -> bool {
+        self.content.is_empty() || self.content == "+"
+    }
+
+    /// Check if it's a dislike (`-` content)
+    pub fn is_dislike(&self) -> bool {
+        self.content == "-"
+    }
+}
+
+impl TryFrom<&Event> for Reaction {
+    type Error = Error;
+
+    fn try_from(event: &Event) -> Result<Self, Self::Error> {
+        // The target event and its author are the last `e` and `p` tags
+        let event_position: Option<usize> = event
+            .tags
+            .iter()
+            .rposition(|t| matches!(t, Tag::Event { .. }));
+        let public_key_position: Option<usize> = event
+            .tags
+            .iter()
+            .rposition(|t| matches!(t, Tag::PublicKey { .. }));
+
+        let mut target: Option<EventPointer> = None;
+        let mut public_key: Option<XOnlyPublicKey> = None;
+        let mut emoji: Option<(String, UncheckedUrl)> = None;
+        let mut tags: Vec<Tag> = Vec::new();
+
+        for (index, tag) in event.tags.iter().enumerate() {
+            match tag {
+                Tag::Event {
+                    event_id,
+                    relay_url,
+                    ..
+                } if Some(index) == event_position => target = Some((*event_id, relay_url.clone())),
+                Tag::PublicKey { public_key: pk, .. } if Some(index) == public_key_position => {
+                    public_key = Some(*pk)
+                }
+                Tag::Emoji { shortcode, url } if emoji.is_none() => {
+                    emoji = Some((shortcode.clone(), url.clone()))
+                }
+                _ => tags.push(tag.clone()),
+            }
+        }
+
+        let (event_id, relay_url) = target.ok_or(Error::TagNotFound(TagKind::E))?;
+
+        Ok(Self {
+            content: event.content.clone(),
+            event_id,
+            relay_url,
+            public_key,
+            emoji,
+            tags,
+        })
+    }
+}
+
+impl From<Reaction> for EventBuilder {
+    fn from(reaction: Reaction) -> Self {
+        let Reaction {
+            content,
+            event_id,
+            relay_url,
+            public_key,
+            emoji,
+            tags: others,
+        } = reaction;
+
+        let mut tags: Vec<Tag> = others;
+        tags.push(Tag::Event {
+            event_id,
+            relay_url,
+            marker: None,
+        });
+        if let Some(public_key) = public_key {
+            tags.push(Tag::public_key(public_key));
+        }
+        if let Some((shortcode, url)) = emoji {
+            tags.push(Tag::Emoji { shortcode, url });
+        }
+
+        Self::new(Kind::Reaction, content, tags)
+    }
+}
+
+/// Zap receipt
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/57.md>
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ZapReceipt {
+    /// Bolt11 invoice
+    pub bolt11: String,
+    /// Payment preimage
+    pub preimage: Option<String>,
+    /// Zap request (`description` tag)
+    pub zap_request: Event,
+    /// Other tags
+    pub tags: Vec<Tag>,
+}
+
+impl ZapReceipt {
+    /// Amount requested by the zap request (millisats)
+    ///
+    /// **The amount is NOT verified against the bolt11 invoice!**
+    pub fn amount(&self) -> Option<u64> {
+        self.zap_request.tags.iter().find_map(|t| match t {
+            Tag::Amount { millisats, .. } => Some(*millisats),
+            _ => None,
+        })
+    }
+
+    /// Zap sender
+    pub fn sender(&self) -> XOnlyPublicKey {
+        self.zap_request.pubkey
+    }
+
+    /// Zap recipient
+    pub fn recipient(&self) -> Option<XOnlyPublicKey> {
+        self.zap_request.public_keys().next().copied()
+    }
+
+    /// Zapped event
+    pub fn event_id(&self) -> Option<EventId> {
+        self.zap_request.event_ids().next().copied()
+    }
+}
+
+impl TryFrom<&Event> for ZapReceipt {
+    type Error = Error;
+
+    fn try_from(event: &Event) -> Result<Self, Self::Error> {
+        let mut bolt11: Option<String> = None;
+        let mut preimage: Option<String> = None;
+        let mut zap_request: Option<Event> = None;
+        let mut tags: Vec<Tag> = Vec::new();
+
+        for tag in event.tags.iter() {
+            match tag {
+                Tag::Bolt11(b) => bolt11 = Some(b.clone()),
+                Tag::Preimage(p) => preimage = Some(p.clone()),
+                Tag::Description(d) => zap_request = Some(Event::from_json(d)?),
+                _ => tags.push(tag.clone()),
+            }
+        }
+
+        Ok(Self {
+            bolt11: bolt11.ok_or(Error::TagNotFound(TagKind::Bolt11))?,
+            preimage,
+            zap_request: zap_request.ok_or(Error::TagNotFound(TagKind::Description))?,
+            tags,
+        })
+    }
+}
+
+impl From<ZapReceipt> for EventBuilder {
+    fn from(receipt: ZapReceipt) -> Self {
+        let ZapReceipt {
+            bolt11,
+            preimage,
+            zap_request,
+            tags: others,
+        } = receipt;
+
+        let mut tags: Vec<Tag> = vec![Tag::Bolt11(bolt11), Tag::Description(zap_request.as_json())];
+        if let Some(preimage) = preimage {
+            tags.push(Tag::Preimage(preimage));
+        }
+        tags.extend(others);
+
+        Self::new(Kind::ZapReceipt, "", tags)
+    }
+}
+
+/// Long-form content
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/23.md>
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LongForm {
+    /// Content (markdown)
+    pub content: String,
+    /// Identifier (`d` tag)
+    pub identifier: String,
+    /// Title
+    pub title: Option<String>,
+    /// Summary
+    pub summary: Option<String>,
+    /// Image
+    pub image: Option<(UncheckedUrl, Option<ImageDimensions>)>,
+    /// First publication timestamp
+    pub published_at: Option<Timestamp>,
+    /// Hashtags
+    pub hashtags: Vec<String>,
+    /// Other tags
+    pub tags: Vec<Tag>,
+}
+
+impl TryFrom<&Event> for LongForm {
+    type Error = Error;
+
+    fn try_from(event: &Event) -> Result<Self, Self::Error> {
+        let mut identifier: Option<String> = None;
+        let mut title: Option<String> = None;
+        let mut summary: Option<String> = None;
+        let mut image: Option<(UncheckedUrl, Option<ImageDimensions>)> = None;
+        let mut published_at: Option<Timestamp> = None;
+        let mut hashtags: Vec<String> = Vec::new();
+        let mut tags: Vec<Tag> = Vec::new();
+
+        for tag in event.tags.iter() {
+            match tag {
+                Tag::Identifier(d) if identifier.is_none() => identifier = Some(d.clone()),
+                Tag::Title(t) => title = Some(t.clone()),
+                Tag::Summary(s) => summary = Some(s.clone()),
+                Tag::Image(url, dim) => image = Some((url.clone(), *dim)),
+                Tag::PublishedAt(timestamp) => published_at = Some(*timestamp),
+                Tag::Hashtag(hashtag) => hashtags.push(hashtag.clone()),
+                _ => tags.push(tag.clone()),
+            }
+        }
+
+        Ok(Self {
+            content: event.content.clone(),
+            identifier: identifier.ok_or(Error::TagNotFound(TagKind::D))?,
+            title,
+            summary,
+            image,
+            published_at,
+            hashtags,
+            tags,
+        })
+    }
+}
+
+impl From<LongForm> for EventBuilder {
+    fn from(article: LongForm) -> Self {
+        let LongForm {
+            content,
+            identifier,
+            title,
+            summary,
+            image,
+            published_at,
+            hashtags,
+            tags: others,
+        } = article;
+
+        let mut tags: Vec<Tag> = vec![Tag::Identifier(identifier)];
+        if let Some(title) = title {
+            tags.push(Tag::Title(title));
+        }
+        if let Some(summary) = summary {
+            tags.push(Tag::Summary(summary));
+        }
+        if let Some((url, dim)) = image {
+            tags.push(Tag::Image(url, dim));
+        }
+        if let Some(published_at) = published_at {
+            tags.push(Tag::PublishedAt(published_at));
+        }
+        tags.extend(hashtags.into_iter().map(Tag::Hashtag));
+        tags.extend(others);
+
+        Self::long_form_text_note(content, tags)
+    }
+}
+
+/// Typed event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypedEvent {
+    /// Text note
+    TextNote(TextNote),
+    /// Reaction
+    Reaction(Reaction),
+    /// Zap receipt
+    ZapReceipt(ZapReceipt),
+    /// Long-form content
+    LongForm(LongForm),
+    /// Live event
+    LiveEvent(Box<LiveEvent>),
+    /// File metadata
+    FileMetadata {
+        /// Description (event content)
+        description: String,
+        /// File metadata
+        metadata: FileMetadata,
+    },
+    /// Relay list
+    RelayList(Vec<(UncheckedUrl, Option<RelayMetadata>)>),
+}
+
+impl TypedEvent {
+    /// Get [`Kind`]
+    pub fn kind(&self) -> Kind {
+        match self {
+            Self::TextNote(..) => Kind::TextNote,
+            Self::Reaction(..) => Kind::Reaction,
+            Self::ZapReceipt(..) => Kind::ZapReceipt,
+            Self::LongForm(..) => Kind::LongFormTextNote,
+            Self::LiveEvent(..) => Kind::LiveEvent,
+            Self::FileMetadata { .. } => Kind::FileMetadata,
+            Self::RelayList(..) => Kind::RelayList,
+        }
+    }
+
+    /// Get NIP01 [`Coordinate`](nip01::Coordinate), if the event is parameterized replaceable
+    pub fn coordinate(&self, public_key: XOnlyPublicKey) -> Option<nip01::Coordinate> {
+        match self {
+            Self::LongForm(article) => Some(
+                nip01::Coordinate::new(self.kind(), public_key)
+                    .identifier(article.identifier.clone()),
+            ),
+            Self::LiveEvent(live_event) => Some(
+                nip01::Coordinate::new(self.kind(), public_key).identifier(live_event.id.clone()),
+            ),
+            _ => None,
+        }
+    }
+}
+
+impl TryFrom<&Event> for TypedEvent {
+    type Error = Error;
+
+    fn try_from(event: &Event) -> Result<Self, Self::Error> {
+        match event.kind {
+            Kind::TextNote => Ok(Self::TextNote(TextNote::try_from(event)?)),
+            Kind::Reaction => Ok(Self::Reaction(Reaction::try_from(event)?)),
+            Kind::ZapReceipt => Ok(Self::ZapReceipt(ZapReceipt::try_from(event)?)),
+            Kind::LongFormTextNote => Ok(Self::LongForm(LongForm::try_from(event)?)),
+            Kind::LiveEvent => Ok(Self::LiveEvent(Box::new(LiveEvent::try_from(
+                event.tags.clone(),
+            )?))),
+            Kind::FileMetadata => Ok(Self::FileMetadata {
+                description: event.content.clone(),
+                metadata: FileMetadata::try_from(event.tags.clone())?,
+            }),
+            Kind::RelayList => Ok(Self::RelayList(nip65::extract_relay_list(event))),
+            kind => Err(Error::UnsupportedKind(kind)),
+        }
+    }
+}
+
+impl From<TypedEvent> for EventBuilder {
+    fn from(typed: TypedEvent) -> Self {
+        match typed {
+            TypedEvent::TextNote(note) => note.into(),
+            TypedEvent::Reaction(reaction) => reaction.into(),
+            TypedEvent::ZapReceipt(receipt) => receipt.into(),
+            TypedEvent::LongForm(article) => article.into(),
+            TypedEvent::LiveEvent(live_event) => Self::live_event(*live_event),
+            TypedEvent::FileMetadata {
+                description,
+                metadata,
+            } => Self::file_metadata(description, metadata),
+            TypedEvent::RelayList(list) => Self::relay_list(list),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Keys;
+
+    #[cfg(feature = "std")]
+    fn round_trip(event: &Event, keys: &Keys) -> Event {
+        let typed: TypedEvent = event.as_typed().unwrap();
+        let builder: EventBuilder = typed.into();
+        builder.to_event(keys).unwrap()
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_text_note() {
+        let keys = Keys::generate();
+        let root =
+            EventId::from_hex("b3e392b11f5d4f28321cedd09303a748acfd0487aea5a7450b3481c60b6e4f87")
+                .unwrap();
+        let reply =
+            EventId::from_hex("2be17aa3031bdcb006f0fce80c146dea9c1c0268b0af2398bb673365c6444d45")
+                .unwrap();
+        let mention =
+            EventId::from_hex("378f145897eea948952674269945e88612420db35791784abf0616b4fed56ef7")
+                .unwrap();
+
+        // Positional `e` tags
+        let event = EventBuilder::new_text_note(
+            "reply",
+            [
+                Tag::event(root),
+                Tag::event(mention),
+                Tag::event(reply),
+                Tag::public_key(keys.public_key()),
+                Tag::Hashtag(String::from("nostr")),
+            ],
+        )
+        .to_event(&keys)
+        .unwrap();
+
+        let note = match event.as_typed().unwrap() {
+            TypedEvent::TextNote(note) => note,
+            _ => panic!("expected text note"),
+        };
+        assert_eq!(note.root, Some((root, None)));
+        assert_eq!(note.reply, Some((reply, None)));
+        assert_eq!(note.mentions, vec![(mention, None)]);
+        assert_eq!(note.public_keys, vec![keys.public_key()]);
+        assert_eq!(note.tags, vec![Tag::Hashtag(String::from("nostr"))]);
+        assert_eq!(note.replied_event_id(), Some(reply));
+
+        // Round-trip to marked `e` tags
+        let event = round_trip(&event, &keys);
+        assert_eq!(event.as_typed().unwrap(), TypedEvent::TextNote(note));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_reaction() {
+        let keys = Keys::generate();
+        let event_id =
+            EventId::from_hex("b3e392b11f5d4f28321cedd09303a748acfd0487aea5a7450b3481c60b6e4f87")
+                .unwrap();
+        let event = EventBuilder::new_reaction(event_id, keys.public_key(), "+")
+            .to_event(&keys)
+            .unwrap();
+
+        let reaction = match event.as_typed().unwrap() {
+            TypedEvent::Reaction(reaction) => reaction,
+            _ => panic!("expected reaction"),
+        };
+        assert_eq!(reaction.event_id, event_id);
+        assert_eq!(reaction.public_key, Some(keys.public_key()));
+        assert!(reaction.is_like());
+
+        let new_event = round_trip(&event, &keys);
+        assert_eq!(new_event.tags, event.tags);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_long_form() {
+        let keys = Keys::generate();
+        let event = EventBuilder::long_form_text_note(
+            "Lorem ipsum",
+            [
+                Tag::Identifier(String::from("lorem-ipsum")),
+                Tag::Title(String::from("Lorem Ipsum")),
+                Tag::PublishedAt(Timestamp::from(1296962229)),
+                Tag::Hashtag(String::from("placeholder")),
+            ],
+        )
+        .to_event(&keys)
+        .unwrap();
+
+        let article = match event.as_typed().unwrap() {
+            TypedEvent::LongForm(article) => article,
+            _ => panic!("expected long-form content"),
+        };
+        assert_eq!(article.identifier, "lorem-ipsum");
+        assert_eq!(article.title.as_deref(), Some("Lorem Ipsum"));
+        assert_eq!(article.published_at, Some(Timestamp::from(1296962229)));
+        assert_eq!(article.hashtags, vec![String::from("placeholder")]);
+
+        let new_event = round_trip(&event, &keys);
+        assert_eq!(new_event.tags, event.tags);
+        assert_eq!(new_event.content, event.content);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_relay_list_and_unsupported_kind() {
+        let keys = Keys::generate();
+        let list = vec![
+            (UncheckedUrl::from("wss://relay.damus.io"), None),
+            (
+                UncheckedUrl::from("wss://nos.lol"),
+                Some(RelayMetadata::Write),
+            ),
+        ];
+        let event = EventBuilder::relay_list(list.clone())
+            .to_event(&keys)
+            .unwrap();
+        assert_eq!(event.as_typed().unwrap(), TypedEvent::RelayList(list));
+        assert_eq!(round_trip(&event, &keys).tags, event.tags);
+
+        let event = EventBuilder::new(Kind::Custom(123), "", [])
+            .to_event(&keys)
+            .unwrap();
+        assert!(matches!(
+            event.as_typed(),
+            Err(Error::UnsupportedKind(Kind::Custom(123)))
+        ));
+    }
+}
@@ -8,7 +8,6 @@
 //! This module defines the [`Keys`] structure.
 
 use core::fmt;
-#[cfg(feature = "std")]
 use core::str::FromStr;
 
 #[cfg(feature = "std")]
@@ -22,7 +21,6 @@ pub use bitcoin::secp256k1::{
 #[cfg(feature = "std")]
 pub mod vanity;
 
-#[cfg(feature = "std")]
 use crate::nips::nip19::FromBech32;
 #[cfg(feature = "std")]
 use crate::SECP256K1;
@@ -73,7 +71,6 @@ pub trait FromSkStr: Sized {
 }
 
 /// Trait for [`Keys`]
-#[cfg(feature = "std")]
 pub trait FromPkStr: Sized {
     /// Error
     type Err;
@@ -145,6 +142,20 @@ impl Keys {
         }
     }
 
+    /// Init [`Keys`] from `hex` or `bech32` secret key
+    pub fn from_sk_str_with_ctx<C>(secp: &Secp256k1<C>, secret_key: &str) -> Result<Self, Error>
+    where
+        C: Signing,
+    {
+        match SecretKey::from_str(secret_key) {
+            Ok(secret_key) => Ok(Self::new_with_ctx(secp, secret_key)),
+            Err(_) => match SecretKey::from_bech32(secret_key) {
+                Ok(secret_key) => Ok(Self::new_with_ctx(secp, secret_key)),
+                Err(_) => Err(Error::InvalidSecretKey),
+            },
+        }
+    }
+
     /// Initialize with public key only (no secret key).
     pub fn from_public_key(public_key: XOnlyPublicKey) -> Self {
         Self {
@@ -239,17 +250,10 @@ impl FromSkStr for Keys {
 
     /// Init [`Keys`] from `hex` or `bech32` secret key
     fn from_sk_str(secret_key: &str) -> Result<Self, Self::Err> {
-        match SecretKey::from_str(secret_key) {
-            Ok(secret_key) => Ok(Self::new(secret_key)),
-            Err(_) => match SecretKey::from_bech32(secret_key) {
-                Ok(secret_key) => Ok(Self::new(secret_key)),
-                Err(_) => Err(Error::InvalidSecretKey),
-            },
-        }
+        Self::from_sk_str_with_ctx(&SECP256K1, secret_key)
     }
 }
 
-#[cfg(feature = "std")]
 impl FromPkStr for Keys {
     type Err = Error;
 
@@ -275,3 +279,51 @@ impl Drop for Keys {
         self.secret_key = None;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use super::*;
+    use crate::util::TestRng;
+
+    const SECRET_KEY_HEX: &str = "6b911fd37cdf5c81d4c0adb1ab7fa822ed253ab0ad9aa18d77257c88b29b718e";
+    const SECRET_KEY_BECH32: &str =
+        "nsec1dwg3l5mumawgr4xq4kc6klagytkj2w4s4kd2rrthy47g3v5mwx8qwrh7sx";
+    const PUBLIC_KEY_HEX: &str = "385c3a6ec0b9d57a4330dbd6284989be5bd00e41c535f9ca39b6ae7c521b81cd";
+
+    #[test]
+    fn test_keys_from_str() {
+        let secp = Secp256k1::new();
+
+        let keys = Keys::from_sk_str_with_ctx(&secp, SECRET_KEY_HEX).unwrap();
+        assert_eq!(keys.public_key().to_string(), PUBLIC_KEY_HEX);
+
+        let keys = Keys::from_sk_str_with_ctx(&secp, SECRET_KEY_BECH32).unwrap();
+        assert_eq!(keys.public_key().to_string(), PUBLIC_KEY_HEX);
+
+        assert_eq!(
+            Keys::from_sk_str_with_ctx(&secp, "invalid").unwrap_err(),
+            Error::InvalidSecretKey
+        );
+
+        let keys = Keys::from_pk_str(PUBLIC_KEY_HEX).unwrap();
+        assert_eq!(keys.public_key().to_string(), PUBLIC_KEY_HEX);
+        assert_eq!(keys.secret_key().unwrap_err(), Error::SkMissing);
+    }
+
+    #[test]
+    fn test_keys_sign_schnorr() {
+        let secp = Secp256k1::new();
+        let mut rng = TestRng::default();
+
+        let keys = Keys::generate_with_ctx(&secp, &mut rng);
+        let message = Message::from_slice(&[7u8; 32]).unwrap();
+        let sig = keys
+            .sign_schnorr_with_ctx(&secp, &message, &mut rng)
+            .unwrap();
+        assert!(secp
+            .verify_schnorr(&sig, &message, &keys.public_key())
+            .is_ok());
+    }
+}
@@ -54,12 +54,13 @@ pub use self::event::tag::{
     TagIndexValues, TagIndexes, TagKind,
 };
 pub use self::event::{
-    Event, EventBuilder, EventId, Kind, MissingPartialEvent, PartialEvent, UnsignedEvent,
+    Event, EventBuilder, EventBuilderPipeline, EventId, EventRef, Kind, MissingPartialEvent,
+    PartialEvent, TypedEvent, UnsignedEvent,
 };
 pub use self::key::Keys;
 pub use self::message::{
-    Alphabet, ClientMessage, Filter, FiltersMatchEvent, GenericTagValue, RawRelayMessage,
-    RelayMessage, SubscriptionId,
+    Alphabet, ClientMessage, Filter, FiltersMatchEvent, GenericTagValue, MatchableEvent,
+    RawRelayMessage, RelayMessage, SubscriptionId,
 };
 pub use self::nips::nip19::{FromBech32, ToBech32};
 pub use self::types::{ChannelId, Contact, Entity, Metadata, Profile, Timestamp, UncheckedUrl};
@@ -13,7 +13,7 @@ pub mod subscription;
 pub use self::client::ClientMessage;
 pub use self::relay::{RawRelayMessage, RelayMessage};
 pub use self::subscription::{
-    Alphabet, Filter, FiltersMatchEvent, GenericTagValue, SubscriptionId,
+    Alphabet, Filter, FiltersMatchEvent, GenericTagValue, MatchableEvent, SubscriptionId,
 };
 use crate::event;
 
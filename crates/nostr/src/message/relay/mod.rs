@@ -121,12 +121,14 @@ pub enum RelayMessage {
         /// Challenge
         challenge: String,
     },
-    /// `["COUNT", <subscription_id>, {"count": <integer>}]` (NIP45)
+    /// `["COUNT", <subscription_id>, {"count": <integer>, "approximate": <bool>}]` (NIP45)
     Count {
         /// Subscription ID
         subscription_id: SubscriptionId,
         /// Events count
         count: usize,
+        /// The count is approximate
+        approximate: bool,
     },
     /// Negentropy Message
     NegMsg {
@@ -221,11 +223,21 @@ impl RelayMessage {
         }
     }
 
-    /// Create new `EVENT` message
+    /// Create new `COUNT` message
     pub fn new_count(subscription_id: SubscriptionId, count: usize) -> Self {
         Self::Count {
             subscription_id,
             count,
+            approximate: false,
+        }
+    }
+
+    /// Create new approximate `COUNT` message
+    pub fn new_approximate_count(subscription_id: SubscriptionId, count: usize) -> Self {
+        Self::Count {
+            subscription_id,
+            count,
+            approximate: true,
         }
     }
 
@@ -252,7 +264,14 @@ impl RelayMessage {
             Self::Count {
                 subscription_id,
                 count,
-            } => json!(["COUNT", subscription_id, { "count": count }]),
+                approximate,
+            } => {
+                if *approximate {
+                    json!(["COUNT", subscription_id, { "count": count, "approximate": true }])
+                } else {
+                    json!(["COUNT", subscription_id, { "count": count }])
+                }
+            }
             Self::NegMsg {
                 subscription_id,
                 message,
@@ -328,9 +347,11 @@ impl TryFrom<RawRelayMessage> for RelayMessage {
             RawRelayMessage::Count {
                 subscription_id,
                 count,
+                approximate,
             } => Ok(Self::Count {
                 subscription_id: SubscriptionId::new(subscription_id),
                 count,
+                approximate,
             }),
             RawRelayMessage::NegMsg {
                 subscription_id,
@@ -494,6 +515,45 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_handle_valid_count() {
+        let id = SubscriptionId::new("random-subscription-id");
+
+        assert_eq!(
+            RelayMessage::from_json(r#"["COUNT","random-subscription-id",{"count":42}]"#).unwrap(),
+            RelayMessage::new_count(id.clone(), 42)
+        );
+        assert_eq!(
+            RelayMessage::from_json(
+                r#"["COUNT","random-subscription-id",{"count":42,"approximate":true}]"#
+            )
+            .unwrap(),
+            RelayMessage::new_approximate_count(id.clone(), 42)
+        );
+
+        // Serialize
+        assert_eq!(
+            RelayMessage::new_count(id.clone(), 42).as_json(),
+            r#"["COUNT","random-subscription-id",{"count":42}]"#
+        );
+        assert_eq!(
+            RelayMessage::new_approximate_count(id, 42).as_json(),
+            r#"["COUNT","random-subscription-id",{"approximate":true,"count":42}]"#
+        );
+    }
+
+    #[test]
+    fn test_handle_invalid_count() {
+        // Missing count
+        assert!(RelayMessage::from_json(r#"["COUNT","random-subscription-id",{}]"#).is_err());
+
+        // Invalid approximate flag
+        assert!(RelayMessage::from_json(
+            r#"["COUNT","random-subscription-id",{"count":42,"approximate":"yes"}]"#
+        )
+        .is_err());
+    }
+
     #[test]
     fn parse_message() {
         // Got this fresh off the wire
@@ -47,12 +47,14 @@ pub enum RawRelayMessage {
         /// Challenge
         challenge: String,
     },
-    /// `["COUNT", <subscription_id>, {"count": <integer>}]` (NIP45)
+    /// `["COUNT", <subscription_id>, {"count": <integer>, "approximate": <bool>}]` (NIP45)
     Count {
         /// Subscription ID
         subscription_id: String,
         /// Events count
         count: usize,
+        /// The count is approximate
+        approximate: bool,
     },
     /// Negentropy Message
     NegMsg {
@@ -171,10 +173,15 @@ impl RawRelayMessage {
                 .ok_or(MessageHandleError::InvalidMessageFormat)?
                 .clone();
             let count: usize = serde_json::from_value(count)?;
+            let approximate: bool = match map.get("approximate") {
+                Some(approximate) => serde_json::from_value(approximate.clone())?,
+                None => false,
+            };
 
             return Ok(Self::Count {
                 subscription_id: serde_json::from_value(v[1].clone())?,
                 count,
+                approximate,
             });
         }
 
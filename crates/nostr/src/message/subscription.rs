@@ -26,7 +26,7 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 use crate::event::{TagIndexValues, TagIndexes};
-use crate::{Event, EventId, JsonUtil, Kind, Timestamp};
+use crate::{Event, EventId, EventRef, JsonUtil, Kind, Timestamp};
 
 /// Alphabet Error
 #[derive(Debug)]
@@ -680,19 +680,28 @@ impl Filter {
 }
 
 impl Filter {
-    fn ids_match(&self, event: &Event) -> bool {
-        self.ids.is_empty() || self.ids.contains(&event.id)
+    fn ids_match<E>(&self, event: &E) -> bool
+    where
+        E: MatchableEvent + ?Sized,
+    {
+        self.ids.is_empty() || self.ids.contains(event.event_id())
     }
 
-    fn authors_match(&self, event: &Event) -> bool {
-        self.authors.is_empty() || self.authors.contains(&event.pubkey)
+    fn authors_match<E>(&self, event: &E) -> bool
+    where
+        E: MatchableEvent + ?Sized,
+    {
+        self.authors.is_empty() || self.authors.contains(event.event_pubkey())
     }
 
-    fn tag_match(&self, event: &Event) -> bool {
+    fn tag_match<E>(&self, event: &E) -> bool
+    where
+        E: MatchableEvent + ?Sized,
+    {
         if self.generic_tags.is_empty() {
             return true;
         }
-        if event.tags.is_empty() {
+        if !event.has_tags() {
             return false;
         }
 
@@ -711,12 +720,16 @@ impl Filter {
         self.kinds.is_empty() || self.kinds.contains(kind)
     }
 
-    /// Determine if [`Filter`] match the provided [`Event`].
-    pub fn match_event(&self, event: &Event) -> bool {
+    /// Determine if [`Filter`] match the provided event ([`Event`] or [`EventRef`]).
+    pub fn match_event<E>(&self, event: &E) -> bool
+    where
+        E: MatchableEvent + ?Sized,
+    {
+        let created_at: Timestamp = event.event_created_at();
         self.ids_match(event)
-            && self.since.map_or(true, |t| event.created_at >= t)
-            && self.until.map_or(true, |t| event.created_at <= t)
-            && self.kind_match(&event.kind)
+            && self.since.map_or(true, |t| created_at >= t)
+            && self.until.map_or(true, |t| created_at <= t)
+            && self.kind_match(&event.event_kind())
             && self.authors_match(event)
             && self.tag_match(event)
     }
@@ -727,14 +740,100 @@ impl Filter {
     }
 }
 
+/// Event that can be matched against a [`Filter`]
+pub trait MatchableEvent {
+    /// Event ID
+    fn event_id(&self) -> &EventId;
+
+    /// Author
+    fn event_pubkey(&self) -> &XOnlyPublicKey;
+
+    /// Timestamp
+    fn event_created_at(&self) -> Timestamp;
+
+    /// Kind
+    fn event_kind(&self) -> Kind;
+
+    /// Check if event has tags
+    fn has_tags(&self) -> bool;
+
+    /// Build tags index
+    fn build_tags_index(&self) -> TagIndexes;
+}
+
+impl MatchableEvent for Event {
+    fn event_id(&self) -> &EventId {
+        &self.id
+    }
+
+    fn event_pubkey(&self) -> &XOnlyPublicKey {
+        &self.pubkey
+    }
+
+    fn event_created_at(&self) -> Timestamp {
+        self.created_at
+    }
+
+    fn event_kind(&self) -> Kind {
+        self.kind
+    }
+
+    fn has_tags(&self) -> bool {
+        !self.tags.is_empty()
+    }
+
+    fn build_tags_index(&self) -> TagIndexes {
+        Event::build_tags_index(self)
+    }
+}
+
+impl<'a> MatchableEvent for EventRef<'a> {
+    fn event_id(&self) -> &EventId {
+        &self.id
+    }
+
+    fn event_pubkey(&self) -> &XOnlyPublicKey {
+        &self.pubkey
+    }
+
+    fn event_created_at(&self) -> Timestamp {
+        self.created_at
+    }
+
+    fn event_kind(&self) -> Kind {
+        self.kind
+    }
+
+    fn has_tags(&self) -> bool {
+        !self.tags.is_empty()
+    }
+
+    fn build_tags_index(&self) -> TagIndexes {
+        EventRef::build_tags_index(self)
+    }
+}
+
 /// Filters match event trait
 pub trait FiltersMatchEvent {
     /// Determine if [`Filter`] match the provided [`Event`].
     fn match_event(&self, event: &Event) -> bool;
+
+    /// Determine if [`Filter`] match the provided event ([`Event`] or [`EventRef`]).
+    fn match_matchable_event<E>(&self, event: &E) -> bool
+    where
+        Self: Sized,
+        E: MatchableEvent + ?Sized;
 }
 
 impl FiltersMatchEvent for Vec<Filter> {
     fn match_event(&self, event: &Event) -> bool {
+        self.match_matchable_event(event)
+    }
+
+    fn match_matchable_event<E>(&self, event: &E) -> bool
+    where
+        E: MatchableEvent + ?Sized,
+    {
         self.iter().any(|f| f.match_event(event))
     }
 }
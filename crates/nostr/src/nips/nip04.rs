@@ -152,13 +152,13 @@ where
 }
 
 #[cfg(test)]
-#[cfg(feature = "std")]
 mod tests {
     use core::str::FromStr;
 
     use bitcoin::secp256k1::{KeyPair, Secp256k1};
 
     use super::*;
+    use crate::util::TestRng;
 
     #[test]
     fn test_encryption_decryption() {
@@ -181,7 +181,8 @@ mod tests {
 
         let content = String::from("Saturn, bringer of old age");
 
-        let encrypted_content = encrypt(&sender_sk, &receiver_pk, &content).unwrap();
+        let encrypted_content =
+            encrypt_with_rng(&mut TestRng::default(), &sender_sk, &receiver_pk, &content).unwrap();
 
         assert_eq!(
             decrypt(&receiver_sk, &sender_pk, &encrypted_content).unwrap(),
@@ -24,7 +24,7 @@ use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::{json, Value};
 
 use super::nip21;
-use crate::event::Event;
+use crate::event::{Event, Tag};
 use crate::key::{self, Keys};
 #[cfg(feature = "std")]
 use crate::SECP256K1;
@@ -353,6 +353,35 @@ impl TryFrom<Vec<String>> for DelegationTag {
     }
 }
 
+impl TryFrom<&Tag> for DelegationTag {
+    type Error = Error;
+
+    fn try_from(tag: &Tag) -> Result<Self, Self::Error> {
+        match tag {
+            Tag::Delegation {
+                delegator,
+                conditions,
+                sig,
+            } => Ok(Self {
+                delegator_pubkey: *delegator,
+                conditions: conditions.clone(),
+                signature: *sig,
+            }),
+            _ => Err(Error::DelegationTagParse),
+        }
+    }
+}
+
+impl From<DelegationTag> for Tag {
+    fn from(tag: DelegationTag) -> Self {
+        Self::Delegation {
+            delegator: tag.delegator_pubkey,
+            conditions: tag.conditions,
+            sig: tag.signature,
+        }
+    }
+}
+
 impl fmt::Display for DelegationTag {
     /// Return tag in JSON string format
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -463,7 +492,7 @@ impl Conditions {
     }
 
     /// Evaluate whether an event satisfies all these conditions
-    fn evaluate(&self, ep: &EventProperties) -> Result<(), ValidationError> {
+    pub(crate) fn evaluate(&self, ep: &EventProperties) -> Result<(), ValidationError> {
         for c in &self.0 {
             c.evaluate(ep)?;
         }
@@ -550,6 +579,7 @@ mod test {
     use bitcoin::secp256k1::{Secp256k1, SecretKey};
 
     use super::*;
+    use crate::util::TestRng;
 
     #[test]
     fn test_serialize_conditions() {
@@ -638,6 +668,55 @@ mod test {
             .is_ok());
     }
 
+    #[test]
+    fn test_create_and_validate_delegation_tag_with_ctx() {
+        let secp = Secp256k1::new();
+        let mut rng = TestRng::default();
+
+        let delegator_secret_key =
+            SecretKey::from_str("b2f3673ee3a659283e6599080e0ab0e669a3c2640914375a9b0b357faae08b17")
+                .unwrap();
+        let delegator_keys = Keys::new_with_ctx(&secp, delegator_secret_key);
+        let delegatee_pubkey = XOnlyPublicKey::from_str(
+            "bea8aeb6c1657e33db5ac75a83910f77e8ec6145157e476b5b88c6e85b1fab34",
+        )
+        .unwrap();
+        let conditions =
+            Conditions::from_str("kind=1&created_at>1676067553&created_at<1678659553").unwrap();
+
+        let tag = DelegationTag::new_with_ctx(
+            &secp,
+            &mut rng,
+            &delegator_keys,
+            delegatee_pubkey,
+            conditions.clone(),
+        )
+        .unwrap();
+
+        assert!(verify_delegation_signature_with_ctx(
+            &secp,
+            delegator_keys.public_key(),
+            tag.signature(),
+            delegatee_pubkey,
+            conditions,
+        )
+        .is_ok());
+        assert!(tag
+            .validate_with_ctx(
+                &secp,
+                delegatee_pubkey,
+                &EventProperties::new(1, 1677000000)
+            )
+            .is_ok());
+        assert!(tag
+            .validate_with_ctx(
+                &secp,
+                delegatee_pubkey,
+                &EventProperties::new(1, 1679000000)
+            )
+            .is_err());
+    }
+
     #[test]
     fn test_delegation_tag_parse_and_validate() {
         let secp = Secp256k1::new();
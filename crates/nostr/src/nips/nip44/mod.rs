@@ -220,13 +220,13 @@ where
 }
 
 #[cfg(test)]
-#[cfg(feature = "std")]
 mod tests {
     use core::str::FromStr;
 
     use bitcoin::secp256k1::{KeyPair, Secp256k1, SecretKey, XOnlyPublicKey};
 
     use super::*;
+    use crate::util::TestRng;
 
     #[test]
     fn test_nip44_encryption_decryption() {
@@ -247,7 +247,14 @@ mod tests {
         let bob_pk = XOnlyPublicKey::from_keypair(&bob_key_pair).0;
 
         let content = String::from("hello");
-        let encrypted_content = encrypt(&alice_sk, &bob_pk, &content, Version::V2).unwrap();
+        let encrypted_content = encrypt_with_rng(
+            &mut TestRng::default(),
+            &alice_sk,
+            &bob_pk,
+            &content,
+            Version::V2,
+        )
+        .unwrap();
         assert_eq!(
             decrypt(&bob_sk, &alice_pk, &encrypted_content).unwrap(),
             content
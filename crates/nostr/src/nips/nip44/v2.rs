@@ -291,7 +291,6 @@ fn hkdf_expand(prk: &[u8], info: &[u8], output_len: usize) -> Vec<u8> {
 }
 
 #[cfg(test)]
-#[cfg(feature = "std")]
 mod tests {
     #![allow(dead_code)]
 
@@ -21,12 +21,15 @@ use crate::{ImageDimensions, Tag, Timestamp, UncheckedUrl};
 pub enum Error {
     /// Unknown [`LiveEventMarker`]
     UnknownLiveEventMarker(String),
+    /// Identifier tag (`d`) not found
+    IdentifierTagNotFound,
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::UnknownLiveEventMarker(u) => write!(f, "Unknown live event marker: {u}"),
+            Self::IdentifierTagNotFound => write!(f, "Identifier tag not found"),
         }
     }
 }
@@ -104,6 +107,7 @@ where
 }
 
 /// Live Event Host
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct LiveEventHost {
     /// Host public key
     pub public_key: XOnlyPublicKey,
@@ -114,6 +118,7 @@ pub struct LiveEventHost {
 }
 
 /// Live Event
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct LiveEvent {
     /// Unique event ID
     pub id: String,
@@ -257,3 +262,74 @@ impl From<LiveEvent> for Vec<Tag> {
         tags
     }
 }
+
+impl TryFrom<Vec<Tag>> for LiveEvent {
+    type Error = Error;
+
+    fn try_from(tags: Vec<Tag>) -> Result<Self, Self::Error> {
+        let id: String = tags
+            .iter()
+            .find_map(|t| match t {
+                Tag::Identifier(id) => Some(id.clone()),
+                _ => None,
+            })
+            .ok_or(Error::IdentifierTagNotFound)?;
+
+        let mut live_event = LiveEvent {
+            id,
+            title: None,
+            summary: None,
+            image: None,
+            hashtags: Vec::new(),
+            streaming: None,
+            recording: None,
+            starts: None,
+            ends: None,
+            status: None,
+            current_participants: None,
+            total_participants: None,
+            relays: Vec::new(),
+            host: None,
+            speakers: Vec::new(),
+            participants: Vec::new(),
+        };
+
+        for tag in tags.into_iter() {
+            match tag {
+                Tag::Title(title) => live_event.title = Some(title),
+                Tag::Summary(summary) => live_event.summary = Some(summary),
+                Tag::Streaming(url) => live_event.streaming = Some(url),
+                Tag::LiveEventStatus(status) => live_event.status = Some(status),
+                Tag::PubKeyLiveEvent {
+                    public_key,
+                    relay_url,
+                    marker,
+                    proof,
+                } => match marker {
+                    LiveEventMarker::Host => {
+                        live_event.host = Some(LiveEventHost {
+                            public_key,
+                            relay_url,
+                            proof,
+                        })
+                    }
+                    LiveEventMarker::Speaker => live_event.speakers.push((public_key, relay_url)),
+                    LiveEventMarker::Participant => {
+                        live_event.participants.push((public_key, relay_url))
+                    }
+                },
+                Tag::Image(image, dim) => live_event.image = Some((image, dim)),
+                Tag::Hashtag(hashtag) => live_event.hashtags.push(hashtag),
+                Tag::Recording(url) => live_event.recording = Some(url),
+                Tag::Starts(starts) => live_event.starts = Some(starts),
+                Tag::Ends(ends) => live_event.ends = Some(ends),
+                Tag::CurrentParticipants(n) => live_event.current_participants = Some(n),
+                Tag::TotalParticipants(n) => live_event.total_participants = Some(n),
+                Tag::Relays(relays) => live_event.relays.extend(relays),
+                _ => {}
+            }
+        }
+
+        Ok(live_event)
+    }
+}
@@ -8,12 +8,37 @@
 
 use alloc::string::String;
 use alloc::vec::Vec;
+use core::fmt;
 
 use bitcoin::hashes::sha256::Hash as Sha256Hash;
 use url_fork::Url;
 
 use crate::{ImageDimensions, Tag};
 
+/// NIP94 error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// Url tag not found
+    UrlTagNotFound,
+    /// MIME type tag not found
+    MimeTypeTagNotFound,
+    /// SHA256 tag not found
+    Sha256TagNotFound,
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UrlTagNotFound => write!(f, "Url tag not found"),
+            Self::MimeTypeTagNotFound => write!(f, "MIME type tag not found"),
+            Self::Sha256TagNotFound => write!(f, "SHA256 tag not found"),
+        }
+    }
+}
+
 /// File Metadata
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct FileMetadata {
@@ -145,3 +170,43 @@ impl From<FileMetadata> for Vec<Tag> {
         tags
     }
 }
+
+impl TryFrom<Vec<Tag>> for FileMetadata {
+    type Error = Error;
+
+    fn try_from(tags: Vec<Tag>) -> Result<Self, Self::Error> {
+        let mut url: Option<Url> = None;
+        let mut mime_type: Option<String> = None;
+        let mut hash: Option<Sha256Hash> = None;
+        let mut aes_256_gcm: Option<(String, String)> = None;
+        let mut size: Option<usize> = None;
+        let mut dim: Option<ImageDimensions> = None;
+        let mut magnet: Option<String> = None;
+        let mut blurhash: Option<String> = None;
+
+        for tag in tags.into_iter() {
+            match tag {
+                Tag::Url(u) => url = Some(u),
+                Tag::MimeType(m) => mime_type = Some(m),
+                Tag::Sha256(h) => hash = Some(h),
+                Tag::Aes256Gcm { key, iv } => aes_256_gcm = Some((key, iv)),
+                Tag::Size(s) => size = Some(s),
+                Tag::Dim(d) => dim = Some(d),
+                Tag::Magnet(m) => magnet = Some(m),
+                Tag::Blurhash(b) => blurhash = Some(b),
+                _ => {}
+            }
+        }
+
+        Ok(Self {
+            url: url.ok_or(Error::UrlTagNotFound)?,
+            mime_type: mime_type.ok_or(Error::MimeTypeTagNotFound)?,
+            hash: hash.ok_or(Error::Sha256TagNotFound)?,
+            aes_256_gcm,
+            size,
+            dim,
+            magnet,
+            blurhash,
+        })
+    }
+}
@@ -19,10 +19,15 @@ pub use serde_json::*;
 pub use url_fork::*;
 
 // Internal modules
+pub use crate::event::borrowed::*;
 pub use crate::event::builder::*;
 pub use crate::event::id::*;
 pub use crate::event::kind::*;
+pub use crate::event::pipeline::*;
+#[cfg(feature = "std")]
+pub use crate::event::pow::*;
 pub use crate::event::tag::*;
+pub use crate::event::typed::*;
 pub use crate::event::unsigned::*;
 pub use crate::event::*;
 pub use crate::key::*;
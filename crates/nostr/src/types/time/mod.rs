@@ -17,7 +17,7 @@ use bitcoin::secp256k1::rand::Rng;
 
 mod supplier;
 
-pub use self::supplier::TimeSupplier;
+pub use self::supplier::{FixedTime, TimeSupplier};
 #[cfg(feature = "std")]
 pub use self::supplier::{Instant, SystemTime, UNIX_EPOCH};
 
@@ -79,3 +79,57 @@ impl TimeSupplier for Instant {
         now.duration_since(since).unwrap_or_default()
     }
 }
+
+/// Fixed time supplier
+///
+/// Always return the same time (duration since UNIX epoch). Useful in `no_std` environments,
+/// where the time can be acquired from an external source (i.e. RTC or host).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FixedTime(Duration);
+
+impl FixedTime {
+    /// New [`FixedTime`] from duration since UNIX epoch
+    pub const fn new(since_epoch: Duration) -> Self {
+        Self(since_epoch)
+    }
+
+    /// Set the current time
+    pub fn set(&mut self, since_epoch: Duration) {
+        self.0 = since_epoch;
+    }
+}
+
+impl From<Timestamp> for FixedTime {
+    fn from(timestamp: Timestamp) -> Self {
+        Self(Duration::from_secs(timestamp.as_u64()))
+    }
+}
+
+impl TimeSupplier for FixedTime {
+    type Now = Duration;
+    type StartingPoint = Duration;
+
+    fn now(&self) -> Self::StartingPoint {
+        self.0
+    }
+
+    fn instant_now(&self) -> Self::Now {
+        self.0
+    }
+
+    fn starting_point(&self) -> Self::StartingPoint {
+        Duration::ZERO
+    }
+
+    fn duration_since_starting_point(&self, now: Self::StartingPoint) -> Duration {
+        now
+    }
+
+    fn elapsed_instant_since(&self, now: Self::Now, since: Self::Now) -> Duration {
+        now.saturating_sub(since)
+    }
+
+    fn elapsed_since(&self, now: Self::StartingPoint, since: Self::StartingPoint) -> Duration {
+        now.saturating_sub(since)
+    }
+}